@@ -14,7 +14,7 @@ use serde::{
 use crate::{
     account::{CiphertextCache, VersionedBalance, VersionedNonce},
     block::{Algorithm, BlockVersion, EXTRA_NONCE_SIZE},
-    crypto::{Address, Hash},
+    crypto::{state_tree::StateProof, Address, Hash},
     difficulty::{CumulativeDifficulty, Difficulty},
     network::Network,
     time::{TimestampMillis, TimestampSeconds}
@@ -232,6 +232,39 @@ pub struct GetStableBalanceResult {
     pub stable_block_hash: Hash 
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct GetStateRootParams {
+    pub topoheight: u64
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetStateRootResult {
+    pub topoheight: u64,
+    pub root: Hash
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetAccountProofParams<'a> {
+    pub address: Cow<'a, Address>,
+    pub asset: Cow<'a, Hash>,
+    // Use the last stable topoheight if not set
+    // Proofs are only available at this topoheight
+    #[serde(default)]
+    pub topoheight: Option<u64>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetAccountProofResult {
+    pub topoheight: u64,
+    // State root against which the proofs are built
+    pub root: Hash,
+    pub nonce: u64,
+    pub nonce_proof: StateProof,
+    // Final balance of the account at this topoheight
+    pub balance: CiphertextCache,
+    pub balance_proof: StateProof
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GetInfoResult {
     pub height: u64,
@@ -257,7 +290,37 @@ pub struct GetInfoResult {
     // software version on which the daemon is running
     pub version: String,
     // Network state (mainnet, testnet, devnet)
-    pub network: Network
+    pub network: Network,
+    // Data retention policy of the node
+    // Not set by older daemons
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeMode {
+    // The full history is available
+    Archive,
+    // Some data below a topoheight is deleted
+    Pruned
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RetentionPolicy {
+    pub mode: NodeMode,
+    // Auto prune mode, only the last N blocks are kept
+    pub keep_blocks: Option<u64>,
+    // Only the transactions of the last N blocks are kept
+    pub keep_txs: Option<u64>,
+    // Transactions of blocks below this topoheight are not available
+    pub txs_pruned_topoheight: Option<u64>,
+    // Only the balances history of the last N blocks is kept
+    pub keep_balances_history: Option<u64>,
+    // Balances below this topoheight are not available,
+    // except for the whitelisted accounts
+    pub balances_pruned_topoheight: Option<u64>,
+    // Accounts for which the full balances history is kept
+    pub balances_history_whitelist: Vec<Address>
 }
 
 #[derive(Serialize, Deserialize)]
@@ -322,6 +385,15 @@ pub struct GetPeersResponse<'a> {
     pub hidden_peers: usize
 }
 
+// Traffic of a packet type
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct PacketBandwidth {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub packets_in: u64,
+    pub packets_out: u64
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PeerEntry<'a> {
     pub id: u64,
@@ -339,6 +411,12 @@ pub struct PeerEntry<'a> {
     pub connected_on: TimestampSeconds,
     pub bytes_sent: usize,
     pub bytes_recv: usize,
+    // Traffic with this peer by packet type
+    #[serde(default)]
+    pub bandwidth: HashMap<Cow<'a, str>, PacketBandwidth>,
+    // Score based on the usefulness of the peer
+    #[serde(default)]
+    pub score: i32
 }
 
 #[derive(Serialize, Deserialize)]
@@ -349,7 +427,15 @@ pub struct P2pStatusResult<'a> {
     pub our_topoheight: u64,
     pub best_topoheight: u64,
     pub median_topoheight: u64,
-    pub peer_id: u64
+    pub peer_id: u64,
+    // Total traffic with all peers since the start
+    #[serde(default)]
+    pub bytes_in: u64,
+    #[serde(default)]
+    pub bytes_out: u64,
+    // Total traffic by packet type
+    #[serde(default)]
+    pub bandwidth: HashMap<Cow<'a, str>, PacketBandwidth>
 }
 
 #[derive(Serialize, Deserialize)]
@@ -514,6 +600,24 @@ pub struct GetMempoolCacheResult {
     balances: HashMap<Hash, CiphertextCache>
 }
 
+impl GetMempoolCacheResult {
+    pub fn get_min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn get_txs(&self) -> &Vec<Hash> {
+        &self.txs
+    }
+
+    pub fn get_balances(&self) -> &HashMap<Hash, CiphertextCache> {
+        &self.balances
+    }
+}
+
 // This struct is used to store the fee rate estimation for the following priority levels:
 // 1. Low
 // 2. Medium
@@ -528,6 +632,24 @@ pub struct FeeRatesEstimated {
     pub default: u64
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct GetEstimatedFeeRateForTargetParams {
+    // Count of blocks in which the TX should be included
+    pub target: u64
+}
+
+// Fee rate estimated for a TX to be included within the target blocks
+#[derive(Serialize, Deserialize)]
+pub struct TargetFeeRateEstimated {
+    // Fee per KB to pay
+    pub fee_rate: u64,
+    // Count of blocks targeted
+    pub target: u64,
+    // Is it based on the TXs included in the previous blocks
+    // Otherwise, not enough TXs were included and the medium fee rate of the mempool is used
+    pub historical: bool
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct GetDifficultyResult {
     pub difficulty: Difficulty,
@@ -594,6 +716,13 @@ pub enum NotifyEvent {
     // When a new transaction is added in mempool
     // it contains TransactionAddedInMempoolEvent struct as value
     TransactionAddedInMempool,
+    // When a transaction has been evicted from mempool because it was full
+    // It contains TransactionEvictedEvent as value
+    TransactionEvicted,
+    // When a transaction has been removed from mempool because it stayed too long in it
+    // or because its reference is too old
+    // It contains TransactionExpiredEvent as value
+    TransactionExpired,
     // When a transaction has been included in a valid block & executed on chain
     // it contains TransactionExecutedEvent struct as value
     TransactionExecuted,
@@ -661,6 +790,10 @@ pub struct StableTopoHeightChangedEvent {
 pub type TransactionAddedInMempoolEvent = TransactionResponse<'static>;
 // Value of NotifyEvent::TransactionOrphaned
 pub type TransactionOrphanedEvent = TransactionResponse<'static>;
+// Value of NotifyEvent::TransactionEvicted
+pub type TransactionEvictedEvent = TransactionResponse<'static>;
+// Value of NotifyEvent::TransactionExpired
+pub type TransactionExpiredEvent = TransactionResponse<'static>;
 
 // Value of NotifyEvent::TransactionExecuted
 #[derive(Serialize, Deserialize)]
@@ -13,6 +13,9 @@ pub const FEE_PER_ACCOUNT_CREATION: u64 = 100000;
 // 0.00005000 XEL per KB
 // Each transfer has a overhead of 5000 atomic units
 pub const FEE_PER_TRANSFER: u64 = 5000;
+// A TX replacing another one with the same nonce in mempool
+// must pay at least 10% more fee per KB
+pub const REPLACE_BY_FEE_MIN_INCREASE_PERCENT: u64 = 10;
 
 // 8 decimals numbers
 pub const COIN_DECIMALS: u8 = 8;
@@ -5,6 +5,7 @@ mod transcript;
 pub mod elgamal;
 pub mod proofs;
 pub mod bech32;
+pub mod state_tree;
 
 pub use hash::*;
 pub use address::*;
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    serializer::{Reader, ReaderError, Serializer, Writer},
+    account::CiphertextCache
+};
+use super::{hash, Hash, PublicKey};
+
+// The state tree is a sparse merkle tree of 256 levels
+// Each leaf is placed at the path given by the hash of its key,
+// but a subtree containing only one leaf is replaced by this leaf,
+// so a proof only contains the siblings until the leaf depth.
+// An empty subtree has a zero hash.
+pub const STATE_TREE_DEPTH: usize = 256;
+
+// Prefixes used to separate each kind of data in the tree
+const ASSET_PREFIX: u8 = 0;
+const NONCE_PREFIX: u8 = 1;
+const BALANCE_PREFIX: u8 = 2;
+
+// Prefixes used to separate leafs from nodes
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+// Path of an asset in the state tree
+pub fn asset_path(asset: &Hash) -> Hash {
+    let mut bytes = Vec::with_capacity(33);
+    bytes.push(ASSET_PREFIX);
+    bytes.extend_from_slice(asset.as_bytes());
+    hash(&bytes)
+}
+
+// Path of an account nonce in the state tree
+pub fn nonce_path(key: &PublicKey) -> Hash {
+    let mut bytes = Vec::with_capacity(33);
+    bytes.push(NONCE_PREFIX);
+    bytes.extend_from_slice(key.as_bytes());
+    hash(&bytes)
+}
+
+// Path of an account balance for an asset in the state tree
+pub fn balance_path(key: &PublicKey, asset: &Hash) -> Hash {
+    let mut bytes = Vec::with_capacity(65);
+    bytes.push(BALANCE_PREFIX);
+    bytes.extend_from_slice(key.as_bytes());
+    bytes.extend_from_slice(asset.as_bytes());
+    hash(&bytes)
+}
+
+// Value stored for an asset, only its decimals are committed
+pub fn asset_value(decimals: u8) -> Vec<u8> {
+    vec![decimals]
+}
+
+// Value stored for a nonce
+pub fn nonce_value(nonce: u64) -> Vec<u8> {
+    nonce.to_be_bytes().to_vec()
+}
+
+// Value stored for a balance, it's the compressed final balance
+pub fn balance_value(balance: &CiphertextCache) -> Vec<u8> {
+    balance.to_bytes()
+}
+
+pub fn leaf_hash(path: &Hash, value: &[u8]) -> Hash {
+    let mut bytes = Vec::with_capacity(1 + 32 + value.len());
+    bytes.push(LEAF_PREFIX);
+    bytes.extend_from_slice(path.as_bytes());
+    bytes.extend_from_slice(value);
+    hash(&bytes)
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut bytes = Vec::with_capacity(65);
+    bytes.push(NODE_PREFIX);
+    bytes.extend_from_slice(left.as_bytes());
+    bytes.extend_from_slice(right.as_bytes());
+    hash(&bytes)
+}
+
+// Returns true if the path goes to the right at this depth
+pub fn path_bit(path: &Hash, depth: usize) -> bool {
+    (path.as_bytes()[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+// Inclusion proof of a leaf in the state tree
+// Siblings are ordered from the root to the leaf
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StateProof {
+    siblings: Vec<Hash>
+}
+
+impl StateProof {
+    pub fn new(siblings: Vec<Hash>) -> Self {
+        Self {
+            siblings
+        }
+    }
+
+    pub fn get_siblings(&self) -> &Vec<Hash> {
+        &self.siblings
+    }
+
+    // Compute the root from the leaf up to the top of the tree
+    pub fn compute_root(&self, path: &Hash, value: &[u8]) -> Option<Hash> {
+        if self.siblings.len() > STATE_TREE_DEPTH {
+            return None
+        }
+
+        let mut current = leaf_hash(path, value);
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            current = if path_bit(path, depth) {
+                node_hash(sibling, &current)
+            } else {
+                node_hash(&current, sibling)
+            };
+        }
+
+        Some(current)
+    }
+
+    // Verify that the value is stored at the path in the tree
+    pub fn verify(&self, root: &Hash, path: &Hash, value: &[u8]) -> bool {
+        self.compute_root(path, value).is_some_and(|computed| computed == *root)
+    }
+}
+
+impl Serializer for StateProof {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u16(self.siblings.len() as u16);
+        for sibling in self.siblings.iter() {
+            writer.write_hash(sibling);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let count = reader.read_u16()? as usize;
+        if count > STATE_TREE_DEPTH {
+            return Err(ReaderError::InvalidSize)
+        }
+
+        let mut siblings = Vec::with_capacity(count);
+        for _ in 0..count {
+            siblings.push(reader.read_hash()?);
+        }
+
+        Ok(Self::new(siblings))
+    }
+
+    fn size(&self) -> usize {
+        2 + self.siblings.len() * 32
+    }
+}
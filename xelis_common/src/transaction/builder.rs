@@ -12,7 +12,7 @@ use std::{
 use crate::{
     account::CiphertextCache,
     api::DataElement,
-    config::XELIS_ASSET,
+    config::{BYTES_PER_KB, XELIS_ASSET},
     crypto::{
         elgamal::{
             Ciphertext,
@@ -85,6 +85,8 @@ pub enum GenerationError<T> {
 pub enum FeeBuilder {
     // calculate tx fees based on its size and multiply by this value
     Multiplier(f64),
+    // pay this fee per KB of the tx, or the minimum fees if they are higher
+    Rate(u64),
     Value(u64) // set a direct value of how much fees you want to pay
 }
 
@@ -331,28 +333,38 @@ impl TransactionBuilder {
         size
     }
 
+    // Estimate the size and the minimum fees required for this TX
+    fn estimate_minimum_fees<B: FeeHelper>(&self, state: &mut B) -> Result<(usize, u64), GenerationError<B::Error>> {
+        // Compute the size and transfers count
+        let size = self.estimate_size();
+        let (transfers, new_addresses) = if let TransactionTypeBuilder::Transfers(transfers) = &self.data {
+            let mut new_addresses = 0;
+            for transfer in transfers {
+                if !state.account_exists(&transfer.destination.get_public_key()).map_err(GenerationError::State)? {
+                    new_addresses += 1;
+                }
+            }
+
+            (transfers.len(), new_addresses)
+        } else {
+            (0, 0)
+        };
+
+        Ok((size, calculate_tx_fee(size, transfers, new_addresses)))
+    }
+
     // Estimate the fees for this TX
     pub fn estimate_fees<B: FeeHelper>(&self, state: &mut B) -> Result<u64, GenerationError<B::Error>> {
         let calculated_fee = match self.fee_builder {
             FeeBuilder::Multiplier(multiplier) => {
-                // Compute the size and transfers count
-                let size = self.estimate_size();
-                let (transfers, new_addresses) = if let TransactionTypeBuilder::Transfers(transfers) = &self.data {
-                    let mut new_addresses = 0;
-                    for transfer in transfers {
-                        if !state.account_exists(&transfer.destination.get_public_key()).map_err(GenerationError::State)? {
-                            new_addresses += 1;
-                        }
-                    }
-
-                    (transfers.len(), new_addresses)
-                } else {
-                    (0, 0)
-                };
-
-                let expected_fee = calculate_tx_fee(size, transfers, new_addresses);
+                let (_, expected_fee) = self.estimate_minimum_fees(state)?;
                 (expected_fee as f64 * multiplier) as u64
             },
+            FeeBuilder::Rate(fee_rate) => {
+                let (size, expected_fee) = self.estimate_minimum_fees(state)?;
+                let kbs = (size as u64 + BYTES_PER_KB as u64 - 1) / BYTES_PER_KB as u64;
+                expected_fee.max(fee_rate.saturating_mul(kbs))
+            },
             // If the value is set, use it
             FeeBuilder::Value(value) => value
         };
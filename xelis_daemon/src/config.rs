@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 use xelis_common::{
     api::daemon::{DevFeeThreshold, HardFork},
     block::BlockVersion,
-    config::MAX_BLOCK_SIZE,
+    config::{BYTES_PER_KB, MAX_BLOCK_SIZE},
     crypto::{
         Address,
         Hash,
@@ -24,6 +24,16 @@ pub const DEFAULT_RPC_BIND_ADDRESS: &str = "0.0.0.0:8080";
 // Default cache size for storage DB
 pub const DEFAULT_CACHE_SIZE: usize = 1024;
 
+// Mempool rules
+// Default maximum size in bytes of all the TXs in mempool
+pub const DEFAULT_MEMPOOL_MAX_SIZE: usize = 64 * BYTES_PER_KB * BYTES_PER_KB; // 64 MB
+// Default maximum count of TXs in mempool for a same sender
+pub const DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER: usize = 64;
+// Default maximum time in seconds a TX can stay in mempool
+pub const DEFAULT_MEMPOOL_TX_MAX_AGE: u64 = 24 * 60 * 60; // 1 day
+// Default maximum distance between the TX reference topoheight and the chain top
+pub const DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE: u64 = 1000;
+
 // Block rules
 // Millis per second, it is used to prevent having random 1000 values anywhere
 pub const MILLIS_PER_SECOND: u64 = 1000;
@@ -45,6 +55,13 @@ pub const TIMESTAMP_IN_FUTURE_LIMIT: TimestampSeconds = 2 * 1000;
 // WARNING: This must be at least 50 blocks for difficulty adjustement
 pub const PRUNE_SAFETY_LIMIT: u64 = STABLE_LIMIT * 10;
 
+// count of blocks read from the storage at once when exporting the chain
+// the storage lock is released between each batch
+pub const EXPORT_CHAIN_BATCH_SIZE: u64 = 1000;
+// count of topoheights or heights read from the storage at once when replaying blocks
+// the storage lock is released between each chunk
+pub const REPLAY_BLOCKS_CHUNK_SIZE: u64 = 1000;
+
 // BlockDAG rules
 pub const STABLE_LIMIT: u64 = 8; // in how many height we consider the block stable
 
@@ -115,6 +132,12 @@ pub const CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS: usize = 4096;
 pub const CHAIN_SYNC_RESPONSE_MAX_BLOCKS: usize = 16384;
 // send last 10 heights
 pub const CHAIN_SYNC_TOP_BLOCKS: usize = 10;
+// blocks per range downloaded from a single peer in boost sync mode
+pub const CHAIN_SYNC_PARALLEL_RANGE_BLOCKS: usize = 32;
+// maximum ranges downloaded at the same time in boost sync mode
+pub const CHAIN_SYNC_PARALLEL_MAX_RANGES: usize = 8;
+// maximum objects requested at the same time to a peer in boost sync mode
+pub const CHAIN_SYNC_PARALLEL_MAX_REQUESTS: usize = 64;
 
 // P2p rules
 // time between each ping
@@ -125,6 +148,15 @@ pub const P2P_PING_PEER_LIST_DELAY: u64 = 60 * 5;
 pub const P2P_PING_PEER_LIST_LIMIT: usize = 16;
 // default number of maximum peers
 pub const P2P_DEFAULT_MAX_PEERS: usize = 32;
+// default number of maximum outgoing peers, priority nodes excluded
+// The others slots are reserved to incoming peers
+pub const P2P_DEFAULT_MAX_OUTGOING_PEERS: usize = 8;
+// maximum number of outgoing peers in the same network group (IPv4 /16 or IPv6 /32)
+pub const P2P_MAX_OUTGOING_PEERS_PER_GROUP: usize = 2;
+// number of outgoing peers saved at stop to reconnect to them first at the next start
+pub const P2P_ANCHOR_PEERS: usize = 2;
+// time in seconds between each save of our anchors, they are also saved at stop
+pub const P2P_SAVE_ANCHORS_DELAY: u64 = 10 * 60;
 // time in seconds between each time we try to connect to a new peer
 pub const P2P_EXTEND_PEERLIST_DELAY: u64 = 60;
 // time in seconds between each time we try to connect to a outgoing peer
@@ -142,6 +174,35 @@ pub const P2P_HEARTBEAT_INTERVAL: u64 = P2P_PING_DELAY / 2;
 // Timeout in seconds
 // If we didn't receive any packet from a peer during this time, we disconnect it
 pub const P2P_PING_TIMEOUT: u64 = P2P_PING_DELAY * 6;
+// default maximum count of chain requests accepted per minute from a peer
+pub const P2P_DEFAULT_CHAIN_REQUESTS_PER_MINUTE: u32 = 60;
+// default maximum count of object requests accepted per minute from a peer
+pub const P2P_DEFAULT_OBJECT_REQUESTS_PER_MINUTE: u32 = 6000;
+// default maximum count of bootstrap chain requests accepted per minute from a peer
+pub const P2P_DEFAULT_BOOTSTRAP_CHAIN_REQUESTS_PER_MINUTE: u32 = 600;
+// minimum count of distinct peers (by IP) confirming the merkle hash of a state snapshot
+pub const P2P_STATE_SNAPSHOT_MIN_PEERS: usize = 3;
+// time in seconds between each resolution of the DNS seeds when our peerlist is running low
+pub const P2P_DNS_SEEDS_RESOLVE_DELAY: u64 = 60 * 15;
+// timeout in milliseconds to resolve a DNS seed
+pub const P2P_DNS_SEED_TIMEOUT: u64 = 5_000;
+
+// Dandelion rules
+// time in seconds of an epoch, a new stem peer is selected at each epoch
+pub const DANDELION_EPOCH_DURATION: u64 = 60 * 10;
+// probability in percent to fluff a stem TX received instead of relaying it
+pub const DANDELION_FLUFF_PROBABILITY: u8 = 10;
+// minimum time in milliseconds before fluffing ourself a stem TX not seen in fluff phase
+pub const DANDELION_EMBARGO_TIMEOUT: u64 = 30_000;
+// maximum random delay in milliseconds added to the embargo timeout
+// so the node fluffing first can't be predicted
+pub const DANDELION_EMBARGO_MAX_DELAY: u64 = 15_000;
+// interval in milliseconds between each check of the expired embargoes
+pub const DANDELION_EMBARGO_CHECK_INTERVAL: u64 = 1_000;
+// number of TXs hashes seen in fluff phase kept in cache
+pub const DANDELION_FLUFFED_CACHE_SIZE: usize = 10240;
+// maximum count of TXs received in stem phase kept hidden from the mempool
+pub const DANDELION_STEMPOOL_MAX_TXS: usize = 1024;
 
 // Peer rules
 // number of seconds to reset the counter
@@ -181,6 +242,31 @@ pub const PEER_PACKET_CHANNEL_SIZE: usize = 1024;
 // Millis
 pub const PEER_SEND_BYTES_TIMEOUT: u64 = 3_000;
 
+// Peer scoring rules
+// bounds of the score, so a peer can't be trusted or banned forever
+pub const PEER_SCORE_MAX: i32 = 1000;
+pub const PEER_SCORE_MIN: i32 = -1000;
+// reward for a block propagated that we added to our chain
+pub const PEER_SCORE_USEFUL_BLOCK: i32 = 10;
+// reward for a TX propagated that we added to our mempool
+pub const PEER_SCORE_USEFUL_TX: i32 = 1;
+// reward for a chain response received in less than `PEER_SCORE_TIMELY_CHAIN_RESPONSE_DELAY`
+pub const PEER_SCORE_TIMELY_CHAIN_RESPONSE: i32 = 5;
+// millis under which a chain response is timely
+pub const PEER_SCORE_TIMELY_CHAIN_RESPONSE_DELAY: u64 = 3_000;
+// penalty for a chain response received after `PEER_SCORE_TIMELY_CHAIN_RESPONSE_DELAY`
+pub const PEER_SCORE_SLOW_CHAIN_RESPONSE: i32 = -10;
+// penalty for a request that timed out
+pub const PEER_SCORE_TIMEOUT: i32 = -20;
+// penalty for an invalid block, TX or packet
+pub const PEER_SCORE_INVALID_OBJECT: i32 = -50;
+// penalty for a block or TX propagated again by the same peer
+pub const PEER_SCORE_DUPLICATE: i32 = -2;
+// score at or under which a peer is evicted to make room for another one when we are full
+pub const PEER_SCORE_EVICTION_THRESHOLD: i32 = -200;
+// score difference required for a candidate from our peerlist to replace our worst peer when we are full
+pub const PEER_SCORE_EVICTION_MARGIN: i32 = 50;
+
 // Hard Forks configured
 const HARD_FORKS: [HardFork; 2] = [
     HardFork {
@@ -237,6 +323,17 @@ const TESTNET_SEED_NODES: [&str; 1] = [
     "74.208.251.149:2125",
 ];
 
+// Mainnet DNS seeds
+// Each hostname is resolved to its A and AAAA records to find new peers
+// Seeds operators can rotate the nodes returned without a new release
+// No DNS seed is operated yet for the project, so the discovery by DNS
+// is only enabled when the seeds are provided using `--dns-seeds`
+const MAINNET_DNS_SEEDS: [&str; 0] = [];
+
+// Testnet DNS seeds
+// Empty for the same reason as the mainnet ones
+const TESTNET_DNS_SEEDS: [&str; 0] = [];
+
 // Genesis block to have the same starting point for every nodes
 // Genesis block in hexadecimal format
 const MAINNET_GENESIS_BLOCK: &str = "0000000000000000000000018efc057580000000000000000000000000000000000000000000000000000000000000000000000000000000000000006423b4908e5bd32241e3443fccfb7bab86a899a8cca12b3fedf255634d156d66";
@@ -281,6 +378,15 @@ pub const fn get_seed_nodes(network: &Network) -> &[&str] {
     }
 }
 
+// Get DNS seeds based on the network used
+pub const fn get_dns_seeds(network: &Network) -> &[&str] {
+    match network {
+        Network::Mainnet => &MAINNET_DNS_SEEDS,
+        Network::Testnet => &TESTNET_DNS_SEEDS,
+        Network::Dev => &[],
+    }
+}
+
 // Get minimum difficulty based on the network
 // Mainnet has a minimum difficulty to prevent spamming the network
 // Testnet has a lower difficulty to allow faster block generation
@@ -1,4 +1,4 @@
-use anyhow::Error;
+use anyhow::{Context, Error};
 use indexmap::IndexSet;
 use lru::LruCache;
 use serde_json::{Value, json};
@@ -8,7 +8,9 @@ use xelis_common::{
             BlockOrderedEvent,
             BlockOrphanedEvent,
             BlockType,
+            NodeMode,
             NotifyEvent,
+            RetentionPolicy,
             StableHeightChangedEvent,
             StableTopoHeightChangedEvent,
             TransactionExecutedEvent,
@@ -32,6 +34,7 @@ use xelis_common::{
         XELIS_ASSET
     },
     crypto::{
+        Address,
         Hash,
         Hashable,
         PublicKey,
@@ -55,11 +58,14 @@ use crate::{
     config::{
         get_genesis_block_hash, get_hex_genesis_block, get_minimum_difficulty,
         BLOCK_TIME_MILLIS, CHAIN_SYNC_RESPONSE_MAX_BLOCKS, CHAIN_SYNC_RESPONSE_MIN_BLOCKS,
-        DEFAULT_CACHE_SIZE, DEFAULT_P2P_BIND_ADDRESS, DEFAULT_RPC_BIND_ADDRESS, DEV_FEES,
+        DEFAULT_CACHE_SIZE, DEFAULT_MEMPOOL_MAX_SIZE, DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER,
+        DEFAULT_MEMPOOL_TX_MAX_AGE, DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE,
+        DEFAULT_P2P_BIND_ADDRESS, DEFAULT_RPC_BIND_ADDRESS, DEV_FEES,
         DEV_PUBLIC_KEY, EMISSION_SPEED_FACTOR, GENESIS_BLOCK_DIFFICULTY,
         MILLIS_PER_SECOND, P2P_DEFAULT_MAX_PEERS, SIDE_BLOCK_REWARD_MAX_BLOCKS, PRUNE_SAFETY_LIMIT,
         SIDE_BLOCK_REWARD_PERCENT, SIDE_BLOCK_REWARD_MIN_PERCENT, STABLE_LIMIT, TIMESTAMP_IN_FUTURE_LIMIT,
-        P2P_DEFAULT_CONCURRENCY_TASK_COUNT_LIMIT
+        P2P_DEFAULT_CONCURRENCY_TASK_COUNT_LIMIT, P2P_DEFAULT_CHAIN_REQUESTS_PER_MINUTE,
+        P2P_DEFAULT_OBJECT_REQUESTS_PER_MINUTE, P2P_DEFAULT_BOOTSTRAP_CHAIN_REQUESTS_PER_MINUTE
     },
     core::{
         blockdag,
@@ -71,9 +77,17 @@ use crate::{
         storage::{DagOrderProvider, DifficultyProvider, Storage},
         tx_selector::{TxSelector, TxSelectorEntry},
         state::{ChainState, ApplicableChainState},
+        state_tree::{build_state_tree, update_state_tree, StateTree},
+        retention::{prune_balances_history, prune_txs_in_range},
         hard_fork::*
     },
-    p2p::P2pServer,
+    p2p::{
+        bandwidth::RequestsLimits,
+        identity::parse_pinned_address,
+        proxy::{get_hostname_address, parse_hostname},
+        P2pConfig,
+        P2pServer
+    },
     rpc::{
         rpc::{
             get_block_type_for_block,
@@ -99,7 +113,7 @@ use std::{
     },
     time::Instant
 };
-use tokio::{sync::{Mutex, RwLock}, net::lookup_host};
+use tokio::{sync::{MappedMutexGuard, Mutex, MutexGuard, RwLock}, net::lookup_host};
 use log::{info, error, debug, warn, trace};
 use rand::Rng;
 
@@ -107,7 +121,8 @@ use super::storage::{
     BlocksAtHeightProvider,
     ClientProtocolProvider,
     PrunedTopoheightProvider,
-    AccountProvider
+    AccountProvider,
+    MempoolProvider
 };
 
 #[derive(Debug, clap::Args)]
@@ -115,23 +130,41 @@ pub struct Config {
     /// Optional node tag
     #[clap(long)]
     pub tag: Option<String>,
-    /// P2p bind address to listen for incoming connections
+    /// P2p bind address to listen for incoming connections.
+    /// 
+    /// Use [::]:2125 to accept both IPv4 and IPv6 connections.
     #[clap(long, default_value_t = String::from(DEFAULT_P2P_BIND_ADDRESS))]
     pub p2p_bind_address: String,
     /// Number of maximums peers allowed
     #[clap(long, default_value_t = P2P_DEFAULT_MAX_PEERS)]
     pub max_peers: usize,
+    /// Number of maximums outgoing peers, priority nodes excluded.
+    /// 
+    /// The remaining slots are reserved to incoming peers.
+    /// By default, it is 8 capped to half of the maximum peers.
+    /// If set, it can't be more than half of the maximum peers, otherwise the daemon is not started.
+    #[clap(long)]
+    pub max_outgoing_peers: Option<usize>,
     /// Rpc bind address to listen for HTTP requests
     #[clap(long, default_value_t = String::from(DEFAULT_RPC_BIND_ADDRESS))]
     pub rpc_bind_address: String,
     /// Add a priority node to connect when P2p is started.
     /// A priority node is connected only one time.
+    /// Its public key can be pinned using the format <key>@<address>.
     #[clap(long)]
     pub priority_nodes: Vec<String>,
     /// An exclusive node is connected and its connection is maintained in case of disconnect
     /// it also replaces seed nodes.
+    /// Its public key can be pinned using the format <key>@<address>.
+    /// For incoming connections, the pinned key is matched by IP only as the remote port is not the P2p port.
     #[clap(long)]
     pub exclusive_nodes: Vec<String>,
+    /// DNS seed resolved to find new peers at startup and when the peerlist is running low.
+    /// 
+    /// Use the format host:port, all the A and AAAA records are used.
+    /// No default DNS seed exists yet, so this option is required to use the DNS discovery.
+    #[clap(long)]
+    pub dns_seeds: Vec<String>,
     /// Set dir path for blockchain storage.
     /// This will be appended by the network name for the database directory.
     /// It must ends with a slash.
@@ -162,6 +195,20 @@ pub struct Config {
     /// before the top.
     #[clap(long)]
     pub auto_prune_keep_n_blocks: Option<u64>,
+    /// Keep the transactions of the last N blocks only.
+    /// 
+    /// Blocks headers are always kept, only the transactions bodies are deleted.
+    #[clap(long)]
+    pub keep_txs_n_blocks: Option<u64>,
+    /// Keep the balances history of the last N blocks only.
+    /// 
+    /// Balances at or above the pruned topoheight are still available.
+    #[clap(long)]
+    pub keep_balances_history_n_blocks: Option<u64>,
+    /// Accounts for which the full balances history is kept
+    /// when the balances history retention is enabled.
+    #[clap(long)]
+    pub balances_history_whitelist: Vec<String>,
     /// Allow fast sync mode.
     /// 
     /// Sync a bootstrapped chain if your local copy is outdated.
@@ -173,7 +220,7 @@ pub struct Config {
     pub allow_fast_sync: bool,
     /// Allow boost chain sync mode.
     /// 
-    /// This will request in parallel all blocks instead of sequentially.
+    /// This will validate all blocks headers first, then request in parallel the blocks to all peers instead of sequentially.
     /// 
     /// It is not enabled by default because it will requests several blocks before validating each previous.
     #[clap(long)]
@@ -197,12 +244,107 @@ pub struct Config {
     /// This is useful for seed nodes under heavy load or for nodes that don't want to connect to others.
     #[clap(long)]
     pub disable_p2p_outgoing_connections: bool,
+    /// Enable the Dandelion++ propagation of transactions.
+    /// 
+    /// New transactions are first relayed along a random path of single peers before being broadcasted to all peers.
+    /// This hides which node has seen a transaction first.
+    #[clap(long)]
+    pub enable_dandelion: bool,
     /// Limit of concurrent tasks accepting new incoming connections.
     #[clap(long, default_value_t = P2P_DEFAULT_CONCURRENCY_TASK_COUNT_LIMIT)]
     pub p2p_concurrency_task_count_limit: usize,
+    /// Maximum count of chain requests accepted per minute from a peer.
+    /// 
+    /// A peer sending more is disconnected and graylisted. Set to 0 to disable the limit.
+    #[clap(long, default_value_t = P2P_DEFAULT_CHAIN_REQUESTS_PER_MINUTE)]
+    pub p2p_chain_requests_per_minute: u32,
+    /// Maximum count of object requests accepted per minute from a peer.
+    /// 
+    /// A peer sending more is disconnected and graylisted. Set to 0 to disable the limit.
+    #[clap(long, default_value_t = P2P_DEFAULT_OBJECT_REQUESTS_PER_MINUTE)]
+    pub p2p_object_requests_per_minute: u32,
+    /// Maximum count of bootstrap chain requests accepted per minute from a peer.
+    /// 
+    /// A peer sending more is disconnected and graylisted. Set to 0 to disable the limit.
+    #[clap(long, default_value_t = P2P_DEFAULT_BOOTSTRAP_CHAIN_REQUESTS_PER_MINUTE)]
+    pub p2p_bootstrap_chain_requests_per_minute: u32,
+    /// SOCKS5 proxy used for all outgoing P2P connections.
+    /// 
+    /// Use the format socks5://[username:password@]host:port, for example socks5://127.0.0.1:9050 for Tor.
+    /// Priority and exclusive nodes can then be set using a hostname like an onion address.
+    #[clap(long)]
+    pub p2p_proxy: Option<String>,
+    /// Hostname shared to others peers to connect to us through a proxy.
+    /// 
+    /// Use the format host:port, for example your onion address with the P2P port.
+    #[clap(long)]
+    pub p2p_advertised_hostname: Option<String>,
     /// Skip the TXs verification when building a block template.
     #[clap(long)]
-    pub skip_block_template_txs_verification: bool
+    pub skip_block_template_txs_verification: bool,
+    /// Maximum size in bytes of all the transactions in mempool.
+    /// 
+    /// When it is reached, the transactions chains with the lowest fee per KB are evicted.
+    #[clap(long, default_value_t = DEFAULT_MEMPOOL_MAX_SIZE)]
+    pub mempool_max_size: usize,
+    /// Maximum count of transactions in mempool for a same sender.
+    #[clap(long, default_value_t = DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER)]
+    pub mempool_max_txs_per_sender: usize,
+    /// Maximum time in seconds a transaction can stay in mempool.
+    /// 
+    /// Expired transactions are removed with the next transactions of the same sender.
+    #[clap(long, default_value_t = DEFAULT_MEMPOOL_TX_MAX_AGE)]
+    pub mempool_tx_max_age: u64,
+    /// Maximum distance in topoheight between the reference of a transaction in mempool and the chain top.
+    /// 
+    /// Transactions with an older reference are removed like expired ones.
+    #[clap(long, default_value_t = DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE)]
+    pub mempool_max_reference_distance: u64
+}
+
+impl Config {
+    // Config with all the network modules disabled and without PoW verification
+    // This is used for in-memory chains that only execute already known blocks
+    pub fn headless() -> Self {
+        Self {
+            tag: None,
+            p2p_bind_address: DEFAULT_P2P_BIND_ADDRESS.to_owned(),
+            max_peers: P2P_DEFAULT_MAX_PEERS,
+            max_outgoing_peers: None,
+            rpc_bind_address: DEFAULT_RPC_BIND_ADDRESS.to_owned(),
+            priority_nodes: Vec::new(),
+            exclusive_nodes: Vec::new(),
+            dns_seeds: Vec::new(),
+            dir_path: None,
+            cache_size: DEFAULT_CACHE_SIZE,
+            disable_getwork_server: true,
+            disable_rpc_server: true,
+            simulator: None,
+            skip_pow_verification: true,
+            disable_p2p_server: true,
+            auto_prune_keep_n_blocks: None,
+            keep_txs_n_blocks: None,
+            keep_balances_history_n_blocks: None,
+            balances_history_whitelist: Vec::new(),
+            allow_fast_sync: false,
+            allow_boost_sync: false,
+            max_chain_response_size: None,
+            disable_ip_sharing: false,
+            disable_p2p_outgoing_connections: true,
+            enable_dandelion: false,
+            p2p_concurrency_task_count_limit: P2P_DEFAULT_CONCURRENCY_TASK_COUNT_LIMIT,
+            p2p_chain_requests_per_minute: P2P_DEFAULT_CHAIN_REQUESTS_PER_MINUTE,
+            p2p_object_requests_per_minute: P2P_DEFAULT_OBJECT_REQUESTS_PER_MINUTE,
+            p2p_bootstrap_chain_requests_per_minute: P2P_DEFAULT_BOOTSTRAP_CHAIN_REQUESTS_PER_MINUTE,
+            p2p_proxy: None,
+            p2p_advertised_hostname: None,
+            skip_block_template_txs_verification: false,
+            mempool_max_size: DEFAULT_MEMPOOL_MAX_SIZE,
+            mempool_max_txs_per_sender: DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER,
+            mempool_tx_max_age: DEFAULT_MEMPOOL_TX_MAX_AGE,
+            mempool_max_reference_distance: DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE
+        }
+    }
 }
 
 pub struct Blockchain<S: Storage> {
@@ -242,11 +384,47 @@ pub struct Blockchain<S: Storage> {
     // using base hash, current tip hash and base height, this cache is used to store the DAG order
     full_order_cache: Mutex<LruCache<(Hash, Hash, u64), IndexSet<Hash>>>,
     // auto prune mode if enabled, will delete all blocks every N and keep only N top blocks (topoheight based)
-    auto_prune_keep_n_blocks: Option<u64>
+    auto_prune_keep_n_blocks: Option<u64>,
+    // if enabled, will delete the txs bodies every N and keep only the txs of the N top blocks
+    keep_txs_n_blocks: Option<u64>,
+    // if enabled, will delete the balances history every N and keep only the history of the N top blocks
+    keep_balances_history_n_blocks: Option<u64>,
+    // accounts for which we keep the full balances history
+    balances_history_whitelist: IndexSet<PublicKey>,
+    // state tree kept in memory with its stable topoheight
+    // it is moved forward with the changes of the next topoheights instead of being rebuilt
+    state_tree: Mutex<(Option<u64>, StateTree)>
+}
+
+// Side effects of a processed block
+// They are kept until the block changes are written in the storage
+struct BlockSideEffects {
+    block_hash: Hash,
+    block: Arc<BlockHeader>,
+    txs: Vec<Immutable<Transaction>>,
+    block_size: usize,
+    cumulative_difficulty: CumulativeDifficulty,
+    current_topoheight: u64,
+    current_height: u64,
+    base_topo_height: u64,
+    highest_topo: u64,
+    // transactions to add back in mempool
+    orphaned_transactions: HashSet<Hash>,
+    should_track_events: HashSet<NotifyEvent>,
+    // events to notify to websocket clients
+    events: HashMap<NotifyEvent, Vec<Value>>,
+    broadcast: bool,
+    mining: bool
 }
 
 impl<S: Storage> Blockchain<S> {
     pub async fn new(config: Config, network: Network, storage: S) -> Result<Arc<Self>, Error> {
+        Self::with_genesis(config, network, storage, None).await
+    }
+
+    // Same as `new` but the genesis block can be provided for networks without a hardcoded one
+    // It is only used if the storage is empty
+    pub async fn with_genesis(config: Config, network: Network, storage: S, genesis: Option<Block>) -> Result<Arc<Self>, Error> {
         // Do some checks on config params
         {
             if config.simulator.is_some() && network != Network::Dev {
@@ -261,6 +439,17 @@ impl<S: Storage> Blockchain<S> {
                 }
             }
 
+            for keep_only in [config.keep_txs_n_blocks, config.keep_balances_history_n_blocks].into_iter().flatten() {
+                if keep_only < PRUNE_SAFETY_LIMIT {
+                    error!("Retention policy should keep at least {} blocks", PRUNE_SAFETY_LIMIT);
+                    return Err(BlockchainError::RetentionPolicy.into())
+                }
+            }
+
+            if config.keep_balances_history_n_blocks.is_none() && !config.balances_history_whitelist.is_empty() {
+                warn!("Balances history whitelist is set but balances history retention is disabled");
+            }
+
             if let Some(size) = config.max_chain_response_size {
                 if size < CHAIN_SYNC_RESPONSE_MIN_BLOCKS || size > CHAIN_SYNC_RESPONSE_MAX_BLOCKS {
                     error!("Max chain response size should be in inclusive range of [{}-{}]", CHAIN_SYNC_RESPONSE_MIN_BLOCKS, CHAIN_SYNC_RESPONSE_MAX_BLOCKS);
@@ -278,6 +467,16 @@ impl<S: Storage> Blockchain<S> {
             }
         }
 
+        let mut balances_history_whitelist = IndexSet::new();
+        for address in config.balances_history_whitelist.iter() {
+            let address = Address::from_string(address).context("Invalid address in balances history whitelist")?;
+            if address.is_mainnet() != network.is_mainnet() {
+                error!("Address {} in balances history whitelist is not on the same network", address);
+                return Err(BlockchainError::InvalidNetwork.into())
+            }
+            balances_history_whitelist.insert(address.to_public_key());
+        }
+
         let on_disk = storage.has_blocks().await;
         let (height, topoheight) = if on_disk {
             info!("Reading last metadata available...");
@@ -293,7 +492,7 @@ impl<S: Storage> Blockchain<S> {
             topoheight: AtomicU64::new(topoheight),
             stable_height: AtomicU64::new(0),
             stable_topoheight: AtomicU64::new(0),
-            mempool: RwLock::new(Mempool::new(network)),
+            mempool: RwLock::new(Mempool::new(network, config.mempool_max_size, config.mempool_max_txs_per_sender, config.mempool_tx_max_age, config.mempool_max_reference_distance)),
             storage: RwLock::new(storage),
             p2p: RwLock::new(None),
             rpc: RwLock::new(None),
@@ -305,12 +504,16 @@ impl<S: Storage> Blockchain<S> {
             tip_work_score_cache: Mutex::new(LruCache::new(NonZeroUsize::new(1024).unwrap())),
             full_order_cache: Mutex::new(LruCache::new(NonZeroUsize::new(1024).unwrap())),
             auto_prune_keep_n_blocks: config.auto_prune_keep_n_blocks,
+            keep_txs_n_blocks: config.keep_txs_n_blocks,
+            keep_balances_history_n_blocks: config.keep_balances_history_n_blocks,
+            balances_history_whitelist,
+            state_tree: Mutex::new((None, StateTree::new())),
             skip_block_template_txs_verification: config.skip_block_template_txs_verification
         };
 
         // include genesis block
         if !on_disk {
-            blockchain.create_genesis_block().await?;
+            blockchain.create_genesis_block(genesis).await?;
         } else {
             debug!("Retrieving tips for computing current difficulty");
             let storage = blockchain.get_storage().read().await;
@@ -331,55 +534,143 @@ impl<S: Storage> Blockchain<S> {
             blockchain.stable_topoheight.store(stable_topoheight, Ordering::SeqCst);
         }
 
+        // reload the mempool TXs saved at last stop
+        blockchain.load_mempool_from_storage().await?;
+
         let arc = Arc::new(blockchain);
         // create P2P Server
         if !config.disable_p2p_server {
             info!("Starting P2p server...");
             // setup exclusive nodes
+            // each node can be prefixed by its public key to pin it
+            let mut pinned_keys = HashMap::new();
+            // nodes set using a hostname, only reachable through the proxy
+            let use_proxy = config.p2p_proxy.is_some();
+            let mut hostnames = HashMap::new();
             let mut exclusive_nodes: Vec<SocketAddr> = Vec::with_capacity(config.exclusive_nodes.len());
             for peer in config.exclusive_nodes {
                 for peer in peer.split(",") {
-                    let addr: SocketAddr = match peer.parse() {
-                        Ok(addr) => addr,
+                    let (peer, key) = match parse_pinned_address(peer) {
+                        Ok(v) => v,
                         Err(e) => {
-                            error!("Error while parsing exclusive node address: {}", e);
+                            error!("Error while parsing exclusive node key: {}", e);
                             continue;
                         }
                     };
+
+                    let addr: SocketAddr = match peer.parse() {
+                        Ok(addr) => addr,
+                        Err(e) => match parse_hostname(peer) {
+                            Ok((host, port)) if use_proxy => {
+                                let addr = get_hostname_address(&host, port);
+                                hostnames.insert(addr, host);
+                                addr
+                            },
+                            _ => {
+                                error!("Error while parsing exclusive node address: {}", e);
+                                continue;
+                            }
+                        }
+                    };
+
+                    if let Some(key) = key {
+                        pinned_keys.insert(addr, key);
+                    }
                     exclusive_nodes.push(addr);
                 }
             }
 
-            match P2pServer::new(config.p2p_concurrency_task_count_limit, config.dir_path, config.tag, config.max_peers, config.p2p_bind_address, Arc::clone(&arc), exclusive_nodes.is_empty(), exclusive_nodes, config.allow_fast_sync, config.allow_boost_sync, config.max_chain_response_size, !config.disable_ip_sharing, config.disable_p2p_outgoing_connections) {
-                Ok(p2p) => {
-                    // connect to priority nodes
-                    for addr in config.priority_nodes {
-                        for addr in addr.split(",") {
-                            let addr: SocketAddr = match addr.parse() {
-                                Ok(addr) => addr,
-                                Err(e) => {
-                                    match lookup_host(&addr).await {
-                                        Ok(it) => {
-                                            info!("Valid host found for {}", addr);
-                                            for addr in it {
-                                                info!("Trying to connect to priority node with IP from DNS resolution: {}", addr);
-                                                p2p.try_to_connect_to_peer(addr, true).await;
-                                            }
-                                        },
-                                        Err(e2) => {
-                                            error!("Error while parsing priority node address: {}, {}", e, e2);
-                                        }
-                                    };
+            // setup priority nodes, resolving the hosts before starting the server to pin their keys
+            let mut priority_nodes: Vec<SocketAddr> = Vec::with_capacity(config.priority_nodes.len());
+            for addr in config.priority_nodes {
+                for addr in addr.split(",") {
+                    let (addr, key) = match parse_pinned_address(addr) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            error!("Error while parsing priority node key: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let addrs: Vec<SocketAddr> = match addr.parse::<SocketAddr>() {
+                        Ok(addr) => vec![addr],
+                        // the host is resolved by the proxy to not leak any DNS request
+                        Err(_) if use_proxy => match parse_hostname(addr) {
+                            Ok((host, port)) => {
+                                let addr = get_hostname_address(&host, port);
+                                hostnames.insert(addr, host);
+                                vec![addr]
+                            },
+                            Err(e) => {
+                                error!("Error while parsing priority node address: {}", e);
+                                continue;
+                            }
+                        },
+                        Err(e) => {
+                            match lookup_host(addr).await {
+                                Ok(it) => {
+                                    info!("Valid host found for {}", addr);
+                                    it.collect()
+                                },
+                                Err(e2) => {
+                                    error!("Error while parsing priority node address: {}, {}", e, e2);
                                     continue;
                                 }
-                            };
-                            info!("Trying to connect to priority node: {}", addr);
-                            p2p.try_to_connect_to_peer(addr, true).await;
+                            }
                         }
+                    };
+
+                    for addr in addrs {
+                        if let Some(key) = key {
+                            pinned_keys.insert(addr, key);
+                        }
+                        priority_nodes.push(addr);
+                    }
+                }
+            }
+
+            let requests_limits = RequestsLimits {
+                chain_requests: config.p2p_chain_requests_per_minute,
+                object_requests: config.p2p_object_requests_per_minute,
+                bootstrap_chain_requests: config.p2p_bootstrap_chain_requests_per_minute
+            };
+
+            let p2p_config = P2pConfig {
+                concurrency: config.p2p_concurrency_task_count_limit,
+                dir_path: config.dir_path,
+                tag: config.tag,
+                max_peers: config.max_peers,
+                max_outgoing_peers: config.max_outgoing_peers,
+                bind_address: config.p2p_bind_address,
+                use_peerlist: exclusive_nodes.is_empty(),
+                exclusive_nodes,
+                pinned_keys,
+                allow_fast_sync_mode: config.allow_fast_sync,
+                allow_boost_sync_mode: config.allow_boost_sync,
+                max_chain_response_size: config.max_chain_response_size,
+                sharable: !config.disable_ip_sharing,
+                disable_outgoing_connections: config.disable_p2p_outgoing_connections,
+                enable_dandelion: config.enable_dandelion,
+                requests_limits,
+                proxy: config.p2p_proxy,
+                hostnames,
+                advertised_hostname: config.p2p_advertised_hostname,
+                dns_seeds: config.dns_seeds
+            };
+
+            match P2pServer::new(p2p_config, Arc::clone(&arc)) {
+                Ok(p2p) => {
+                    // connect to priority nodes
+                    for addr in priority_nodes {
+                        info!("Trying to connect to priority node: {}", addr);
+                        p2p.try_to_connect_to_peer(addr, true).await;
                     }
                     *arc.p2p.write().await = Some(p2p);
                 },
-                Err(e) => error!("Error while starting P2p server: {}", e)
+                Err(e) => {
+                    error!("Error while starting P2p server: {}", e);
+                    return Err(BlockchainError::from(e).into())
+                }
             };
         }
 
@@ -404,6 +695,42 @@ impl<S: Storage> Blockchain<S> {
         Ok(arc)
     }
 
+    // Reload the mempool TXs saved in storage when the node was stopped
+    // Every TX is verified again against the current chain state
+    // and dropped if it's no longer valid
+    async fn load_mempool_from_storage(&self) -> Result<(), BlockchainError> {
+        let mut storage = self.storage.write().await;
+        let mut txs = storage.get_mempool_txs().await?;
+        if txs.is_empty() {
+            return Ok(())
+        }
+
+        info!("Reloading {} TXs in mempool", txs.len());
+        // Add them by nonce so each sender's TXs are verified in order
+        txs.sort_by_key(|(_, tx, _)| tx.get_nonce());
+
+        // Each TX is only deleted once reloaded or rejected
+        // so an interrupted reload keeps the remaining ones
+        let mut dropped = 0;
+        for (hash, tx, first_seen) in txs {
+            match self.add_tx_to_mempool_with_storage_and_hash(&*storage, Arc::new(tx), hash.clone(), false).await {
+                Ok(()) => self.mempool.write().await.set_first_seen(&hash, first_seen)?,
+                Err(e) => {
+                    debug!("Dropping TX {} saved in mempool: {}", hash, e);
+                    dropped += 1;
+                }
+            }
+
+            storage.delete_mempool_tx(&hash).await?;
+        }
+
+        if dropped > 0 {
+            warn!("{} TXs saved in mempool are no longer valid and were dropped", dropped);
+        }
+
+        Ok(())
+    }
+
     // Detect if the simulator task has been started
     pub fn is_simulator_enabled(&self) -> bool {
         self.simulator.is_some()
@@ -435,6 +762,17 @@ impl<S: Storage> Blockchain<S> {
 
         {
             let mut storage = self.storage.write().await;
+            // save the mempool TXs to reload them at next start
+            {
+                let mempool = self.mempool.read().await;
+                info!("Saving {} TXs from mempool", mempool.size());
+                for (hash, sorted_tx) in mempool.get_txs() {
+                    if let Err(e) = storage.save_mempool_tx(hash, sorted_tx.get_tx(), sorted_tx.get_first_seen()).await {
+                        error!("Error while saving TX {} from mempool: {}", hash, e);
+                    }
+                }
+            }
+
             if let Err(e) = storage.stop().await {
                 error!("Error while stopping storage: {}", e);
             }
@@ -467,6 +805,9 @@ impl<S: Storage> Blockchain<S> {
         let stable_topoheight = storage.get_topo_height_for_hash(&stable_hash).await?;
         self.stable_topoheight.store(stable_topoheight, Ordering::SeqCst);
 
+        // The state may have been replaced, the state tree must be built again
+        *self.state_tree.lock().await = (None, StateTree::new());
+
         // Recompute the difficulty with new tips
         let (difficulty, _) = self.get_difficulty_at_tips(&*storage, tips.iter()).await?;
         self.set_difficulty(difficulty).await;
@@ -481,7 +822,7 @@ impl<S: Storage> Blockchain<S> {
     }
 
     // function to include the genesis block and register the public dev key.
-    async fn create_genesis_block(&self) -> Result<(), BlockchainError> {
+    async fn create_genesis_block(&self, genesis: Option<Block>) -> Result<(), BlockchainError> {
         let mut storage = self.storage.write().await;
 
         // register XELIS asset
@@ -503,6 +844,14 @@ impl<S: Storage> Blockchain<S> {
             }
 
             (genesis, expected_hash)
+        } else if let Some(genesis) = genesis {
+            info!("Using the genesis block provided...");
+            if genesis.get_height() != 0 || !genesis.get_tips().is_empty() {
+                return Err(BlockchainError::InvalidGenesisHash)
+            }
+
+            let genesis_hash = genesis.hash();
+            (genesis, genesis_hash)
         } else {
             warn!("No genesis block found!");
             info!("Generating a new genesis block...");
@@ -613,6 +962,93 @@ impl<S: Storage> Blockchain<S> {
         }
     }
 
+    // Verify that we can delete data until topoheight while keeping a marge of PRUNE_SAFETY_LIMIT
+    fn verify_retention_topoheight(&self, topoheight: u64) -> Result<(), BlockchainError> {
+        if topoheight == 0 {
+            return Err(BlockchainError::PruneZero)
+        }
+
+        let current_topoheight = self.get_topo_height();
+        if topoheight >= current_topoheight || current_topoheight - topoheight < PRUNE_SAFETY_LIMIT {
+            return Err(BlockchainError::PruneHeightTooHigh)
+        }
+
+        Ok(())
+    }
+
+    // delete all txs bodies included in blocks until topoheight in param
+    // blocks headers are kept, so the chain can still be verified and served as headers
+    pub async fn prune_txs_until_topoheight_for_storage(&self, topoheight: u64, storage: &mut S) -> Result<u64, BlockchainError> {
+        self.verify_retention_topoheight(topoheight)?;
+
+        // blocks under the pruned topoheight are already deleted
+        let pruned_topoheight = storage.get_pruned_topoheight().await?.unwrap_or(0);
+        let last_pruned_topoheight = storage.get_txs_pruned_topoheight().await?.unwrap_or(0).max(pruned_topoheight);
+        if topoheight <= last_pruned_topoheight {
+            debug!("txs are already pruned until topoheight {}", last_pruned_topoheight);
+            return Ok(last_pruned_topoheight)
+        }
+
+        let count = prune_txs_in_range(storage, last_pruned_topoheight, topoheight).await?;
+        debug!("Pruned {} txs until topoheight {}", count, topoheight);
+        storage.set_txs_pruned_topoheight(topoheight).await?;
+        Ok(topoheight)
+    }
+
+    // delete all the balances history until topoheight in param, except for whitelisted accounts
+    // balances at or above the topoheight are still available
+    pub async fn prune_balances_history_until_topoheight_for_storage(&self, topoheight: u64, storage: &mut S) -> Result<u64, BlockchainError> {
+        self.verify_retention_topoheight(topoheight)?;
+
+        let last_pruned_topoheight = storage.get_balances_pruned_topoheight().await?.unwrap_or(0);
+        if topoheight <= last_pruned_topoheight {
+            debug!("balances history is already pruned until topoheight {}", last_pruned_topoheight);
+            return Ok(last_pruned_topoheight)
+        }
+
+        // Versions below the pruned topoheight are already deleted
+        let from = last_pruned_topoheight.max(storage.get_pruned_topoheight().await?.unwrap_or(0));
+        let count = prune_balances_history(storage, from, topoheight, &self.balances_history_whitelist).await?;
+        debug!("Pruned {} versioned balances until topoheight {}", count, topoheight);
+        storage.set_balances_pruned_topoheight(topoheight).await?;
+        Ok(topoheight)
+    }
+
+    // Topoheight under which we can't provide full blocks to peers
+    // Blocks with pruned txs are considered as pruned for them
+    pub async fn get_advertised_pruned_topoheight(&self, storage: &S) -> Result<Option<u64>, BlockchainError> {
+        let pruned_topoheight = storage.get_pruned_topoheight().await?;
+        let txs_pruned_topoheight = storage.get_txs_pruned_topoheight().await?;
+        Ok(pruned_topoheight.max(txs_pruned_topoheight))
+    }
+
+    // Get the data retention policy of this node
+    pub async fn get_retention_policy(&self, storage: &S) -> Result<RetentionPolicy, BlockchainError> {
+        let pruned_topoheight = storage.get_pruned_topoheight().await?;
+        let txs_pruned_topoheight = storage.get_txs_pruned_topoheight().await?;
+        let balances_pruned_topoheight = storage.get_balances_pruned_topoheight().await?;
+
+        let archive = pruned_topoheight.is_none()
+            && txs_pruned_topoheight.is_none()
+            && balances_pruned_topoheight.is_none()
+            && self.auto_prune_keep_n_blocks.is_none()
+            && self.keep_txs_n_blocks.is_none()
+            && self.keep_balances_history_n_blocks.is_none();
+
+        let mainnet = self.network.is_mainnet();
+        Ok(RetentionPolicy {
+            mode: if archive { NodeMode::Archive } else { NodeMode::Pruned },
+            keep_blocks: self.auto_prune_keep_n_blocks,
+            keep_txs: self.keep_txs_n_blocks,
+            txs_pruned_topoheight,
+            keep_balances_history: self.keep_balances_history_n_blocks,
+            balances_pruned_topoheight,
+            balances_history_whitelist: self.balances_history_whitelist.iter()
+                .map(|key| key.as_address(mainnet))
+                .collect()
+        })
+    }
+
     // determine the topoheight of the nearest sync block until limit topoheight
     pub async fn locate_nearest_sync_block_for_topoheight<P>(&self, provider: &P, mut topoheight: u64, current_height: u64) -> Result<u64, BlockchainError>
     where
@@ -683,6 +1119,58 @@ impl<S: Storage> Blockchain<S> {
         storage.get_hash_at_topo_height(self.get_topo_height()).await
     }
 
+    // Move the state tree kept in memory forward until the stable topoheight
+    // The root of each topoheight reached is stored, so they never have to be computed again
+    // The tree is only built from the whole state when it is not loaded yet or was invalidated
+    async fn store_state_roots_until_topoheight(&self, storage: &mut S, stable_topoheight: u64) -> Result<(), BlockchainError> {
+        let mut state_tree = self.state_tree.lock().await;
+        let (tree_topoheight, tree) = &mut *state_tree;
+        // Taken until the tree is updated, so an error can't leave it half updated
+        match tree_topoheight.take() {
+            Some(from_topoheight) if from_topoheight <= stable_topoheight => {
+                for topoheight in from_topoheight + 1..=stable_topoheight {
+                    update_state_tree(tree, storage, topoheight).await?;
+                    storage.set_state_root_at_topoheight(topoheight, &tree.root()?).await?;
+                }
+            },
+            _ => {
+                *tree = build_state_tree(storage, stable_topoheight).await?;
+                storage.set_state_root_at_topoheight(stable_topoheight, &tree.root()?).await?;
+            }
+        }
+        *tree_topoheight = Some(stable_topoheight);
+
+        Ok(())
+    }
+
+    // Get the state tree kept in memory with the stable topoheight it is at
+    // Proofs can only be built against it, any older state would require to build the whole tree again
+    pub async fn get_state_tree(&self) -> Result<(u64, MappedMutexGuard<'_, StateTree>), BlockchainError> {
+        let state_tree = self.state_tree.lock().await;
+        let topoheight = state_tree.0.ok_or_else(|| BlockchainError::StateNotAvailable(self.get_stable_topoheight()))?;
+
+        Ok((topoheight, MutexGuard::map(state_tree, |(_, tree)| tree)))
+    }
+
+    // Forget the state tree kept in memory if it contains changes above this topoheight
+    async fn invalidate_state_tree_above(&self, topoheight: u64) {
+        let mut state_tree = self.state_tree.lock().await;
+        if state_tree.0.is_some_and(|tree_topoheight| tree_topoheight > topoheight) {
+            *state_tree = (None, StateTree::new());
+        }
+    }
+
+    // Get the state tree root at a stable topoheight
+    // Only the roots stored when the topoheight became stable are available
+    pub async fn get_state_root_at_topoheight(&self, topoheight: u64) -> Result<Hash, BlockchainError> {
+        let storage = self.storage.read().await;
+        if !storage.has_state_root_at_topoheight(topoheight).await? {
+            return Err(BlockchainError::StateNotAvailable(topoheight))
+        }
+
+        storage.get_state_root_at_topoheight(topoheight).await
+    }
+
     // Verify if we have the current block in storage by locking it ourself
     pub async fn has_block(&self, hash: &Hash) -> Result<bool, BlockchainError> {
         let storage = self.storage.read().await;
@@ -1306,6 +1794,28 @@ impl<S: Storage> Blockchain<S> {
         self.add_tx_to_mempool_with_storage_and_hash(&*storage, Arc::new(tx), hash, broadcast).await
     }
 
+    // Verify a TX like it would be added to the mempool, but without adding it
+    // Used by the TXs which must stay hidden from others peers, like the Dandelion stem TXs
+    pub async fn verify_tx_for_mempool(&self, tx: &Transaction, hash: &Hash) -> Result<(), BlockchainError> {
+        let tx_size = tx.size();
+        if tx_size > MAX_TRANSACTION_SIZE {
+            return Err(BlockchainError::TxTooBig(tx_size, MAX_TRANSACTION_SIZE))
+        }
+
+        let storage = self.storage.read().await;
+        let mempool = self.mempool.read().await;
+        if mempool.contains_tx(hash) {
+            return Err(BlockchainError::TxAlreadyInMempool(hash.clone()))
+        }
+
+        if storage.is_tx_executed_in_a_block(hash)? {
+            return Err(BlockchainError::TxAlreadyInBlockchain(hash.clone()))
+        }
+
+        let version = get_version_at_height(self.get_network(), self.get_height());
+        mempool.verify_tx(&*storage, self.get_stable_topoheight(), self.get_topo_height(), tx, version).await
+    }
+
     // Add a tx to the mempool with the given hash, it will verify the TX and check that it is not already in mempool or in blockchain
     // and its validity (nonce, balance, etc...)
     pub async fn add_tx_to_mempool_with_storage_and_hash<'a>(&'a self, storage: &S, tx: Arc<Transaction>, hash: Hash, broadcast: bool) -> Result<(), BlockchainError> {
@@ -1314,7 +1824,7 @@ impl<S: Storage> Blockchain<S> {
             return Err(BlockchainError::TxTooBig(tx_size, MAX_TRANSACTION_SIZE))
         }
 
-        {
+        let evicted_txs = {
             let mut mempool = self.mempool.write().await;
     
             if mempool.contains_tx(&hash) {
@@ -1330,13 +1840,8 @@ impl<S: Storage> Blockchain<S> {
             let current_topoheight = self.get_topo_height();
             // get the highest nonce available
             // if presents, it means we have at least one tx from this owner in mempool
+            // A TX with the same nonce already in mempool can be replaced if the new one pays enough fees
             if let Some(cache) = mempool.get_cache_for(tx.get_source()) {
-                // we accept to delete a tx from mempool if the new one has a higher fee
-                if let Some(hash) = cache.has_tx_with_same_nonce(tx.get_nonce()) {
-                    // A TX with the same nonce is already in mempool
-                    return Err(BlockchainError::TxNonceAlreadyUsed(tx.get_nonce(), hash.as_ref().clone()))
-                }
-
                 // check that the nonce is in the range
                 if !(tx.get_nonce() <= cache.get_max() + 1 && tx.get_nonce() >= cache.get_min()) {
                     debug!("TX {} nonce is not in the range of the pending TXs for this owner, received: {}, expected between {} and {}", hash, tx.get_nonce(), cache.get_min(), cache.get_max());
@@ -1345,7 +1850,34 @@ impl<S: Storage> Blockchain<S> {
             }
 
             let version = get_version_at_height(self.get_network(), self.get_height());
-            mempool.add_tx(storage, stable_topoheight, current_topoheight, hash.clone(), tx.clone(), tx_size, version).await?;
+            mempool.add_tx(storage, stable_topoheight, current_topoheight, hash.clone(), tx.clone(), tx_size, version).await?
+        };
+
+        if !evicted_txs.is_empty() {
+            debug!("{} TXs evicted from mempool to add TX {}", evicted_txs.len(), hash);
+            if let Some(rpc) = self.rpc.read().await.as_ref() {
+                if rpc.is_event_tracked(&NotifyEvent::TransactionEvicted).await {
+                    let events: Vec<Value> = evicted_txs.iter().map(|(tx_hash, sorted_tx)| {
+                        let data = RPCTransaction::from_tx(sorted_tx.get_tx(), tx_hash, storage.is_mainnet());
+                        json!(TransactionResponse {
+                            blocks: None,
+                            executed_in_block: None,
+                            in_mempool: false,
+                            first_seen: Some(sorted_tx.get_first_seen()),
+                            data,
+                        })
+                    }).collect();
+
+                    let rpc = rpc.clone();
+                    spawn_task("rpc-notify-tx-evicted", async move {
+                        for event in events {
+                            if let Err(e) = rpc.notify_clients(&NotifyEvent::TransactionEvicted, event).await {
+                                debug!("Error while broadcasting event TransactionEvicted to websocket: {}", e);
+                            }
+                        }
+                    });
+                }
+            }
         }
 
         if broadcast {
@@ -1584,49 +2116,58 @@ impl<S: Storage> Blockchain<S> {
         let mut chain_state = ChainState::new(storage, stable_topoheight, topoheight, block.get_version());
 
         let mut failed_sources = HashSet::new();
-        while let Some(TxSelectorEntry { size, hash, tx }) = tx_selector.next() {
-            if block_size + total_txs_size + size >= MAX_BLOCK_SIZE {
-                break;
+        while let Some(package) = tx_selector.next_package() {
+            // All the TXs of a package are from the same source
+            let source = package[0].tx.get_source();
+            if failed_sources.contains(&source) {
+                debug!("Skipping package of {} TXs because its source has failed before", package.len());
+                continue;
             }
 
-            if orphaned_blocks.is_none() {
-                let blocks = self.get_orphaned_blocks_for_tips_until_height(storage, block.get_tips().iter().cloned(), stable_height).await?;
-                warn!("Found {} orphaned blocks linked for block template", blocks.len());
-                orphaned_blocks = Some(blocks);
+            // The whole package must fit in the block, otherwise its source is skipped
+            // as its next TXs depend on it, but smaller packages may still fit
+            let package_size: usize = package.iter().map(|entry| entry.size + HASH_SIZE).sum();
+            if block_size + total_txs_size + package_size >= MAX_BLOCK_SIZE {
+                debug!("Skipping package of {} TXs ({} bytes) from {} because it doesn't fit in the block", package.len(), package_size, source.as_address(self.network.is_mainnet()));
+                failed_sources.insert(source);
+                continue;
             }
 
-            if let Some(orphaned_blocks) = orphaned_blocks.as_ref() {
-                // We don't want to re-include a TX that is already in a TIP block, even if its not executed yet
-                for (block_hash, block) in orphaned_blocks.iter() {
-                    if block.get_transactions().contains(hash.as_ref()) {
-                        warn!("Skipping TX {} because it is included in tips {}", hash, block_hash);
-                        continue;
+            for TxSelectorEntry { size, hash, tx } in package {
+                if orphaned_blocks.is_none() {
+                    let blocks = self.get_orphaned_blocks_for_tips_until_height(storage, block.get_tips().iter().cloned(), stable_height).await?;
+                    warn!("Found {} orphaned blocks linked for block template", blocks.len());
+                    orphaned_blocks = Some(blocks);
+                }
+
+                if let Some(orphaned_blocks) = orphaned_blocks.as_ref() {
+                    // We don't want to re-include a TX that is already in a TIP block, even if its not executed yet
+                    for (block_hash, block) in orphaned_blocks.iter() {
+                        if block.get_transactions().contains(hash.as_ref()) {
+                            warn!("Skipping TX {} because it is included in tips {}", hash, block_hash);
+                            continue;
+                        }
                     }
                 }
-            }
 
-            if !self.skip_block_template_txs_verification {
-                // Check if the TX is valid for this potential block
-                trace!("Checking TX {} with nonce {}, {}", hash, tx.get_nonce(), tx.get_source().as_address(self.network.is_mainnet()));
+                if !self.skip_block_template_txs_verification {
+                    // Check if the TX is valid for this potential block
+                    trace!("Checking TX {} with nonce {}, {}", hash, tx.get_nonce(), source.as_address(self.network.is_mainnet()));
 
-                let source = tx.get_source();
-                if failed_sources.contains(&source) {
-                    debug!("Skipping TX {} because its source has failed before", hash);
-                    continue;
+                    if let Err(e) = tx.verify(&mut chain_state).await {
+                        warn!("TX {} ({}) is not valid for mining: {}", hash, source.as_address(self.network.is_mainnet()), e);
+                        // Next TXs of the package depend on this one
+                        failed_sources.insert(source);
+                        break;
+                    }
                 }
 
-                if let Err(e) = tx.verify(&mut chain_state).await {
-                    warn!("TX {} ({}) is not valid for mining: {}", hash, source.as_address(self.network.is_mainnet()), e);
-                    failed_sources.insert(source);
-                    continue;
-                }
+                trace!("Selected {} (nonce: {}, fees: {}) for mining", hash, tx.get_nonce(), format_xelis(tx.get_fee()));
+                // TODO no clone
+                block.txs_hashes.insert(hash.as_ref().clone());
+                block_size += HASH_SIZE; // add the hash size
+                total_txs_size += size;
             }
-
-            trace!("Selected {} (nonce: {}, fees: {}) for mining", hash, tx.get_nonce(), format_xelis(tx.get_fee()));
-            // TODO no clone
-            block.txs_hashes.insert(hash.as_ref().clone());
-            block_size += HASH_SIZE; // add the hash size
-            total_txs_size += size;
         }
 
         Ok(block)
@@ -1663,6 +2204,56 @@ impl<S: Storage> Blockchain<S> {
 
     // Add a new block in chain using the requested storage
     pub async fn add_new_block_for_storage(&self, storage: &mut S, block: Block, broadcast: bool, mining: bool) -> Result<(), BlockchainError> {
+        self.add_new_block_with_verification(storage, block, broadcast, mining, true).await
+    }
+
+    // Add a block coming from a trusted source (like an exported chain file)
+    // PoW and transactions proofs are not verified
+    pub async fn add_trusted_block(&self, block: Block) -> Result<(), BlockchainError> {
+        let mut storage = self.storage.write().await;
+        self.add_new_block_with_verification(&mut storage, block, false, false, false).await
+    }
+
+    // All the changes made by the block are staged in the storage and written at once
+    // This prevent to have a partially executed block on disk in case of crash
+    async fn add_new_block_with_verification(&self, storage: &mut S, block: Block, broadcast: bool, mining: bool, verify: bool) -> Result<(), BlockchainError> {
+        // Keep the cached values to restore them if the block is rejected
+        let topoheight = self.get_topo_height();
+        let height = self.get_height();
+        let stable_height = self.get_stable_height();
+        let stable_topoheight = self.get_stable_topoheight();
+        let difficulty = self.get_difficulty().await;
+
+        storage.start_commit_point().await?;
+        let res = self.process_new_block(storage, block, broadcast, mining, verify).await;
+        let commit = storage.end_commit_point(res.is_ok()).await;
+
+        // Cached values must also be restored if the changes couldn't be written
+        if res.is_err() || commit.is_err() {
+            debug!("Block rejected, restoring cached values");
+            self.topoheight.store(topoheight, Ordering::SeqCst);
+            self.height.store(height, Ordering::SeqCst);
+            self.stable_height.store(stable_height, Ordering::SeqCst);
+            self.stable_topoheight.store(stable_topoheight, Ordering::SeqCst);
+            self.set_difficulty(difficulty).await;
+        }
+
+        commit?;
+        let effects = res?;
+
+        let new_stable_topoheight = self.get_stable_topoheight();
+        if new_stable_topoheight > stable_topoheight {
+            if let Err(e) = self.store_state_roots_until_topoheight(storage, new_stable_topoheight).await {
+                warn!("Error while storing state roots until topoheight {}: {}", new_stable_topoheight, e);
+            }
+        }
+
+        self.apply_block_side_effects(storage, effects).await;
+
+        Ok(())
+    }
+
+    async fn process_new_block(&self, storage: &mut S, block: Block, broadcast: bool, mining: bool, verify: bool) -> Result<BlockSideEffects, BlockchainError> {
         let start = Instant::now();
 
         // Expected version for this block
@@ -1775,9 +2366,9 @@ impl<S: Storage> Blockchain<S> {
         }
 
         // verify PoW and get difficulty for this block based on tips
-        let skip_pow = self.skip_pow_verification();
+        let skip_pow = self.skip_pow_verification() || !verify;
         let pow_hash = if skip_pow {
-            // Simulator is enabled, we don't need to compute the PoW hash
+            // Simulator is enabled or block is trusted, we don't need to compute the PoW hash
             Hash::zero()
         } else {
             let algorithm = get_pow_algorithm_for_version(version);
@@ -1866,7 +2457,7 @@ impl<S: Storage> Blockchain<S> {
                 batch.push(tx);
             }
 
-            if !batch.is_empty() {
+            if !batch.is_empty() && verify {
                 debug!("proof verifications of TXs ({}) in block {}", batch.iter().map(|v| v.hash().to_string()).collect::<Vec<String>>().join(","), block_hash);
                 // Verify all valid transactions in one batch
                 Transaction::verify_batch(batch.as_slice(), &mut chain_state).await?;
@@ -1914,9 +2505,7 @@ impl<S: Storage> Blockchain<S> {
         let mut full_order = self.generate_full_order(storage, &best_tip, &base_hash, base_height, base_topo_height).await?;
         debug!("Generated full order size: {}, with base ({}) topo height: {}", full_order.len(), base_hash, base_topo_height);
 
-        // rpc server lock
-        let rpc_server = self.rpc.read().await;
-        let should_track_events = if let Some(rpc) = rpc_server.as_ref() {
+        let should_track_events = if let Some(rpc) = self.rpc.read().await.as_ref() {
             rpc.get_tracked_events().await
         } else {
             HashSet::new()
@@ -2200,6 +2789,24 @@ impl<S: Storage> Blockchain<S> {
                     }
                 }
             }
+
+            if let Some(keep_only) = self.keep_txs_n_blocks {
+                if current_topoheight % keep_only == 0 && current_topoheight > keep_only {
+                    info!("Pruning txs until topoheight {} (keep only {} blocks)", current_topoheight - keep_only, keep_only);
+                    if let Err(e) = self.prune_txs_until_topoheight_for_storage(current_topoheight - keep_only, storage).await {
+                        warn!("Error while trying to prune txs: {}", e);
+                    }
+                }
+            }
+
+            if let Some(keep_only) = self.keep_balances_history_n_blocks {
+                if current_topoheight % keep_only == 0 && current_topoheight > keep_only {
+                    info!("Pruning balances history until topoheight {} (keep only {} blocks)", current_topoheight - keep_only, keep_only);
+                    if let Err(e) = self.prune_balances_history_until_topoheight_for_storage(current_topoheight - keep_only, storage).await {
+                        warn!("Error while trying to prune balances history: {}", e);
+                    }
+                }
+            }
         }
 
         // Store the new tips available
@@ -2247,20 +2854,80 @@ impl<S: Storage> Blockchain<S> {
             self.set_difficulty(difficulty).await;
         }
 
+        info!("Processed block {} at height {} in {}ms with {} txs (DAG: {})", block_hash, block.get_height(), start.elapsed().as_millis(), block.get_txs_count(), block_is_ordered);
+
+        Ok(BlockSideEffects {
+            block_hash,
+            block,
+            txs,
+            block_size,
+            cumulative_difficulty,
+            current_topoheight,
+            current_height,
+            base_topo_height,
+            highest_topo,
+            orphaned_transactions,
+            should_track_events,
+            events,
+            broadcast,
+            mining
+        })
+    }
+
+    // Apply the side effects of a block once its changes are written in the storage
+    // If the block is rejected or its changes can't be written, none of them are applied
+    // Errors are only logged as the block is already part of the chain at this point
+    async fn apply_block_side_effects(&self, storage: &S, effects: BlockSideEffects) {
+        let BlockSideEffects {
+            block_hash,
+            block,
+            txs,
+            block_size,
+            cumulative_difficulty,
+            current_topoheight,
+            current_height,
+            base_topo_height,
+            highest_topo,
+            mut orphaned_transactions,
+            should_track_events,
+            mut events,
+            broadcast,
+            mining
+        } = effects;
+
         // Check if the event is tracked
         let orphan_event_tracked = should_track_events.contains(&NotifyEvent::TransactionOrphaned);
 
-        // Clean mempool from old txs if the DAG has been updated
-        let mempool_deleted_txs = if highest_topo >= current_topoheight {
+        // Clean mempool from expired and old txs if the DAG has been updated
+        let (mempool_expired_txs, mempool_deleted_txs) = if highest_topo >= current_topoheight {
             debug!("Locking mempool write mode");
             let mut mempool = self.mempool.write().await;
             debug!("mempool write mode ok");
+            let expired_txs = mempool.remove_expired_txs(highest_topo);
             let version = get_version_at_height(self.get_network(), current_height);
-            mempool.clean_up(&*storage, base_topo_height, highest_topo, version).await
+            let deleted_txs = mempool.clean_up(storage, base_topo_height, highest_topo, version).await;
+            (expired_txs, deleted_txs)
         } else {
-            Vec::new()
+            (Vec::new(), Vec::new())
         };
 
+        if !mempool_expired_txs.is_empty() {
+            debug!("{} TXs expired in mempool", mempool_expired_txs.len());
+            if should_track_events.contains(&NotifyEvent::TransactionExpired) {
+                for (tx_hash, sorted_tx) in mempool_expired_txs {
+                    let data = RPCTransaction::from_tx(&sorted_tx.get_tx(), &tx_hash, storage.is_mainnet());
+                    let data = TransactionResponse {
+                        blocks: None,
+                        executed_in_block: None,
+                        in_mempool: false,
+                        first_seen: Some(sorted_tx.get_first_seen()),
+                        data,
+                    };
+                    events.entry(NotifyEvent::TransactionExpired).or_insert_with(Vec::new).push(json!(data));
+                }
+            }
+        }
+
         if orphan_event_tracked {
             for (tx_hash, sorted_tx) in mempool_deleted_txs {
                 // Delete it from our orphaned transactions list
@@ -2270,8 +2937,13 @@ impl<S: Storage> Blockchain<S> {
                     trace!("Transaction {} was marked as orphaned, but got deleted from mempool. Prevent adding it back", tx_hash);
                 }
                 // Verify that the TX was not executed in a block
-                if storage.is_tx_executed_in_a_block(&tx_hash)? {
-                    continue;
+                match storage.is_tx_executed_in_a_block(&tx_hash) {
+                    Ok(false) => {},
+                    Ok(true) => continue,
+                    Err(e) => {
+                        warn!("Error while checking if tx {} was executed: {}", tx_hash, e);
+                        continue;
+                    }
                 }
 
                 let data = RPCTransaction::from_tx(&sorted_tx.get_tx(), &tx_hash, storage.is_mainnet());
@@ -2291,56 +2963,66 @@ impl<S: Storage> Blockchain<S> {
             debug!("Adding back orphaned tx {}", tx_hash);
             // It is verified in add_tx_to_mempool function too
             // But to prevent loading the TX from storage and to fire wrong event
-            if !storage.is_tx_executed_in_a_block(&tx_hash)? {
-                let tx = match storage.get_transaction(&tx_hash).await {
-                    Ok(tx) => tx,
-                    Err(e) => {
-                        warn!("Error while loading orphaned tx: {}", e);
-                        continue;
-                    }
-                };
+            match storage.is_tx_executed_in_a_block(&tx_hash) {
+                Ok(false) => {},
+                Ok(true) => continue,
+                Err(e) => {
+                    warn!("Error while checking if orphaned tx {} was executed: {}", tx_hash, e);
+                    continue;
+                }
+            }
 
-                // Clone only if its necessary
-                if !orphan_event_tracked {
-                    if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(&storage, tx, tx_hash, false).await {
-                        warn!("Error while adding back orphaned tx: {}", e);
-                    }
-                } else {
-                    if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(&storage, tx.clone(), tx_hash.clone(), false).await {
-                        warn!("Error while adding back orphaned tx: {}, broadcasting event", e);
-                        // We couldn't add it back to mempool, let's notify this event
-                        let data = RPCTransaction::from_tx(&tx, &tx_hash, storage.is_mainnet());
-                        let data = TransactionResponse {
-                            blocks: None,
-                            executed_in_block: None,
-                            in_mempool: false,
-                            first_seen: None,
-                            data,
-                        };
-                        events.entry(NotifyEvent::TransactionOrphaned).or_insert_with(Vec::new).push(json!(data));
-                    }
+            let tx = match storage.get_transaction(&tx_hash).await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    warn!("Error while loading orphaned tx: {}", e);
+                    continue;
+                }
+            };
+
+            // Clone only if its necessary
+            if !orphan_event_tracked {
+                if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(storage, tx, tx_hash, false).await {
+                    warn!("Error while adding back orphaned tx: {}", e);
+                }
+            } else {
+                if let Err(e) = self.add_tx_to_mempool_with_storage_and_hash(storage, tx.clone(), tx_hash.clone(), false).await {
+                    warn!("Error while adding back orphaned tx: {}, broadcasting event", e);
+                    // We couldn't add it back to mempool, let's notify this event
+                    let data = RPCTransaction::from_tx(&tx, &tx_hash, storage.is_mainnet());
+                    let data = TransactionResponse {
+                        blocks: None,
+                        executed_in_block: None,
+                        in_mempool: false,
+                        first_seen: None,
+                        data,
+                    };
+                    events.entry(NotifyEvent::TransactionOrphaned).or_insert_with(Vec::new).push(json!(data));
                 }
             }
         }
 
-        info!("Processed block {} at height {} in {}ms with {} txs (DAG: {})", block_hash, block.get_height(), start.elapsed().as_millis(), block.get_txs_count(), block_is_ordered);
-
         // Broadcast to p2p nodes
         if broadcast {
             trace!("Broadcasting block");
             if let Some(p2p) = self.p2p.read().await.as_ref() {
-                trace!("P2p locked, broadcasting in new task");
-                let p2p = p2p.clone();
-                let pruned_topoheight = storage.get_pruned_topoheight().await?;
-                let block = block.clone();
-                let block_hash = block_hash.clone();
-                spawn_task("broadcast-block", async move {
-                    p2p.broadcast_block(&block, cumulative_difficulty, current_topoheight, current_height, pruned_topoheight, &block_hash, mining).await;
-                });
+                match storage.get_pruned_topoheight().await {
+                    Ok(pruned_topoheight) => {
+                        trace!("P2p locked, broadcasting in new task");
+                        let p2p = p2p.clone();
+                        let block = block.clone();
+                        let block_hash = block_hash.clone();
+                        spawn_task("broadcast-block", async move {
+                            p2p.broadcast_block(&block, cumulative_difficulty, current_topoheight, current_height, pruned_topoheight, &block_hash, mining).await;
+                        });
+                    },
+                    Err(e) => warn!("Error while loading pruned topoheight to broadcast block {}: {}", block_hash, e)
+                }
             }
         }
 
         // broadcast to websocket new block
+        let rpc_server = self.rpc.read().await;
         if let Some(rpc) = rpc_server.as_ref() {
             // if we have a getwork server, and that its not from syncing, notify miners
             if broadcast {
@@ -2379,7 +3061,6 @@ impl<S: Storage> Blockchain<S> {
                 }
             });
         }
-
         Ok(())
     }
 
@@ -2542,6 +3223,9 @@ impl<S: Storage> Blockchain<S> {
         let (new_height, new_topoheight, mut txs) = storage.pop_blocks(current_height, current_topoheight, count, until).await?;
         debug!("New topoheight: {} (diff: {})", new_topoheight, current_topoheight - new_topoheight);
 
+        // The blocks removed may have been applied on the state tree
+        self.invalidate_state_tree_above(new_topoheight).await;
+
         // Clean mempool from old txs if the DAG has been updated
         {
             let mut mempool = self.mempool.write().await;
@@ -2712,6 +3396,11 @@ pub fn get_block_dev_fee(height: u64) -> u64 {
 
 #[cfg(test)]
 mod tests {
+    use xelis_common::crypto::KeyPair;
+    use crate::core::{
+        storage::{BalanceProvider, BlockProvider, MemoryStorage},
+        test_chain::{BlockSpec, TestChain, GENESIS}
+    };
     use super::*;
 
     #[test]
@@ -2737,4 +3426,69 @@ mod tests {
         assert_eq!(get_block_dev_fee(DEV_FEES[1].height), 5);
         assert_eq!(get_block_dev_fee(DEV_FEES[1].height + 1), 5);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_headless_blockchain_in_memory() {
+        let storage = MemoryStorage::new(Network::Dev).unwrap();
+        let blockchain = Blockchain::new(Config::headless(), Network::Dev, storage).await.unwrap();
+        assert_eq!(blockchain.get_height(), 0);
+        assert_eq!(blockchain.get_topo_height(), 0);
+
+        let miner = KeyPair::new().get_public_key().compress();
+        for _ in 0..3 {
+            let header = blockchain.get_block_template(miner.clone()).await.unwrap();
+            let block = blockchain.build_block_from_header(Immutable::Owned(header)).await.unwrap();
+            blockchain.add_new_block(block, false, false).await.unwrap();
+        }
+
+        assert_eq!(blockchain.get_height(), 3);
+        assert_eq!(blockchain.get_topo_height(), 3);
+        {
+            let storage = blockchain.get_storage().read().await;
+            assert_eq!(storage.count_blocks().await.unwrap(), 4);
+            assert!(storage.has_balance_for(&miner, &XELIS_ASSET).await.unwrap());
+        }
+
+        blockchain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_mempool_persistence() {
+        let mut chain = TestChain::new().await;
+        let alice = KeyPair::new();
+        let bob = KeyPair::new();
+        chain.add_block(BlockSpec::new("a", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+        chain.add_block(BlockSpec::new("b", &["a"]).miner(&bob.get_public_key().compress())).await.unwrap();
+
+        let destination = KeyPair::new().get_public_key().compress();
+        let alice_tx = chain.transfer(&alice, chain.miner_reward("a").await, "b", &destination, 1).await;
+        let bob_tx = chain.transfer(&bob, chain.miner_reward("b").await, "b", &destination, 1).await;
+        let (alice_hash, bob_hash) = (alice_tx.hash(), bob_tx.hash());
+
+        let blockchain = chain.blockchain();
+        blockchain.add_tx_to_mempool(alice_tx, false).await.unwrap();
+        blockchain.add_tx_to_mempool(bob_tx.clone(), false).await.unwrap();
+        let first_seen = blockchain.get_mempool().read().await.get_sorted_tx(&alice_hash).unwrap().get_first_seen();
+
+        // Mempool is saved and cleared
+        blockchain.stop().await;
+        assert_eq!(blockchain.get_mempool().read().await.size(), 0);
+
+        // Bob TX is executed while the node is stopped
+        chain.add_block(BlockSpec::new("c", &["b"]).tx(bob_tx)).await.unwrap();
+
+        let blockchain = chain.blockchain();
+        blockchain.load_mempool_from_storage().await.unwrap();
+        {
+            let mempool = blockchain.get_mempool().read().await;
+            assert_eq!(mempool.size(), 1);
+            assert_eq!(mempool.get_sorted_tx(&alice_hash).unwrap().get_first_seen(), first_seen);
+            assert!(!mempool.contains_tx(&bob_hash));
+        }
+
+        // Saved TXs are deleted once loaded
+        assert!(blockchain.get_storage().read().await.get_mempool_txs().await.unwrap().is_empty());
+
+        chain.stop().await;
+    }
+}
@@ -0,0 +1,308 @@
+use std::io::{Read, Write};
+use xelis_common::{
+    block::Block,
+    config::MAX_BLOCK_SIZE,
+    network::Network,
+    serializer::{Reader, ReaderError, Serializer, Writer}
+};
+use super::error::BlockchainError;
+
+// Magic bytes written at the start of every chain file
+const CHAIN_FILE_MAGIC: &[u8; 4] = b"XELC";
+// Current version of the chain file format
+pub const CHAIN_FILE_VERSION: u8 = 1;
+// magic (4) + version (1) + network (1) + start topoheight (8) + end topoheight (8)
+const CHAIN_FILE_HEADER_SIZE: usize = 22;
+// Size of the blake3 checksum written at the end of the file
+const CHECKSUM_SIZE: usize = 32;
+// Highest topoheight accepted in a chain file, far above any real chain
+const MAX_CHAIN_FILE_TOPOHEIGHT: u64 = u32::MAX as u64;
+
+// Header of a chain file
+// Blocks are stored in topological order from start to end topoheight (inclusive)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainFileHeader {
+    pub network: Network,
+    pub start_topoheight: u64,
+    pub end_topoheight: u64
+}
+
+impl ChainFileHeader {
+    pub fn new(network: Network, start_topoheight: u64, end_topoheight: u64) -> Self {
+        Self {
+            network,
+            start_topoheight,
+            end_topoheight
+        }
+    }
+
+    // Number of blocks stored in the file
+    // Returns None if the topoheight range is invalid
+    pub fn blocks_count(&self) -> Option<u64> {
+        if self.start_topoheight > self.end_topoheight || self.end_topoheight > MAX_CHAIN_FILE_TOPOHEIGHT {
+            return None
+        }
+
+        (self.end_topoheight - self.start_topoheight).checked_add(1)
+    }
+}
+
+impl Serializer for ChainFileHeader {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(CHAIN_FILE_MAGIC);
+        writer.write_u8(CHAIN_FILE_VERSION);
+        self.network.write(writer);
+        writer.write_u64(&self.start_topoheight);
+        writer.write_u64(&self.end_topoheight);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let magic: [u8; 4] = reader.read_bytes(4)?;
+        if magic != *CHAIN_FILE_MAGIC {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        if reader.read_u8()? != CHAIN_FILE_VERSION {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let network = Network::read(reader)?;
+        let start_topoheight = reader.read_u64()?;
+        let end_topoheight = reader.read_u64()?;
+        let header = Self::new(network, start_topoheight, end_topoheight);
+        if header.blocks_count().is_none() {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        Ok(header)
+    }
+
+    fn size(&self) -> usize {
+        CHAIN_FILE_HEADER_SIZE
+    }
+}
+
+// Write blocks in a chain file
+// Each block is prefixed by its size, and a blake3 checksum
+// of all the previous bytes is appended at the end of the file
+pub struct ChainFileWriter<W: Write> {
+    inner: W,
+    hasher: blake3::Hasher,
+    // Blocks expected based on the header
+    expected: u64,
+    // Blocks written until now
+    written: u64
+}
+
+impl<W: Write> ChainFileWriter<W> {
+    pub fn new(mut inner: W, header: &ChainFileHeader) -> Result<Self, BlockchainError> {
+        let expected = header.blocks_count()
+            .ok_or(BlockchainError::InvalidChainFile("invalid topoheight range"))?;
+        let bytes = header.to_bytes();
+        inner.write_all(&bytes)?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&bytes);
+
+        Ok(Self {
+            inner,
+            hasher,
+            expected,
+            written: 0
+        })
+    }
+
+    // Append the next block in topological order
+    pub fn write_block(&mut self, block: &Block) -> Result<(), BlockchainError> {
+        if self.written >= self.expected {
+            return Err(BlockchainError::InvalidChainFile("too many blocks written"))
+        }
+
+        let bytes = block.to_bytes();
+        let size = (bytes.len() as u32).to_be_bytes();
+        self.inner.write_all(&size)?;
+        self.inner.write_all(&bytes)?;
+        self.hasher.update(&size);
+        self.hasher.update(&bytes);
+        self.written += 1;
+
+        Ok(())
+    }
+
+    // Write the checksum and flush the file
+    pub fn finish(mut self) -> Result<W, BlockchainError> {
+        if self.written != self.expected {
+            return Err(BlockchainError::InvalidChainFile("missing blocks"))
+        }
+
+        let checksum = self.hasher.finalize();
+        self.inner.write_all(checksum.as_bytes())?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+// Read blocks from a chain file
+// The checksum is only known once all blocks have been read,
+// so a first pass using `verify` should be done before applying them
+pub struct ChainFileReader<R: Read> {
+    inner: R,
+    hasher: blake3::Hasher,
+    header: ChainFileHeader,
+    // Blocks left to read
+    remaining: u64
+}
+
+impl<R: Read> ChainFileReader<R> {
+    pub fn new(mut inner: R) -> Result<Self, BlockchainError> {
+        let mut bytes = [0u8; CHAIN_FILE_HEADER_SIZE];
+        inner.read_exact(&mut bytes)?;
+
+        let header = ChainFileHeader::from_bytes(&bytes)
+            .map_err(|_| BlockchainError::InvalidChainFile("invalid header"))?;
+        // Already verified when reading the header
+        let remaining = header.blocks_count()
+            .ok_or(BlockchainError::InvalidChainFile("invalid topoheight range"))?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&bytes);
+
+        Ok(Self {
+            inner,
+            hasher,
+            remaining,
+            header
+        })
+    }
+
+    pub fn get_header(&self) -> &ChainFileHeader {
+        &self.header
+    }
+
+    // Read the next block without deserializing it
+    pub fn next_block_bytes(&mut self) -> Result<Option<Vec<u8>>, BlockchainError> {
+        if self.remaining == 0 {
+            return Ok(None)
+        }
+
+        let mut size = [0u8; 4];
+        self.inner.read_exact(&mut size)?;
+        let len = u32::from_be_bytes(size) as usize;
+        if len > MAX_BLOCK_SIZE {
+            return Err(BlockchainError::InvalidChainFile("block too big"))
+        }
+
+        let mut bytes = vec![0u8; len];
+        self.inner.read_exact(&mut bytes)?;
+        self.hasher.update(&size);
+        self.hasher.update(&bytes);
+        self.remaining -= 1;
+
+        Ok(Some(bytes))
+    }
+
+    // Read and deserialize the next block
+    pub fn next_block(&mut self) -> Result<Option<Block>, BlockchainError> {
+        match self.next_block_bytes()? {
+            Some(bytes) => Ok(Some(Block::from_bytes(&bytes)?)),
+            None => Ok(None)
+        }
+    }
+
+    // Read all the remaining blocks and compare the checksum
+    // Returns the number of blocks read
+    pub fn verify(mut self) -> Result<u64, BlockchainError> {
+        let mut count = 0;
+        while self.next_block_bytes()?.is_some() {
+            count += 1;
+        }
+
+        let mut checksum = [0u8; CHECKSUM_SIZE];
+        self.inner.read_exact(&mut checksum)?;
+        if *self.hasher.finalize().as_bytes() != checksum {
+            return Err(BlockchainError::ChainFileChecksum)
+        }
+
+        // Nothing should be written after the checksum
+        let mut extra = [0u8; 1];
+        if self.inner.read(&mut extra)? != 0 {
+            return Err(BlockchainError::InvalidChainFile("unexpected data after checksum"))
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use indexmap::IndexSet;
+    use xelis_common::{
+        block::{BlockHeader, BlockVersion, EXTRA_NONCE_SIZE},
+        crypto::{Hashable, KeyPair},
+        immutable::Immutable
+    };
+    use super::*;
+
+    fn create_block(timestamp: u64) -> Block {
+        let miner = KeyPair::new().get_public_key().compress();
+        let header = BlockHeader::new(BlockVersion::V0, 0, timestamp, IndexSet::new(), [0u8; EXTRA_NONCE_SIZE], miner, IndexSet::new());
+        Block::new(Immutable::Owned(header), Vec::new())
+    }
+
+    fn create_file(blocks: &[Block]) -> Vec<u8> {
+        let header = ChainFileHeader::new(Network::Dev, 0, blocks.len() as u64 - 1);
+        let mut writer = ChainFileWriter::new(Vec::new(), &header).unwrap();
+        for block in blocks {
+            writer.write_block(block).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_chain_file_roundtrip() {
+        let blocks = vec![create_block(1), create_block(2), create_block(3)];
+        let bytes = create_file(&blocks);
+
+        let reader = ChainFileReader::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(*reader.get_header(), ChainFileHeader::new(Network::Dev, 0, 2));
+        assert_eq!(reader.verify().unwrap(), 3);
+
+        let mut reader = ChainFileReader::new(Cursor::new(&bytes)).unwrap();
+        for block in blocks.iter() {
+            let read = reader.next_block().unwrap().unwrap();
+            assert_eq!(read.hash(), block.hash());
+        }
+        assert!(reader.next_block().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chain_file_corrupted() {
+        let blocks = vec![create_block(1), create_block(2)];
+        let mut bytes = create_file(&blocks);
+        // Flip a byte in the first block
+        bytes[CHAIN_FILE_HEADER_SIZE + 10] ^= 0xFF;
+
+        let reader = ChainFileReader::new(Cursor::new(&bytes)).unwrap();
+        assert!(matches!(reader.verify(), Err(BlockchainError::ChainFileChecksum)));
+    }
+
+    #[test]
+    fn test_chain_file_invalid_range() {
+        for (start, end) in [(1, 0), (0, u64::MAX), (0, MAX_CHAIN_FILE_TOPOHEIGHT + 1)] {
+            let header = ChainFileHeader::new(Network::Dev, start, end);
+            assert!(header.blocks_count().is_none());
+            assert!(ChainFileWriter::new(Vec::new(), &header).is_err());
+            assert!(ChainFileReader::new(Cursor::new(header.to_bytes())).is_err());
+        }
+    }
+
+    #[test]
+    fn test_chain_file_missing_blocks() {
+        let header = ChainFileHeader::new(Network::Dev, 0, 1);
+        let mut writer = ChainFileWriter::new(Vec::new(), &header).unwrap();
+        writer.write_block(&create_block(1)).unwrap();
+        assert!(writer.finish().is_err());
+    }
+}
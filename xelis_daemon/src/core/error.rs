@@ -62,6 +62,8 @@ pub enum DiskContext {
     EstimatedCovarianceForBlockHash,
     #[error("get balances merkle hash at topoheight")]
     BalancesMerkleHashAtTopoHeight,
+    #[error("get state root at topoheight")]
+    StateRootAtTopoHeight,
     #[error("get last topoheight for nonce")]
     LastTopoheightForNonce,
     #[error("get last nonce")]
@@ -233,6 +235,10 @@ pub enum BlockchainError {
     UnexpectedTransactionVariant,
     #[error("Unexpected error on database: {}", _0)]
     DatabaseError(#[from] sled::Error),
+    #[error("Unexpected error on RocksDB: {}", _0)]
+    RocksDBError(#[from] rocksdb::Error),
+    #[error("Column family '{}' not found in database", _0)]
+    ColumnFamilyNotFound(&'static str),
     #[error("Unsupported operation")]
     UnsupportedOperation,
     #[error("Data not found on disk: {}", _0)]
@@ -295,6 +301,8 @@ pub enum BlockchainError {
     PruneLowerThanLastPruned,
     #[error("Auto prune mode is misconfigured")]
     AutoPruneMode,
+    #[error("Retention policy is misconfigured")]
+    RetentionPolicy,
     #[error(transparent)]
     TryFromSliceError(#[from] std::array::TryFromSliceError),
     #[error("Invalid ciphertext")]
@@ -318,7 +326,39 @@ pub enum BlockchainError {
     #[error("Transfer count is invalid")]
     TransferCount,
     #[error("Invalid commitments assets")]
-    Commitments
+    Commitments,
+    #[error("Invalid chain file: {}", _0)]
+    InvalidChainFile(&'static str),
+    #[error("Chain file checksum mismatch, file is corrupted")]
+    ChainFileChecksum,
+    #[error("Invalid state snapshot: {}", _0)]
+    InvalidStateSnapshot(&'static str),
+    #[error("State snapshot checksum mismatch, file is corrupted")]
+    StateSnapshotChecksum,
+    #[error("State at topoheight {} is not available, it must be stable and not pruned", _0)]
+    StateNotAvailable(u64),
+    #[error("Duplicated leaf {} in state tree", _0)]
+    DuplicatedStateLeaf(Hash),
+    #[error("A commit point is already started")]
+    CommitPointAlreadyStarted,
+    #[error("No commit point started")]
+    NoCommitPoint,
+    #[error("Invalid replay range from {} to {}", _0, _1)]
+    InvalidReplayRange(u64, u64),
+    #[error("Block {} at topoheight {} can't be replayed: {}", _0, _1, _2)]
+    ReplayBlock(Hash, u64, Box<BlockchainError>),
+    #[error("Too many TXs in mempool for {}, limit is {}", _0, _1)]
+    TooManyTxsForSender(Address, usize),
+    #[error("Mempool is full, TX {} fee per KB is too low to be added", _0)]
+    MempoolFull(Hash),
+    #[error("Tx {} is expired, its reference at topoheight {} is too far from the chain top", _0, _1)]
+    TxExpired(Hash, u64),
+    #[error("Tx {} can't be replaced, expected a fee per KB of at least {}, got {}", _0, format_xelis(*_1), format_xelis(*_2))]
+    ReplacementFeeTooLow(Hash, u64, u64),
+    #[error("Transactions of block {} are pruned, only its header is available", _0)]
+    BlockTxsPruned(Hash),
+    #[error("Tx {} can't be replaced, expected a fee of at least {} to pay for all the removed TXs, got {}", _0, format_xelis(*_1), format_xelis(*_2))]
+    ReplacementTotalFeeTooLow(Hash, u64, u64)
 }
 
 impl BlockchainError {
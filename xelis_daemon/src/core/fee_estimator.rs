@@ -0,0 +1,215 @@
+use std::{collections::HashMap, sync::Arc};
+use log::trace;
+use xelis_common::{
+    config::{BYTES_PER_KB, FEE_PER_KB},
+    crypto::Hash
+};
+
+// Highest count of blocks that can be targeted for a TX inclusion
+pub const MAX_CONFIRMATION_TARGET: u64 = 64;
+// Probability required for a fee rate to be included within the target
+pub const CONFIRMATION_PROBABILITY: f64 = 0.9;
+// Weight kept at each new topoheight, so recent blocks count more than old ones
+// After ~350 blocks, a TX counts for half
+const DECAY: f64 = 0.998;
+// Minimum weight of TXs required to estimate a fee rate
+const MIN_SAMPLES: f64 = 10.0;
+// Each bucket fee rate is 10% higher than the previous one
+const BUCKET_SPACING: f64 = 1.1;
+// Highest fee rate tracked, all TXs above are in the last bucket
+const MAX_BUCKET_FEE_RATE: u64 = FEE_PER_KB * 10_000;
+
+// TXs confirmed at a fee rate range
+struct Bucket {
+    // Lowest fee per KB of this bucket
+    fee_rate: u64,
+    // Weight of TXs confirmed after N + 1 blocks
+    confirmed: Vec<f64>,
+    // Weight of all TXs, including those that took more than the maximum target
+    total: f64
+}
+
+// TX seen in mempool and waiting to be included
+struct PendingTx {
+    bucket: usize,
+    topoheight: u64
+}
+
+// Fee estimator based on the confirmed TXs history
+// Each TX added in mempool is tracked with the topoheight it was seen at,
+// and once executed in a block, the count of blocks it waited is saved in its fee rate bucket
+// A fee rate is estimated for a target when enough TXs paying at least it were included in time
+pub struct FeeEstimator {
+    buckets: Vec<Bucket>,
+    pending: HashMap<Arc<Hash>, PendingTx>,
+    // Last topoheight processed
+    topoheight: u64
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        let mut buckets = Vec::new();
+        let mut fee_rate = FEE_PER_KB as f64;
+        while (fee_rate as u64) < MAX_BUCKET_FEE_RATE {
+            buckets.push(Bucket {
+                fee_rate: fee_rate as u64,
+                confirmed: vec![0f64; MAX_CONFIRMATION_TARGET as usize],
+                total: 0f64
+            });
+            fee_rate *= BUCKET_SPACING;
+        }
+
+        Self {
+            buckets,
+            pending: HashMap::new(),
+            topoheight: 0
+        }
+    }
+
+    // Find the bucket for a fee rate
+    fn get_bucket_index(&self, fee_rate: u64) -> usize {
+        self.buckets.partition_point(|bucket| bucket.fee_rate <= fee_rate).saturating_sub(1)
+    }
+
+    // Track a TX added in mempool at the topoheight
+    // A TX already tracked keeps its first topoheight
+    pub fn track_tx(&mut self, hash: Arc<Hash>, fee: u64, size: usize, topoheight: u64) {
+        let fee_rate = fee.saturating_mul(BYTES_PER_KB as u64) / (size as u64).max(1);
+        let bucket = self.get_bucket_index(fee_rate);
+        trace!("Tracking TX {} with fee rate {} at topoheight {}", hash, fee_rate, topoheight);
+        self.pending.entry(hash).or_insert(PendingTx { bucket, topoheight });
+    }
+
+    // Save the count of blocks a tracked TX waited before being executed
+    pub fn confirm_tx(&mut self, hash: &Hash, topoheight: u64) {
+        if let Some(pending) = self.pending.remove(hash) {
+            let blocks = topoheight.saturating_sub(pending.topoheight).max(1);
+            trace!("TX {} confirmed after {} blocks", hash, blocks);
+            let bucket = &mut self.buckets[pending.bucket];
+            bucket.total += 1f64;
+            if blocks <= MAX_CONFIRMATION_TARGET {
+                bucket.confirmed[blocks as usize - 1] += 1f64;
+            }
+        }
+    }
+
+    // Stop tracking a TX that can't be included anymore
+    pub fn untrack_tx(&mut self, hash: &Hash) {
+        self.pending.remove(hash);
+    }
+
+    // Stop tracking all the TXs
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    // Decay the history for each new topoheight
+    // TXs waiting for more than the maximum target are saved as not confirmed in time
+    pub fn update_topoheight(&mut self, topoheight: u64) {
+        if topoheight <= self.topoheight {
+            return;
+        }
+
+        let decay = DECAY.powi((topoheight - self.topoheight).min(i32::MAX as u64) as i32);
+        for bucket in self.buckets.iter_mut() {
+            bucket.total *= decay;
+            for confirmed in bucket.confirmed.iter_mut() {
+                *confirmed *= decay;
+            }
+        }
+        self.topoheight = topoheight;
+
+        let buckets = &mut self.buckets;
+        self.pending.retain(|_, pending| {
+            let keep = topoheight.saturating_sub(pending.topoheight) <= MAX_CONFIRMATION_TARGET;
+            if !keep {
+                buckets[pending.bucket].total += 1f64;
+            }
+            keep
+        });
+    }
+
+    // Estimate the lowest fee per KB for a TX to be included within the target blocks with the probability
+    // Buckets are grouped from the highest fee rate until they have enough TXs,
+    // and each group must reach the probability
+    // TXs still pending for more than the target count as failures
+    // Returns None if not enough TXs were confirmed
+    pub fn estimate_fee_rate(&self, target: u64, probability: f64) -> Option<u64> {
+        let target = target.clamp(1, MAX_CONFIRMATION_TARGET) as usize;
+
+        let mut failures = vec![0f64; self.buckets.len()];
+        for pending in self.pending.values() {
+            if self.topoheight.saturating_sub(pending.topoheight) > target as u64 {
+                failures[pending.bucket] += 1f64;
+            }
+        }
+
+        let mut estimated = None;
+        let (mut within, mut total) = (0f64, 0f64);
+        for (bucket, failures) in self.buckets.iter().zip(failures).rev() {
+            within += bucket.confirmed[..target].iter().sum::<f64>();
+            total += bucket.total + failures;
+            if total < MIN_SAMPLES {
+                continue;
+            }
+
+            if within / total < probability {
+                break;
+            }
+
+            estimated = Some(bucket.fee_rate);
+            within = 0f64;
+            total = 0f64;
+        }
+
+        estimated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::crypto::hash;
+    use super::*;
+
+    // Track and confirm N TXs with the fee per KB after the count of blocks
+    fn confirm_txs(estimator: &mut FeeEstimator, count: usize, fee_rate: u64, blocks: u64) {
+        for i in 0..count {
+            let hash = Arc::new(hash(format!("{}-{}-{}", fee_rate, blocks, i).as_bytes()));
+            estimator.track_tx(hash.clone(), fee_rate, BYTES_PER_KB, estimator.topoheight);
+            estimator.confirm_tx(&hash, estimator.topoheight + blocks);
+        }
+    }
+
+    #[test]
+    fn test_estimate_fee_rate() {
+        let mut estimator = FeeEstimator::new();
+        assert_eq!(estimator.estimate_fee_rate(1, CONFIRMATION_PROBABILITY), None);
+
+        confirm_txs(&mut estimator, 20, FEE_PER_KB * 10, 1);
+        confirm_txs(&mut estimator, 20, FEE_PER_KB * 2, 5);
+        confirm_txs(&mut estimator, 20, FEE_PER_KB, 20);
+
+        let next_block = estimator.estimate_fee_rate(1, CONFIRMATION_PROBABILITY).unwrap();
+        assert!(next_block > FEE_PER_KB * 2 && next_block <= FEE_PER_KB * 10);
+
+        let five_blocks = estimator.estimate_fee_rate(5, CONFIRMATION_PROBABILITY).unwrap();
+        assert!(five_blocks > FEE_PER_KB && five_blocks <= FEE_PER_KB * 2);
+
+        assert_eq!(estimator.estimate_fee_rate(20, CONFIRMATION_PROBABILITY), Some(FEE_PER_KB));
+    }
+
+    #[test]
+    fn test_pending_txs_are_failures() {
+        let mut estimator = FeeEstimator::new();
+        confirm_txs(&mut estimator, 20, FEE_PER_KB, 1);
+        assert_eq!(estimator.estimate_fee_rate(1, CONFIRMATION_PROBABILITY), Some(FEE_PER_KB));
+
+        // Same fee rate but never included
+        for i in 0..20 {
+            let hash = Arc::new(hash(format!("pending-{}", i).as_bytes()));
+            estimator.track_tx(hash, FEE_PER_KB, BYTES_PER_KB, estimator.topoheight);
+        }
+        estimator.update_topoheight(estimator.topoheight + 2);
+        assert_eq!(estimator.estimate_fee_rate(1, CONFIRMATION_PROBABILITY), None);
+    }
+}
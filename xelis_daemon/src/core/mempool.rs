@@ -1,10 +1,11 @@
 use super::{
     state::MempoolState,
     error::BlockchainError,
+    fee_estimator::{FeeEstimator, CONFIRMATION_PROBABILITY},
     storage::Storage
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{btree_map, BTreeMap, HashMap, HashSet},
     sync::Arc,
     mem,
 };
@@ -12,7 +13,7 @@ use serde::{Serialize, Deserialize};
 use indexmap::IndexSet;
 use log::{debug, info, trace, warn};
 use xelis_common::{
-    config::{BYTES_PER_KB, FEE_PER_KB},
+    config::{BYTES_PER_KB, FEE_PER_KB, REPLACE_BY_FEE_MIN_INCREASE_PERCENT},
     api::daemon::FeeRatesEstimated,
     block::BlockVersion,
     crypto::{
@@ -53,6 +54,49 @@ pub struct AccountCache {
     balances: HashMap<Hash, Ciphertext>
 }
 
+// Chains of TXs of each sender indexed by their fee per KB
+// Used to select the chains to evict without going through all the senders
+#[derive(Default)]
+struct ChainsByFeeRate {
+    // fee per KB and size in bytes of the chain of each sender
+    chains: HashMap<PublicKey, (u64, usize)>,
+    // senders ordered by the fee per KB of their chain
+    ordered: BTreeMap<u64, HashSet<PublicKey>>
+}
+
+impl ChainsByFeeRate {
+    // Insert or update the chain of a sender
+    fn insert(&mut self, key: PublicKey, fee_rate: u64, size: usize) {
+        self.remove(&key);
+        self.ordered.entry(fee_rate).or_default().insert(key.clone());
+        self.chains.insert(key, (fee_rate, size));
+    }
+
+    fn remove(&mut self, key: &PublicKey) {
+        if let Some((fee_rate, _)) = self.chains.remove(key) {
+            if let btree_map::Entry::Occupied(mut entry) = self.ordered.entry(fee_rate) {
+                entry.get_mut().remove(key);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.chains.clear();
+        self.ordered.clear();
+    }
+
+    // Iterate over the chains from the lowest fee per KB to the highest
+    // Returns the fee per KB, the size and the sender of each chain
+    fn iter(&self) -> impl Iterator<Item = (u64, usize, &PublicKey)> {
+        self.ordered.iter().flat_map(move |(fee_rate, keys)| {
+            keys.iter().map(move |key| (*fee_rate, self.chains.get(key).map_or(0, |(_, size)| *size), key))
+        })
+    }
+}
+
 // Mempool is used to store all TXs waiting to be included in a block
 // All TXs must be verified before adding them to the mempool
 // Caches are used to store the nonce/order cache for each sender and their encrypted balances
@@ -63,16 +107,37 @@ pub struct Mempool {
     // store all txs waiting to be included in a block
     txs: HashMap<Arc<Hash>, SortedTx>,
     // store all sender's nonce for faster finding
-    caches: HashMap<PublicKey, AccountCache>
+    caches: HashMap<PublicKey, AccountCache>,
+    // chains of each sender ordered by fee per KB for the eviction
+    chains: ChainsByFeeRate,
+    // size in bytes of all txs
+    bytes: usize,
+    // maximum size in bytes of all txs
+    max_size: usize,
+    // maximum count of txs for a sender
+    max_txs_per_sender: usize,
+    // maximum time in seconds a tx can stay in mempool
+    tx_max_age: u64,
+    // maximum distance between the tx reference topoheight and the chain top
+    max_reference_distance: u64,
+    // track how many blocks txs waited to be included
+    fee_estimator: FeeEstimator
 }
 
 impl Mempool {
     // Create a new empty mempool
-    pub fn new(network: Network) -> Self {
+    pub fn new(network: Network, max_size: usize, max_txs_per_sender: usize, tx_max_age: u64, max_reference_distance: u64) -> Self {
         Mempool {
             mainnet: network.is_mainnet(),
             txs: HashMap::new(),
-            caches: HashMap::new()
+            caches: HashMap::new(),
+            chains: ChainsByFeeRate::default(),
+            bytes: 0,
+            max_size,
+            max_txs_per_sender,
+            tx_max_age,
+            max_reference_distance,
+            fee_estimator: FeeEstimator::new()
         }
     }
 
@@ -125,8 +190,124 @@ impl Mempool {
         Ok(Self::internal_estimate_fee_rates(fee_rates))
     }
 
+    // Estimate the fee per kB rate for a TX to be included within the target blocks
+    // It is based on the TXs seen in mempool and included in the previous blocks
+    // Returns None if not enough TXs were included
+    pub fn estimate_fee_rate_for_target(&self, target: u64) -> Option<u64> {
+        self.fee_estimator.estimate_fee_rate(target, CONFIRMATION_PROBABILITY)
+    }
+
+    // Compute the fee per KB of a chain of TXs
+    fn get_chain_fee_rate_per_kb(fees: u64, size: usize) -> u64 {
+        fees.saturating_mul(BYTES_PER_KB as u64) / (size as u64).max(1)
+    }
+
+    // Returns the total fees and size in bytes of all TXs in a cache
+    fn get_chain_fees_and_size(&self, cache: &AccountCache) -> (u64, usize) {
+        cache.txs.iter()
+            .filter_map(|hash| self.txs.get(hash))
+            .fold((0, 0), |(fees, size), tx| (fees.saturating_add(tx.get_fee()), size + tx.get_size()))
+    }
+
+    // Update the indexed fee per KB of a sender chain once its cache changed
+    fn update_chain_fee_rate(&mut self, key: &PublicKey) {
+        match self.caches.get(key).map(|cache| self.get_chain_fees_and_size(cache)) {
+            Some((fees, size)) => self.chains.insert(key.clone(), Self::get_chain_fee_rate_per_kb(fees, size), size),
+            None => self.chains.remove(key)
+        }
+    }
+
+    // Select the senders to evict to have enough space for a new TX
+    // TXs of a sender are linked together by their nonces, so a chain is always evicted completely,
+    // starting by the lowest fee per KB chain
+    // Only the chains with a fee per KB lower than the chain of the new TX can be evicted
+    fn select_chains_to_evict(&self, hash: &Hash, tx: &Transaction, size: usize) -> Result<Vec<PublicKey>, BlockchainError> {
+        let required = (self.bytes + size).saturating_sub(self.max_size);
+        if required == 0 {
+            return Ok(Vec::new())
+        }
+
+        let source = tx.get_source();
+        let (mut fees, mut chain_size) = (tx.get_fee(), size);
+        if let Some(cache) = self.caches.get(source) {
+            let (cache_fees, cache_size) = self.get_chain_fees_and_size(cache);
+            fees = fees.saturating_add(cache_fees);
+            chain_size += cache_size;
+        }
+        let fee_rate = Self::get_chain_fee_rate_per_kb(fees, chain_size);
+
+        let mut freed = 0;
+        let mut keys = Vec::new();
+        for (chain_fee_rate, size, key) in self.chains.iter() {
+            if freed >= required || chain_fee_rate >= fee_rate {
+                break;
+            }
+
+            if key == source {
+                continue;
+            }
+
+            freed += size;
+            keys.push(key.clone());
+        }
+
+        if freed < required {
+            debug!("Mempool is full, only {} bytes can be freed for TX {} while {} are required", freed, hash, required);
+            return Err(BlockchainError::MempoolFull(hash.clone()))
+        }
+
+        Ok(keys)
+    }
+
+    // Delete all the TXs of a sender
+    fn evict_chain(&mut self, key: &PublicKey) -> Vec<(Arc<Hash>, SortedTx)> {
+        let mut evicted = Vec::new();
+        self.chains.remove(key);
+        if let Some(cache) = self.caches.remove(key) {
+            for hash in cache.txs {
+                if let Some(sorted_tx) = self.txs.remove(&hash) {
+                    debug!("Evicting TX {} from {} with nonce {}", hash, key.as_address(self.mainnet), sorted_tx.get_tx().get_nonce());
+                    self.bytes -= sorted_tx.get_size();
+                    self.fee_estimator.untrack_tx(&hash);
+                    evicted.push((hash, sorted_tx));
+                } else {
+                    warn!("TX {} not found in mempool while evicting", hash);
+                }
+            }
+        }
+
+        evicted
+    }
+
+    // Verify a TX against the mempool state without adding it
+    pub async fn verify_tx<S: Storage>(&self, storage: &S, stable_topoheight: u64, topoheight: u64, tx: &Transaction, block_version: BlockVersion) -> Result<(), BlockchainError> {
+        let mut state = MempoolState::new(&self, storage, stable_topoheight, topoheight, block_version);
+        tx.verify(&mut state).await?;
+        Ok(())
+    }
+
     // All checks are made in Blockchain before calling this function
-    pub async fn add_tx<S: Storage>(&mut self, storage: &S, stable_topoheight: u64, topoheight: u64, hash: Hash, tx: Arc<Transaction>, size: usize, block_version: BlockVersion) -> Result<(), BlockchainError> {
+    // If the mempool is full, the chains with the lowest fee per KB are evicted and returned
+    // If a TX with the same nonce is already present, it is replaced and returned with its invalidated TXs
+    pub async fn add_tx<S: Storage>(&mut self, storage: &S, stable_topoheight: u64, topoheight: u64, hash: Hash, tx: Arc<Transaction>, size: usize, block_version: BlockVersion) -> Result<Vec<(Arc<Hash>, SortedTx)>, BlockchainError> {
+        // Don't accept a TX that would be removed at the next expiration check
+        let now = get_current_time_in_seconds();
+        if self.is_tx_expired(&tx, now, now, topoheight) {
+            return Err(BlockchainError::TxExpired(hash, tx.get_reference().topoheight))
+        }
+
+        // Check the limits first, verifying the TX is expensive
+        if let Some(cache) = self.caches.get(tx.get_source()) {
+            if let Some(replaced) = cache.has_tx_with_same_nonce(tx.get_nonce()).cloned() {
+                return self.replace_tx(storage, stable_topoheight, topoheight, replaced, hash, tx, size, block_version).await
+            }
+
+            if cache.txs.len() >= self.max_txs_per_sender {
+                return Err(BlockchainError::TooManyTxsForSender(tx.get_source().as_address(self.mainnet), self.max_txs_per_sender))
+            }
+        }
+        let to_evict = self.select_chains_to_evict(&hash, &tx, size)?;
+
         let mut state = MempoolState::new(&self, storage, stable_topoheight, topoheight, block_version);
         tx.verify(&mut state).await?;
 
@@ -134,38 +315,17 @@ impl Mempool {
             .ok_or_else(|| BlockchainError::AccountNotFound(tx.get_source().as_address(storage.is_mainnet())))?
             .iter().map(|(asset, ciphertext)| (Hash::clone(*asset), ciphertext.clone())).collect();
 
+        let mut evicted = Vec::new();
+        for key in to_evict {
+            evicted.extend(self.evict_chain(&key));
+        }
+
         let hash = Arc::new(hash);
         let nonce = tx.get_nonce();
         // update the cache for this owner
-        let mut must_update = true;
         if let Some(cache) = self.caches.get_mut(tx.get_source()) {
-            // delete the TX if its in the range of already tracked nonces
             trace!("Cache found for owner {} with nonce range {}-{}, nonce = {}", tx.get_source().as_address(self.mainnet), cache.get_min(), cache.get_max(), nonce);
-
-            // Support the case where the nonce is already used in cache
-            // If a user want to cancel its TX, he can just resend a TX with same nonce and higher fee
-            // NOTE: This is not possible anymore, disabled in blockchain function
-            if nonce >= cache.get_min() && nonce <= cache.get_max() {
-                trace!("nonce {} is in range {}-{}", nonce, cache.get_min(), cache.get_max());
-                // because it's based on order and we may have the same order
-                let index = ((nonce - cache.get_min()) % (cache.get_max() + 1 - cache.get_min())) as usize;
-                cache.txs.insert(hash.clone());
-                must_update = false;
-
-                if let Some(tx_hash) = cache.txs.swap_remove_index(index) {
-                    trace!("TX {} with same nonce found in cache, removing it from sorted txs", tx_hash);
-                    // remove the tx hash from sorted txs
-                    if self.txs.remove(&tx_hash).is_none() {
-                        warn!("TX {} not found in mempool while deleting collision with {}", tx_hash, hash);
-                    }
-                } else {
-                    warn!("No TX found in cache for nonce {} while adding {}", nonce, hash);
-                }
-            }
-
-            if must_update {
-                cache.update(nonce, hash.clone());
-            }
+            cache.update(nonce, hash.clone());
             // Update re-computed balances
             cache.set_balances(balances);
         } else {
@@ -182,6 +342,7 @@ impl Mempool {
             self.caches.insert(tx.get_source().clone(), cache);
         }
 
+        self.fee_estimator.track_tx(hash.clone(), tx.get_fee(), size, topoheight);
         let sorted_tx = SortedTx {
             size,
             first_seen: get_current_time_in_seconds(),
@@ -189,15 +350,147 @@ impl Mempool {
         };
 
         // insert in map
+        let key = sorted_tx.get_tx().get_source().clone();
         self.txs.insert(hash, sorted_tx);
+        self.bytes += size;
+        self.update_chain_fee_rate(&key);
 
-        Ok(())
+        Ok(evicted)
+    }
+
+    // Verify a TX replacing the one with the same nonce in the sender cache, taken out of the mempool
+    // The new TX is verified against the balances before the replaced TX,
+    // then the TXs with a higher nonce are verified again against the new balances
+    // The first one being invalid is removed with all the next ones, the new TX must pay for all of them
+    // Returns the index of the replaced TX, the count of next TXs still valid and the sender balances after them
+    async fn verify_replacement<S: Storage>(&self, storage: &S, stable_topoheight: u64, topoheight: u64, cache: &AccountCache, replaced: &Arc<Hash>, hash: &Hash, tx: &Arc<Transaction>, block_version: BlockVersion) -> Result<(usize, usize, HashMap<Hash, Ciphertext>), BlockchainError> {
+        let index = cache.txs.get_index_of(replaced).ok_or_else(|| BlockchainError::TxNotFound(replaced.as_ref().clone()))?;
+        let mut txs = Vec::with_capacity(cache.txs.len());
+        for tx_hash in cache.txs.iter() {
+            match self.txs.get(tx_hash) {
+                Some(sorted_tx) => txs.push(sorted_tx.get_tx()),
+                None => {
+                    warn!("TX {} not found in mempool while replacing {}", tx_hash, replaced);
+                    return Err(BlockchainError::TxNotFound(tx_hash.as_ref().clone()))
+                }
+            }
+        }
+
+        let mut state = MempoolState::new(&self, storage, stable_topoheight, topoheight, block_version);
+        if index > 0 {
+            Transaction::verify_batch(&txs[..index], &mut state).await?;
+        }
+        tx.verify(&mut state).await?;
+
+        // Count the dependent TXs still valid with the new balances
+        let mut valid = 0;
+        for (tx_hash, dependent) in cache.txs.iter().skip(index + 1).zip(&txs[index + 1..]) {
+            if let Err(e) = dependent.verify(&mut state).await {
+                debug!("TX {} is not valid anymore after replacing TX {}: {}", tx_hash, replaced, e);
+                break;
+            }
+            valid += 1;
+        }
+
+        // Otherwise a long chain of TXs could be removed again and again for the fee of its first TX
+        let removed_fees = txs[index + 1 + valid..].iter()
+            .fold(txs[index].get_fee(), |fees, tx| fees.saturating_add(tx.get_fee()));
+        let required_fee = removed_fees.saturating_mul(100 + REPLACE_BY_FEE_MIN_INCREASE_PERCENT) / 100;
+        if tx.get_fee() < required_fee {
+            debug!("TX {} can't replace TX {}, it removes {} TXs paying {} fees", hash, replaced, txs.len() - index - valid, removed_fees);
+            return Err(BlockchainError::ReplacementTotalFeeTooLow(replaced.as_ref().clone(), required_fee, tx.get_fee()))
+        }
+
+        let balances = state.get_sender_balances(tx.get_source())
+            .ok_or_else(|| BlockchainError::AccountNotFound(tx.get_source().as_address(self.mainnet)))?
+            .into_iter().map(|(asset, ciphertext)| (asset.clone(), ciphertext)).collect();
+
+        Ok((index, valid, balances))
+    }
+
+    // Replace the TX having the same nonce by a new one paying a higher fee per KB
+    // Returns the replaced TX, the removed TXs and the evicted chains
+    async fn replace_tx<S: Storage>(&mut self, storage: &S, stable_topoheight: u64, topoheight: u64, replaced: Arc<Hash>, hash: Hash, tx: Arc<Transaction>, size: usize, block_version: BlockVersion) -> Result<Vec<(Arc<Hash>, SortedTx)>, BlockchainError> {
+        let replaced_tx = self.txs.get(&replaced).ok_or_else(|| BlockchainError::TxNotFound(replaced.as_ref().clone()))?;
+        let replaced_size = replaced_tx.get_size();
+        let replaced_fee_rate = Self::get_chain_fee_rate_per_kb(replaced_tx.get_fee(), replaced_size);
+        let required_fee_rate = replaced_fee_rate.saturating_mul(100 + REPLACE_BY_FEE_MIN_INCREASE_PERCENT) / 100;
+        let fee_rate = Self::get_chain_fee_rate_per_kb(tx.get_fee(), size);
+        if fee_rate < required_fee_rate {
+            return Err(BlockchainError::ReplacementFeeTooLow(replaced.as_ref().clone(), required_fee_rate, fee_rate))
+        }
+
+        let to_evict = self.select_chains_to_evict(&hash, &tx, size.saturating_sub(replaced_size))?;
+
+        // Take the cache so its balances are not used during the verification
+        // It is put back on any error
+        let key = tx.get_source().clone();
+        let mut cache = self.caches.remove(&key).ok_or_else(|| BlockchainError::TxNotFound(replaced.as_ref().clone()))?;
+        let (index, valid, balances) = match self.verify_replacement(storage, stable_topoheight, topoheight, &cache, &replaced, &hash, &tx, block_version).await {
+            Ok(res) => res,
+            Err(e) => {
+                debug!("TX {} can't replace TX {}: {}", hash, replaced, e);
+                self.caches.insert(key, cache);
+                return Err(e)
+            }
+        };
+
+        let hash = Arc::new(hash);
+        info!("TX {} is replacing TX {} with nonce {}", hash, replaced, tx.get_nonce());
+
+        // Rebuild the ordered TXs with the new one
+        let mut removed = Vec::new();
+        let mut ordered = IndexSet::with_capacity(cache.txs.len());
+        for (i, tx_hash) in cache.txs.drain(..).enumerate() {
+            if i == index {
+                ordered.insert(hash.clone());
+                removed.push(tx_hash);
+            } else if i <= index + valid {
+                ordered.insert(tx_hash);
+            } else {
+                removed.push(tx_hash);
+            }
+        }
+        cache.max = cache.min + ordered.len() as u64 - 1;
+        cache.txs = ordered;
+        cache.set_balances(balances);
+        self.caches.insert(key.clone(), cache);
+
+        let mut evicted = Vec::with_capacity(removed.len());
+        for tx_hash in removed {
+            if let Some(sorted_tx) = self.txs.remove(&tx_hash) {
+                self.bytes -= sorted_tx.get_size();
+                self.fee_estimator.untrack_tx(&tx_hash);
+                evicted.push((tx_hash, sorted_tx));
+            } else {
+                warn!("TX {} not found in mempool while replacing {}", tx_hash, replaced);
+            }
+        }
+
+        for key in to_evict {
+            evicted.extend(self.evict_chain(&key));
+        }
+
+        self.fee_estimator.track_tx(hash.clone(), tx.get_fee(), size, topoheight);
+        let sorted_tx = SortedTx {
+            size,
+            first_seen: get_current_time_in_seconds(),
+            tx
+        };
+
+        self.txs.insert(hash, sorted_tx);
+        self.bytes += size;
+        self.update_chain_fee_rate(&key);
+
+        Ok(evicted)
     }
 
     // Remove a TX using its hash from mempool
     // This will recalculate the cache bounds
     pub fn remove_tx(&mut self, hash: &Hash) -> Result<(), BlockchainError> {
         let tx = self.txs.remove(hash).ok_or_else(|| BlockchainError::TxNotFound(hash.clone()))?;
+        self.bytes -= tx.get_size();
+        self.fee_estimator.untrack_tx(hash);
         // remove the tx hash from sorted txs
         let key = tx.get_tx().get_source();
         let mut delete = false;
@@ -251,6 +544,7 @@ impl Mempool {
             trace!("Removing empty nonce cache for owner {}", key.as_address(self.mainnet));
             self.caches.remove(key);
         }
+        self.update_chain_fee_rate(key);
 
         Ok(())
     }
@@ -272,6 +566,14 @@ impl Mempool {
         Ok(tx)
     }
 
+    // Restore the timestamp when a TX was first seen
+    // This is used when reloading the TXs saved at last stop
+    pub fn set_first_seen(&mut self, hash: &Hash, first_seen: TimestampSeconds) -> Result<(), BlockchainError> {
+        let tx = self.txs.get_mut(hash).ok_or_else(|| BlockchainError::TxNotFound(hash.clone()))?;
+        tx.first_seen = first_seen;
+        Ok(())
+    }
+
     // Get a TX (cloned) from mempool using its hash
     pub fn get_tx(&self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
         let tx = self.get_sorted_tx(hash)?;
@@ -317,6 +619,9 @@ impl Mempool {
     pub fn clear(&mut self) {
         self.txs.clear();
         self.caches.clear();
+        self.chains.clear();
+        self.bytes = 0;
+        self.fee_estimator.clear_pending();
     }
 
     // Drain all txs from mempool
@@ -327,10 +632,75 @@ impl Mempool {
         }
 
         self.caches.clear();
+        self.chains.clear();
+        self.bytes = 0;
+        self.fee_estimator.clear_pending();
 
         txs
     }
 
+    // Check if a TX stayed too long in mempool or if its reference is too far from the chain top
+    fn is_tx_expired(&self, tx: &Transaction, first_seen: TimestampSeconds, now: TimestampSeconds, topoheight: u64) -> bool {
+        now.saturating_sub(first_seen) > self.tx_max_age
+            || topoheight.saturating_sub(tx.get_reference().topoheight) > self.max_reference_distance
+    }
+
+    // delete all expired txs, with all the next txs of the same sender as they depend on them
+    // Balances of the caches left are outdated, `clean_up` must be called after to verify them again
+    pub fn remove_expired_txs(&mut self, topoheight: u64) -> Vec<(Arc<Hash>, SortedTx)> {
+        trace!("Removing expired TXs from mempool...");
+        let now = get_current_time_in_seconds();
+
+        // Lowest expired nonce for each sender
+        let mut expired_nonces = Vec::new();
+        for (key, cache) in self.caches.iter() {
+            let nonce = cache.txs.iter()
+                .filter_map(|hash| self.txs.get(hash))
+                .filter(|sorted_tx| self.is_tx_expired(sorted_tx.get_tx(), sorted_tx.get_first_seen(), now, topoheight))
+                .map(|sorted_tx| sorted_tx.get_tx().get_nonce())
+                .min();
+
+            if let Some(nonce) = nonce {
+                expired_nonces.push((key.clone(), nonce));
+            }
+        }
+
+        let mut expired_transactions = Vec::new();
+        for (key, nonce) in expired_nonces {
+            let Some(cache) = self.caches.get_mut(&key) else {
+                continue;
+            };
+
+            debug!("TXs for owner {} are expired starting at nonce {}", key.as_address(self.mainnet), nonce);
+            let mut hashes = Vec::new();
+            cache.txs.retain(|hash| {
+                let keep = self.txs.get(hash).is_some_and(|sorted_tx| sorted_tx.get_tx().get_nonce() < nonce);
+                if !keep {
+                    hashes.push(Arc::clone(hash));
+                }
+                keep
+            });
+
+            if cache.txs.is_empty() {
+                self.caches.remove(&key);
+            } else {
+                cache.max = nonce - 1;
+            }
+
+            for hash in hashes {
+                if let Some(sorted_tx) = self.txs.remove(&hash) {
+                    debug!("Deleting expired TX {} for owner {}", hash, key.as_address(self.mainnet));
+                    self.bytes -= sorted_tx.get_size();
+                    self.fee_estimator.untrack_tx(&hash);
+                    expired_transactions.push((hash, sorted_tx));
+                }
+            }
+            self.update_chain_fee_rate(&key);
+        }
+
+        expired_transactions
+    }
+
     // delete all old txs not compatible anymore with current state of chain
     // this is called when a new block is added to the chain
     // Because of DAG reorg, we can't only check updated keys from new block,
@@ -338,6 +708,7 @@ impl Mempool {
     // So we need to check all keys from mempool and compare it from storage
     pub async fn clean_up<S: Storage>(&mut self, storage: &S, stable_topoheight: u64, topoheight: u64, block_version: BlockVersion) -> Vec<(Arc<Hash>, SortedTx)> {
         trace!("Cleaning up mempool...");
+        self.fee_estimator.update_topoheight(topoheight);
 
         // All deleted sorted txs with their hashes
         let mut deleted_transactions: Vec<(Arc<Hash>, SortedTx)> = Vec::new();
@@ -513,6 +884,23 @@ impl Mempool {
             }
         }
 
+        // All the chains may have changed, index them again
+        self.chains.clear();
+        let keys: Vec<PublicKey> = self.caches.keys().cloned().collect();
+        for key in keys {
+            self.update_chain_fee_rate(&key);
+        }
+
+        for (hash, sorted_tx) in deleted_transactions.iter() {
+            self.bytes -= sorted_tx.get_size();
+            // Only the TXs executed are used for the fee estimation
+            if storage.is_tx_executed_in_a_block(hash).unwrap_or(false) {
+                self.fee_estimator.confirm_tx(hash, topoheight);
+            } else {
+                self.fee_estimator.untrack_tx(hash);
+            }
+        }
+
         deleted_transactions
     }
 
@@ -619,8 +1007,194 @@ impl AccountCache {
 
 #[cfg(test)]
 mod tests {
+    use xelis_common::{
+        crypto::{Hashable, KeyPair},
+        transaction::builder::FeeBuilder
+    };
+    use crate::{
+        config::{DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE, DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER, DEFAULT_MEMPOOL_TX_MAX_AGE},
+        core::{
+            blockchain::Config,
+            hard_fork::get_version_at_height,
+            test_chain::{BlockSpec, TestChain, GENESIS}
+        }
+    };
     use super::*;
 
+    async fn add_tx(mempool: &mut Mempool, chain: &TestChain, tx: &Arc<Transaction>) -> Result<Vec<(Arc<Hash>, SortedTx)>, BlockchainError> {
+        let blockchain = chain.blockchain();
+        let storage = blockchain.get_storage().read().await;
+        let version = get_version_at_height(blockchain.get_network(), blockchain.get_height());
+        mempool.add_tx(&*storage, blockchain.get_stable_topoheight(), blockchain.get_topo_height(), tx.hash(), tx.clone(), tx.size(), version).await
+    }
+
+    #[tokio::test]
+    async fn test_mempool_eviction() {
+        let mut chain = TestChain::new().await;
+        let labels = ["a", "b", "c", "d"];
+        let accounts: Vec<KeyPair> = labels.iter().map(|_| KeyPair::new()).collect();
+        let mut tip = GENESIS;
+        for (label, account) in labels.iter().zip(accounts.iter()) {
+            chain.add_block(BlockSpec::new(label, &[tip]).miner(&account.get_public_key().compress())).await.unwrap();
+            tip = *label;
+        }
+
+        // Same size for all TXs, only the fee is different
+        let destination = KeyPair::new().get_public_key().compress();
+        let mut txs = Vec::new();
+        for ((label, account), multiplier) in labels.iter().zip(accounts.iter()).zip([2f64, 3f64, 4f64, 1f64]) {
+            let balance = chain.miner_reward(label).await;
+            let tx = chain.transfer_with_fee(account, balance, "d", &destination, 1, FeeBuilder::Multiplier(multiplier)).await;
+            txs.push(Arc::new(tx));
+        }
+
+        let size = txs[0].size();
+        let mut mempool = Mempool::new(Network::Dev, size * 2, DEFAULT_MEMPOOL_MAX_TXS_PER_SENDER, DEFAULT_MEMPOOL_TX_MAX_AGE, DEFAULT_MEMPOOL_MAX_REFERENCE_DISTANCE);
+        assert!(add_tx(&mut mempool, &chain, &txs[0]).await.unwrap().is_empty());
+        assert!(add_tx(&mut mempool, &chain, &txs[1]).await.unwrap().is_empty());
+
+        // Lowest fee, nothing can be evicted for it
+        let err = add_tx(&mut mempool, &chain, &txs[3]).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::MempoolFull(_)));
+        assert_eq!(mempool.size(), 2);
+
+        // Highest fee, the lowest fee chain is evicted
+        let evicted = add_tx(&mut mempool, &chain, &txs[2]).await.unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(*evicted[0].0, txs[0].hash());
+        assert!(!mempool.contains_tx(&txs[0].hash()));
+        assert!(mempool.get_cache_for(txs[0].get_source()).is_none());
+        assert_eq!(mempool.size(), 2);
+        assert_eq!(mempool.bytes, size * 2);
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_mempool_replace_by_fee() {
+        let mut chain = TestChain::new().await;
+        let alice = KeyPair::new();
+        chain.add_block(BlockSpec::new("a", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+
+        let balance = chain.miner_reward("a").await;
+        let destination = KeyPair::new().get_public_key().compress();
+        let tx = chain.transfer(&alice, balance, "a", &destination, 1).await;
+        let (tx_hash, fee) = (tx.hash(), tx.get_fee());
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+
+        let dependent = chain.transfer(&alice, balance - 1 - fee, "a", &destination, 1).await;
+        let dependent_hash = dependent.hash();
+        chain.blockchain().add_tx_to_mempool(dependent, false).await.unwrap();
+
+        // Same fee per KB
+        let replacement = chain.replace_transfer(&alice, balance, "a", &destination, 1, FeeBuilder::Value(fee)).await;
+        assert_eq!(replacement.get_nonce(), 0);
+        let err = chain.blockchain().add_tx_to_mempool(replacement, false).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ReplacementFeeTooLow(_, _, _)));
+
+        // Higher fee per KB, but it doesn't pay for the dependent TX removed
+        let replacement = chain.replace_transfer(&alice, balance, "a", &destination, 1, FeeBuilder::Value(fee * 2)).await;
+        let err = chain.blockchain().add_tx_to_mempool(replacement, false).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::ReplacementTotalFeeTooLow(_, _, _)));
+        {
+            // Nothing changed in mempool
+            let mempool = chain.blockchain().get_mempool().read().await;
+            assert!(mempool.contains_tx(&tx_hash));
+            assert!(mempool.contains_tx(&dependent_hash));
+            assert!(mempool.get_cache_for(&alice.get_public_key().compress()).is_some());
+        }
+
+        let replacement = chain.replace_transfer(&alice, balance, "a", &destination, 1, FeeBuilder::Value(fee * 3)).await;
+        let replacement_hash = replacement.hash();
+        chain.blockchain().add_tx_to_mempool(replacement, false).await.unwrap();
+
+        {
+            let mempool = chain.blockchain().get_mempool().read().await;
+            assert!(mempool.contains_tx(&replacement_hash));
+            assert!(!mempool.contains_tx(&tx_hash));
+            // The dependent TX was built on the balance of the replaced one
+            assert!(!mempool.contains_tx(&dependent_hash));
+
+            let cache = mempool.get_cache_for(&alice.get_public_key().compress()).unwrap();
+            assert_eq!((cache.get_min(), cache.get_max()), (0, 0));
+            assert_eq!(mempool.size(), 1);
+        }
+
+        // A TX can be chained after the replacement
+        let tx = chain.transfer(&alice, balance - 1 - fee * 3, "a", &destination, 1).await;
+        assert_eq!(tx.get_nonce(), 1);
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_mempool_max_txs_per_sender() {
+        let mut config = Config::headless();
+        config.mempool_max_txs_per_sender = 1;
+        let mut chain = TestChain::with_config(config).await;
+        let alice = KeyPair::new();
+        chain.add_block(BlockSpec::new("a", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+
+        let balance = chain.miner_reward("a").await;
+        let destination = KeyPair::new().get_public_key().compress();
+        let tx = chain.transfer(&alice, balance, "a", &destination, 1).await;
+        let fee = tx.get_fee();
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+
+        let tx = chain.transfer(&alice, balance - 1 - fee, "a", &destination, 1).await;
+        assert_eq!(tx.get_nonce(), 1);
+        let err = chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::TooManyTxsForSender(_, 1)));
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_mempool_expired_txs() {
+        let mut config = Config::headless();
+        config.mempool_max_reference_distance = 1;
+        let mut chain = TestChain::with_config(config).await;
+        let alice = KeyPair::new();
+        let bob = KeyPair::new();
+        chain.add_block(BlockSpec::new("a", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+        chain.add_block(BlockSpec::new("b", &["a"]).miner(&bob.get_public_key().compress())).await.unwrap();
+
+        let balance = chain.miner_reward("a").await;
+        let destination = KeyPair::new().get_public_key().compress();
+        let tx = chain.transfer(&alice, balance, "a", &destination, 1).await;
+        let fee = tx.get_fee();
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+        let tx = chain.transfer(&alice, balance - 1 - fee, "a", &destination, 1).await;
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+
+        let tx = chain.transfer(&bob, chain.miner_reward("b").await, "b", &destination, 1).await;
+        let bob_hash = tx.hash();
+        chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap();
+
+        // Reference of Alice TXs is now too far from the chain top
+        chain.add_block(BlockSpec::new("c", &["b"])).await.unwrap();
+        let tx = chain.transfer(&alice, balance, "a", &destination, 1).await;
+        let err = chain.blockchain().add_tx_to_mempool(tx, false).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::TxExpired(_, _)));
+        {
+            let mut mempool = chain.blockchain().get_mempool().write().await;
+            assert!(mempool.get_cache_for(&alice.get_public_key().compress()).is_none());
+            assert_eq!(mempool.size(), 1);
+            assert!(mempool.contains_tx(&bob_hash));
+
+            // Bob TX stayed too long in mempool
+            mempool.set_first_seen(&bob_hash, 0).unwrap();
+            let expired = mempool.remove_expired_txs(chain.blockchain().get_topo_height());
+            assert_eq!(expired.len(), 1);
+            assert_eq!(*expired[0].0, bob_hash);
+            assert_eq!(mempool.size(), 0);
+            assert_eq!(mempool.bytes, 0);
+        }
+
+        chain.stop().await;
+    }
+
     #[test]
     fn test_estimated_fee_rates() {
         // Let say we have the following TXs:
@@ -9,5 +9,13 @@ pub mod nonce_checker;
 pub mod tx_selector;
 pub mod state;
 pub mod merkle;
+pub mod chain_file;
+pub mod state_snapshot;
+pub mod state_tree;
+pub mod retention;
+pub mod replay;
+pub mod fee_estimator;
+#[cfg(test)]
+pub mod test_chain;
 
 pub mod hard_fork;
\ No newline at end of file
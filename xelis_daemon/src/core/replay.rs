@@ -0,0 +1,327 @@
+use std::fmt;
+use indexmap::IndexSet;
+use log::{debug, info, warn};
+use xelis_common::{
+    account::{CiphertextCache, VersionedBalance},
+    config::XELIS_ASSET,
+    crypto::{Address, Hash, PublicKey},
+    network::Network,
+    transaction::TransactionType
+};
+use tokio::sync::RwLock;
+use crate::config::{DEV_PUBLIC_KEY, REPLAY_BLOCKS_CHUNK_SIZE};
+use super::{
+    blockchain::{Blockchain, Config},
+    error::BlockchainError,
+    state_tree::{build_state_tree, update_state_tree, StateTree},
+    storage::{DagOrderProvider, MemoryStorage, Storage}
+};
+
+// First difference found between the stored chain and its replay
+#[derive(Debug)]
+pub enum Divergence {
+    // Another block (or none) is ordered at this topoheight
+    Order {
+        topoheight: u64,
+        expected: Hash,
+        actual: Option<Hash>
+    },
+    // Final balance, or output balance if `output` is set
+    Balance {
+        topoheight: u64,
+        account: Address,
+        asset: Hash,
+        output: bool,
+        expected: Option<CiphertextCache>,
+        actual: Option<CiphertextCache>
+    },
+    Nonce {
+        topoheight: u64,
+        account: Address,
+        expected: Option<u64>,
+        actual: Option<u64>
+    },
+    StateRoot {
+        topoheight: u64,
+        expected: Hash,
+        actual: Hash
+    }
+}
+
+fn display_option<T: fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "none".to_owned()
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Order { topoheight, expected, actual } => write!(f, "Block at topoheight {}: expected {}, got {}", topoheight, expected, display_option(actual)),
+            Self::Balance { topoheight, account, asset, output, expected, actual } => write!(
+                f,
+                "{} balance of {} for asset {} at topoheight {}: expected {}, got {}",
+                if *output { "Output" } else { "Final" },
+                account,
+                asset,
+                topoheight,
+                display_option(expected),
+                display_option(actual)
+            ),
+            Self::Nonce { topoheight, account, expected, actual } => write!(f, "Nonce of {} at topoheight {}: expected {}, got {}", account, topoheight, display_option(expected), display_option(actual)),
+            Self::StateRoot { topoheight, expected, actual } => write!(f, "State root at topoheight {}: expected {}, got {}", topoheight, expected, actual)
+        }
+    }
+}
+
+async fn get_balance_at_exact_topoheight<S: Storage>(storage: &S, key: &PublicKey, asset: &Hash, topoheight: u64) -> Result<Option<VersionedBalance>, BlockchainError> {
+    if storage.has_balance_at_exact_topoheight(key, asset, topoheight).await? {
+        Ok(Some(storage.get_balance_at_exact_topoheight(key, asset, topoheight).await?))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn get_nonce_at_exact_topoheight<S: Storage>(storage: &S, key: &PublicKey, topoheight: u64) -> Result<Option<u64>, BlockchainError> {
+    if storage.has_nonce_at_exact_topoheight(key, topoheight).await? {
+        Ok(Some(storage.get_nonce_at_exact_topoheight(key, topoheight).await?.get_nonce()))
+    } else {
+        Ok(None)
+    }
+}
+
+// Re-execute all the blocks from the genesis block until the topoheight `to` in an in-memory chain
+// Then compare the balances, nonces and state roots stored in range [from, to] with the replayed ones
+// Orphaned blocks are replayed too as they are used to compute the side blocks rewards
+// The stored chain is read by chunks and its lock is released between them,
+// so `to` must be a stable topoheight if blocks are still added to it
+// Returns the first divergence found
+pub async fn replay_blocks<S: Storage>(storage: &RwLock<S>, network: Network, from: u64, to: u64) -> Result<Option<Divergence>, BlockchainError> {
+    let genesis = {
+        let storage = storage.read().await;
+        if from > to || to > storage.get_top_topoheight()? {
+            return Err(BlockchainError::InvalidReplayRange(from, to))
+        }
+
+        // All the blocks with their transactions are required since the genesis block
+        if storage.get_pruned_topoheight().await?.is_some() || storage.get_txs_pruned_topoheight().await?.is_some() {
+            return Err(BlockchainError::StateNotAvailable(from))
+        }
+
+        if let Some(pruned_topoheight) = storage.get_balances_pruned_topoheight().await? {
+            if from < pruned_topoheight {
+                return Err(BlockchainError::StateNotAvailable(from))
+            }
+        }
+
+        storage.get_block_by_hash(&storage.get_hash_at_topo_height(0).await?).await?
+    };
+
+    let replay = Blockchain::with_genesis(Config::headless(), network, MemoryStorage::new(network)?, Some(genesis)).await?;
+
+    let res = replay_and_compare(storage, &replay, from, to).await;
+    replay.stop().await;
+
+    res
+}
+
+async fn replay_and_compare<S: Storage>(storage: &RwLock<S>, replay: &Blockchain<MemoryStorage>, from: u64, to: u64) -> Result<Option<Divergence>, BlockchainError> {
+    // Replay every block up to the highest height ordered in range
+    let mut max_height = 0;
+    for start in (0..=to).step_by(REPLAY_BLOCKS_CHUNK_SIZE as usize) {
+        let storage = storage.read().await;
+        for topoheight in start..=to.min(start + REPLAY_BLOCKS_CHUNK_SIZE - 1) {
+            let hash = storage.get_hash_at_topo_height(topoheight).await?;
+            max_height = max_height.max(storage.get_height_for_block_hash(&hash).await?);
+        }
+    }
+
+    info!("Replaying blocks until height {}", max_height);
+    for start in (1..=max_height).step_by(REPLAY_BLOCKS_CHUNK_SIZE as usize) {
+        let end = max_height.min(start + REPLAY_BLOCKS_CHUNK_SIZE - 1);
+        // Copy the blocks of this chunk, the lock isn't held while they are executed
+        let mut blocks = Vec::new();
+        {
+            let storage = storage.read().await;
+            for height in start..=end {
+                // Add them in their topological order, orphaned blocks last
+                let mut at_height = Vec::new();
+                for hash in storage.get_blocks_at_height(height).await? {
+                    let topoheight = if storage.is_block_topological_ordered(&hash).await {
+                        Some(storage.get_topo_height_for_hash(&hash).await?)
+                    } else {
+                        None
+                    };
+                    at_height.push((hash, topoheight));
+                }
+                at_height.sort_by_key(|(_, topoheight)| topoheight.unwrap_or(u64::MAX));
+
+                for (hash, topoheight) in at_height {
+                    let block = storage.get_block_by_hash(&hash).await?;
+                    blocks.push((hash, topoheight, block));
+                }
+            }
+        }
+
+        for (hash, topoheight, block) in blocks {
+            if let Err(e) = replay.add_new_block(block, false, false).await {
+                match topoheight {
+                    Some(topoheight) if topoheight <= to => return Err(BlockchainError::ReplayBlock(hash, topoheight, Box::new(e))),
+                    _ => warn!("Skipping block {} not ordered in replayed range: {}", hash, e)
+                }
+            }
+        }
+
+        info!("Replayed blocks until height {}/{}", end, max_height);
+    }
+
+    info!("Comparing state from topoheight {} to {}", from, to);
+    let replayed = replay.get_storage().read().await;
+    // State tree of the replayed chain, moved forward with each topoheight compared
+    let mut replayed_tree: Option<(u64, StateTree)> = None;
+    for start in (from..=to).step_by(REPLAY_BLOCKS_CHUNK_SIZE as usize) {
+        let guard = storage.read().await;
+        let storage = &*guard;
+        for topoheight in start..=to.min(start + REPLAY_BLOCKS_CHUNK_SIZE - 1) {
+            if let Some(divergence) = compare_topoheight(storage, &*replayed, &mut replayed_tree, topoheight).await? {
+                return Ok(Some(divergence))
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Compare the state changed at a topoheight between the stored and the replayed chains
+async fn compare_topoheight<S: Storage>(storage: &S, replayed: &MemoryStorage, replayed_tree: &mut Option<(u64, StateTree)>, topoheight: u64) -> Result<Option<Divergence>, BlockchainError> {
+    let hash = storage.get_hash_at_topo_height(topoheight).await?;
+    let mut actual = replayed.get_hash_at_topo_height(topoheight).await.ok();
+    if let Some(actual_hash) = actual.as_ref() {
+        if !replayed.is_block_topological_ordered(actual_hash).await {
+            actual = None;
+        }
+    }
+
+    if actual.as_ref() != Some(&hash) {
+        return Ok(Some(Divergence::Order { topoheight, expected: hash, actual }))
+    }
+
+    // Collect all the accounts that may be updated by this block
+    let block = storage.get_block_by_hash(&hash).await?;
+    let mut balances = IndexSet::new();
+    let mut nonces = IndexSet::new();
+    balances.insert((block.get_miner().clone(), XELIS_ASSET));
+    balances.insert((DEV_PUBLIC_KEY.clone(), XELIS_ASSET));
+    for tx in block.get_transactions() {
+        nonces.insert(tx.get_source().clone());
+        for asset in tx.get_assets() {
+            balances.insert((tx.get_source().clone(), asset.clone()));
+        }
+
+        if let TransactionType::Transfers(transfers) = tx.get_data() {
+            for transfer in transfers {
+                balances.insert((transfer.get_destination().clone(), transfer.get_asset().clone()));
+            }
+        }
+    }
+
+    debug!("Comparing {} balances and {} nonces at topoheight {}", balances.len(), nonces.len(), topoheight);
+    let mainnet = storage.is_mainnet();
+    for (key, asset) in balances {
+        let expected = get_balance_at_exact_topoheight(storage, &key, &asset, topoheight).await?;
+        let actual = get_balance_at_exact_topoheight(replayed, &key, &asset, topoheight).await?;
+        let (expected_final, expected_output) = match expected {
+            Some(version) => {
+                let (final_balance, output_balance, _, _) = version.consume();
+                (Some(final_balance), output_balance)
+            },
+            None => (None, None)
+        };
+        let (actual_final, actual_output) = match actual {
+            Some(version) => {
+                let (final_balance, output_balance, _, _) = version.consume();
+                (Some(final_balance), output_balance)
+            },
+            None => (None, None)
+        };
+
+        for (output, expected, actual) in [(false, expected_final, actual_final), (true, expected_output, actual_output)] {
+            if expected.as_ref().map(CiphertextCache::compress) != actual.as_ref().map(CiphertextCache::compress) {
+                return Ok(Some(Divergence::Balance { topoheight, account: key.as_address(mainnet), asset, output, expected, actual }))
+            }
+        }
+    }
+
+    for key in nonces {
+        let expected = get_nonce_at_exact_topoheight(storage, &key, topoheight).await?;
+        let actual = get_nonce_at_exact_topoheight(replayed, &key, topoheight).await?;
+        if expected != actual {
+            return Ok(Some(Divergence::Nonce { topoheight, account: key.as_address(mainnet), expected, actual }))
+        }
+    }
+
+    // Compare the state roots stored for this topoheight
+    if storage.has_state_root_at_topoheight(topoheight).await? {
+        let expected = storage.get_state_root_at_topoheight(topoheight).await?;
+        let tree = match replayed_tree.take() {
+            Some((tree_topoheight, mut tree)) => {
+                for topo in tree_topoheight + 1..=topoheight {
+                    update_state_tree(&mut tree, replayed, topo).await?;
+                }
+                tree
+            },
+            None => build_state_tree(replayed, topoheight).await?
+        };
+        let (_, tree) = replayed_tree.insert((topoheight, tree));
+
+        let actual = tree.root()?;
+        if expected != actual {
+            return Ok(Some(Divergence::StateRoot { topoheight, expected, actual }))
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        storage::BalanceProvider,
+        test_chain::{BlockSpec, TestChain, GENESIS}
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay_blocks() {
+        let mut chain = TestChain::new().await;
+        chain.add_block(BlockSpec::new("a", &[GENESIS])).await.unwrap();
+        chain.add_block(BlockSpec::new("b", &["a"])).await.unwrap();
+        chain.add_block(BlockSpec::new("c", &["b"])).await.unwrap();
+
+        let storage = chain.blockchain().get_storage();
+        assert!(replay_blocks(storage, Network::Dev, 0, 3).await.unwrap().is_none());
+        assert!(matches!(replay_blocks(storage, Network::Dev, 2, 4).await, Err(BlockchainError::InvalidReplayRange(2, 4))));
+
+        // Corrupt the miner balance stored at topoheight 2
+        {
+            let mut storage = chain.blockchain().get_storage().write().await;
+            let mut version = VersionedBalance::zero();
+            version.set_previous_topoheight(Some(1));
+            storage.set_balance_at_topoheight(&XELIS_ASSET, 2, &chain.miner(), &version).await.unwrap();
+        }
+
+        let divergence = replay_blocks(chain.blockchain().get_storage(), Network::Dev, 1, 3).await.unwrap();
+        match divergence {
+            Some(Divergence::Balance { topoheight, account, output, expected, actual, .. }) => {
+                assert_eq!(topoheight, 2);
+                assert_eq!(account, chain.miner().as_address(false));
+                assert!(!output);
+                assert!(expected.is_some() && actual.is_some());
+            },
+            _ => panic!("Expected a balance divergence")
+        }
+
+        chain.stop().await;
+    }
+}
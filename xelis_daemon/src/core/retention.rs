@@ -0,0 +1,144 @@
+use indexmap::IndexSet;
+use log::{debug, trace};
+use xelis_common::crypto::PublicKey;
+use super::{error::BlockchainError, storage::Storage};
+
+// Delete the transactions bodies of all the blocks ordered in range [from, to)
+// Blocks headers and the blocks linked to each transaction are kept
+// A transaction is deleted only if all the blocks including it are ordered below `to`
+// Returns the count of transactions deleted
+pub async fn prune_txs_in_range<S: Storage>(storage: &mut S, from: u64, to: u64) -> Result<usize, BlockchainError> {
+    debug!("Pruning transactions from topoheight {} to {}", from, to);
+    let mut count = 0;
+    for topoheight in from..to {
+        let hash = storage.get_hash_at_topo_height(topoheight).await?;
+        let header = storage.get_block_header_by_hash(&hash).await?;
+        'txs: for tx in header.get_txs_hashes() {
+            // It may have been deleted already through another block
+            if !storage.has_transaction(tx).await? {
+                continue;
+            }
+
+            if storage.has_tx_blocks(tx)? {
+                for block in storage.get_blocks_for_tx(tx)? {
+                    // Orphaned blocks may still be reordered, keep the transaction for them
+                    if !storage.is_block_topological_ordered(&block).await || storage.get_topo_height_for_hash(&block).await? >= to {
+                        trace!("TX {} is also included in block {}, keeping it", tx, block);
+                        continue 'txs;
+                    }
+                }
+            }
+
+            trace!("Pruning TX {} from block {} at topoheight {}", tx, hash, topoheight);
+            storage.prune_transaction(tx).await?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+// Delete the balances history below the topoheight, except for the whitelisted accounts
+// The version in use at topoheight is kept as the first version available,
+// so every balance at or above the topoheight can still be retrieved
+// Only the balances updated in range [from, topoheight) are visited: the version in use at `from`
+// of every other balance was already made the first one available by the previous run
+// Returns the count of versions deleted
+pub async fn prune_balances_history<S: Storage>(storage: &mut S, from: u64, topoheight: u64, whitelist: &IndexSet<PublicKey>) -> Result<usize, BlockchainError> {
+    debug!("Pruning balances history from topoheight {} to {}", from, topoheight);
+    let mut balances = IndexSet::new();
+    for topo in from..topoheight {
+        for (key, asset, _) in storage.get_balances_updated_at_topoheight(topo).await? {
+            if !whitelist.contains(&key) {
+                balances.insert((key, asset));
+            }
+        }
+    }
+
+    let mut count = 0;
+    for (key, asset) in balances {
+        let Some((version_topoheight, mut version)) = storage.get_balance_at_maximum_topoheight(&key, &asset, topoheight).await? else {
+            continue;
+        };
+
+        let Some(previous_topoheight) = version.get_previous_topoheight() else {
+            continue;
+        };
+
+        trace!("Pruning balance history of {} for asset {} below topoheight {}", key.as_address(storage.is_mainnet()), asset, version_topoheight);
+        // This version is now the first one available
+        version.set_previous_topoheight(None);
+        storage.set_balance_at_topoheight(&asset, version_topoheight, &key, &version).await?;
+
+        let mut next = Some(previous_topoheight);
+        while let Some(previous_topoheight) = next {
+            let previous = storage.delete_balance_at_topoheight(&key, &asset, previous_topoheight).await?;
+            next = previous.get_previous_topoheight();
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{
+        account::VersionedBalance,
+        config::XELIS_ASSET,
+        crypto::KeyPair,
+        network::Network
+    };
+    use crate::core::storage::{AccountProvider, BalanceProvider, MemoryStorage};
+    use super::*;
+
+    // Store a balance version at each topoheight given
+    async fn create_account(storage: &mut MemoryStorage, topoheights: &[u64]) -> PublicKey {
+        let key = KeyPair::new().get_public_key().compress();
+        storage.set_account_registration_topoheight(&key, 0).await.unwrap();
+
+        let mut previous = None;
+        for topoheight in topoheights.iter().copied() {
+            let mut version = VersionedBalance::zero();
+            version.set_previous_topoheight(previous);
+            storage.set_last_balance_to(&key, &XELIS_ASSET, topoheight, &version).await.unwrap();
+            previous = Some(topoheight);
+        }
+
+        key
+    }
+
+    #[tokio::test]
+    async fn test_prune_balances_history() {
+        let mut storage = MemoryStorage::new(Network::Dev).unwrap();
+        let pruned = create_account(&mut storage, &[0, 5, 10, 20]).await;
+        let whitelisted = create_account(&mut storage, &[0, 5, 10, 20]).await;
+
+        let mut whitelist = IndexSet::new();
+        whitelist.insert(whitelisted.clone());
+
+        let count = prune_balances_history(&mut storage, 0, 15, &whitelist).await.unwrap();
+        assert_eq!(count, 2);
+
+        // The version used at topoheight 15 is still available
+        let (topoheight, version) = storage.get_balance_at_maximum_topoheight(&pruned, &XELIS_ASSET, 15).await.unwrap().unwrap();
+        assert_eq!(topoheight, 10);
+        assert_eq!(version.get_previous_topoheight(), None);
+        assert!(storage.get_balance_at_maximum_topoheight(&pruned, &XELIS_ASSET, 5).await.unwrap().is_none());
+        assert!(storage.get_balance_at_maximum_topoheight(&pruned, &XELIS_ASSET, 20).await.unwrap().is_some());
+
+        // Whitelisted account keeps its full history
+        let (topoheight, _) = storage.get_balance_at_maximum_topoheight(&whitelisted, &XELIS_ASSET, 5).await.unwrap().unwrap();
+        assert_eq!(topoheight, 5);
+
+        // Nothing left to prune
+        assert_eq!(prune_balances_history(&mut storage, 0, 15, &whitelist).await.unwrap(), 0);
+
+        // Only the balances updated since the last run are visited
+        let count = prune_balances_history(&mut storage, 15, 25, &whitelist).await.unwrap();
+        assert_eq!(count, 1);
+        let (topoheight, version) = storage.get_balance_at_maximum_topoheight(&pruned, &XELIS_ASSET, 25).await.unwrap().unwrap();
+        assert_eq!(topoheight, 20);
+        assert_eq!(version.get_previous_topoheight(), None);
+    }
+}
@@ -0,0 +1,794 @@
+use std::{
+    collections::HashSet,
+    io::{Read, Write}
+};
+use log::{debug, trace};
+use xelis_common::{
+    account::{AccountSummary, VersionedNonce},
+    asset::AssetWithData,
+    block::Block,
+    config::MAX_BLOCK_SIZE,
+    crypto::{
+        Hash,
+        Hashable,
+        KeyPair,
+        PublicKey,
+        Signature,
+        HASH_SIZE,
+        SIGNATURE_SIZE
+    },
+    network::Network,
+    serializer::{Reader, ReaderError, Serializer, Writer}
+};
+use crate::{
+    config::PRUNE_SAFETY_LIMIT,
+    p2p::packet::bootstrap_chain::{BlockMetadata, MAX_ITEMS_PER_PAGE}
+};
+use super::{
+    error::BlockchainError,
+    state_tree::StateTree,
+    storage::Storage
+};
+
+// Magic bytes written at the start of every state snapshot
+const STATE_SNAPSHOT_MAGIC: &[u8; 4] = b"XELS";
+// Current version of the state snapshot format
+pub const STATE_SNAPSHOT_VERSION: u8 = 1;
+// magic (4) + version (1) + network (1) + topoheight (8)
+const STATE_SNAPSHOT_HEADER_SIZE: usize = 14;
+// Number of blocks stored in a snapshot: the stable block and the ones below it
+pub const STATE_SNAPSHOT_BLOCKS: u64 = PRUNE_SAFETY_LIMIT + 1;
+
+// Header of a state snapshot
+// The snapshot contains the whole state at the topoheight
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSnapshotHeader {
+    pub network: Network,
+    pub topoheight: u64
+}
+
+impl StateSnapshotHeader {
+    pub fn new(network: Network, topoheight: u64) -> Self {
+        Self {
+            network,
+            topoheight
+        }
+    }
+
+    // Lowest topoheight of the blocks stored in the snapshot
+    pub fn lowest_topoheight(&self) -> u64 {
+        self.topoheight - PRUNE_SAFETY_LIMIT
+    }
+}
+
+impl Serializer for StateSnapshotHeader {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(STATE_SNAPSHOT_MAGIC);
+        writer.write_u8(STATE_SNAPSHOT_VERSION);
+        self.network.write(writer);
+        writer.write_u64(&self.topoheight);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let magic: [u8; 4] = reader.read_bytes(4)?;
+        if magic != *STATE_SNAPSHOT_MAGIC {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        if reader.read_u8()? != STATE_SNAPSHOT_VERSION {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        let network = Network::read(reader)?;
+        let topoheight = reader.read_u64()?;
+        if topoheight < PRUNE_SAFETY_LIMIT {
+            return Err(ReaderError::InvalidValue)
+        }
+
+        Ok(Self::new(network, topoheight))
+    }
+
+    fn size(&self) -> usize {
+        STATE_SNAPSHOT_HEADER_SIZE
+    }
+}
+
+// State of an account at the snapshot topoheight
+#[derive(Debug)]
+pub struct SnapshotAccount {
+    pub key: PublicKey,
+    pub registration_topoheight: u64,
+    // Topoheight of the last nonce change
+    pub nonce_topoheight: u64,
+    pub nonce: u64,
+    // Balances sorted by asset
+    pub balances: Vec<(Hash, AccountSummary)>
+}
+
+impl SnapshotAccount {
+    // Insert the nonce and the balances in the state tree
+    // Only the data that is the same on every node is used:
+    // the registration and versions topoheights depend on how the node got synced
+    pub fn insert_into(&self, tree: &mut StateTree) {
+        tree.insert_nonce(&self.key, self.nonce);
+        for (asset, summary) in self.balances.iter() {
+            tree.insert_balance(&self.key, asset, &summary.get_stable_version().final_balance);
+        }
+    }
+}
+
+impl Serializer for SnapshotAccount {
+    fn write(&self, writer: &mut Writer) {
+        self.key.write(writer);
+        writer.write_u64(&self.registration_topoheight);
+        writer.write_u64(&self.nonce_topoheight);
+        writer.write_u64(&self.nonce);
+        writer.write_u32(&(self.balances.len() as u32));
+        for (asset, summary) in self.balances.iter() {
+            writer.write_hash(asset);
+            summary.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let key = PublicKey::read(reader)?;
+        let registration_topoheight = reader.read_u64()?;
+        let nonce_topoheight = reader.read_u64()?;
+        let nonce = reader.read_u64()?;
+
+        let count = reader.read_u32()?;
+        let mut balances: Vec<(Hash, AccountSummary)> = Vec::new();
+        for _ in 0..count {
+            let asset = reader.read_hash()?;
+            // Balances must be sorted by asset
+            if let Some((previous, _)) = balances.last() {
+                if *previous >= asset {
+                    return Err(ReaderError::InvalidValue)
+                }
+            }
+            let summary = AccountSummary::read(reader)?;
+            balances.push((asset, summary));
+        }
+
+        Ok(Self {
+            key,
+            registration_topoheight,
+            nonce_topoheight,
+            nonce,
+            balances
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.key.size()
+        + self.registration_topoheight.size()
+        + self.nonce_topoheight.size()
+        + self.nonce.size()
+        + 4
+        + self.balances.iter().map(|(asset, summary)| asset.size() + summary.size()).sum::<usize>()
+    }
+}
+
+// Block stored in the snapshot with its metadata
+#[derive(Debug)]
+pub struct SnapshotBlock {
+    pub topoheight: u64,
+    pub metadata: BlockMetadata,
+    pub block: Block
+}
+
+impl Serializer for SnapshotBlock {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.topoheight);
+        self.metadata.write(writer);
+        self.block.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let topoheight = reader.read_u64()?;
+        let metadata = BlockMetadata::read(reader)?;
+        let block = Block::read(reader)?;
+
+        Ok(Self {
+            topoheight,
+            metadata,
+            block
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.topoheight.size() + self.metadata.size() + self.block.size()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum EntryKind {
+    End,
+    Asset,
+    Account,
+    Block
+}
+
+impl EntryKind {
+    fn id(&self) -> u8 {
+        match self {
+            Self::End => 0,
+            Self::Asset => 1,
+            Self::Account => 2,
+            Self::Block => 3
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Self::End,
+            1 => Self::Asset,
+            2 => Self::Account,
+            3 => Self::Block,
+            _ => return None
+        })
+    }
+}
+
+// Entries must be written in a canonical order:
+// assets sorted by hash, then accounts sorted by key, then blocks sorted by topoheight
+// So two nodes will always produce the same file for the same state
+struct EntryOrder {
+    last: Option<(EntryKind, Vec<u8>)>
+}
+
+impl EntryOrder {
+    fn new() -> Self {
+        Self {
+            last: None
+        }
+    }
+
+    fn check(&mut self, kind: EntryKind, key: &[u8]) -> Result<(), BlockchainError> {
+        if let Some((last_kind, last_key)) = &self.last {
+            if (kind, key) <= (*last_kind, last_key.as_slice()) {
+                return Err(BlockchainError::InvalidStateSnapshot("entries are not in canonical order"))
+            }
+        }
+        self.last = Some((kind, key.to_vec()));
+        Ok(())
+    }
+}
+
+pub enum SnapshotEntry {
+    Asset(AssetWithData),
+    Account(SnapshotAccount),
+    Block(SnapshotBlock)
+}
+
+// Write the state in a snapshot file
+// Each entry is prefixed by its kind and size
+// The merkle hash, a blake3 checksum of all the previous bytes
+// and the signature of the checksum are appended at the end of the file
+pub struct StateSnapshotWriter<W: Write> {
+    inner: W,
+    hasher: blake3::Hasher,
+    tree: StateTree,
+    order: EntryOrder,
+    // Assets written until now
+    assets: u64,
+    // Blocks written until now
+    blocks: u64
+}
+
+impl<W: Write> StateSnapshotWriter<W> {
+    pub fn new(mut inner: W, header: &StateSnapshotHeader) -> Result<Self, BlockchainError> {
+        let bytes = header.to_bytes();
+        inner.write_all(&bytes)?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&bytes);
+
+        Ok(Self {
+            inner,
+            hasher,
+            tree: StateTree::new(),
+            order: EntryOrder::new(),
+            assets: 0,
+            blocks: 0
+        })
+    }
+
+    fn write_entry(&mut self, kind: EntryKind, bytes: &[u8]) -> Result<(), BlockchainError> {
+        let id = [kind.id()];
+        let size = (bytes.len() as u32).to_be_bytes();
+        self.inner.write_all(&id)?;
+        self.inner.write_all(&size)?;
+        self.inner.write_all(bytes)?;
+        self.hasher.update(&id);
+        self.hasher.update(&size);
+        self.hasher.update(bytes);
+
+        Ok(())
+    }
+
+    pub fn write_asset(&mut self, asset: &AssetWithData) -> Result<(), BlockchainError> {
+        self.order.check(EntryKind::Asset, asset.get_asset().as_bytes())?;
+        self.tree.insert_asset(asset);
+        self.assets += 1;
+        self.write_entry(EntryKind::Asset, &asset.to_bytes())
+    }
+
+    pub fn write_account(&mut self, account: &SnapshotAccount) -> Result<(), BlockchainError> {
+        self.order.check(EntryKind::Account, account.key.as_bytes())?;
+        account.insert_into(&mut self.tree);
+        self.write_entry(EntryKind::Account, &account.to_bytes())
+    }
+
+    pub fn write_block(&mut self, block: &SnapshotBlock) -> Result<(), BlockchainError> {
+        self.order.check(EntryKind::Block, &block.topoheight.to_be_bytes())?;
+        self.blocks += 1;
+        self.write_entry(EntryKind::Block, &block.to_bytes())
+    }
+
+    // Write the merkle hash, the checksum and its signature, then flush the file
+    // Returns the merkle hash of the state
+    pub fn finish(mut self, keypair: &KeyPair) -> Result<(W, Hash), BlockchainError> {
+        if self.blocks != STATE_SNAPSHOT_BLOCKS {
+            return Err(BlockchainError::InvalidStateSnapshot("invalid blocks count"))
+        }
+
+        // At least the native asset must be present
+        if self.assets == 0 {
+            return Err(BlockchainError::InvalidStateSnapshot("empty state"))
+        }
+
+        self.write_entry(EntryKind::End, &[])?;
+
+        let merkle_hash = self.tree.root()?;
+        self.inner.write_all(merkle_hash.as_bytes())?;
+        self.hasher.update(merkle_hash.as_bytes());
+
+        let checksum = self.hasher.finalize();
+        self.inner.write_all(checksum.as_bytes())?;
+
+        let signature = keypair.sign(checksum.as_bytes());
+        self.inner.write_all(&keypair.get_public_key().compress().to_bytes())?;
+        self.inner.write_all(&signature.to_bytes())?;
+        self.inner.flush()?;
+
+        Ok((self.inner, merkle_hash))
+    }
+}
+
+// Result of a successful snapshot verification
+#[derive(Debug)]
+pub struct VerifiedStateSnapshot {
+    pub merkle_hash: Hash,
+    // Key that signed the snapshot
+    pub signer: PublicKey,
+    pub assets: u64,
+    pub accounts: u64
+}
+
+// Read the entries from a state snapshot
+// The checksum is only known once all entries have been read,
+// so a first pass using `verify` should be done before applying them
+pub struct StateSnapshotReader<R: Read> {
+    inner: R,
+    hasher: blake3::Hasher,
+    tree: StateTree,
+    order: EntryOrder,
+    header: StateSnapshotHeader,
+    // Set once the end entry is read
+    done: bool
+}
+
+impl<R: Read> StateSnapshotReader<R> {
+    pub fn new(mut inner: R) -> Result<Self, BlockchainError> {
+        let mut bytes = [0u8; STATE_SNAPSHOT_HEADER_SIZE];
+        inner.read_exact(&mut bytes)?;
+
+        let header = StateSnapshotHeader::from_bytes(&bytes)
+            .map_err(|_| BlockchainError::InvalidStateSnapshot("invalid header"))?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&bytes);
+
+        Ok(Self {
+            inner,
+            hasher,
+            tree: StateTree::new(),
+            order: EntryOrder::new(),
+            header,
+            done: false
+        })
+    }
+
+    pub fn get_header(&self) -> &StateSnapshotHeader {
+        &self.header
+    }
+
+    // Read and deserialize the next entry
+    pub fn next_entry(&mut self) -> Result<Option<SnapshotEntry>, BlockchainError> {
+        if self.done {
+            return Ok(None)
+        }
+
+        let mut id = [0u8; 1];
+        self.inner.read_exact(&mut id)?;
+        let kind = EntryKind::from_id(id[0])
+            .ok_or(BlockchainError::InvalidStateSnapshot("invalid entry kind"))?;
+
+        let mut size = [0u8; 4];
+        self.inner.read_exact(&mut size)?;
+        let len = u32::from_be_bytes(size) as usize;
+        if len > MAX_BLOCK_SIZE {
+            return Err(BlockchainError::InvalidStateSnapshot("entry too big"))
+        }
+
+        let mut bytes = vec![0u8; len];
+        self.inner.read_exact(&mut bytes)?;
+        self.hasher.update(&id);
+        self.hasher.update(&size);
+        self.hasher.update(&bytes);
+
+        let entry = match kind {
+            EntryKind::End => {
+                if len != 0 {
+                    return Err(BlockchainError::InvalidStateSnapshot("invalid end entry"))
+                }
+                self.done = true;
+                return Ok(None)
+            },
+            EntryKind::Asset => {
+                let asset = AssetWithData::from_bytes(&bytes)?;
+                self.order.check(kind, asset.get_asset().as_bytes())?;
+                self.tree.insert_asset(&asset);
+                SnapshotEntry::Asset(asset)
+            },
+            EntryKind::Account => {
+                let account = SnapshotAccount::from_bytes(&bytes)?;
+                self.order.check(kind, account.key.as_bytes())?;
+                account.insert_into(&mut self.tree);
+                SnapshotEntry::Account(account)
+            },
+            EntryKind::Block => {
+                let block = SnapshotBlock::from_bytes(&bytes)?;
+                if block.topoheight < self.header.lowest_topoheight() || block.topoheight > self.header.topoheight {
+                    return Err(BlockchainError::InvalidStateSnapshot("block topoheight out of range"))
+                }
+                self.order.check(kind, &block.topoheight.to_be_bytes())?;
+                SnapshotEntry::Block(block)
+            }
+        };
+
+        Ok(Some(entry))
+    }
+
+    // Read all the remaining entries, then check the merkle hash, the checksum and the signature
+    pub fn verify(mut self) -> Result<VerifiedStateSnapshot, BlockchainError> {
+        let (mut assets, mut accounts, mut blocks) = (0, 0, 0);
+        while let Some(entry) = self.next_entry()? {
+            match entry {
+                SnapshotEntry::Asset(_) => assets += 1,
+                SnapshotEntry::Account(_) => accounts += 1,
+                SnapshotEntry::Block(_) => blocks += 1
+            }
+        }
+
+        if blocks != STATE_SNAPSHOT_BLOCKS {
+            return Err(BlockchainError::InvalidStateSnapshot("invalid blocks count"))
+        }
+
+        if assets == 0 {
+            return Err(BlockchainError::InvalidStateSnapshot("empty state"))
+        }
+
+        let mut trailer = [0u8; HASH_SIZE * 3 + SIGNATURE_SIZE];
+        self.inner.read_exact(&mut trailer)?;
+        let (merkle_hash, rest) = trailer.split_at(HASH_SIZE);
+        let (checksum, rest) = rest.split_at(HASH_SIZE);
+        let (signer, signature) = rest.split_at(HASH_SIZE);
+
+        self.hasher.update(merkle_hash);
+        if self.hasher.finalize().as_bytes() != checksum {
+            return Err(BlockchainError::StateSnapshotChecksum)
+        }
+
+        let merkle_hash = Hash::from_bytes(merkle_hash)?;
+        if self.tree.root()? != merkle_hash {
+            return Err(BlockchainError::InvalidStateSnapshot("merkle hash mismatch"))
+        }
+
+        let signer = PublicKey::from_bytes(signer)?;
+        let signature = Signature::from_bytes(signature)?;
+        let key = signer.decompress()
+            .map_err(|_| BlockchainError::InvalidStateSnapshot("invalid signer key"))?;
+        if !signature.verify(checksum, &key) {
+            return Err(BlockchainError::InvalidStateSnapshot("invalid signature"))
+        }
+
+        // Nothing should be written after the signature
+        let mut extra = [0u8; 1];
+        if self.inner.read(&mut extra)? != 0 {
+            return Err(BlockchainError::InvalidStateSnapshot("unexpected data after signature"))
+        }
+
+        Ok(VerifiedStateSnapshot {
+            merkle_hash,
+            signer,
+            assets,
+            accounts
+        })
+    }
+}
+
+// Retrieve all the assets registered until the topoheight, sorted by hash
+async fn get_sorted_assets<S: Storage>(storage: &S, topoheight: u64) -> Result<Vec<AssetWithData>, BlockchainError> {
+    let mut assets = Vec::new();
+    loop {
+        let page = storage.get_partial_assets(MAX_ITEMS_PER_PAGE, assets.len(), 0, topoheight).await?;
+        let len = page.len();
+        assets.extend(page);
+        if len < MAX_ITEMS_PER_PAGE {
+            break;
+        }
+    }
+
+    assets.sort_by(|a, b| a.get_asset().cmp(b.get_asset()));
+    Ok(assets)
+}
+
+// Retrieve all the keys registered until the topoheight, sorted by their bytes
+async fn get_sorted_keys<S: Storage>(storage: &S, topoheight: u64) -> Result<Vec<PublicKey>, BlockchainError> {
+    let mut keys = Vec::new();
+    loop {
+        let page = storage.get_registered_keys(MAX_ITEMS_PER_PAGE, keys.len(), 0, topoheight).await?;
+        let len = page.len();
+        keys.extend(page);
+        if len < MAX_ITEMS_PER_PAGE {
+            break;
+        }
+    }
+
+    keys.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+    Ok(keys)
+}
+
+// Load the state of an account at the topoheight
+async fn get_snapshot_account<S: Storage>(storage: &S, key: PublicKey, assets: &[AssetWithData], topoheight: u64) -> Result<SnapshotAccount, BlockchainError> {
+    let registration_topoheight = storage.get_account_registration_topoheight(&key).await?;
+    let (nonce_topoheight, nonce) = match storage.get_nonce_at_maximum_topoheight(&key, topoheight).await? {
+        Some((topoheight, version)) => (topoheight, version.get_nonce()),
+        None => (registration_topoheight, 0)
+    };
+
+    let mut balances = Vec::new();
+    for asset in assets {
+        if let Some(summary) = storage.get_account_summary_for(&key, asset.get_asset(), 0, topoheight).await? {
+            balances.push((asset.get_asset().clone(), summary));
+        }
+    }
+
+    Ok(SnapshotAccount {
+        key,
+        registration_topoheight,
+        nonce_topoheight,
+        nonce,
+        balances
+    })
+}
+
+// Write the whole state at a stable topoheight with the top blocks
+// Caller must verify that the topoheight is stable and not pruned
+pub async fn write_state_snapshot<S: Storage, W: Write>(storage: &S, header: &StateSnapshotHeader, inner: W, keypair: &KeyPair) -> Result<(W, Hash), BlockchainError> {
+    let topoheight = header.topoheight;
+    let mut writer = StateSnapshotWriter::new(inner, header)?;
+
+    let assets = get_sorted_assets(storage, topoheight).await?;
+    for asset in assets.iter() {
+        trace!("Writing asset {} in snapshot", asset.get_asset());
+        writer.write_asset(asset)?;
+    }
+
+    let keys = get_sorted_keys(storage, topoheight).await?;
+    debug!("Writing {} accounts in snapshot", keys.len());
+    for key in keys {
+        let account = get_snapshot_account(storage, key, &assets, topoheight).await?;
+        writer.write_account(&account)?;
+    }
+
+    for topoheight in header.lowest_topoheight()..=topoheight {
+        let hash = storage.get_hash_at_topo_height(topoheight).await?;
+        let metadata = BlockMetadata {
+            supply: storage.get_supply_at_topo_height(topoheight).await?,
+            reward: storage.get_block_reward_at_topo_height(topoheight)?,
+            difficulty: storage.get_difficulty_for_block_hash(&hash).await?,
+            cumulative_difficulty: storage.get_cumulative_difficulty_for_block_hash(&hash).await?,
+            p: storage.get_estimated_covariance_for_block_hash(&hash).await?,
+            hash
+        };
+        let block = storage.get_block_by_hash(&metadata.hash).await?;
+        writer.write_block(&SnapshotBlock { topoheight, metadata, block })?;
+    }
+
+    writer.finish(keypair)
+}
+
+// Apply a verified snapshot on a chain that only contains the genesis block
+// Data is saved the same way as the bootstrap sync does it
+// Blockchain must be reloaded from disk once done
+pub async fn apply_state_snapshot<S: Storage, R: Read>(storage: &mut S, mut reader: StateSnapshotReader<R>, merkle_hash: &Hash) -> Result<(), BlockchainError> {
+    let header = reader.get_header().clone();
+    let mut lowest_topoheight = header.topoheight;
+    let mut top_block = None;
+
+    while let Some(entry) = reader.next_entry()? {
+        match entry {
+            SnapshotEntry::Asset(asset) => {
+                let (asset, data) = asset.consume();
+                debug!("Saving asset {} from snapshot", asset);
+                storage.add_asset(&asset, data).await?;
+            },
+            SnapshotEntry::Account(account) => {
+                let key = account.key;
+                storage.set_account_registration_topoheight(&key, account.registration_topoheight).await?;
+                storage.set_last_nonce_to(&key, account.nonce_topoheight, &VersionedNonce::new(account.nonce, None)).await?;
+
+                for (asset, summary) in account.balances {
+                    let ((stable_topo, stable), output) = summary.as_versions();
+                    storage.set_last_balance_to(&key, &asset, stable_topo, &stable).await?;
+
+                    // save the output balance if it's different from the stable one
+                    if let Some((topo, output)) = output {
+                        storage.set_balance_at_topoheight(&asset, topo, &key, &output).await?;
+                    }
+                }
+            },
+            SnapshotEntry::Block(SnapshotBlock { topoheight, metadata, block }) => {
+                let hash = block.hash();
+                if hash != metadata.hash {
+                    return Err(BlockchainError::InvalidStateSnapshot("block hash mismatch"))
+                }
+
+                if topoheight == header.topoheight {
+                    top_block = Some((hash.clone(), block.get_header().get_height()));
+                }
+
+                // Genesis block may be included
+                if storage.has_block_with_hash(&hash).await? {
+                    debug!("Block {} at topoheight {} already in storage, skipping", hash, topoheight);
+                    continue;
+                }
+
+                lowest_topoheight = lowest_topoheight.min(topoheight);
+                debug!("Saving block {} at topoheight {} from snapshot", hash, topoheight);
+                let (block_header, txs) = block.split();
+                for tx_hash in block_header.get_txs_hashes() {
+                    storage.add_block_for_tx(tx_hash, &hash)?;
+                }
+
+                storage.set_supply_at_topo_height(topoheight, metadata.supply)?;
+                storage.set_block_reward_at_topo_height(topoheight, metadata.reward)?;
+                storage.set_topo_height_for_block(&hash, topoheight).await?;
+                storage.set_cumulative_difficulty_for_block_hash(&hash, metadata.cumulative_difficulty).await?;
+                storage.save_block(block_header.to_arc(), &txs, metadata.difficulty, metadata.p, hash).await?;
+            }
+        }
+    }
+
+    let (top_hash, top_height) = top_block.ok_or(BlockchainError::InvalidStateSnapshot("missing top block"))?;
+
+    // Create a snapshot for all the keys not updated at the lowest topoheight
+    // The versions saved before the snapshot, like the genesis block ones, must not be used anymore
+    storage.create_snapshot_balances_at_topoheight(lowest_topoheight).await?;
+    storage.create_snapshot_nonces_at_topoheight(lowest_topoheight).await?;
+    storage.create_snapshot_registrations_at_topoheight(lowest_topoheight).await?;
+
+    // Delete all old data
+    storage.delete_versioned_balances_below_topoheight(lowest_topoheight).await?;
+    storage.delete_versioned_nonces_below_topoheight(lowest_topoheight).await?;
+    storage.delete_registrations_below_topoheight(lowest_topoheight).await?;
+
+    storage.set_state_root_at_topoheight(header.topoheight, merkle_hash).await?;
+    storage.set_pruned_topoheight(lowest_topoheight).await?;
+    storage.set_top_topoheight(header.topoheight)?;
+    storage.set_top_height(top_height)?;
+    storage.store_tips(&HashSet::from([top_hash]))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use indexmap::IndexSet;
+    use xelis_common::{
+        asset::AssetData,
+        block::{BlockHeader, BlockVersion, EXTRA_NONCE_SIZE},
+        difficulty::{CumulativeDifficulty, Difficulty},
+        immutable::Immutable,
+        varuint::VarUint
+    };
+    use super::*;
+
+    fn create_block(timestamp: u64) -> Block {
+        let miner = KeyPair::new().get_public_key().compress();
+        let header = BlockHeader::new(BlockVersion::V0, 0, timestamp, IndexSet::new(), [0u8; EXTRA_NONCE_SIZE], miner, IndexSet::new());
+        Block::new(Immutable::Owned(header), Vec::new())
+    }
+
+    fn create_snapshot(keypair: &KeyPair) -> (Vec<u8>, Hash) {
+        let header = StateSnapshotHeader::new(Network::Dev, PRUNE_SAFETY_LIMIT + 10);
+        let mut writer = StateSnapshotWriter::new(Vec::new(), &header).unwrap();
+        writer.write_asset(&AssetWithData::new(Hash::zero(), AssetData::new(0, 8))).unwrap();
+
+        let mut keys: Vec<PublicKey> = (0..3).map(|_| KeyPair::new().get_public_key().compress()).collect();
+        keys.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        for (i, key) in keys.into_iter().enumerate() {
+            writer.write_account(&SnapshotAccount {
+                key,
+                registration_topoheight: 0,
+                nonce_topoheight: 0,
+                nonce: i as u64,
+                balances: Vec::new()
+            }).unwrap();
+        }
+
+        for topoheight in header.lowest_topoheight()..=header.topoheight {
+            let block = create_block(topoheight);
+            let metadata = BlockMetadata {
+                hash: block.hash(),
+                supply: 0,
+                reward: 0,
+                difficulty: Difficulty::from_u64(1),
+                cumulative_difficulty: CumulativeDifficulty::from_u64(1),
+                p: VarUint::zero()
+            };
+            writer.write_block(&SnapshotBlock { topoheight, metadata, block }).unwrap();
+        }
+
+        let (bytes, merkle_hash) = writer.finish(keypair).unwrap();
+        (bytes, merkle_hash)
+    }
+
+    #[test]
+    fn test_state_snapshot_roundtrip() {
+        let keypair = KeyPair::new();
+        let (bytes, merkle_hash) = create_snapshot(&keypair);
+
+        let reader = StateSnapshotReader::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(*reader.get_header(), StateSnapshotHeader::new(Network::Dev, PRUNE_SAFETY_LIMIT + 10));
+
+        let verified = reader.verify().unwrap();
+        assert_eq!(verified.merkle_hash, merkle_hash);
+        assert_eq!(verified.signer, keypair.get_public_key().compress());
+        assert_eq!(verified.assets, 1);
+        assert_eq!(verified.accounts, 3);
+    }
+
+    #[test]
+    fn test_state_snapshot_corrupted() {
+        let (mut bytes, _) = create_snapshot(&KeyPair::new());
+        // Flip a byte in the first entry
+        bytes[STATE_SNAPSHOT_HEADER_SIZE + 10] ^= 0xFF;
+
+        let reader = StateSnapshotReader::new(Cursor::new(&bytes)).unwrap();
+        assert!(reader.verify().is_err());
+    }
+
+    #[test]
+    fn test_state_snapshot_invalid_signature() {
+        let (mut bytes, _) = create_snapshot(&KeyPair::new());
+        // Flip a byte in the signature
+        let index = bytes.len() - SIGNATURE_SIZE;
+        bytes[index] ^= 0x01;
+
+        let reader = StateSnapshotReader::new(Cursor::new(&bytes)).unwrap();
+        assert!(matches!(reader.verify(), Err(BlockchainError::InvalidStateSnapshot(_))));
+    }
+
+    #[test]
+    fn test_state_snapshot_canonical_order() {
+        let header = StateSnapshotHeader::new(Network::Dev, PRUNE_SAFETY_LIMIT);
+        let mut writer = StateSnapshotWriter::new(Vec::new(), &header).unwrap();
+        writer.write_asset(&AssetWithData::new(Hash::max(), AssetData::new(0, 8))).unwrap();
+        assert!(writer.write_asset(&AssetWithData::new(Hash::zero(), AssetData::new(0, 8))).is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use log::debug;
+use xelis_common::{
+    account::CiphertextCache,
+    asset::AssetWithData,
+    crypto::{
+        state_tree::{
+            asset_path,
+            asset_value,
+            balance_path,
+            balance_value,
+            leaf_hash,
+            node_hash,
+            nonce_path,
+            nonce_value,
+            path_bit,
+            StateProof
+        },
+        Hash,
+        PublicKey
+    }
+};
+use crate::p2p::packet::bootstrap_chain::MAX_ITEMS_PER_PAGE;
+use super::{error::BlockchainError, storage::Storage};
+
+// Sparse merkle tree over the assets, nonces and balances at a topoheight
+// Leafs can be inserted in any order, they are sorted by path
+// before computing the root or a proof
+pub struct StateTree {
+    // Path and hash of each leaf
+    leaves: Vec<(Hash, Hash)>,
+    sorted: bool,
+    // Leaves to insert or replace, merged in the sorted leaves when needed
+    updates: HashMap<Hash, Hash>,
+    // Root computed since the last change
+    root: Option<Hash>
+}
+
+impl StateTree {
+    pub fn new() -> Self {
+        Self {
+            leaves: Vec::new(),
+            sorted: true,
+            updates: HashMap::new(),
+            root: None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn insert(&mut self, path: Hash, value: &[u8]) {
+        let hash = leaf_hash(&path, value);
+        self.leaves.push((path, hash));
+        self.sorted = false;
+        self.root = None;
+    }
+
+    // Insert a leaf or replace the one already present at this path
+    pub fn update(&mut self, path: Hash, value: &[u8]) {
+        let hash = leaf_hash(&path, value);
+        self.updates.insert(path, hash);
+        self.root = None;
+    }
+
+    pub fn insert_asset(&mut self, asset: &AssetWithData) {
+        self.insert(asset_path(asset.get_asset()), &asset_value(asset.get_data().get_decimals()));
+    }
+
+    pub fn insert_nonce(&mut self, key: &PublicKey, nonce: u64) {
+        self.insert(nonce_path(key), &nonce_value(nonce));
+    }
+
+    pub fn insert_balance(&mut self, key: &PublicKey, asset: &Hash, balance: &CiphertextCache) {
+        self.insert(balance_path(key, asset), &balance_value(balance));
+    }
+
+    fn sort(&mut self) -> Result<(), BlockchainError> {
+        if !self.sorted {
+            self.leaves.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            if let Some(window) = self.leaves.windows(2).find(|window| window[0].0 == window[1].0) {
+                return Err(BlockchainError::DuplicatedStateLeaf(window[0].0.clone()))
+            }
+            self.sorted = true;
+        }
+
+        if !self.updates.is_empty() {
+            // Replace the existing leaves in place, only the new ones require to sort again
+            let len = self.leaves.len();
+            for (path, hash) in std::mem::take(&mut self.updates) {
+                match self.leaves[..len].binary_search_by(|(leaf, _)| leaf.cmp(&path)) {
+                    Ok(index) => self.leaves[index].1 = hash,
+                    Err(_) => self.leaves.push((path, hash))
+                }
+            }
+
+            if self.leaves.len() > len {
+                self.leaves.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Compute the root of the tree
+    pub fn root(&mut self) -> Result<Hash, BlockchainError> {
+        self.sort()?;
+        let root = self.root.get_or_insert_with(|| subtree_root(&self.leaves, 0));
+        Ok(root.clone())
+    }
+
+    // Build the inclusion proof of a leaf
+    // Returns None if no leaf is present at this path
+    pub fn prove(&mut self, path: &Hash) -> Result<Option<StateProof>, BlockchainError> {
+        self.sort()?;
+
+        let mut leaves = self.leaves.as_slice();
+        let mut siblings = Vec::new();
+        let mut depth = 0;
+        loop {
+            match leaves {
+                [] => return Ok(None),
+                [(leaf, _)] => {
+                    if leaf != path {
+                        return Ok(None)
+                    }
+                    return Ok(Some(StateProof::new(siblings)))
+                },
+                _ => {}
+            }
+
+            let (left, right) = leaves.split_at(leaves.partition_point(|(leaf, _)| !path_bit(leaf, depth)));
+            if path_bit(path, depth) {
+                siblings.push(subtree_root(left, depth + 1));
+                leaves = right;
+            } else {
+                siblings.push(subtree_root(right, depth + 1));
+                leaves = left;
+            }
+            depth += 1;
+        }
+    }
+}
+
+// Compute the root of a subtree, leaves must be sorted by path and unique
+// A subtree with only one leaf is the leaf itself
+fn subtree_root(leaves: &[(Hash, Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => Hash::zero(),
+        [(_, hash)] => hash.clone(),
+        _ => {
+            let (left, right) = leaves.split_at(leaves.partition_point(|(path, _)| !path_bit(path, depth)));
+            node_hash(&subtree_root(left, depth + 1), &subtree_root(right, depth + 1))
+        }
+    }
+}
+
+// Build the state tree at a topoheight
+// Every registered account has a nonce leaf, even if it never sent a transaction
+pub async fn build_state_tree<S: Storage>(storage: &S, topoheight: u64) -> Result<StateTree, BlockchainError> {
+    debug!("Building state tree at topoheight {}", topoheight);
+    let mut tree = StateTree::new();
+
+    let mut assets = Vec::new();
+    loop {
+        let page = storage.get_partial_assets(MAX_ITEMS_PER_PAGE, assets.len(), 0, topoheight).await?;
+        let len = page.len();
+        for asset in page {
+            tree.insert_asset(&asset);
+            assets.push(asset.to_asset());
+        }
+
+        if len < MAX_ITEMS_PER_PAGE {
+            break;
+        }
+    }
+
+    let mut skip = 0;
+    loop {
+        let keys = storage.get_registered_keys(MAX_ITEMS_PER_PAGE, skip, 0, topoheight).await?;
+        for key in keys.iter() {
+            let nonce = storage.get_nonce_at_maximum_topoheight(key, topoheight).await?
+                .map(|(_, version)| version.get_nonce())
+                .unwrap_or(0);
+            tree.insert_nonce(key, nonce);
+
+            for asset in assets.iter() {
+                if let Some((_, version)) = storage.get_balance_at_maximum_topoheight(key, asset, topoheight).await? {
+                    tree.insert_balance(key, asset, version.get_balance());
+                }
+            }
+        }
+
+        skip += keys.len();
+        if keys.len() < MAX_ITEMS_PER_PAGE {
+            break;
+        }
+    }
+
+    Ok(tree)
+}
+
+// Move the state tree from the previous topoheight to this one
+// Only the leaves updated at this topoheight are read instead of the whole state
+pub async fn update_state_tree<S: Storage>(tree: &mut StateTree, storage: &S, topoheight: u64) -> Result<(), BlockchainError> {
+    debug!("Updating state tree at topoheight {}", topoheight);
+    let mut skip = 0;
+    loop {
+        let page = storage.get_partial_assets(MAX_ITEMS_PER_PAGE, skip, topoheight, topoheight).await?;
+        for asset in page.iter() {
+            tree.update(asset_path(asset.get_asset()), &asset_value(asset.get_data().get_decimals()));
+        }
+
+        skip += page.len();
+        if page.len() < MAX_ITEMS_PER_PAGE {
+            break;
+        }
+    }
+
+    // Every registered account has a nonce leaf, even if it never sent a transaction
+    for key in storage.get_registered_keys_at_topoheight(topoheight).await? {
+        let nonce = storage.get_nonce_at_maximum_topoheight(&key, topoheight).await?
+            .map(|(_, version)| version.get_nonce())
+            .unwrap_or(0);
+        tree.update(nonce_path(&key), &nonce_value(nonce));
+    }
+
+    for (key, version) in storage.get_nonces_updated_at_topoheight(topoheight).await? {
+        tree.update(nonce_path(&key), &nonce_value(version.get_nonce()));
+    }
+
+    for (key, asset, version) in storage.get_balances_updated_at_topoheight(topoheight).await? {
+        tree.update(balance_path(&key, &asset), &balance_value(version.get_balance()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::crypto::hash;
+    use super::*;
+
+    fn create_tree(count: u64) -> StateTree {
+        let mut tree = StateTree::new();
+        for i in 0..count {
+            tree.insert(hash(&i.to_be_bytes()), &i.to_be_bytes());
+        }
+        tree
+    }
+
+    #[test]
+    fn test_state_tree_empty() {
+        assert_eq!(StateTree::new().root().unwrap(), Hash::zero());
+    }
+
+    #[test]
+    fn test_state_tree_single_leaf() {
+        let mut tree = create_tree(1);
+        let path = hash(&0u64.to_be_bytes());
+        assert_eq!(tree.root().unwrap(), leaf_hash(&path, &0u64.to_be_bytes()));
+    }
+
+    #[test]
+    fn test_state_tree_order_independent() {
+        let mut tree = StateTree::new();
+        for i in (0..50u64).rev() {
+            tree.insert(hash(&i.to_be_bytes()), &i.to_be_bytes());
+        }
+        assert_eq!(tree.root().unwrap(), create_tree(50).root().unwrap());
+    }
+
+    #[test]
+    fn test_state_tree_proofs() {
+        let mut tree = create_tree(100);
+        let root = tree.root().unwrap();
+        for i in 0..100u64 {
+            let path = hash(&i.to_be_bytes());
+            let proof = tree.prove(&path).unwrap().unwrap();
+            assert!(proof.verify(&root, &path, &i.to_be_bytes()));
+            // Wrong value
+            assert!(!proof.verify(&root, &path, &(i + 1).to_be_bytes()));
+        }
+
+        // Not in the tree
+        assert!(tree.prove(&hash(&1000u64.to_be_bytes())).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_state_tree_update() {
+        let mut tree = create_tree(50);
+        tree.root().unwrap();
+
+        // Replace the last leaves and add new ones
+        for i in 40..60u64 {
+            tree.update(hash(&i.to_be_bytes()), &(i * 2).to_be_bytes());
+        }
+
+        let mut expected = create_tree(40);
+        for i in 40..60u64 {
+            expected.insert(hash(&i.to_be_bytes()), &(i * 2).to_be_bytes());
+        }
+        assert_eq!(tree.root().unwrap(), expected.root().unwrap());
+    }
+
+    #[test]
+    fn test_state_tree_duplicated_leaf() {
+        let mut tree = create_tree(2);
+        tree.insert(hash(&0u64.to_be_bytes()), &[1]);
+        assert!(matches!(tree.root(), Err(BlockchainError::DuplicatedStateLeaf(_))));
+    }
+}
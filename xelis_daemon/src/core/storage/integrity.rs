@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter}
+};
+use log::{debug, info};
+use xelis_common::{
+    account::{VersionedBalance, VersionedNonce},
+    block::BlockHeader,
+    crypto::{Hash, PublicKey},
+    serializer::Serializer
+};
+use crate::core::error::BlockchainError;
+use super::{
+    kv::{Column, KvBackend},
+    OrderedHashes,
+    Tips
+};
+
+// Inconsistency found while walking the storage
+#[derive(Debug)]
+pub enum IntegrityIssue {
+    // Block ordered above the current top topoheight
+    OrderedAboveTop { topoheight: u64, hash: Hash },
+    // hash_at_topo entry pointing to a block not stored
+    DanglingHashAtTopo { topoheight: u64, hash: Hash },
+    // hash_at_topo entry without its topo_by_hash pointer
+    MissingTopoByHash { topoheight: u64, hash: Hash },
+    // topo_by_hash entry not matching hash_at_topo
+    DanglingTopoByHash { hash: Hash, topoheight: u64 },
+    // Versioned balance stored above the current top topoheight
+    BalanceAboveTop { key: PublicKey, asset: Hash, topoheight: u64 },
+    // Versioned balance with a previous topoheight pointing to a missing version
+    BrokenBalanceChain { key: PublicKey, asset: Hash, topoheight: u64, previous_topoheight: u64 },
+    // Balance pointer to a missing version
+    DanglingBalancePointer { key: PublicKey, asset: Hash, topoheight: u64 },
+    // Versioned nonce stored above the current top topoheight
+    NonceAboveTop { key: PublicKey, topoheight: u64 },
+    // Versioned nonce with a previous topoheight pointing to a missing version
+    BrokenNonceChain { key: PublicKey, topoheight: u64, previous_topoheight: u64 },
+    // Nonce pointer to a missing version
+    DanglingNoncePointer { key: PublicKey, topoheight: u64 },
+    // Block hash registered at a height but not stored
+    DanglingBlockAtHeight { height: u64, hash: Hash },
+    // Block stored but not registered at its height
+    MissingBlockAtHeight { height: u64, hash: Hash },
+    // Transaction linked to a block not stored
+    DanglingTxBlock { tx: Hash, block: Hash },
+    // Transaction marked as executed in a block not stored
+    DanglingTxExecuted { tx: Hash, block: Hash },
+    // Transaction stored without any block linked to it
+    OrphanedTransaction { tx: Hash },
+    // Prefixed registration not matching the account registration
+    DanglingRegistration { key: PublicKey, topoheight: u64 },
+    // Account registration without its prefixed entry
+    MissingPrefixedRegistration { key: PublicKey, topoheight: u64 }
+}
+
+impl Display for IntegrityIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OrderedAboveTop { topoheight, hash } => write!(f, "block {} ordered at topoheight {} above top topoheight", hash, topoheight),
+            Self::DanglingHashAtTopo { topoheight, hash } => write!(f, "hash_at_topo {} points to unknown block {}", topoheight, hash),
+            Self::MissingTopoByHash { topoheight, hash } => write!(f, "no topo_by_hash for block {} at topoheight {}", hash, topoheight),
+            Self::DanglingTopoByHash { hash, topoheight } => write!(f, "topo_by_hash {} points to topoheight {} not matching hash_at_topo", hash, topoheight),
+            Self::BalanceAboveTop { key, asset, topoheight } => write!(f, "balance {} of {} stored at topoheight {} above top topoheight", asset, key, topoheight),
+            Self::BrokenBalanceChain { key, asset, topoheight, previous_topoheight } => write!(f, "balance {} of {} at topoheight {} points to missing version at {}", asset, key, topoheight, previous_topoheight),
+            Self::DanglingBalancePointer { key, asset, topoheight } => write!(f, "last balance {} of {} points to missing version at {}", asset, key, topoheight),
+            Self::NonceAboveTop { key, topoheight } => write!(f, "nonce of {} stored at topoheight {} above top topoheight", key, topoheight),
+            Self::BrokenNonceChain { key, topoheight, previous_topoheight } => write!(f, "nonce of {} at topoheight {} points to missing version at {}", key, topoheight, previous_topoheight),
+            Self::DanglingNoncePointer { key, topoheight } => write!(f, "last nonce of {} points to missing version at {}", key, topoheight),
+            Self::DanglingBlockAtHeight { height, hash } => write!(f, "unknown block {} registered at height {}", hash, height),
+            Self::MissingBlockAtHeight { height, hash } => write!(f, "block {} not registered at height {}", hash, height),
+            Self::DanglingTxBlock { tx, block } => write!(f, "tx {} linked to unknown block {}", tx, block),
+            Self::DanglingTxExecuted { tx, block } => write!(f, "tx {} executed in unknown block {}", tx, block),
+            Self::OrphanedTransaction { tx } => write!(f, "tx {} is not linked to any block", tx),
+            Self::DanglingRegistration { key, topoheight } => write!(f, "registration of {} at topoheight {} not matching the account registration", key, topoheight),
+            Self::MissingPrefixedRegistration { key, topoheight } => write!(f, "no prefixed registration for {} at topoheight {}", key, topoheight)
+        }
+    }
+}
+
+fn read_topoheight(bytes: &[u8]) -> Result<u64, BlockchainError> {
+    Ok(u64::from_bytes(&bytes[0..8])?)
+}
+
+// Walk all the columns of the backend and cross-check their pointers
+// If repair is set, each issue is fixed as soon as it is found
+// so the following checks are done on the repaired data
+pub(super) fn check_backend<B: KvBackend>(backend: &B, top_topoheight: u64, pruned_topoheight: Option<u64>, repair: bool) -> Result<Vec<IntegrityIssue>, BlockchainError> {
+    let mut issues = Vec::new();
+
+    info!("Checking topoheight pointers");
+    check_topoheights(backend, top_topoheight, repair, &mut issues)?;
+    info!("Checking versioned balances");
+    check_balances(backend, top_topoheight, repair, &mut issues)?;
+    info!("Checking versioned nonces");
+    check_nonces(backend, top_topoheight, repair, &mut issues)?;
+    info!("Checking blocks at height");
+    check_blocks_at_height(backend, pruned_topoheight, repair, &mut issues)?;
+    info!("Checking transactions links");
+    check_transactions(backend, repair, &mut issues)?;
+    info!("Checking registrations");
+    check_registrations(backend, top_topoheight, repair, &mut issues)?;
+
+    Ok(issues)
+}
+
+// Count the entries of a column, used to rebuild the counters after a repair
+pub(super) fn count_entries<B: KvBackend>(backend: &B, column: Column) -> Result<u64, BlockchainError> {
+    let mut count = 0;
+    for el in backend.iter_from(column, &[])? {
+        el?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn check_topoheights<B: KvBackend>(backend: &B, top_topoheight: u64, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    for el in backend.iter_from(Column::HashAtTopo, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&key)?;
+        let hash = Hash::from_bytes(&value)?;
+
+        if topoheight > top_topoheight {
+            if repair {
+                backend.delete(Column::HashAtTopo, &key)?;
+                if backend.get(Column::TopoByHash, hash.as_bytes())?.as_deref() == Some(&key[..]) {
+                    backend.delete(Column::TopoByHash, hash.as_bytes())?;
+                }
+            }
+            issues.push(IntegrityIssue::OrderedAboveTop { topoheight, hash });
+            continue;
+        }
+
+        if !backend.contains(Column::Blocks, hash.as_bytes())? {
+            if repair {
+                backend.delete(Column::HashAtTopo, &key)?;
+            }
+            issues.push(IntegrityIssue::DanglingHashAtTopo { topoheight, hash });
+            continue;
+        }
+
+        if !backend.contains(Column::TopoByHash, hash.as_bytes())? {
+            if repair {
+                backend.put(Column::TopoByHash, hash.as_bytes(), &key)?;
+            }
+            issues.push(IntegrityIssue::MissingTopoByHash { topoheight, hash });
+        }
+    }
+
+    for el in backend.iter_from(Column::TopoByHash, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&value)?;
+        if !backend.contains(Column::Blocks, &key)? || backend.get(Column::HashAtTopo, &value)?.as_deref() != Some(&key[..]) {
+            if repair {
+                backend.delete(Column::TopoByHash, &key)?;
+            }
+            issues.push(IntegrityIssue::DanglingTopoByHash { hash: Hash::from_bytes(&key)?, topoheight });
+        }
+    }
+
+    Ok(())
+}
+
+// Keep the highest version stored for a versioned key suffix
+// Used to repair the pointers without scanning the versions again
+fn track_highest_version(highest_versions: &mut HashMap<Vec<u8>, u64>, suffix: &[u8], topoheight: u64) {
+    let highest = highest_versions.entry(suffix.to_vec()).or_insert(topoheight);
+    if *highest < topoheight {
+        *highest = topoheight;
+    }
+}
+
+fn check_balances<B: KvBackend>(backend: &B, top_topoheight: u64, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    // Highest version kept for each [key][asset], only filled when repairing
+    let mut highest_versions = HashMap::new();
+    // Versioned keys: [topoheight][key][asset]
+    for el in backend.iter_from(Column::VersionedBalances, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&key)?;
+        let account = PublicKey::from_bytes(&key[8..40])?;
+        let asset = Hash::from_bytes(&key[40..72])?;
+
+        if topoheight > top_topoheight {
+            if repair {
+                backend.delete(Column::VersionedBalances, &key)?;
+            }
+            issues.push(IntegrityIssue::BalanceAboveTop { key: account, asset, topoheight });
+            continue;
+        }
+
+        if repair {
+            track_highest_version(&mut highest_versions, &key[8..], topoheight);
+        }
+
+        let mut version = VersionedBalance::from_bytes(&value)?;
+        if let Some(previous_topoheight) = version.get_previous_topoheight() {
+            let mut previous_key = key.to_vec();
+            previous_key[0..8].copy_from_slice(&previous_topoheight.to_be_bytes());
+            if previous_topoheight >= topoheight || !backend.contains(Column::VersionedBalances, &previous_key)? {
+                if repair {
+                    // Cut the chain here, this version still contains the full balance
+                    version.set_previous_topoheight(None);
+                    backend.put(Column::VersionedBalances, &key, &version.to_bytes())?;
+                }
+                issues.push(IntegrityIssue::BrokenBalanceChain { key: account, asset, topoheight, previous_topoheight });
+            }
+        }
+    }
+
+    // Pointers keys: [key][asset]
+    for el in backend.iter_from(Column::Balances, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&value)?;
+        let mut versioned_key = Vec::with_capacity(72);
+        versioned_key.extend_from_slice(&value[0..8]);
+        versioned_key.extend_from_slice(&key);
+
+        if topoheight > top_topoheight || !backend.contains(Column::VersionedBalances, &versioned_key)? {
+            if repair {
+                match highest_versions.get(&key[..]) {
+                    Some(highest) => backend.put(Column::Balances, &key, &highest.to_be_bytes())?,
+                    None => backend.delete(Column::Balances, &key)?
+                };
+            }
+            issues.push(IntegrityIssue::DanglingBalancePointer { key: PublicKey::from_bytes(&key[0..32])?, asset: Hash::from_bytes(&key[32..64])?, topoheight });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_nonces<B: KvBackend>(backend: &B, top_topoheight: u64, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    // Highest version kept for each key, only filled when repairing
+    let mut highest_versions = HashMap::new();
+    // Versioned keys: [topoheight][key]
+    for el in backend.iter_from(Column::VersionedNonces, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&key)?;
+        let account = PublicKey::from_bytes(&key[8..40])?;
+
+        if topoheight > top_topoheight {
+            if repair {
+                backend.delete(Column::VersionedNonces, &key)?;
+            }
+            issues.push(IntegrityIssue::NonceAboveTop { key: account, topoheight });
+            continue;
+        }
+
+        if repair {
+            track_highest_version(&mut highest_versions, &key[8..], topoheight);
+        }
+
+        let mut version = VersionedNonce::from_bytes(&value)?;
+        if let Some(previous_topoheight) = version.get_previous_topoheight() {
+            let mut previous_key = key.to_vec();
+            previous_key[0..8].copy_from_slice(&previous_topoheight.to_be_bytes());
+            if previous_topoheight >= topoheight || !backend.contains(Column::VersionedNonces, &previous_key)? {
+                if repair {
+                    version.set_previous_topoheight(None);
+                    backend.put(Column::VersionedNonces, &key, &version.to_bytes())?;
+                }
+                issues.push(IntegrityIssue::BrokenNonceChain { key: account, topoheight, previous_topoheight });
+            }
+        }
+    }
+
+    // Pointers keys: [key]
+    for el in backend.iter_from(Column::Nonces, &[])? {
+        let (key, value) = el?;
+        let topoheight = read_topoheight(&value)?;
+        let mut versioned_key = Vec::with_capacity(40);
+        versioned_key.extend_from_slice(&value[0..8]);
+        versioned_key.extend_from_slice(&key);
+
+        if topoheight > top_topoheight || !backend.contains(Column::VersionedNonces, &versioned_key)? {
+            if repair {
+                match highest_versions.get(&key[..]) {
+                    Some(highest) => backend.put(Column::Nonces, &key, &highest.to_be_bytes())?,
+                    None => backend.delete(Column::Nonces, &key)?
+                };
+            }
+            issues.push(IntegrityIssue::DanglingNoncePointer { key: PublicKey::from_bytes(&key)?, topoheight });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_blocks_at_height<B: KvBackend>(backend: &B, pruned_topoheight: Option<u64>, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    // Pruning doesn't clean the blocks at height, ignore everything below the pruned block
+    let minimum_height = match pruned_topoheight {
+        Some(topoheight) => match backend.get(Column::HashAtTopo, &topoheight.to_be_bytes())? {
+            Some(hash) => match backend.get(Column::Blocks, &hash)? {
+                Some(header) => BlockHeader::from_bytes(&header)?.get_height(),
+                None => 0
+            },
+            None => 0
+        },
+        None => 0
+    };
+    debug!("Checking blocks at height above {}", minimum_height);
+
+    for el in backend.iter_from(Column::BlocksAtHeight, &minimum_height.to_be_bytes())? {
+        let (key, value) = el?;
+        let height = read_topoheight(&key)?;
+        let OrderedHashes(mut hashes) = OrderedHashes::from_bytes(&value)?;
+        let mut updated = false;
+        for hash in hashes.clone() {
+            if !backend.contains(Column::Blocks, hash.as_bytes())? {
+                hashes.shift_remove(&hash);
+                updated = true;
+                issues.push(IntegrityIssue::DanglingBlockAtHeight { height, hash });
+            }
+        }
+
+        if repair && updated {
+            if hashes.is_empty() {
+                backend.delete(Column::BlocksAtHeight, &key)?;
+            } else {
+                backend.put(Column::BlocksAtHeight, &key, &OrderedHashes(hashes).to_bytes())?;
+            }
+        }
+    }
+
+    for el in backend.iter_from(Column::Blocks, &[])? {
+        let (key, value) = el?;
+        let header = BlockHeader::from_bytes(&value)?;
+        let height = header.get_height();
+        let height_key = height.to_be_bytes();
+        let mut hashes = match backend.get(Column::BlocksAtHeight, &height_key)? {
+            Some(bytes) => OrderedHashes::from_bytes(&bytes)?.0,
+            None => Default::default()
+        };
+
+        let hash = Hash::from_bytes(&key)?;
+        if !hashes.contains(&hash) {
+            if repair {
+                hashes.insert(hash.clone());
+                backend.put(Column::BlocksAtHeight, &height_key, &OrderedHashes(hashes).to_bytes())?;
+            }
+            issues.push(IntegrityIssue::MissingBlockAtHeight { height, hash });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_transactions<B: KvBackend>(backend: &B, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    for el in backend.iter_from(Column::TxBlocks, &[])? {
+        let (key, value) = el?;
+        let tx = Hash::from_bytes(&key)?;
+        let mut blocks = Tips::from_bytes(&value)?;
+        let mut updated = false;
+        for block in blocks.clone() {
+            if !backend.contains(Column::Blocks, block.as_bytes())? {
+                blocks.remove(&block);
+                updated = true;
+                issues.push(IntegrityIssue::DanglingTxBlock { tx: tx.clone(), block });
+            }
+        }
+
+        if repair && updated {
+            if blocks.is_empty() {
+                backend.delete(Column::TxBlocks, &key)?;
+            } else {
+                backend.put(Column::TxBlocks, &key, &blocks.to_bytes())?;
+            }
+        }
+    }
+
+    for el in backend.iter_from(Column::TxsExecuted, &[])? {
+        let (key, value) = el?;
+        if !backend.contains(Column::Blocks, &value)? {
+            if repair {
+                backend.delete(Column::TxsExecuted, &key)?;
+            }
+            issues.push(IntegrityIssue::DanglingTxExecuted { tx: Hash::from_bytes(&key)?, block: Hash::from_bytes(&value)? });
+        }
+    }
+
+    for el in backend.iter_from(Column::Transactions, &[])? {
+        let (key, _) = el?;
+        let linked = match backend.get(Column::TxBlocks, &key)? {
+            Some(bytes) => !Tips::from_bytes(&bytes)?.is_empty(),
+            None => false
+        };
+
+        if !linked {
+            if repair {
+                backend.delete(Column::Transactions, &key)?;
+                backend.delete(Column::TxBlocks, &key)?;
+                backend.delete(Column::TxsExecuted, &key)?;
+            }
+            issues.push(IntegrityIssue::OrphanedTransaction { tx: Hash::from_bytes(&key)? });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_registrations<B: KvBackend>(backend: &B, top_topoheight: u64, repair: bool, issues: &mut Vec<IntegrityIssue>) -> Result<(), BlockchainError> {
+    // Prefixed keys: [topoheight][key]
+    for el in backend.iter_from(Column::RegistrationsPrefixed, &[])? {
+        let (key, _) = el?;
+        let topoheight = read_topoheight(&key)?;
+        let account = &key[8..40];
+        let registered = backend.get(Column::Registrations, account)?;
+        if topoheight > top_topoheight || registered.as_deref() != Some(&key[0..8]) {
+            if repair {
+                backend.delete(Column::RegistrationsPrefixed, &key)?;
+                if registered.as_deref() == Some(&key[0..8]) {
+                    backend.delete(Column::Registrations, account)?;
+                }
+            }
+            issues.push(IntegrityIssue::DanglingRegistration { key: PublicKey::from_bytes(account)?, topoheight });
+        }
+    }
+
+    for el in backend.iter_from(Column::Registrations, &[])? {
+        let (key, value) = el?;
+        let mut prefixed_key = Vec::with_capacity(40);
+        prefixed_key.extend_from_slice(&value[0..8]);
+        prefixed_key.extend_from_slice(&key);
+        if !backend.contains(Column::RegistrationsPrefixed, &prefixed_key)? {
+            if repair {
+                backend.put(Column::RegistrationsPrefixed, &prefixed_key, &[])?;
+            }
+            issues.push(IntegrityIssue::MissingPrefixedRegistration { key: PublicKey::from_bytes(&key)?, topoheight: read_topoheight(&value)? });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{crypto::KeyPair, serializer::Serializer};
+    use super::{super::memory::MemoryBackend, *};
+
+    #[test]
+    fn test_dangling_topo_pointers() {
+        let backend = MemoryBackend::new();
+        let hash = Hash::new([1u8; 32]);
+        // hash_at_topo pointing to a block that doesn't exist
+        backend.put(Column::HashAtTopo, &0u64.to_be_bytes(), hash.as_bytes()).unwrap();
+        backend.put(Column::TopoByHash, hash.as_bytes(), &0u64.to_be_bytes()).unwrap();
+
+        let issues = check_backend(&backend, 0, None, false).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(issues[0], IntegrityIssue::DanglingHashAtTopo { topoheight: 0, .. }));
+        assert!(matches!(issues[1], IntegrityIssue::DanglingTopoByHash { topoheight: 0, .. }));
+
+        let issues = check_backend(&backend, 0, None, true).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert!(check_backend(&backend, 0, None, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_broken_nonce_chain() {
+        let backend = MemoryBackend::new();
+        let key = KeyPair::new().get_public_key().compress();
+        let mut versioned_key = Vec::new();
+        versioned_key.extend_from_slice(&5u64.to_be_bytes());
+        versioned_key.extend_from_slice(key.as_bytes());
+
+        // Version at 5 points to a missing version at 3
+        backend.put(Column::VersionedNonces, &versioned_key, &VersionedNonce::new(2, Some(3)).to_bytes()).unwrap();
+        backend.put(Column::Nonces, key.as_bytes(), &5u64.to_be_bytes()).unwrap();
+
+        let issues = check_backend(&backend, 10, None, true).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0], IntegrityIssue::BrokenNonceChain { topoheight: 5, previous_topoheight: 3, .. }));
+
+        let version = VersionedNonce::from_bytes(&backend.get(Column::VersionedNonces, &versioned_key).unwrap().unwrap()).unwrap();
+        assert_eq!(version.get_previous_topoheight(), None);
+        assert_eq!(version.get_nonce(), 2);
+    }
+
+    #[test]
+    fn test_nonce_above_top_is_removed() {
+        let backend = MemoryBackend::new();
+        let key = KeyPair::new().get_public_key().compress();
+        for topoheight in [2u64, 8] {
+            let mut versioned_key = Vec::new();
+            versioned_key.extend_from_slice(&topoheight.to_be_bytes());
+            versioned_key.extend_from_slice(key.as_bytes());
+            let previous = if topoheight == 8 { Some(2) } else { None };
+            backend.put(Column::VersionedNonces, &versioned_key, &VersionedNonce::new(topoheight, previous).to_bytes()).unwrap();
+        }
+        // Pointer still on the popped version
+        backend.put(Column::Nonces, key.as_bytes(), &8u64.to_be_bytes()).unwrap();
+
+        let issues = check_backend(&backend, 5, None, true).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(backend.get(Column::Nonces, key.as_bytes()).unwrap(), Some(2u64.to_be_bytes().to_vec()));
+        assert!(check_backend(&backend, 5, None, false).unwrap().is_empty());
+    }
+}
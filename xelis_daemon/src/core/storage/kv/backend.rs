@@ -0,0 +1,38 @@
+use crate::core::{error::BlockchainError, storage::snapshot::Snapshot};
+use super::Column;
+
+// Iterator over the entries of a column, sorted by key
+pub type KvIterator<'a> = Box<dyn Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), BlockchainError>> + Send + 'a>;
+
+// Raw ordered key-value store used by the KvStorage
+// Each column is an independent keyspace, and keys must be
+// sorted in lexicographic order when iterating
+pub trait KvBackend: Send + Sync + 'static {
+    // Get the value stored at key in the column
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, BlockchainError>;
+
+    // Insert or overwrite the value stored at key in the column
+    fn put(&self, column: Column, key: &[u8], value: &[u8]) -> Result<(), BlockchainError>;
+
+    // Delete the key from the column, do nothing if it doesn't exist
+    fn delete(&self, column: Column, key: &[u8]) -> Result<(), BlockchainError>;
+
+    // Check if the key is present in the column
+    fn contains(&self, column: Column, key: &[u8]) -> Result<bool, BlockchainError> {
+        Ok(self.get(column, key)?.is_some())
+    }
+
+    // Iterate over all the entries of the column
+    // starting at the first key greater or equal to start
+    fn iter_from<'a>(&'a self, column: Column, start: &[u8]) -> Result<KvIterator<'a>, BlockchainError>;
+
+    // Write all the staged changes at once
+    // Either all of them are written or none
+    fn apply_snapshot(&self, snapshot: Snapshot) -> Result<(), BlockchainError>;
+
+    // Size used by the backend on disk in bytes
+    fn size_on_disk(&self) -> Result<u64, BlockchainError>;
+
+    // Flush all pending writes to disk
+    fn flush(&self) -> Result<(), BlockchainError>;
+}
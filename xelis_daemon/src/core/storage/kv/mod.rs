@@ -0,0 +1,1280 @@
+mod backend;
+mod providers;
+
+pub use backend::{KvBackend, KvIterator};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use indexmap::IndexSet;
+use crate::{
+    config::PRUNE_SAFETY_LIMIT,
+    core::error::{BlockchainError, DiskContext}
+};
+use xelis_common::{
+    account::{VersionedBalance, VersionedNonce},
+    block::{Block, BlockHeader},
+    crypto::{Hash, PublicKey},
+    difficulty::{CumulativeDifficulty, Difficulty},
+    immutable::Immutable,
+    network::Network,
+    serializer::{Reader, Serializer},
+    transaction::Transaction
+};
+use std::{
+    collections::HashSet,
+    hash::Hash as StdHash,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        RwLock
+    }
+};
+use tokio::sync::Mutex;
+use lru::LruCache;
+use log::{debug, trace, warn, info};
+
+use super::{
+    integrity::{self, IntegrityIssue},
+    snapshot::{Snapshot, SnapshotIter},
+    BalanceProvider,
+    BlocksAtHeightProvider,
+    DagOrderProvider,
+    DifficultyProvider,
+    NonceProvider,
+    PrunedTopoheightProvider,
+    ClientProtocolProvider,
+    TransactionProvider,
+    BlockProvider,
+    NetworkProvider,
+    StateRootProvider,
+    Storage,
+    Tips
+};
+
+// Constant keys used for extra column
+const TIPS: &[u8; 4] = b"TIPS";
+const TOP_TOPO_HEIGHT: &[u8; 4] = b"TOPO";
+const TOP_HEIGHT: &[u8; 4] = b"TOPH";
+const NETWORK: &[u8] = b"NET";
+const PRUNED_TOPOHEIGHT: &[u8; 4] = b"PRUN";
+const TXS_PRUNED_TOPOHEIGHT: &[u8; 4] = b"PTXS";
+const BALANCES_PRUNED_TOPOHEIGHT: &[u8; 4] = b"PBAL";
+// Counters (prevent to perform a O(n))
+const ACCOUNTS_COUNT: &[u8; 4] = b"CACC";
+const TXS_COUNT: &[u8; 4] = b"CTXS";
+const ASSETS_COUNT: &[u8; 4] = b"CAST";
+const BLOCKS_COUNT: &[u8; 4] = b"CBLK";
+const BLOCKS_EXECUTION_ORDER_COUNT: &[u8; 4] = b"EBLK";
+
+// All columns used by the storage
+// Each one of them is the equivalent of a Sled Tree
+// and is using the same keys/values layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    // all txs stored on disk
+    Transactions,
+    // all txs executed in block
+    TxsExecuted,
+    // all blocks execution order
+    BlocksExecutionOrder,
+    // all blocks on disk
+    Blocks,
+    // all blocks height at specific height
+    BlocksAtHeight,
+    // all extra data saved on disk
+    Extra,
+    // topo at hash on disk
+    TopoByHash,
+    // hash at topo height on disk
+    HashAtTopo,
+    // cumulative difficulty for each block hash on disk
+    CumulativeDifficulty,
+    // Difficulty estimated covariance (P)
+    DifficultyCovariance,
+    // keep tracks of all available assets on network
+    Assets,
+    // account nonces to prevent TX replay attack
+    Nonces,
+    // block reward for each block topoheight
+    Rewards,
+    // supply for each block topoheight
+    Supply,
+    // difficulty for each block hash
+    Difficulty,
+    // all blocks hashes where a tx was included in
+    TxBlocks,
+    // all versioned nonces using prefixed keys
+    VersionedNonces,
+    // all balances pointers with prefixed keys
+    Balances,
+    // all versioned balances using prefixed keys
+    VersionedBalances,
+    // all merkle hashes for each topoheight
+    MerkleHashes,
+    // Account registrations topoheight
+    Registrations,
+    // Account registrations prefixed by their topoheight for easier deletion
+    RegistrationsPrefixed,
+    // mempool TXs saved when stopping the node
+    Mempool,
+    // state tree roots for each stable topoheight
+    StateRoots
+}
+
+impl Column {
+    pub const ALL: [Column; 24] = [
+        Column::Transactions,
+        Column::TxsExecuted,
+        Column::BlocksExecutionOrder,
+        Column::Blocks,
+        Column::BlocksAtHeight,
+        Column::Extra,
+        Column::TopoByHash,
+        Column::HashAtTopo,
+        Column::CumulativeDifficulty,
+        Column::DifficultyCovariance,
+        Column::Assets,
+        Column::Nonces,
+        Column::Rewards,
+        Column::Supply,
+        Column::Difficulty,
+        Column::TxBlocks,
+        Column::VersionedNonces,
+        Column::Balances,
+        Column::VersionedBalances,
+        Column::MerkleHashes,
+        Column::Registrations,
+        Column::RegistrationsPrefixed,
+        Column::Mempool,
+        Column::StateRoots
+    ];
+
+    // Name of the column in the backend
+    // Same names as the Sled trees
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Transactions => "transactions",
+            Self::TxsExecuted => "txs_executed",
+            Self::BlocksExecutionOrder => "blocks_execution_order",
+            Self::Blocks => "blocks",
+            Self::BlocksAtHeight => "blocks_at_height",
+            Self::Extra => "extra",
+            Self::TopoByHash => "topo_at_hash",
+            Self::HashAtTopo => "hash_at_topo",
+            Self::CumulativeDifficulty => "cumulative_difficulty",
+            Self::DifficultyCovariance => "difficulty_covariance",
+            Self::Assets => "assets",
+            Self::Nonces => "nonces",
+            Self::Rewards => "rewards",
+            Self::Supply => "supply",
+            Self::Difficulty => "difficulty",
+            Self::TxBlocks => "tx_blocks",
+            Self::VersionedNonces => "versioned_nonces",
+            Self::Balances => "balances",
+            Self::VersionedBalances => "versioned_balances",
+            Self::MerkleHashes => "merkle_hashes",
+            Self::Registrations => "registrations",
+            Self::RegistrationsPrefixed => "registrations_prefixed",
+            Self::Mempool => "mempool",
+            Self::StateRoots => "state_roots"
+        }
+    }
+}
+
+// Storage built on top of any ordered key-value backend
+// All the logic is shared, only the raw reads/writes are delegated to the backend
+pub struct KvStorage<B: KvBackend> {
+    // Network used by the storage
+    network: Network,
+    // Backend holding all the columns
+    backend: B,
+    // Changes staged while a commit point is started
+    snapshot: RwLock<Option<Snapshot>>,
+
+    // all available caches
+    // Transaction cache
+    transactions_cache: Option<Mutex<LruCache<Hash, Arc<Transaction>>>>,
+    // Block header cache
+    blocks_cache: Option<Mutex<LruCache<Hash, Arc<BlockHeader>>>>,
+    // Blocks Tips cache
+    past_blocks_cache: Option<Mutex<LruCache<Hash, Arc<IndexSet<Hash>>>>>,
+    // Topoheight by hash cache
+    topo_by_hash_cache: Option<Mutex<LruCache<Hash, u64>>>,
+    // Hash by topoheight cache
+    hash_at_topo_cache: Option<Mutex<LruCache<u64, Hash>>>,
+    // Cumulative difficulty cache
+    cumulative_difficulty_cache: Option<Mutex<LruCache<Hash, CumulativeDifficulty>>>,
+    // Assets cache
+    assets_cache: Option<Mutex<LruCache<Hash, ()>>>,
+    // Tips cache: current chain Tips
+    tips_cache: Tips,
+    // Pruned topoheight cache
+    pruned_topoheight: Option<u64>,
+
+    // Atomic counters
+    // Count of assets
+    assets_count: AtomicU64,
+    // Count of accounts
+    accounts_count: AtomicU64,
+    // Count of transactions
+    transactions_count: AtomicU64,
+    // Count of blocks
+    blocks_count: AtomicU64,
+    // Count of blocks added in chain
+    blocks_execution_count: AtomicU64
+}
+
+macro_rules! init_cache {
+    ($cache_size: expr) => {{
+        if let Some(size) = &$cache_size {
+            Some(Mutex::new(LruCache::new(NonZeroUsize::new(*size).unwrap())))
+        } else {
+            None
+        }
+    }};
+}
+
+impl<B: KvBackend> KvStorage<B> {
+    // Build the storage on top of an already opened backend
+    // and load all the metadata available in it
+    pub fn with_backend(backend: B, cache_size: Option<usize>, network: Network) -> Result<Self, BlockchainError> {
+        let mut storage = Self {
+            network,
+            backend,
+            snapshot: RwLock::new(None),
+            transactions_cache: init_cache!(cache_size),
+            blocks_cache: init_cache!(cache_size),
+            past_blocks_cache: init_cache!(cache_size),
+            topo_by_hash_cache: init_cache!(cache_size),
+            hash_at_topo_cache: init_cache!(cache_size),
+            cumulative_difficulty_cache: init_cache!(cache_size),
+            assets_cache: init_cache!(cache_size),
+            tips_cache: HashSet::new(),
+            pruned_topoheight: None,
+            assets_count: AtomicU64::new(0),
+            accounts_count: AtomicU64::new(0),
+            transactions_count: AtomicU64::new(0),
+            blocks_count: AtomicU64::new(0),
+            blocks_execution_count: AtomicU64::new(0)
+        };
+
+        // Verify that we are opening a DB on same network
+        // This prevent any corruption made by user
+        if storage.has_network()? {
+            let storage_network = storage.load_from_disk::<Network>(Column::Extra, NETWORK, DiskContext::Network)?;
+            if storage_network != network {
+                return Err(BlockchainError::InvalidNetwork);
+            }
+        } else {
+            storage.set_network(&network)?;
+        }
+
+        storage.load_cache_from_disk();
+
+        Ok(storage)
+    }
+
+    // Load the cached values (tips, pruned topoheight and counters) from the disk
+    fn load_cache_from_disk(&mut self) {
+        self.tips_cache = HashSet::new();
+        self.pruned_topoheight = None;
+        for counter in [&self.assets_count, &self.transactions_count, &self.blocks_count, &self.accounts_count, &self.blocks_execution_count] {
+            counter.store(0, Ordering::SeqCst);
+        }
+
+        // Load tips from disk if available
+        if let Ok(tips) = self.load_from_disk::<Tips>(Column::Extra, TIPS, DiskContext::Tips) {
+            debug!("Found tips: {}", tips.len());
+            self.tips_cache = tips;
+        }
+
+        // Load the pruned topoheight from disk if available
+        if let Ok(pruned_topoheight) = self.load_from_disk::<u64>(Column::Extra, PRUNED_TOPOHEIGHT, DiskContext::PrunedTopoHeight) {
+            debug!("Found pruned topoheight: {}", pruned_topoheight);
+            self.pruned_topoheight = Some(pruned_topoheight);
+        }
+
+        // Load the assets count from disk if available
+        if let Ok(assets_count) = self.load_from_disk::<u64>(Column::Extra, ASSETS_COUNT, DiskContext::AssetsCount) {
+            debug!("Found assets count: {}", assets_count);
+            self.assets_count.store(assets_count, Ordering::SeqCst);
+        }
+
+        // Load the txs count from disk if available
+        if let Ok(txs_count) = self.load_from_disk::<u64>(Column::Extra, TXS_COUNT, DiskContext::TxsCount) {
+            debug!("Found txs count: {}", txs_count);
+            self.transactions_count.store(txs_count, Ordering::SeqCst);
+        }
+
+        // Load the blocks count from disk if available
+        if let Ok(blocks_count) = self.load_from_disk::<u64>(Column::Extra, BLOCKS_COUNT, DiskContext::BlocksCount) {
+            debug!("Found blocks count: {}", blocks_count);
+            self.blocks_count.store(blocks_count, Ordering::SeqCst);
+        }
+
+        // Load the accounts count from disk if available
+        if let Ok(accounts_count) = self.load_from_disk::<u64>(Column::Extra, ACCOUNTS_COUNT, DiskContext::AccountsCount) {
+            debug!("Found accounts count: {}", accounts_count);
+            self.accounts_count.store(accounts_count, Ordering::SeqCst);
+        }
+
+        // Load the blocks execution count from disk if available
+        if let Ok(blocks_execution_count) = self.load_from_disk::<u64>(Column::Extra, BLOCKS_EXECUTION_ORDER_COUNT, DiskContext::BlocksExecutionOrderCount) {
+            debug!("Found blocks execution count: {}", blocks_execution_count);
+            self.blocks_execution_count.store(blocks_execution_count, Ordering::SeqCst);
+        }
+    }
+
+    // Get the backend used by this storage
+    pub fn get_backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn get_raw(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, BlockchainError> {
+        if let Some(snapshot) = self.snapshot.read()?.as_ref() {
+            if let Some(value) = snapshot.get(column, key) {
+                return Ok(value.map(|value| value.to_vec()))
+            }
+        }
+
+        self.backend.get(column, key)
+    }
+
+    fn insert_into_disk<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, column: Column, key: K, value: V) -> Result<(), BlockchainError> {
+        if let Some(snapshot) = self.snapshot.write()?.as_mut() {
+            snapshot.put(column, key.as_ref().to_vec(), value.as_ref().to_vec());
+            return Ok(())
+        }
+
+        self.backend.put(column, key.as_ref(), value.as_ref())
+    }
+
+    fn remove_from_disk<K: AsRef<[u8]>>(&self, column: Column, key: K) -> Result<(), BlockchainError> {
+        if let Some(snapshot) = self.snapshot.write()?.as_mut() {
+            snapshot.delete(column, key.as_ref().to_vec());
+            return Ok(())
+        }
+
+        self.backend.delete(column, key.as_ref())
+    }
+
+    // Remove a key and returns its previous value if it was present
+    fn take_from_disk<K: AsRef<[u8]>>(&self, column: Column, key: K) -> Result<Option<Vec<u8>>, BlockchainError> {
+        let value = self.get_raw(column, key.as_ref())?;
+        if value.is_some() {
+            self.remove_from_disk(column, key)?;
+        }
+        Ok(value)
+    }
+
+    fn contains_key<K: AsRef<[u8]>>(&self, column: Column, key: K) -> Result<bool, BlockchainError> {
+        if let Some(snapshot) = self.snapshot.read()?.as_ref() {
+            if let Some(value) = snapshot.get(column, key.as_ref()) {
+                return Ok(value.is_some())
+            }
+        }
+
+        self.backend.contains(column, key.as_ref())
+    }
+
+    fn is_empty(&self, column: Column) -> Result<bool, BlockchainError> {
+        Ok(self.iter_keys(column)?.next().is_none())
+    }
+
+    // Iterate over all the entries of a column starting at the first key greater or equal to start
+    // Staged changes are merged with the entries from the backend
+    fn iter_from(&self, column: Column, start: &[u8]) -> Result<KvIterator<'_>, BlockchainError> {
+        let changes = match self.snapshot.read()?.as_ref() {
+            Some(snapshot) => snapshot.changes_from(column, start),
+            None => Vec::new()
+        };
+
+        let iter = self.backend.iter_from(column, start)?;
+        if changes.is_empty() {
+            return Ok(iter)
+        }
+
+        Ok(Box::new(SnapshotIter::new(iter, changes)))
+    }
+
+    // Iterate over all the entries of a column in lexicographic order of the keys
+    fn iter(&self, column: Column) -> Result<KvIterator<'_>, BlockchainError> {
+        self.iter_from(column, &[])
+    }
+
+    // Iterate over all the keys of a column
+    fn iter_keys(&self, column: Column) -> Result<impl Iterator<Item = Result<Box<[u8]>, BlockchainError>> + Send + '_, BlockchainError> {
+        Ok(self.iter(column)?.map(|res| res.map(|(key, _)| key)))
+    }
+
+    // Iterate over all the entries of a column starting with the prefix
+    fn scan_prefix(&self, column: Column, prefix: &[u8]) -> Result<impl Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), BlockchainError>> + Send + '_, BlockchainError> {
+        let owned = prefix.to_vec();
+        Ok(self.iter_from(column, prefix)?
+            .take_while(move |res| match res {
+                Ok((key, _)) => key.starts_with(&owned),
+                // Let the error goes through
+                Err(_) => true
+            }))
+    }
+
+    fn load_optional_from_disk<T: Serializer>(&self, column: Column, key: &[u8]) -> Result<Option<T>, BlockchainError> {
+        match self.get_raw(column, key)? {
+            Some(bytes) => {
+                let mut reader = Reader::new(&bytes);
+                let value = T::read(&mut reader)?;
+                Ok(Some(value))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn load_from_disk<T: Serializer>(&self, column: Column, key: &[u8], context: DiskContext) -> Result<T, BlockchainError> {
+        match self.get_raw(column, key)? {
+            Some(bytes) => {
+                let mut reader = Reader::new(&bytes);
+                let value = T::read(&mut reader)?;
+                Ok(value)
+            },
+            None => Err(BlockchainError::NotFoundOnDisk(context))
+        }
+    }
+
+    async fn get_cacheable_arc_data<K: Eq + StdHash + Serializer + Clone, V: Serializer>(&self, column: Column, cache: &Option<Mutex<LruCache<K, Arc<V>>>>, key: &K, context: DiskContext) -> Result<Arc<V>, BlockchainError> {
+        let value = if let Some(cache) = cache {
+            let mut cache = cache.lock().await;
+            if let Some(value) = cache.get(key) {
+                return Ok(Arc::clone(&value));
+            }
+
+            let value = Arc::new(self.load_from_disk(column, &key.to_bytes(), context)?);
+            cache.put(key.clone(), Arc::clone(&value));
+            value
+        } else {
+            Arc::new(self.load_from_disk(column, &key.to_bytes(), context)?)
+        };
+
+        Ok(value)
+    }
+
+    async fn get_cacheable_data<K: Eq + StdHash + Serializer + Clone, V: Serializer + Clone>(&self, column: Column, cache: &Option<Mutex<LruCache<K, V>>>, key: &K, context: DiskContext) -> Result<V, BlockchainError> {
+        let value = if let Some(cache) = cache {
+            let mut cache = cache.lock().await;
+            if let Some(value) = cache.get(key) {
+                return Ok(value.clone());
+            }
+
+            let value: V = self.load_from_disk(column, &key.to_bytes(), context)?;
+            cache.put(key.clone(), value.clone());
+            value
+        } else {
+            self.load_from_disk(column, &key.to_bytes(), context)?
+        };
+
+        Ok(value)
+    }
+
+    async fn delete_cacheable_data<K: Eq + StdHash + Serializer + Clone, V: Serializer>(&self, column: Column, cache: &Option<Mutex<LruCache<K, V>>>, key: &K) -> Result<V, BlockchainError> {
+        let bytes = match self.take_from_disk(column, key.to_bytes())? {
+            Some(data) => data,
+            None => return Err(BlockchainError::NotFoundOnDisk(DiskContext::DeleteData))
+        };
+
+        if let Some(cache) = cache {
+            let mut cache = cache.lock().await;
+            if let Some(value) = cache.pop(key) {
+                return Ok(value);
+            }
+        }
+
+        let mut reader = Reader::new(&bytes);
+        let value = V::read(&mut reader)?;
+        Ok(value)
+    }
+
+    // Delete a cacheable data from disk and cache behind a Arc
+    async fn delete_arc_cacheable_data<K: Eq + StdHash + Serializer + Clone, V: Serializer>(&self, column: Column, cache: &Option<Mutex<LruCache<K, Arc<V>>>>, key: &K) -> Result<Arc<V>, BlockchainError> {
+        let bytes = match self.take_from_disk(column, key.to_bytes())? {
+            Some(data) => data,
+            None => return Err(BlockchainError::NotFoundOnDisk(DiskContext::DeleteData))
+        };
+
+        if let Some(cache) = cache {
+            let mut cache = cache.lock().await;
+            if let Some(value) = cache.pop(key) {
+                return Ok(value);
+            }
+        }
+
+        let mut reader = Reader::new(&bytes);
+        let value = V::read(&mut reader)?;
+        Ok(Arc::new(value))
+    }
+
+    async fn contains_data<K: Eq + StdHash + Serializer + Clone, V>(&self, column: Column, cache: &Option<Mutex<LruCache<K, V>>>, key: &K) -> Result<bool, BlockchainError> {
+        if let Some(cache) = cache {
+            let cache = cache.lock().await;
+            return Ok(cache.contains(key) || self.contains_key(column, &key.to_bytes())?)
+        }
+
+        self.contains_key(column, &key.to_bytes())
+    }
+
+    // Update the assets count and store it on disk
+    fn store_assets_count(&self, count: u64) -> Result<(), BlockchainError> {
+        self.assets_count.store(count, Ordering::SeqCst);
+        self.insert_into_disk(Column::Extra, ASSETS_COUNT, &count.to_be_bytes())
+    }
+
+    fn delete_versioned_column_above_topoheight(&self, column: Column, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned {} above topoheight {}", column.name(), topoheight);
+        // Keys are prefixed by the topoheight in big endian, so we can seek directly to it
+        let start = (topoheight + 1).to_be_bytes();
+        for el in self.iter_from(column, &start)? {
+            let (key, _) = el?;
+            self.remove_from_disk(column, &key)?;
+        }
+        Ok(())
+    }
+
+    fn delete_versioned_column_below_topoheight(&self, column: Column, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned {} below topoheight {}", column.name(), topoheight);
+        for el in self.iter_keys(column)? {
+            let key = el?;
+            let topo = u64::from_bytes(&key[0..8])?;
+            // Keys are sorted, nothing left to delete
+            if topo >= topoheight {
+                break;
+            }
+            self.remove_from_disk(column, &key)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<B: KvBackend> Storage for KvStorage<B> {
+    async fn clear_caches(&mut self) -> Result<(), BlockchainError> {
+        if let Some(cache) = self.transactions_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.blocks_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.past_blocks_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.topo_by_hash_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.hash_at_topo_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.cumulative_difficulty_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        if let Some(cache) = self.assets_cache.as_ref() {
+            let mut cache = cache.lock().await;
+            cache.clear();
+        }
+
+        Ok(())
+    }
+
+    // Delete the whole block using its topoheight
+    async fn delete_block_at_topoheight(&mut self, topoheight: u64) -> Result<(Hash, Arc<BlockHeader>, Vec<(Hash, Arc<Transaction>)>), BlockchainError> {
+        trace!("Delete block at topoheight {topoheight}");
+
+        // delete topoheight<->hash pointers
+        let hash = self.delete_cacheable_data(Column::HashAtTopo, &self.hash_at_topo_cache, &topoheight).await?;
+
+        trace!("Deleting block execution order");
+        self.delete_arc_cacheable_data::<_, u64>(Column::BlocksExecutionOrder, &None, &hash).await?;
+
+        trace!("Hash is {hash} at topo {topoheight}");
+
+        self.delete_cacheable_data::<Hash, u64>(Column::TopoByHash, &self.topo_by_hash_cache, &hash).await?;
+
+        trace!("deleting block header {}", hash);
+        let block = self.delete_arc_cacheable_data(Column::Blocks, &self.blocks_cache, &hash).await?;
+        trace!("block header deleted successfully");
+
+        trace!("Deleting supply and block reward");
+        let supply: u64 = self.delete_cacheable_data(Column::Supply, &None, &topoheight).await?;
+        trace!("Supply was {}", supply);
+
+        let reward: u64 = self.delete_cacheable_data(Column::Rewards, &None, &topoheight).await?;
+        trace!("Reward for block {} was: {}", hash, reward);
+
+        trace!("Deleting difficulty");
+        let _: Difficulty = self.delete_cacheable_data(Column::Difficulty, &None, &hash).await?;
+
+        trace!("Deleting cumulative difficulty");
+        let cumulative_difficulty: CumulativeDifficulty = self.delete_cacheable_data(Column::CumulativeDifficulty, &self.cumulative_difficulty_cache, &hash).await?;
+        trace!("Cumulative difficulty deleted: {}", cumulative_difficulty);
+
+        trace!("Deleting state root");
+        self.delete_state_root_at_topoheight(topoheight).await?;
+
+        let mut txs = Vec::new();
+        for tx_hash in block.get_transactions() {
+            // Should we delete the tx too or only unlink it
+            let mut should_delete = true;
+            if self.has_tx_blocks(tx_hash)? {
+                let mut blocks: Tips = self.delete_cacheable_data(Column::TxBlocks, &None, tx_hash).await?;
+                let blocks_len =  blocks.len();
+                blocks.remove(&hash);
+                should_delete = blocks.is_empty();
+                self.set_blocks_for_tx(tx_hash, &blocks)?;
+                trace!("Tx was included in {}, blocks left: {}", blocks_len, blocks.into_iter().map(|b| b.to_string()).collect::<Vec<String>>().join(", "));
+            }
+
+            if self.is_tx_executed_in_a_block(tx_hash)? {
+                trace!("Tx {} was executed, deleting", tx_hash);
+                self.remove_tx_executed(&tx_hash)?;
+            }
+
+            // We have to check first as we may have already deleted it because of client protocol
+            // which allow multiple time the same txs in differents blocks
+            if should_delete && self.contains_data(Column::Transactions, &self.transactions_cache, tx_hash).await? {
+                trace!("Deleting TX {} in block {}", tx_hash, hash);
+                let tx: Arc<Transaction> = self.delete_arc_cacheable_data(Column::Transactions, &self.transactions_cache, tx_hash).await?;
+                txs.push((tx_hash.clone(), tx));
+            }
+        }
+
+        // remove the block hash from the set, and delete the set if empty
+        if self.has_blocks_at_height(block.get_height()).await? {
+            self.remove_block_hash_at_height(&hash, block.get_height()).await?;
+        }
+
+        // Delete cache of past blocks
+        if let Some(cache) = &self.past_blocks_cache {
+            let mut cache = cache.lock().await;
+            cache.pop(&hash);
+        }
+
+        Ok((hash, block, txs))
+    }
+
+    async fn delete_versioned_balances_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned balances at topoheight {}", topoheight);
+        for el in self.scan_prefix(Column::VersionedBalances, &topoheight.to_be_bytes())? {
+            let (key, value) = el?;
+            // Delete this version from DB
+            self.remove_from_disk(Column::VersionedBalances, &key)?;
+
+            // Deserialize keys part
+            let asset = Hash::from_bytes(&key[40..72])?;
+            let key = PublicKey::from_bytes(&key[8..40])?;
+
+            let last_topoheight = self.get_last_topoheight_for_balance(&key, &asset).await?;
+            if last_topoheight >= topoheight {
+                // Deserialize value, it is needed to get the previous topoheight
+                let versioned_balance = VersionedBalance::from_bytes(&value)?;
+
+                // Now records changes, for each balances
+                let db_key = self.get_balance_key_for(&key, &asset);
+                if let Some(previous_topoheight) = versioned_balance.get_previous_topoheight() {
+                    self.insert_into_disk(Column::Balances, &db_key, &previous_topoheight.to_be_bytes())?;
+                } else {
+                    // if there is no previous topoheight, it means that this is the first version
+                    // so we can delete the balance
+                    self.remove_from_disk(Column::Balances, &db_key)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_versioned_nonces_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned nonces at topoheight {}", topoheight);
+        // Collect them first as we need a mutable access to update the pointers
+        let entries = self.scan_prefix(Column::VersionedNonces, &topoheight.to_be_bytes())?
+            .collect::<Result<Vec<_>, BlockchainError>>()?;
+
+        for (key, value) in entries {
+            // Delete this version from DB
+            self.remove_from_disk(Column::VersionedNonces, &key)?;
+
+            // Deserialize keys part
+            let key = PublicKey::from_bytes(&key[8..40])?;
+
+            // Because of chain reorg, it may have been already deleted
+            if let Ok(last_topoheight) = self.get_last_topoheight_for_nonce(&key).await {
+                if last_topoheight >= topoheight {
+                    // Deserialize value, it is needed to get the previous topoheight
+                    let version = VersionedNonce::from_bytes(&value)?;
+                    // Now records changes
+                    if let Some(previous_topoheight) = version.get_previous_topoheight() {
+                        self.set_last_topoheight_for_nonce(&key, previous_topoheight).await?;
+                    } else {
+                        // if there is no previous topoheight, it means that this is the first version
+                        // so we can delete the balance
+                        self.delete_last_topoheight_for_nonce(&key).await?;
+                    }
+                }
+            }
+        }
+
+        trace!("delete versioned nonces at topoheight {} done!", topoheight);
+        Ok(())
+    }
+
+    async fn delete_versioned_balances_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned balances above topoheight {}!", topoheight);
+        self.delete_versioned_column_above_topoheight(Column::VersionedBalances, topoheight)
+    }
+
+    async fn delete_versioned_nonces_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned nonces above topoheight {}", topoheight);
+        self.delete_versioned_column_above_topoheight(Column::VersionedNonces, topoheight)
+    }
+
+    async fn delete_registrations_above_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete registrations above topoheight {}", topoheight);
+        let start = (topoheight + 1).to_be_bytes();
+        for el in self.iter_from(Column::RegistrationsPrefixed, &start)? {
+            let (key, _) = el?;
+            self.remove_from_disk(Column::RegistrationsPrefixed, &key)?;
+            let pkey = &key[8..40];
+            self.remove_from_disk(Column::Registrations, pkey)?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_registrations_below_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete registrations below topoheight {}", topoheight);
+        let mut buf = [0u8; 40];
+        for el in self.iter(Column::Registrations)? {
+            let (key, value) = el?;
+            let topo = u64::from_bytes(&value[0..8])?;
+            if topo < topoheight {
+                buf[0..8].copy_from_slice(&value);
+                buf[8..40].copy_from_slice(&key);
+
+                self.remove_from_disk(Column::RegistrationsPrefixed, &buf)?;
+                self.remove_from_disk(Column::Registrations, &key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_versioned_balances_below_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned balances below topoheight {}!", topoheight);
+        self.delete_versioned_column_below_topoheight(Column::VersionedBalances, topoheight)
+    }
+
+    async fn delete_versioned_nonces_below_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete versioned nonces below topoheight {}", topoheight);
+        self.delete_versioned_column_below_topoheight(Column::VersionedNonces, topoheight)
+    }
+
+    // The first versioned balance that is under the topoheight is bumped to topoheight
+    async fn create_snapshot_balances_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        // column where PublicKey are stored with the highest balance topoheight in it
+        for el in self.iter(Column::Balances)? {
+            let (key_bytes, value) = el?;
+            let key = PublicKey::from_bytes(&key_bytes[0..32])?;
+            let asset = Hash::from_bytes(&key_bytes[32..64])?;
+            let highest_balance_topoheight = u64::from_bytes(&value)?;
+
+            // retrieve the highest versioned balance
+            let mut versioned_balance = self.get_balance_at_exact_topoheight(&key, &asset, highest_balance_topoheight).await?;
+
+            // if the highest topoheight for this account is less than the snapshot topoheight
+            // update it to the topoheight
+            // otherwise, delete the previous topoheight in VersionedBalance which is under topoheight
+            if highest_balance_topoheight <= topoheight {
+                // save the new highest topoheight
+                self.insert_into_disk(Column::Balances, &key_bytes, &topoheight.to_be_bytes())?;
+                // remove the previous topoheight
+                versioned_balance.set_previous_topoheight(None);
+
+                // save it
+                let key = self.get_versioned_balance_key(&key, &asset, topoheight);
+                self.insert_into_disk(Column::VersionedBalances, key, versioned_balance.to_bytes())?;
+            } else {
+                // find the first VersionedBalance which is under topoheight
+                let mut current_version_topoheight = highest_balance_topoheight;
+                while let Some(previous_topoheight) = versioned_balance.get_previous_topoheight() {
+                    if previous_topoheight <= topoheight {
+                        // update the current versioned balance that refer to the pruned versioned balance
+                        {
+                            versioned_balance.set_previous_topoheight(Some(topoheight));
+                            let key = self.get_versioned_balance_key(&key, &asset, current_version_topoheight);
+                            self.insert_into_disk(Column::VersionedBalances, key, versioned_balance.to_bytes())?;
+                        }
+
+                        // Now update the previous version which is under topoheight
+                        {
+                            let mut previous_version = self.get_balance_at_exact_topoheight(&key, &asset, previous_topoheight).await?;
+                            previous_version.set_previous_topoheight(None);
+                            let key = self.get_versioned_balance_key(&key, &asset, topoheight);
+                            self.insert_into_disk(Column::VersionedBalances, key, previous_version.to_bytes())?;
+                        }
+                        break;
+                    }
+
+                    // keep searching
+                    versioned_balance = self.get_balance_at_exact_topoheight(&key, &asset, previous_topoheight).await?;
+                    current_version_topoheight = previous_topoheight;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The first versioned nonce that is under the topoheight is bumped to topoheight
+    async fn create_snapshot_nonces_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        // column where PublicKey are stored with the highest nonce topoheight in it
+        for el in self.iter(Column::Nonces)? {
+            let (key_bytes, value) = el?;
+            let key = PublicKey::from_bytes(&key_bytes)?;
+            let highest_topoheight = u64::from_bytes(&value)?;
+
+            // retrieve the highest versioned nonce
+            let mut versioned_nonce = self.get_nonce_at_exact_topoheight(&key, highest_topoheight).await?;
+
+            // if the highest topoheight for this account is less than the snapshot topoheight
+            // update it to the topoheight
+            // otherwise, delete the previous topoheight in VersionedNonce which is under topoheight
+            if highest_topoheight <= topoheight {
+                // save the new highest topoheight
+                self.insert_into_disk(Column::Nonces, &key_bytes, &topoheight.to_be_bytes())?;
+                // remove the previous topoheight
+                versioned_nonce.set_previous_topoheight(None);
+
+                // save it
+                let key = self.get_versioned_nonce_key(&key, topoheight);
+                self.insert_into_disk(Column::VersionedNonces, key, versioned_nonce.to_bytes())?;
+            } else {
+                // find the first VersionedNonce which is under topoheight
+                let mut current_version_topoheight = highest_topoheight;
+                while let Some(previous_topoheight) = versioned_nonce.get_previous_topoheight() {
+                    if previous_topoheight <= topoheight {
+                        // update the current versioned nonce that refer to the pruned versioned nonce
+                        {
+                            versioned_nonce.set_previous_topoheight(Some(topoheight));
+                            let key = self.get_versioned_nonce_key(&key, current_version_topoheight);
+                            self.insert_into_disk(Column::VersionedNonces, key, versioned_nonce.to_bytes())?;
+                        }
+
+                        // Now update the previous version which is under topoheight
+                        {
+                            let mut previous_version = self.get_nonce_at_exact_topoheight(&key, previous_topoheight).await?;
+                            previous_version.set_previous_topoheight(None);
+                            let key = self.get_versioned_nonce_key(&key, topoheight);
+                            self.insert_into_disk(Column::VersionedNonces, key, previous_version.to_bytes())?;
+                        }
+                        break;
+                    }
+
+                    // keep searching
+                    versioned_nonce = self.get_nonce_at_exact_topoheight(&key, previous_topoheight).await?;
+                    current_version_topoheight = previous_topoheight;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_snapshot_registrations_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("create snapshot registrations at topoheight {}", topoheight);
+        // column where PublicKey are stored with the registration topoheight in it
+        let mut buf = [0u8; 40];
+        for el in self.iter(Column::Registrations)? {
+            let (key, value) = el?;
+            let registration_topo = u64::from_bytes(&value)?;
+
+            // if the registration topoheight for this account is less than the snapshot topoheight
+            // update it to the topoheight
+            if registration_topo <= topoheight {
+                // Delete the prefixed registration
+                buf[0..8].copy_from_slice(&value);
+                buf[8..40].copy_from_slice(&key);
+                self.remove_from_disk(Column::RegistrationsPrefixed, &buf)?;
+
+                // save the new registration topoheight
+                self.insert_into_disk(Column::Registrations, &key, &topoheight.to_be_bytes())?;
+
+                // Overwrite with the new topoheight
+                buf[0..8].copy_from_slice(&topoheight.to_be_bytes());
+                self.insert_into_disk(Column::RegistrationsPrefixed, &buf, &[] as &[u8])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn pop_blocks(&mut self, mut height: u64, mut topoheight: u64, count: u64, stable_topo_height: u64) -> Result<(u64, u64, Vec<(Hash, Arc<Transaction>)>), BlockchainError> {
+        trace!("pop blocks from height: {}, topoheight: {}, count: {}", height, topoheight, count);
+        if topoheight < count as u64 { // also prevent removing genesis block
+            return Err(BlockchainError::NotEnoughBlocks);
+        }
+
+        // search the lowest topo height available based on count + 1
+        // (last lowest topo height accepted)
+        let mut lowest_topo = topoheight - count;
+        trace!("Lowest topoheight for rewind: {}", lowest_topo);
+
+        let pruned_topoheight = self.get_pruned_topoheight().await?.unwrap_or(0);
+
+        // we must check that we are stopping a sync block
+        // easy way for this: check the block at topo is currently alone at height
+        while lowest_topo > pruned_topoheight {
+            let hash = self.get_hash_at_topo_height(lowest_topo).await?;
+            let block_height = self.get_height_for_block_hash(&hash).await?;
+            let blocks_at_height = self.get_blocks_at_height(block_height).await?;
+            info!("blocks at height: {}", blocks_at_height.len());
+            if blocks_at_height.len() == 1 {
+                info!("Sync block found at topoheight {}", lowest_topo);
+                break;
+            } else {
+                warn!("No sync block found at topoheight {} we must go lower if possible", lowest_topo);
+                lowest_topo -= 1;
+            }
+        }
+
+        if pruned_topoheight != 0 {
+            let safety_pruned_topoheight = pruned_topoheight + PRUNE_SAFETY_LIMIT;
+            if lowest_topo <= safety_pruned_topoheight && stable_topo_height != 0 {
+                warn!("Pruned topoheight is {}, lowest topoheight is {}, rewind only until {}", pruned_topoheight, lowest_topo, safety_pruned_topoheight);
+                lowest_topo = safety_pruned_topoheight;
+            }
+        }
+
+        // new TIPS for chain
+        let mut tips = self.get_tips().await?;
+
+        // Delete all orphaned blocks tips
+        for tip in tips.clone() {
+            if !self.is_block_topological_ordered(&tip).await {
+                debug!("Tip {} is not ordered, removing", tip);
+                tips.remove(&tip);
+            }
+        }
+
+        // all txs to be rewinded
+        let mut txs = Vec::new();
+        let mut done = 0;
+        'main: loop {
+            // stop rewinding if its genesis block or if we reached the lowest topo
+            if topoheight <= lowest_topo || topoheight <= stable_topo_height || height == 0 { // prevent removing genesis block
+                trace!("Done: {done}, count: {count}, height: {height}, topoheight: {topoheight}, lowest topo: {lowest_topo}, stable topo: {stable_topo_height}");
+                break 'main;
+            }
+
+            // Delete the hash at topoheight
+            let (hash, block, block_txs) = self.delete_block_at_topoheight(topoheight).await?;
+            trace!("Block {} at topoheight {} deleted", hash, topoheight);
+            txs.extend(block_txs);
+
+            // generate new tips
+            trace!("Removing {} from {} tips", hash, tips.len());
+            tips.remove(&hash);
+
+            for hash in block.get_tips() {
+                trace!("Adding {} to {} tips", hash, tips.len());
+                tips.insert(hash.clone());
+            }
+
+            if topoheight <= pruned_topoheight {
+                warn!("Pruned topoheight is reached, this is not healthy, starting from 0");
+                topoheight = 0;
+                height = 0;
+
+                tips.clear();
+                tips.insert(self.get_hash_at_topo_height(0).await?);
+
+                self.remove_from_disk(Column::Extra, PRUNED_TOPOHEIGHT)?;
+                self.pruned_topoheight = None;
+
+                break 'main;
+            }
+
+            topoheight -= 1;
+            // height of old block become new height
+            if block.get_height() < height {
+                height = block.get_height();
+            }
+            done += 1;
+        }
+
+        debug!("Blocks processed {}, new topoheight: {}, new height: {}, tips: {}", done, topoheight, height, tips.len());
+
+        trace!("Cleaning assets");
+
+        // All deleted assets
+        let mut deleted_assets = HashSet::new();
+
+        // clean all assets
+        for el in self.iter(Column::Assets)? {
+            let (key, value) = el.context("error on asset iterator")?;
+            let asset = Hash::from_bytes(&key)?;
+            trace!("verifying asset registered: {}", asset);
+
+            let registration_topoheight = u64::from_bytes(&value)?;
+            if registration_topoheight > topoheight {
+                trace!("Asset {} was registered at topoheight {}, deleting", asset, registration_topoheight);
+                // Delete it from registered assets
+                self.remove_from_disk(Column::Assets, &key).context(format!("Error while deleting asset {asset} from registered assets"))?;
+
+                deleted_assets.insert(asset);
+            }
+        }
+
+        trace!("Cleaning nonces");
+        // now let's process nonces versions
+        // we set the new highest topoheight to the highest found under the new topoheight
+        for el in self.iter(Column::Nonces)? {
+            let (key, value) = el?;
+            let highest_topoheight = u64::from_bytes(&value)?;
+            if highest_topoheight < pruned_topoheight {
+                warn!("wrong nonce topoheight stored, highest topoheight is {}, pruned topoheight is {}", highest_topoheight, pruned_topoheight);
+                self.remove_from_disk(Column::Nonces, &key)?;
+                continue;
+            }
+
+            if highest_topoheight > topoheight {
+                self.remove_from_disk(Column::Nonces, &key)?;
+                self.store_accounts_count(self.count_accounts().await? - 1)?;
+
+                // find the first version which is under topoheight
+                let pkey = PublicKey::from_bytes(&key)?;
+                trace!("Highest topoheight for {} nonce is {}, above {}", pkey.as_address(self.is_mainnet()), highest_topoheight, topoheight);
+                let mut version = self.get_nonce_at_exact_topoheight(&pkey, highest_topoheight).await
+                    .context(format!("Error while retrieving nonce at exact topoheight {highest_topoheight}"))?;
+
+                while let Some(previous_topoheight) = version.get_previous_topoheight() {
+                    if previous_topoheight <= topoheight {
+                        // we find the new highest version which is under new topoheight
+                        trace!("New highest version nonce for {} is at topoheight {}", pkey.as_address(self.is_mainnet()), previous_topoheight);
+                        self.insert_into_disk(Column::Nonces, &key, &previous_topoheight.to_be_bytes())?;
+                        self.store_accounts_count(self.count_accounts().await? + 1)?;
+                        break;
+                    }
+
+                    // keep searching
+                    version = self.get_nonce_at_exact_topoheight(&pkey, previous_topoheight).await
+                        .context(format!("Error while searching nonce at exact topoheight"))?;
+                }
+            } else {
+                // nothing to do as its under the rewinded topoheight
+            }
+        }
+
+        trace!("Cleaning balances");
+        // do balances too
+        for el in self.iter(Column::Balances)? {
+            let (key, value) = el?;
+            let asset = Hash::from_bytes(&key[32..64])?;
+            let mut delete = false;
+
+            // if the asset is not deleted, we can process it
+            if !deleted_assets.contains(&asset) {
+                let highest_topoheight = u64::from_bytes(&value)?;
+                if highest_topoheight > topoheight && highest_topoheight >= pruned_topoheight {
+                    // find the first version which is under topoheight
+                    let pkey = PublicKey::from_bytes(&key[0..32])?;
+                    trace!("Highest topoheight for balance {} is {}, above {}", pkey.as_address(self.is_mainnet()), highest_topoheight, topoheight);
+
+                    let mut version = self.get_balance_at_exact_topoheight(&pkey, &asset, highest_topoheight).await
+                        .context(format!("Error while retrieving balance at exact topoheight {highest_topoheight}"))?;
+
+                    // Mark for deletion if we can't find a version under the new topoheight
+                    delete = true;
+
+                    while let Some(previous_topoheight) = version.get_previous_topoheight() {
+                        if previous_topoheight <= topoheight {
+                            // we find the new highest version which is under new topoheight
+                            trace!("New highest version balance for {} is at topoheight {} with asset {}", pkey.as_address(self.is_mainnet()), previous_topoheight, asset);
+                            self.insert_into_disk(Column::Balances, &key, &previous_topoheight.to_be_bytes())?;
+                            delete = false;
+                            break;
+                        }
+
+                        // keep searching
+                        version = self.get_balance_at_exact_topoheight(&pkey, &asset, previous_topoheight).await?;
+                    }
+                }
+            } else {
+                delete = true;
+            }
+
+            if delete {
+                self.remove_from_disk(Column::Balances, &key)?;
+            }
+        }
+
+        warn!("Blocks rewinded: {}, new topoheight: {}, new height: {}", done, topoheight, height);
+
+        trace!("Cleaning versioned balances and nonces");
+
+        // now delete all versioned balances and nonces above the new topoheight
+        self.delete_versioned_balances_above_topoheight(topoheight).await?;
+        self.delete_versioned_nonces_above_topoheight(topoheight).await?;
+        // Delete also registrations
+        self.delete_registrations_above_topoheight(topoheight).await?;
+
+        trace!("Cleaning caches");
+        // Clear all caches to not have old data after rewind
+        self.clear_caches().await?;
+
+        trace!("Storing new pointers");
+        // store the new tips and topo topoheight
+        self.store_tips(&tips)?;
+        self.set_top_topoheight(topoheight)?;
+        self.set_top_height(height)?;
+
+        // Reduce the count of blocks stored
+        let count = self.count_blocks().await? - done;
+        self.store_blocks_count(count)?;
+
+        Ok((height, topoheight, txs))
+    }
+
+    async fn get_top_block_hash(&self) -> Result<Hash, BlockchainError> {
+        trace!("get top block hash");
+        self.get_hash_at_topo_height(self.get_top_topoheight()?).await
+    }
+
+    fn get_top_topoheight(&self) -> Result<u64, BlockchainError> {
+        trace!("get top topoheight");
+        self.load_from_disk(Column::Extra, TOP_TOPO_HEIGHT, DiskContext::TopTopoHeight)
+    }
+
+    fn set_top_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("set new top topoheight at {}", topoheight);
+        self.insert_into_disk(Column::Extra, TOP_TOPO_HEIGHT, &topoheight.to_be_bytes())
+    }
+
+    fn get_top_height(&self) -> Result<u64, BlockchainError> {
+        trace!("get top height");
+        self.load_from_disk(Column::Extra, TOP_HEIGHT, DiskContext::TopHeight)
+    }
+
+    fn set_top_height(&mut self, height: u64) -> Result<(), BlockchainError> {
+        trace!("set new top height at {}", height);
+        self.insert_into_disk(Column::Extra, TOP_HEIGHT, &height.to_be_bytes())
+    }
+
+    async fn get_top_block_header(&self) -> Result<(Arc<BlockHeader>, Hash), BlockchainError> {
+        trace!("get top block header");
+        let hash = self.get_top_block_hash().await?;
+        Ok((self.get_block_header_by_hash(&hash).await?, hash))
+    }
+
+    async fn get_top_block(&self) -> Result<Block, BlockchainError> {
+        trace!("get top block");
+        let (block, _) = self.get_top_block_header().await?;
+        let mut transactions = Vec::new();
+        for tx in block.get_transactions() {
+            let transaction = self.get_transaction(tx).await?;
+            transactions.push(Immutable::Arc(transaction));
+        }
+
+        let block = Block::new(Immutable::Arc(block), transactions);
+        Ok(block)
+    }
+
+    async fn get_tips(&self) -> Result<Tips, BlockchainError> {
+        trace!("get tips");
+        Ok(self.tips_cache.clone())
+    }
+
+    fn store_tips(&mut self, tips: &Tips) -> Result<(), BlockchainError> {
+        trace!("Saving {} Tips", tips.len());
+        self.insert_into_disk(Column::Extra, TIPS, tips.to_bytes())?;
+        self.tips_cache = tips.clone();
+        Ok(())
+    }
+
+    // Returns the current size on disk in bytes
+    async fn get_size_on_disk(&self) -> Result<u64, BlockchainError> {
+        self.backend.size_on_disk()
+    }
+
+    async fn stop(&mut self) -> Result<(), BlockchainError> {
+        info!("Stopping Storage...");
+        info!("Flushing database");
+        self.backend.flush()?;
+        info!("Database flushed");
+        Ok(())
+    }
+
+    async fn check_integrity(&mut self, repair: bool) -> Result<Vec<IntegrityIssue>, BlockchainError> {
+        let top_topoheight = self.get_top_topoheight()?;
+        let pruned_topoheight = self.get_pruned_topoheight().await?;
+        let issues = integrity::check_backend(&self.backend, top_topoheight, pruned_topoheight, repair)?;
+        if repair && !issues.is_empty() {
+            // Caches and counters may be outdated after the repair
+            self.clear_caches().await?;
+            self.store_accounts_count(integrity::count_entries(&self.backend, Column::Nonces)?)?;
+            self.store_transactions_count(integrity::count_entries(&self.backend, Column::Transactions)?)?;
+        }
+        Ok(issues)
+    }
+
+    async fn start_commit_point(&mut self) -> Result<(), BlockchainError> {
+        trace!("start commit point");
+        let mut snapshot = self.snapshot.write()?;
+        if snapshot.is_some() {
+            return Err(BlockchainError::CommitPointAlreadyStarted)
+        }
+        *snapshot = Some(Snapshot::new());
+        Ok(())
+    }
+
+    async fn end_commit_point(&mut self, apply: bool) -> Result<(), BlockchainError> {
+        trace!("end commit point, apply: {}", apply);
+        let snapshot = self.snapshot.write()?.take()
+            .ok_or(BlockchainError::NoCommitPoint)?;
+
+        let res = if apply {
+            self.backend.apply_snapshot(snapshot)
+        } else {
+            Ok(())
+        };
+
+        // Caches may contain values that were never written
+        if !apply || res.is_err() {
+            self.clear_caches().await?;
+            self.load_cache_from_disk();
+        }
+
+        res
+    }
+
+    async fn get_unexecuted_transactions(&self) -> Result<IndexSet<Hash>, BlockchainError> {
+        trace!("get unexecuted transactions");
+        let mut txs = IndexSet::new();
+        for el in self.iter_keys(Column::Transactions)? {
+            let key = el?;
+            let tx_hash = Hash::from_bytes(&key)?;
+            if !self.is_tx_executed_in_a_block(&tx_hash)? {
+                txs.insert(tx_hash);
+            }
+        }
+
+        Ok(txs)
+    }
+}
@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use indexmap::IndexSet;
+use log::trace;
+use xelis_common::{crypto::PublicKey, serializer::Serializer};
+use crate::core::{
+    error::{BlockchainError, DiskContext},
+    storage::{
+        kv::{Column, KvBackend, KvStorage},
+        AccountProvider
+    }
+};
+
+fn prefixed_db_key(topoheight: u64, key: &PublicKey) -> [u8; 40] {
+    prefixed_db_key_no_u64(&topoheight.to_bytes(), key)
+}
+
+fn prefixed_db_key_no_u64(topoheight: &[u8], key: &PublicKey) -> [u8; 40] {
+    let mut buf = [0u8; 40];
+    buf[0..8].copy_from_slice(&topoheight);
+    buf[8..40].copy_from_slice(key.as_bytes());
+    buf
+}
+
+#[async_trait]
+impl<B: KvBackend> AccountProvider for KvStorage<B> {
+    async fn get_account_registration_topoheight(&self, key: &PublicKey) -> Result<u64, BlockchainError> {
+        self.load_from_disk(Column::Registrations, key.as_bytes(), DiskContext::AccountRegistrationTopoHeight)
+    }
+
+    async fn set_account_registration_topoheight(&mut self, key: &PublicKey, topoheight: u64) -> Result<(), BlockchainError> {
+        if let Some(old) = self.get_raw(Column::Registrations, key.as_bytes())? {
+            self.remove_from_disk(Column::RegistrationsPrefixed, &prefixed_db_key_no_u64(&old, key))?;
+        }
+
+        self.insert_into_disk(Column::Registrations, key.as_bytes(), topoheight.to_bytes())?;
+        self.insert_into_disk(Column::RegistrationsPrefixed, prefixed_db_key(topoheight, key), &[] as &[u8])?;
+
+        Ok(())
+    }
+
+    async fn is_account_registered(&self, key: &PublicKey) -> Result<bool, BlockchainError> {
+        let value = self.load_optional_from_disk::<u64>(Column::Registrations, key.as_bytes())?;
+        if let Some(topo) = value {
+            return self.contains_key(Column::RegistrationsPrefixed, prefixed_db_key(topo, key))
+        }
+
+        Ok(false)
+    }
+
+    async fn is_account_registered_at_topoheight(&self, key: &PublicKey, topoheight: u64) -> Result<bool, BlockchainError> {
+        if !self.is_account_registered(key).await? {
+            return Ok(false);
+        }
+
+        let registration_topoheight = self.get_account_registration_topoheight(key).await?;
+        Ok(registration_topoheight <= topoheight)
+    }
+
+    async fn delete_registrations_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        for el in self.scan_prefix(Column::RegistrationsPrefixed, &topoheight.to_bytes())? {
+            let (k, _) = el?;
+            self.remove_from_disk(Column::RegistrationsPrefixed, &k)?;
+            let key = &k[8..40];
+            self.remove_from_disk(Column::Registrations, key)?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_registered_keys_at_topoheight(&self, topoheight: u64) -> Result<IndexSet<PublicKey>, BlockchainError> {
+        trace!("get registered keys at topoheight {}", topoheight);
+        let mut keys = IndexSet::new();
+        for el in self.scan_prefix(Column::RegistrationsPrefixed, &topoheight.to_bytes())? {
+            let (k, _) = el?;
+            keys.insert(PublicKey::from_bytes(&k[8..40])?);
+        }
+
+        Ok(keys)
+    }
+
+    // Get all keys that got registered in the range given
+    async fn get_registered_keys(&self, maximum: usize, skip: usize, minimum_topoheight: u64, maximum_topoheight: u64) -> Result<IndexSet<PublicKey>, BlockchainError> {
+        trace!("get partial keys, maximum: {}, skip: {}, minimum_topoheight: {}, maximum_topoheight: {}", maximum, skip, minimum_topoheight, maximum_topoheight);
+
+        let mut keys: IndexSet<PublicKey> = IndexSet::new();
+        let mut skip_count = 0;
+        for el in self.iter_keys(Column::RegistrationsPrefixed)? {
+            let key = el?;
+            let topo = u64::from_bytes(&key[0..8])?;
+
+            // Keys are sorted by topoheight, we can stop here
+            if topo > maximum_topoheight {
+                break;
+            }
+
+            // Skip if not in range
+            if topo < minimum_topoheight {
+                continue;
+            }
+
+            // Skip if asked
+            if skip_count < skip {
+                skip_count += 1;
+                continue;
+            }
+
+            keys.insert(PublicKey::from_bytes(&key[8..40])?);
+            if keys.len() >= maximum {
+                break;
+            }
+        }
+
+        Ok(keys)
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use async_trait::async_trait;
+use log::{debug, trace};
+use xelis_common::{
+    block::{Block, BlockHeader},
+    crypto::Hash,
+    difficulty::Difficulty,
+    immutable::Immutable,
+    serializer::Serializer,
+    transaction::Transaction,
+    varuint::VarUint
+};
+use crate::core::{
+    error::BlockchainError,
+    storage::{
+        kv::{Column, KvBackend, KvStorage, BLOCKS_COUNT},
+        BlockProvider,
+        BlocksAtHeightProvider,
+        TransactionProvider
+    }
+};
+
+impl<B: KvBackend> KvStorage<B> {
+    // Update the blocks count and store it on disk
+    pub(crate) fn store_blocks_count(&self, count: u64) -> Result<(), BlockchainError> {
+        self.blocks_count.store(count, Ordering::SeqCst);
+        self.insert_into_disk(Column::Extra, BLOCKS_COUNT, &count.to_be_bytes())
+    }
+}
+
+#[async_trait]
+impl<B: KvBackend> BlockProvider for KvStorage<B> {
+    async fn has_blocks(&self) -> bool {
+        trace!("has blocks");
+        !self.is_empty(Column::Blocks).unwrap_or(true)
+    }
+
+    async fn count_blocks(&self) -> Result<u64, BlockchainError> {
+        trace!("count blocks");
+        Ok(self.blocks_count.load(Ordering::SeqCst))
+    }
+
+    async fn has_block_with_hash(&self, hash: &Hash) -> Result<bool, BlockchainError> {
+        trace!("has block {}", hash);
+        self.contains_data(Column::Blocks, &self.blocks_cache, hash).await
+    }
+
+    async fn save_block(&mut self, block: Arc<BlockHeader>, txs: &Vec<Immutable<Transaction>>, difficulty: Difficulty, p: VarUint, hash: Hash) -> Result<(), BlockchainError> {
+        debug!("Storing new {} with hash: {}, difficulty: {}", block, hash, difficulty);
+
+        // Store transactions
+        let mut txs_count = 0;
+        for (hash, tx) in block.get_transactions().iter().zip(txs) { // first save all txs, then save block
+            if !self.has_transaction(hash).await? {
+                self.insert_into_disk(Column::Transactions, hash.as_bytes(), tx.to_bytes())?;
+                txs_count += 1;
+            }
+        }
+
+        // Increase only if necessary
+        if txs_count > 0 {
+            self.store_transactions_count(self.count_transactions().await? + txs_count)?;
+        }
+
+        // Store block header and increase blocks count if it's a new block
+        let is_new = !self.contains_key(Column::Blocks, hash.as_bytes())?;
+        self.insert_into_disk(Column::Blocks, hash.as_bytes(), block.to_bytes())?;
+        if is_new {
+            self.store_blocks_count(self.count_blocks().await? + 1)?;
+        }
+
+        // Store difficulty
+        self.insert_into_disk(Column::Difficulty, hash.as_bytes(), difficulty.to_bytes())?;
+        // Store P
+        self.insert_into_disk(Column::DifficultyCovariance, hash.as_bytes(), p.to_bytes())?;
+
+        self.add_block_hash_at_height(hash.clone(), block.get_height()).await?;
+
+        if let Some(cache) = &self.blocks_cache {
+            let mut cache = cache.lock().await;
+            cache.put(hash, block);
+        }
+
+        Ok(())
+    }
+
+    async fn get_block_by_hash(&self, hash: &Hash) -> Result<Block, BlockchainError> {
+        trace!("get block by hash {}", hash);
+        let block = self.get_block_header_by_hash(hash).await?;
+        let mut transactions = Vec::new();
+        for tx in block.get_transactions() {
+            // Only the header is kept for blocks below the txs pruned topoheight
+            if !self.has_transaction(tx).await? {
+                return Err(BlockchainError::BlockTxsPruned(hash.clone()))
+            }
+
+            let transaction = self.get_transaction(tx).await?;
+            transactions.push(Immutable::Arc(transaction));
+        }
+
+        let block = Block::new(Immutable::Arc(block), transactions);
+        Ok(block)
+    }
+
+    async fn delete_block_with_hash(&mut self, hash: &Hash) -> Result<Block, BlockchainError> {
+        debug!("Deleting block with hash: {}", hash);
+
+        // Delete block header
+        let header = self.delete_arc_cacheable_data(Column::Blocks, &self.blocks_cache, &hash).await?;
+
+        // Decrease blocks count
+        self.store_blocks_count(self.count_blocks().await? - 1)?;
+
+        // Delete difficulty
+        self.remove_from_disk(Column::Difficulty, hash.as_bytes())?;
+        // Delete P
+        self.remove_from_disk(Column::DifficultyCovariance, hash.as_bytes())?;
+
+        self.remove_block_hash_at_height(&hash, header.get_height()).await?;
+
+        let mut transactions = Vec::new();
+        for tx in header.get_transactions() {
+            let transaction = self.get_transaction(&tx).await?;
+            transactions.push(Immutable::Arc(transaction));
+        }
+
+        let block = Block::new(Immutable::Arc(header), transactions);
+
+        Ok(block)
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use indexmap::IndexSet;
+use xelis_common::{crypto::Hash, serializer::Serializer};
+use crate::core::{
+    error::{BlockchainError, DiskContext},
+    storage::{
+        kv::{Column, KvBackend, KvStorage, BLOCKS_EXECUTION_ORDER_COUNT},
+        BlockExecutionOrderProvider
+    }
+};
+
+#[async_trait]
+impl<B: KvBackend> BlockExecutionOrderProvider for KvStorage<B> {
+    async fn get_blocks_execution_order(&self, skip: usize, count: usize) -> Result<IndexSet<Hash>, BlockchainError> {
+        let order = self.iter_keys(Column::BlocksExecutionOrder)?
+            .skip(skip)
+            .take(count)
+            .map(|x| Ok(Hash::from_bytes(&x?)?))
+            .collect::<Result<_, BlockchainError>>()?;
+
+        Ok(order)
+    }
+
+    async fn get_block_position_in_order(&self, hash: &Hash) -> Result<u64, BlockchainError> {
+        let position = self.load_from_disk(Column::BlocksExecutionOrder, hash.as_bytes(), DiskContext::SearchBlockPositionInOrder)?;
+        Ok(position)
+    }
+
+    async fn has_block_position_in_order(&self, hash: &Hash) -> Result<bool, BlockchainError> {
+        self.contains_key(Column::BlocksExecutionOrder, hash.as_bytes())
+    }
+
+    async fn add_block_execution_to_order(&mut self, hash: &Hash) -> Result<(), BlockchainError> {
+        let position = self.blocks_execution_count.fetch_add(1, Ordering::SeqCst);
+        self.insert_into_disk(Column::BlocksExecutionOrder, hash.to_bytes(), position.to_bytes())?;
+        self.insert_into_disk(Column::Extra, BLOCKS_EXECUTION_ORDER_COUNT, &position.to_be_bytes())
+    }
+
+    async fn get_blocks_execution_count(&self) -> u64 {
+        self.blocks_execution_count.load(Ordering::SeqCst)
+    }
+
+    async fn swap_blocks_executions_positions(&mut self, left: &Hash, right: &Hash) -> Result<(), BlockchainError> {
+        let left_position = self.get_block_position_in_order(left).await?;
+        let right_position = self.get_block_position_in_order(right).await?;
+
+        self.insert_into_disk(Column::BlocksExecutionOrder, left.to_bytes(), right_position.to_bytes())?;
+        self.insert_into_disk(Column::BlocksExecutionOrder, right.to_bytes(), left_position.to_bytes())?;
+
+        Ok(())
+    }
+}
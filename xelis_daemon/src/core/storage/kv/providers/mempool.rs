@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use log::trace;
+use xelis_common::{
+    crypto::Hash,
+    serializer::Serializer,
+    time::TimestampSeconds,
+    transaction::Transaction
+};
+use crate::core::{
+    error::BlockchainError,
+    storage::{
+        kv::{Column, KvBackend, KvStorage},
+        providers::mempool_tx_to_bytes,
+        MempoolProvider
+    }
+};
+
+#[async_trait]
+impl<B: KvBackend> MempoolProvider for KvStorage<B> {
+    async fn save_mempool_tx(&mut self, hash: &Hash, tx: &Transaction, first_seen: TimestampSeconds) -> Result<(), BlockchainError> {
+        trace!("save mempool tx {}", hash);
+        self.insert_into_disk(Column::Mempool, hash.as_bytes(), mempool_tx_to_bytes(tx, first_seen))
+    }
+
+    async fn get_mempool_txs(&self) -> Result<Vec<(Hash, Transaction, TimestampSeconds)>, BlockchainError> {
+        trace!("get mempool txs");
+        let mut txs = Vec::new();
+        for el in self.iter(Column::Mempool)? {
+            let (key, value) = el?;
+            let hash = Hash::from_bytes(&key)?;
+            let (first_seen, tx) = <(TimestampSeconds, Transaction)>::from_bytes(&value)?;
+            txs.push((hash, tx, first_seen));
+        }
+
+        Ok(txs)
+    }
+
+    async fn delete_mempool_tx(&mut self, hash: &Hash) -> Result<(), BlockchainError> {
+        trace!("delete mempool tx {}", hash);
+        self.remove_from_disk(Column::Mempool, hash.as_bytes())
+    }
+}
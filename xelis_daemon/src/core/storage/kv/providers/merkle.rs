@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+use log::trace;
+use xelis_common::{crypto::Hash, serializer::Serializer};
+use crate::core::{
+    error::{BlockchainError, DiskContext},
+    storage::{
+        kv::{Column, KvBackend, KvStorage},
+        MerkleHashProvider
+    }
+};
+
+#[async_trait]
+impl<B: KvBackend> MerkleHashProvider for KvStorage<B> {
+    async fn get_balances_merkle_hash_at_topoheight(&self, topoheight: u64) -> Result<Hash, BlockchainError> {
+        trace!("get merkle hash at topoheight {}", topoheight);
+        self.load_from_disk(Column::MerkleHashes, &topoheight.to_bytes(), DiskContext::BalancesMerkleHashAtTopoHeight)
+    }
+
+    async fn set_balances_merkle_hash_at_topoheight(&mut self, topoheight: u64, merkle_proof: &Hash) -> Result<(), BlockchainError> {
+        trace!("set merkle hash {} at topoheight {}", merkle_proof, topoheight);
+        self.insert_into_disk(Column::MerkleHashes, &topoheight.to_bytes(), merkle_proof.as_bytes())
+    }
+}
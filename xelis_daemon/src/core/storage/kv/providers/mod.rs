@@ -0,0 +1,19 @@
+// Implementations of all the storage providers for KvStorage
+// Traits are defined in the storage providers module
+mod asset;
+mod blocks_at_height;
+mod dag_order;
+mod difficulty;
+mod pruned_topoheight;
+mod nonce;
+mod balance;
+mod client_protocol;
+mod transaction;
+mod block;
+mod blockdag;
+mod merkle;
+mod account;
+mod block_execution_order;
+mod network;
+mod mempool;
+mod state_root;
\ No newline at end of file
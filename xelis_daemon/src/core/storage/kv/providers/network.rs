@@ -0,0 +1,30 @@
+use xelis_common::{network::Network, serializer::Serializer};
+use log::trace;
+use crate::core::{
+    error::BlockchainError,
+    storage::{
+        kv::{Column, KvBackend, KvStorage, NETWORK},
+        NetworkProvider
+    }
+};
+
+impl<B: KvBackend> NetworkProvider for KvStorage<B> {
+    fn get_network(&self) -> Result<Network, BlockchainError> {
+        trace!("get network");
+        Ok(self.network)
+    }
+
+    fn is_mainnet(&self) -> bool {
+        self.network.is_mainnet()
+    }
+
+    fn set_network(&mut self, network: &Network) -> Result<(), BlockchainError> {
+        trace!("set network to {}", network);
+        self.insert_into_disk(Column::Extra, NETWORK, network.to_bytes())
+    }
+
+    fn has_network(&self) -> Result<bool, BlockchainError> {
+        trace!("has network");
+        self.contains_key(Column::Extra, NETWORK)
+    }
+}
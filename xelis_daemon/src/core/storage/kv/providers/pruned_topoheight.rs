@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use crate::core::{
+    error::BlockchainError,
+    storage::{
+        kv::{
+            Column,
+            KvBackend,
+            KvStorage,
+            BALANCES_PRUNED_TOPOHEIGHT,
+            PRUNED_TOPOHEIGHT,
+            TXS_PRUNED_TOPOHEIGHT
+        },
+        PrunedTopoheightProvider
+    }
+};
+
+#[async_trait]
+impl<B: KvBackend> PrunedTopoheightProvider for KvStorage<B> {
+    async fn set_pruned_topoheight(&mut self, pruned_topoheight: u64) -> Result<(), BlockchainError> {
+        self.pruned_topoheight = Some(pruned_topoheight);
+        self.insert_into_disk(Column::Extra, PRUNED_TOPOHEIGHT, &pruned_topoheight.to_be_bytes())
+    }
+
+    async fn get_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
+        Ok(self.pruned_topoheight)
+    }
+
+    async fn get_txs_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
+        self.load_optional_from_disk(Column::Extra, TXS_PRUNED_TOPOHEIGHT)
+    }
+
+    async fn set_txs_pruned_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        self.insert_into_disk(Column::Extra, TXS_PRUNED_TOPOHEIGHT, &topoheight.to_be_bytes())
+    }
+
+    async fn get_balances_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
+        self.load_optional_from_disk(Column::Extra, BALANCES_PRUNED_TOPOHEIGHT)
+    }
+
+    async fn set_balances_pruned_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        self.insert_into_disk(Column::Extra, BALANCES_PRUNED_TOPOHEIGHT, &topoheight.to_be_bytes())
+    }
+}
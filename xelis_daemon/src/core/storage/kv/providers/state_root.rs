@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use log::trace;
+use xelis_common::{crypto::Hash, serializer::Serializer};
+use crate::core::{
+    error::{BlockchainError, DiskContext},
+    storage::{
+        kv::{Column, KvBackend, KvStorage},
+        StateRootProvider
+    }
+};
+
+#[async_trait]
+impl<B: KvBackend> StateRootProvider for KvStorage<B> {
+    async fn has_state_root_at_topoheight(&self, topoheight: u64) -> Result<bool, BlockchainError> {
+        trace!("has state root at topoheight {}", topoheight);
+        self.contains_key(Column::StateRoots, &topoheight.to_bytes())
+    }
+
+    async fn get_state_root_at_topoheight(&self, topoheight: u64) -> Result<Hash, BlockchainError> {
+        trace!("get state root at topoheight {}", topoheight);
+        self.load_from_disk(Column::StateRoots, &topoheight.to_bytes(), DiskContext::StateRootAtTopoHeight)
+    }
+
+    async fn set_state_root_at_topoheight(&mut self, topoheight: u64, root: &Hash) -> Result<(), BlockchainError> {
+        trace!("set state root {} at topoheight {}", root, topoheight);
+        self.insert_into_disk(Column::StateRoots, &topoheight.to_bytes(), root.as_bytes())
+    }
+
+    async fn delete_state_root_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError> {
+        trace!("delete state root at topoheight {}", topoheight);
+        self.remove_from_disk(Column::StateRoots, &topoheight.to_bytes())
+    }
+}
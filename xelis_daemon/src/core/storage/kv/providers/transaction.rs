@@ -0,0 +1,60 @@
+use std::{collections::HashSet, sync::{atomic::Ordering, Arc}};
+use async_trait::async_trait;
+use log::trace;
+use xelis_common::{
+    transaction::Transaction,
+    crypto::Hash,
+};
+use crate::core::{
+    error::{
+        BlockchainError,
+        DiskContext
+    },
+    storage::{
+        kv::{Column, KvBackend, KvStorage, TXS_COUNT},
+        TransactionProvider
+    }
+};
+
+impl<B: KvBackend> KvStorage<B> {
+    // Update the txs count and store it on disk
+    pub(crate) fn store_transactions_count(&self, count: u64) -> Result<(), BlockchainError> {
+        self.transactions_count.store(count, Ordering::SeqCst);
+        self.insert_into_disk(Column::Extra, TXS_COUNT, &count.to_be_bytes())
+    }
+}
+
+#[async_trait]
+impl<B: KvBackend> TransactionProvider for KvStorage<B> {
+    async fn get_transaction(&self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
+        trace!("get transaction for hash {}", hash);
+        self.get_cacheable_arc_data(Column::Transactions, &self.transactions_cache, hash, DiskContext::GetTransaction).await
+    }
+
+    async fn get_transaction_size(&self, hash: &Hash) -> Result<usize, BlockchainError> {
+        trace!("get transaction size for hash {}", hash);
+        let data = self.get_raw(Column::Transactions, hash.as_bytes())?;
+        data.map(|data| data.len()).ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))
+    }
+
+    async fn has_transaction(&self, hash: &Hash) -> Result<bool, BlockchainError> {
+        trace!("has transaction {}", hash);
+        self.contains_data(Column::Transactions, &self.transactions_cache, hash).await
+    }
+
+    async fn count_transactions(&self) -> Result<u64, BlockchainError> {
+        trace!("count transactions");
+        Ok(self.transactions_count.load(Ordering::SeqCst))
+    }
+
+    async fn delete_transaction(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
+        self.delete_cacheable_data::<Hash, HashSet<Hash>>(Column::TxBlocks, &None, hash).await?;
+        self.delete_arc_cacheable_data(Column::Transactions, &self.transactions_cache, hash).await
+    }
+
+    async fn prune_transaction(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
+        let transaction = self.delete_arc_cacheable_data(Column::Transactions, &self.transactions_cache, hash).await?;
+        self.store_transactions_count(self.count_transactions().await?.saturating_sub(1))?;
+        Ok(transaction)
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::RwLock,
+    vec::IntoIter
+};
+use xelis_common::network::Network;
+use crate::core::error::BlockchainError;
+use super::{
+    kv::{Column, KvBackend, KvIterator, KvStorage},
+    Snapshot
+};
+
+// Number of entries copied at once while iterating a column
+const ITER_CHUNK_SIZE: usize = 64;
+
+type MemoryColumn = RwLock<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+// In-memory backend, nothing is ever written to disk
+// Each column is a sorted map to keep the same iteration order as the disk backends
+pub struct MemoryBackend {
+    columns: Vec<MemoryColumn>
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self {
+            columns: Column::ALL.iter()
+                .map(|_| RwLock::new(BTreeMap::new()))
+                .collect()
+        }
+    }
+
+    fn get_column(&self, column: Column) -> &MemoryColumn {
+        &self.columns[column as usize]
+    }
+}
+
+// Iterator copying the entries of a column by chunks
+// The lock is only held while a chunk is copied, so keys can be
+// deleted while iterating like the disk backends
+struct MemoryIterator<'a> {
+    column: &'a MemoryColumn,
+    // Lower bound of the next chunk to copy
+    next: Bound<Vec<u8>>,
+    chunk: IntoIter<(Vec<u8>, Vec<u8>)>,
+    // No entries left in the column after the current chunk
+    done: bool
+}
+
+impl<'a> MemoryIterator<'a> {
+    fn new(column: &'a MemoryColumn, start: &[u8]) -> Self {
+        Self {
+            column,
+            next: Bound::Included(start.to_vec()),
+            chunk: Vec::new().into_iter(),
+            done: false
+        }
+    }
+}
+
+impl<'a> Iterator for MemoryIterator<'a> {
+    type Item = Result<(Box<[u8]>, Box<[u8]>), BlockchainError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.chunk.as_slice().is_empty() && !self.done {
+            let map = match self.column.read() {
+                Ok(map) => map,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()))
+                }
+            };
+
+            let chunk: Vec<_> = map.range((self.next.clone(), Bound::Unbounded))
+                .take(ITER_CHUNK_SIZE)
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+
+            self.done = chunk.len() < ITER_CHUNK_SIZE;
+            if let Some((key, _)) = chunk.last() {
+                self.next = Bound::Excluded(key.clone());
+            }
+            self.chunk = chunk.into_iter();
+        }
+
+        self.chunk.next()
+            .map(|(key, value)| Ok((key.into_boxed_slice(), value.into_boxed_slice())))
+    }
+}
+
+impl KvBackend for MemoryBackend {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, BlockchainError> {
+        let map = self.get_column(column).read()?;
+        Ok(map.get(key).cloned())
+    }
+
+    fn put(&self, column: Column, key: &[u8], value: &[u8]) -> Result<(), BlockchainError> {
+        let mut map = self.get_column(column).write()?;
+        map.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, column: Column, key: &[u8]) -> Result<(), BlockchainError> {
+        let mut map = self.get_column(column).write()?;
+        map.remove(key);
+        Ok(())
+    }
+
+    fn contains(&self, column: Column, key: &[u8]) -> Result<bool, BlockchainError> {
+        let map = self.get_column(column).read()?;
+        Ok(map.contains_key(key))
+    }
+
+    // Entries are copied by chunks so the lock is not held during the iteration
+    // and an iteration stopped early doesn't copy the whole column
+    fn iter_from<'a>(&'a self, column: Column, start: &[u8]) -> Result<KvIterator<'a>, BlockchainError> {
+        Ok(Box::new(MemoryIterator::new(self.get_column(column), start)))
+    }
+
+    // All the columns are locked before writing
+    // so no reader can see a partially applied snapshot
+    fn apply_snapshot(&self, snapshot: Snapshot) -> Result<(), BlockchainError> {
+        let mut maps = Vec::with_capacity(self.columns.len());
+        for column in self.columns.iter() {
+            maps.push(column.write()?);
+        }
+
+        for (column, changes) in snapshot.into_changes() {
+            let map = &mut maps[column as usize];
+            for (key, value) in changes {
+                match value {
+                    Some(value) => map.insert(key, value),
+                    None => map.remove(&key)
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    fn size_on_disk(&self) -> Result<u64, BlockchainError> {
+        Ok(0)
+    }
+
+    fn flush(&self) -> Result<(), BlockchainError> {
+        Ok(())
+    }
+}
+
+pub type MemoryStorage = KvStorage<MemoryBackend>;
+
+impl MemoryStorage {
+    // No LRU caches are used as everything is already in memory
+    pub fn new(network: Network) -> Result<Self, BlockchainError> {
+        Self::with_backend(MemoryBackend::new(), None, network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::storage::Storage;
+    use super::*;
+
+    #[test]
+    fn test_iter_from_is_sorted() {
+        let backend = MemoryBackend::new();
+        backend.put(Column::Balances, &[2, 0], &[1]).unwrap();
+        backend.put(Column::Balances, &[1, 1], &[2]).unwrap();
+        backend.put(Column::Balances, &[1, 0], &[3]).unwrap();
+        backend.put(Column::Nonces, &[1, 0], &[4]).unwrap();
+
+        let keys: Vec<_> = backend.iter_from(Column::Balances, &[1, 1]).unwrap()
+            .map(|res| res.unwrap().0.to_vec())
+            .collect();
+        assert_eq!(keys, vec![vec![1, 1], vec![2, 0]]);
+    }
+
+    #[test]
+    fn test_delete_while_iterating() {
+        let backend = MemoryBackend::new();
+        for i in 0u8..10 {
+            backend.put(Column::VersionedNonces, &[i], &[i]).unwrap();
+        }
+
+        for res in backend.iter_from(Column::VersionedNonces, &[5]).unwrap() {
+            let (key, _) = res.unwrap();
+            backend.delete(Column::VersionedNonces, &key).unwrap();
+        }
+
+        assert!(backend.contains(Column::VersionedNonces, &[4]).unwrap());
+        assert!(!backend.contains(Column::VersionedNonces, &[5]).unwrap());
+        assert_eq!(backend.iter_from(Column::VersionedNonces, &[]).unwrap().count(), 5);
+    }
+
+    #[test]
+    fn test_iter_from_over_chunks() {
+        let backend = MemoryBackend::new();
+        let count = ITER_CHUNK_SIZE as u16 * 2 + 1;
+        for i in 0..count {
+            backend.put(Column::Assets, &i.to_be_bytes(), &[]).unwrap();
+        }
+
+        let keys: Vec<_> = backend.iter_from(Column::Assets, &1u16.to_be_bytes()).unwrap()
+            .map(|res| u16::from_be_bytes(res.unwrap().0.as_ref().try_into().unwrap()))
+            .collect();
+        assert_eq!(keys, (1..count).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_commit_point() {
+        let mut storage = MemoryStorage::new(Network::Dev).unwrap();
+        storage.set_top_topoheight(1).unwrap();
+
+        // Discarded changes are never written
+        storage.start_commit_point().await.unwrap();
+        storage.set_top_topoheight(2).unwrap();
+        assert_eq!(storage.get_top_topoheight().unwrap(), 2);
+        storage.end_commit_point(false).await.unwrap();
+        assert_eq!(storage.get_top_topoheight().unwrap(), 1);
+
+        // Applied changes only reach the backend when the commit point ends
+        storage.start_commit_point().await.unwrap();
+        storage.set_top_topoheight(3).unwrap();
+        assert_eq!(storage.get_backend().get(Column::Extra, b"TOPO").unwrap(), Some(1u64.to_be_bytes().to_vec()));
+        storage.end_commit_point(true).await.unwrap();
+        assert_eq!(storage.get_backend().get(Column::Extra, b"TOPO").unwrap(), Some(3u64.to_be_bytes().to_vec()));
+
+        assert!(storage.end_commit_point(true).await.is_err());
+    }
+}
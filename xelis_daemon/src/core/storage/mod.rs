@@ -1,8 +1,18 @@
 mod providers;
 mod sled;
+mod kv;
+mod rocksdb;
+mod memory;
+mod integrity;
+mod snapshot;
 
 pub use self::{
     sled::{SledStorage, StorageMode},
+    kv::{KvBackend, KvStorage},
+    rocksdb::RocksStorage,
+    memory::MemoryStorage,
+    integrity::IntegrityIssue,
+    snapshot::Snapshot,
     providers::*,
 };
 
@@ -19,8 +29,18 @@ use crate::core::error::BlockchainError;
 // Represents the tips of the chain or of a block
 pub type Tips = HashSet<Hash>;
 
+// Database engine used to store the chain
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum StorageBackend {
+    Sled,
+    #[value(name = "rocksdb")]
+    RocksDB,
+    // Nothing is persisted, everything is lost on exit
+    Memory
+}
+
 #[async_trait]
-pub trait Storage: BlockExecutionOrderProvider + DagOrderProvider + PrunedTopoheightProvider + NonceProvider + AccountProvider + ClientProtocolProvider + BlockDagProvider + MerkleHashProvider + NetworkProvider + Sync + Send + 'static {
+pub trait Storage: BlockExecutionOrderProvider + DagOrderProvider + PrunedTopoheightProvider + NonceProvider + AccountProvider + ClientProtocolProvider + BlockDagProvider + MerkleHashProvider + StateRootProvider + NetworkProvider + MempoolProvider + Sync + Send + 'static {
     // Clear caches if exists
     async fn clear_caches(&mut self) -> Result<(), BlockchainError>;
 
@@ -100,4 +120,16 @@ pub trait Storage: BlockExecutionOrderProvider + DagOrderProvider + PrunedTopohe
 
     // Get all the unexecuted transactions
     async fn get_unexecuted_transactions(&self) -> Result<IndexSet<Hash>, BlockchainError>;
+
+    // Walk all the stored data and cross-check the pointers between them
+    // If repair is set, dangling or orphaned entries are fixed or deleted
+    async fn check_integrity(&mut self, repair: bool) -> Result<Vec<IntegrityIssue>, BlockchainError>;
+
+    // Stage all the next writes in memory instead of writing them directly
+    // Reads will still see the staged changes
+    async fn start_commit_point(&mut self) -> Result<(), BlockchainError>;
+
+    // Write atomically all the changes staged since the commit point was started
+    // If apply is false, they are discarded and the storage is back to its previous state
+    async fn end_commit_point(&mut self, apply: bool) -> Result<(), BlockchainError>;
 }
\ No newline at end of file
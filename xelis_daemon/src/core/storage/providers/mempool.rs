@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use xelis_common::{
+    crypto::Hash,
+    serializer::{Serializer, Writer},
+    time::TimestampSeconds,
+    transaction::Transaction
+};
+use crate::core::error::BlockchainError;
+
+// Mempool provider allow to keep the mempool TXs across restarts
+// TXs are saved with the timestamp they were first seen
+// They must be verified again against the chain state once loaded
+#[async_trait]
+pub trait MempoolProvider {
+    // Save a mempool TX
+    async fn save_mempool_tx(&mut self, hash: &Hash, tx: &Transaction, first_seen: TimestampSeconds) -> Result<(), BlockchainError>;
+
+    // Load all the saved mempool TXs
+    async fn get_mempool_txs(&self) -> Result<Vec<(Hash, Transaction, TimestampSeconds)>, BlockchainError>;
+
+    // Delete a saved mempool TX once it has been reloaded or rejected
+    async fn delete_mempool_tx(&mut self, hash: &Hash) -> Result<(), BlockchainError>;
+}
+
+// Value stored for a mempool TX
+pub(crate) fn mempool_tx_to_bytes(tx: &Transaction, first_seen: TimestampSeconds) -> Vec<u8> {
+    let mut writer = Writer::new();
+    first_seen.write(&mut writer);
+    tx.write(&mut writer);
+    writer.bytes()
+}
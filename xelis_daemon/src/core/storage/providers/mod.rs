@@ -13,9 +13,11 @@ mod merkle;
 mod account;
 mod block_execution_order;
 mod network;
+mod mempool;
+mod state_root;
 
 pub use asset::AssetProvider;
-pub use blocks_at_height::BlocksAtHeightProvider;
+pub use blocks_at_height::{BlocksAtHeightProvider, OrderedHashes};
 pub use dag_order::DagOrderProvider;
 pub use difficulty::DifficultyProvider;
 pub use pruned_topoheight::PrunedTopoheightProvider;
@@ -28,4 +30,7 @@ pub use blockdag::BlockDagProvider;
 pub use merkle::MerkleHashProvider;
 pub use account::AccountProvider;
 pub use block_execution_order::BlockExecutionOrderProvider;
-pub use network::NetworkProvider;
\ No newline at end of file
+pub use network::NetworkProvider;
+pub use mempool::MempoolProvider;
+pub use state_root::StateRootProvider;
+pub(crate) use mempool::mempool_tx_to_bytes;
\ No newline at end of file
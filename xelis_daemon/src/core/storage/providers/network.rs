@@ -1,6 +1,5 @@
-use xelis_common::{network::Network, serializer::Serializer};
-use log::trace;
-use crate::core::{error::BlockchainError, storage::{sled::NETWORK, SledStorage}};
+use xelis_common::network::Network;
+use crate::core::error::BlockchainError;
 
 pub trait NetworkProvider {
     // Get the network from cache
@@ -15,25 +14,3 @@ pub trait NetworkProvider {
     // Do we have a network stored in DB ?
     fn has_network(&self) -> Result<bool, BlockchainError>;
 }
-
-impl NetworkProvider for SledStorage {
-    fn get_network(&self) -> Result<Network, BlockchainError> {
-        trace!("get network");
-        Ok(self.network)
-    }
-
-    fn is_mainnet(&self) -> bool {
-        self.network.is_mainnet()
-    }
-
-    fn set_network(&mut self, network: &Network) -> Result<(), BlockchainError> {
-        trace!("set network to {}", network);
-        self.extra.insert(NETWORK, network.to_bytes())?;
-        Ok(())
-    }
-
-    fn has_network(&self) -> Result<bool, BlockchainError> {
-        trace!("has network");
-        Ok(self.extra.contains_key(NETWORK)?)
-    }
-}
\ No newline at end of file
@@ -1,8 +1,5 @@
 use async_trait::async_trait;
-use crate::core::{
-    error::BlockchainError,
-    storage::{sled::PRUNED_TOPOHEIGHT, SledStorage},
-};
+use crate::core::error::BlockchainError;
 
 // This trait is used for pruning
 #[async_trait]
@@ -12,17 +9,16 @@ pub trait PrunedTopoheightProvider {
 
     // set the pruned topoheight on disk
     async fn set_pruned_topoheight(&mut self, pruned_topoheight: u64) -> Result<(), BlockchainError>;
-}
 
-#[async_trait]
-impl PrunedTopoheightProvider for SledStorage {
-    async fn set_pruned_topoheight(&mut self, pruned_topoheight: u64) -> Result<(), BlockchainError> {
-        self.pruned_topoheight = Some(pruned_topoheight);
-        self.extra.insert(PRUNED_TOPOHEIGHT, &pruned_topoheight.to_be_bytes())?;
-        Ok(())
-    }
+    // get the topoheight below which the transactions bodies were deleted
+    async fn get_txs_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError>;
+
+    // set the topoheight below which the transactions bodies were deleted
+    async fn set_txs_pruned_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
+
+    // get the topoheight below which the balances history was deleted
+    async fn get_balances_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError>;
 
-    async fn get_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
-        Ok(self.pruned_topoheight)
-    }
+    // set the topoheight below which the balances history was deleted
+    async fn set_balances_pruned_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
 }
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use xelis_common::crypto::Hash;
+use crate::core::error::BlockchainError;
+
+// State root provider allow to give the root of the state tree at a specific topoheight
+// The state tree contains the assets, nonces and balances
+// Only the roots at a stable topoheight are stored, they are deleted with their block
+#[async_trait]
+pub trait StateRootProvider {
+    // Check if a state root is stored at a specific topoheight
+    async fn has_state_root_at_topoheight(&self, topoheight: u64) -> Result<bool, BlockchainError>;
+
+    // Get the state root at a specific topoheight
+    async fn get_state_root_at_topoheight(&self, topoheight: u64) -> Result<Hash, BlockchainError>;
+
+    // Set the state root at a specific topoheight
+    async fn set_state_root_at_topoheight(&mut self, topoheight: u64, root: &Hash) -> Result<(), BlockchainError>;
+
+    // Delete the state root at a specific topoheight if any
+    async fn delete_state_root_at_topoheight(&mut self, topoheight: u64) -> Result<(), BlockchainError>;
+}
@@ -1,20 +1,10 @@
-use std::{collections::HashSet, sync::{atomic::Ordering, Arc}};
+use std::sync::Arc;
 use async_trait::async_trait;
-use log::trace;
 use xelis_common::{
     transaction::Transaction,
     crypto::Hash,
 };
-use crate::core::{
-    error::{
-        BlockchainError,
-        DiskContext
-    },
-    storage::{
-        sled::TXS_COUNT,
-        SledStorage
-    }
-};
+use crate::core::error::BlockchainError;
 
 #[async_trait]
 pub trait TransactionProvider {
@@ -32,42 +22,8 @@ pub trait TransactionProvider {
 
     // Delete a transaction from the storage using its hash
     async fn delete_transaction(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError>;
-}
 
-impl SledStorage {
-    // Update the txs count and store it on disk
-    pub(super) fn store_transactions_count(&self, count: u64) -> Result<(), BlockchainError> {
-        self.transactions_count.store(count, Ordering::SeqCst);
-        self.extra.insert(TXS_COUNT, &count.to_be_bytes())?;
-        Ok(())
-    }    
+    // Delete only the transaction body, blocks linked to it are kept
+    // It is not counted anymore in the transactions stored
+    async fn prune_transaction(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError>;
 }
-
-#[async_trait]
-impl TransactionProvider for SledStorage {
-    async fn get_transaction(&self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
-        trace!("get transaction for hash {}", hash);
-        self.get_cacheable_arc_data(&self.transactions, &self.transactions_cache, hash, DiskContext::GetTransaction).await
-    }
-
-    async fn get_transaction_size(&self, hash: &Hash) -> Result<usize, BlockchainError> {
-        trace!("get transaction size for hash {}", hash);
-        let data = self.transactions.get(hash.as_bytes())?;
-        data.map(|data| data.len()).ok_or(BlockchainError::NotFoundOnDisk(DiskContext::LoadData))
-    }
-
-    async fn has_transaction(&self, hash: &Hash) -> Result<bool, BlockchainError> {
-        trace!("has transaction {}", hash);
-        self.contains_data(&self.transactions, &self.transactions_cache, hash).await
-    }
-
-    async fn count_transactions(&self) -> Result<u64, BlockchainError> {
-        trace!("count transactions");
-        Ok(self.transactions_count.load(Ordering::SeqCst))
-    }
-
-    async fn delete_transaction(&mut self, hash: &Hash) -> Result<Arc<Transaction>, BlockchainError> {
-        self.delete_cacheable_data::<Hash, HashSet<Hash>>(&self.tx_blocks, &None, hash).await?;
-        self.delete_data(&self.transactions, &self.transactions_cache, hash).await
-    }
-}
\ No newline at end of file
@@ -0,0 +1,143 @@
+use ::rocksdb::{
+    properties,
+    BlockBasedOptions,
+    Cache,
+    ColumnFamily,
+    ColumnFamilyDescriptor,
+    DBCompressionType,
+    Direction,
+    IteratorMode,
+    Options,
+    WriteBatch,
+    DB
+};
+use xelis_common::network::Network;
+use crate::core::error::BlockchainError;
+use super::{
+    kv::{Column, KvBackend, KvIterator, KvStorage},
+    Snapshot,
+    StorageMode
+};
+
+// Default block cache size
+const DEFAULT_DB_CACHE_CAPACITY: u64 = 16 * 1024 * 1024; // 16 MB
+
+impl Into<DBCompressionType> for StorageMode {
+    fn into(self) -> DBCompressionType {
+        match self {
+            Self::HighThroughput => DBCompressionType::Lz4,
+            Self::LowSpace => DBCompressionType::Zstd
+        }
+    }
+}
+
+// RocksDB backend, each column is stored in its own column family
+pub struct RocksBackend {
+    // opened DB with all the column families
+    db: DB
+}
+
+impl RocksBackend {
+    pub fn open(path: String, internal_cache_size: Option<u64>, mode: StorageMode) -> Result<Self, BlockchainError> {
+        // Block cache is shared between all the column families
+        let cache = Cache::new_lru_cache(internal_cache_size.unwrap_or(DEFAULT_DB_CACHE_CAPACITY) as usize);
+        let mut block_options = BlockBasedOptions::default();
+        block_options.set_block_cache(&cache);
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options.set_compression_type(mode.into());
+        options.set_block_based_table_factory(&block_options);
+
+        let descriptors = Column::ALL.iter()
+            .map(|column| ColumnFamilyDescriptor::new(column.name(), options.clone()));
+
+        let db = DB::open_cf_descriptors(&options, path, descriptors)?;
+        Ok(Self { db })
+    }
+
+    // Retrieve the column family handle
+    // All of them are opened at startup, so this should never fail
+    fn get_cf(&self, column: Column) -> Result<&ColumnFamily, BlockchainError> {
+        self.db.cf_handle(column.name())
+            .ok_or(BlockchainError::ColumnFamilyNotFound(column.name()))
+    }
+}
+
+impl KvBackend for RocksBackend {
+    fn get(&self, column: Column, key: &[u8]) -> Result<Option<Vec<u8>>, BlockchainError> {
+        let cf = self.get_cf(column)?;
+        Ok(self.db.get_cf(cf, key)?)
+    }
+
+    fn put(&self, column: Column, key: &[u8], value: &[u8]) -> Result<(), BlockchainError> {
+        let cf = self.get_cf(column)?;
+        self.db.put_cf(cf, key, value)?;
+        Ok(())
+    }
+
+    fn delete(&self, column: Column, key: &[u8]) -> Result<(), BlockchainError> {
+        let cf = self.get_cf(column)?;
+        self.db.delete_cf(cf, key)?;
+        Ok(())
+    }
+
+    fn contains(&self, column: Column, key: &[u8]) -> Result<bool, BlockchainError> {
+        let cf = self.get_cf(column)?;
+        Ok(self.db.get_pinned_cf(cf, key)?.is_some())
+    }
+
+    fn iter_from<'a>(&'a self, column: Column, start: &[u8]) -> Result<KvIterator<'a>, BlockchainError> {
+        let cf = self.get_cf(column)?;
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(start, Direction::Forward))
+            .map(|res| res.map_err(BlockchainError::from));
+        Ok(Box::new(iter))
+    }
+
+    // All the column families are updated in one write batch
+    fn apply_snapshot(&self, snapshot: Snapshot) -> Result<(), BlockchainError> {
+        let mut batch = WriteBatch::default();
+        for (column, changes) in snapshot.into_changes() {
+            let cf = self.get_cf(column)?;
+            for (key, value) in changes {
+                match value {
+                    Some(value) => batch.put_cf(cf, key, value),
+                    None => batch.delete_cf(cf, key)
+                }
+            }
+        }
+
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    // This is the sum of all SST files of each column family
+    fn size_on_disk(&self) -> Result<u64, BlockchainError> {
+        let mut size = 0;
+        for column in Column::ALL {
+            let cf = self.get_cf(column)?;
+            size += self.db.property_int_value_cf(cf, properties::TOTAL_SST_FILES_SIZE)?.unwrap_or(0);
+        }
+        Ok(size)
+    }
+
+    fn flush(&self) -> Result<(), BlockchainError> {
+        for column in Column::ALL {
+            let cf = self.get_cf(column)?;
+            self.db.flush_cf(cf)?;
+        }
+        self.db.flush_wal(true)?;
+        Ok(())
+    }
+}
+
+pub type RocksStorage = KvStorage<RocksBackend>;
+
+impl RocksStorage {
+    pub fn new(dir_path: String, cache_size: Option<usize>, network: Network, internal_cache_size: Option<u64>, mode: StorageMode) -> Result<Self, BlockchainError> {
+        let path = format!("{}{}-rocksdb", dir_path, network.to_string().to_lowercase());
+        let backend = RocksBackend::open(path, internal_cache_size, mode)?;
+        Self::with_backend(backend, cache_size, network)
+    }
+}
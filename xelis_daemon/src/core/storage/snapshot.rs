@@ -0,0 +1,163 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    iter::Peekable,
+    ops::Bound,
+    vec::IntoIter
+};
+use super::kv::Column;
+
+// Changes staged for one column
+// A None value means that the key got deleted
+pub type Changes = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+// All the writes done since the start of a commit point
+// Nothing is written on disk until the snapshot is applied,
+// so every read must look in it first before going to the disk
+pub struct Snapshot {
+    columns: Vec<Changes>
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self {
+            columns: Column::ALL.iter()
+                .map(|_| Changes::new())
+                .collect()
+        }
+    }
+
+    // Returns None if the key was not changed
+    // Some(None) if the key was deleted
+    pub fn get(&self, column: Column, key: &[u8]) -> Option<Option<&[u8]>> {
+        self.columns[column as usize].get(key)
+            .map(|value| value.as_deref())
+    }
+
+    pub fn put(&mut self, column: Column, key: Vec<u8>, value: Vec<u8>) {
+        self.columns[column as usize].insert(key, Some(value));
+    }
+
+    pub fn delete(&mut self, column: Column, key: Vec<u8>) {
+        self.columns[column as usize].insert(key, None);
+    }
+
+    // Copy all the changes with a key greater or equal to start
+    pub fn changes_from(&self, column: Column, start: &[u8]) -> Vec<(Vec<u8>, Option<Vec<u8>>)> {
+        self.columns[column as usize].range::<[u8], _>((Bound::Included(start), Bound::Unbounded))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // Consume the snapshot to write the changes
+    pub fn into_changes(self) -> impl Iterator<Item = (Column, Changes)> {
+        Column::ALL.iter().copied().zip(self.columns)
+    }
+}
+
+// Iterator merging the entries from the disk with the staged changes
+// Both must be sorted by key, a staged change always override the disk entry
+pub struct SnapshotIter<I: Iterator> {
+    inner: Peekable<I>,
+    changes: Peekable<IntoIter<(Vec<u8>, Option<Vec<u8>>)>>
+}
+
+impl<I: Iterator> SnapshotIter<I> {
+    pub fn new(inner: I, changes: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> Self {
+        Self {
+            inner: inner.peekable(),
+            changes: changes.into_iter().peekable()
+        }
+    }
+}
+
+impl<I, K, V, E> Iterator for SnapshotIter<I>
+where
+    I: Iterator<Item = Result<(K, V), E>>,
+    K: AsRef<[u8]> + From<Vec<u8>>,
+    V: From<Vec<u8>>
+{
+    type Item = Result<(K, V), E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ordering = match (self.changes.peek(), self.inner.peek()) {
+                (None, _) => return self.inner.next(),
+                (Some(_), None) => Ordering::Less,
+                (Some((key, _)), Some(Ok((inner_key, _)))) => key.as_slice().cmp(AsRef::<[u8]>::as_ref(inner_key)),
+                // Let the error goes through
+                (Some(_), Some(Err(_))) => return self.inner.next()
+            };
+
+            match ordering {
+                Ordering::Greater => return self.inner.next(),
+                // Entry on disk is overwritten by the staged change
+                Ordering::Equal => {
+                    self.inner.next();
+                },
+                Ordering::Less => {}
+            }
+
+            // Deleted keys are skipped
+            if let Some((key, Some(value))) = self.changes.next() {
+                return Some(Ok((key.into(), value.into())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(entries: &[(&[u8], &[u8])]) -> impl Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), ()>> {
+        entries.iter()
+            .map(|(key, value)| Ok((key.to_vec().into_boxed_slice(), value.to_vec().into_boxed_slice())))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_snapshot_iter_merge() {
+        let mut snapshot = Snapshot::new();
+        snapshot.put(Column::Balances, vec![0], vec![10]);
+        snapshot.put(Column::Balances, vec![2], vec![20]);
+        snapshot.delete(Column::Balances, vec![3]);
+        snapshot.put(Column::Balances, vec![5], vec![50]);
+        // Not in the same column
+        snapshot.put(Column::Nonces, vec![1], vec![0]);
+
+        let inner = disk(&[(&[1], &[1]), (&[2], &[2]), (&[3], &[3]), (&[4], &[4])]);
+        let entries: Vec<_> = SnapshotIter::new(inner, snapshot.changes_from(Column::Balances, &[]))
+            .map(|res| {
+                let (key, value) = res.unwrap();
+                (key.to_vec(), value.to_vec())
+            })
+            .collect();
+
+        assert_eq!(entries, vec![
+            (vec![0], vec![10]),
+            (vec![1], vec![1]),
+            (vec![2], vec![20]),
+            (vec![4], vec![4]),
+            (vec![5], vec![50])
+        ]);
+    }
+
+    #[test]
+    fn test_snapshot_changes_from() {
+        let mut snapshot = Snapshot::new();
+        snapshot.put(Column::VersionedNonces, vec![1, 0], vec![]);
+        snapshot.delete(Column::VersionedNonces, vec![1, 1]);
+        snapshot.put(Column::VersionedNonces, vec![2, 0], vec![]);
+        snapshot.put(Column::VersionedNonces, vec![0, 1], vec![]);
+
+        let keys: Vec<_> = snapshot.changes_from(Column::VersionedNonces, &[1])
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec![vec![1, 0], vec![1, 1], vec![2, 0]]);
+        assert_eq!(snapshot.get(Column::VersionedNonces, &[1, 1]), Some(None));
+        assert_eq!(snapshot.get(Column::VersionedNonces, &[3]), None);
+    }
+}
@@ -0,0 +1,467 @@
+// Deterministic harness to build DAG shapes on an in-memory blockchain
+// Blocks are identified by a label, "genesis" being the genesis block
+// Timestamps are relative to the genesis block and blocks use the difficulty expected at their tips
+// NOTE: tips with the same cumulative difficulty are sorted by hash, the highest first.
+// Hashes change on each run because the genesis block depends on the current time,
+// so a block competing with a tied one must be built using `BlockSpec::before`
+
+use std::{collections::HashMap, sync::Arc};
+use indexmap::IndexSet;
+use xelis_common::{
+    account::CiphertextCache,
+    block::{Block, BlockHeader, EXTRA_NONCE_SIZE},
+    config::XELIS_ASSET,
+    crypto::{
+        elgamal::Ciphertext,
+        hash,
+        Hash,
+        Hashable,
+        KeyPair,
+        PublicKey
+    },
+    immutable::Immutable,
+    network::Network,
+    transaction::{
+        builder::{
+            AccountState,
+            FeeBuilder,
+            FeeHelper,
+            TransactionBuilder,
+            TransactionTypeBuilder,
+            TransferBuilder
+        },
+        Reference,
+        Transaction,
+        TxVersion
+    }
+};
+use super::{
+    blockchain::{get_block_dev_fee, Blockchain, Config},
+    blockdag,
+    error::BlockchainError,
+    hard_fork::get_version_at_height,
+    storage::{
+        AccountProvider,
+        BalanceProvider,
+        BlockDagProvider,
+        ClientProtocolProvider,
+        DagOrderProvider,
+        DifficultyProvider,
+        MemoryStorage,
+        NonceProvider
+    }
+};
+
+pub const GENESIS: &str = "genesis";
+
+// Description of a block to add in the test chain
+pub struct BlockSpec {
+    label: String,
+    tips: Vec<String>,
+    // in milliseconds after the genesis block
+    timestamp: Option<u64>,
+    // blocks that must be ordered after this one if they are tied
+    before: Vec<String>,
+    miner: Option<PublicKey>,
+    txs: Vec<Transaction>
+}
+
+impl BlockSpec {
+    pub fn new(label: &str, tips: &[&str]) -> Self {
+        Self {
+            label: label.to_owned(),
+            tips: tips.iter().map(|tip| (*tip).to_owned()).collect(),
+            timestamp: None,
+            before: Vec::new(),
+            miner: None,
+            txs: Vec::new()
+        }
+    }
+
+    // By default, the block is 1ms after its most recent tip
+    pub fn timestamp(mut self, millis_after_genesis: u64) -> Self {
+        self.timestamp = Some(millis_after_genesis);
+        self
+    }
+
+    // The block hash is made higher than the hash of the given block
+    // so it is ordered first when both have the same cumulative difficulty
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.to_owned());
+        self
+    }
+
+    // By default, the test chain miner is used
+    pub fn miner(mut self, miner: &PublicKey) -> Self {
+        self.miner = Some(miner.clone());
+        self
+    }
+
+    pub fn tx(mut self, tx: Transaction) -> Self {
+        self.txs.push(tx);
+        self
+    }
+}
+
+pub struct TestChain {
+    blockchain: Arc<Blockchain<MemoryStorage>>,
+    blocks: HashMap<String, Hash>,
+    genesis_timestamp: u64,
+    miner: KeyPair
+}
+
+impl TestChain {
+    pub async fn new() -> Self {
+        Self::with_config(Config::headless()).await
+    }
+
+    pub async fn with_config(config: Config) -> Self {
+        let storage = MemoryStorage::new(Network::Dev).unwrap();
+        let blockchain = Blockchain::new(config, Network::Dev, storage).await.unwrap();
+
+        let (genesis, genesis_timestamp) = {
+            let storage = blockchain.get_storage().read().await;
+            let hash = storage.get_hash_at_topo_height(0).await.unwrap();
+            let timestamp = storage.get_timestamp_for_block_hash(&hash).await.unwrap();
+            (hash, timestamp)
+        };
+
+        let mut blocks = HashMap::new();
+        blocks.insert(GENESIS.to_owned(), genesis);
+
+        Self {
+            blockchain,
+            blocks,
+            genesis_timestamp,
+            miner: KeyPair::new()
+        }
+    }
+
+    pub fn blockchain(&self) -> &Arc<Blockchain<MemoryStorage>> {
+        &self.blockchain
+    }
+
+    pub fn miner(&self) -> PublicKey {
+        self.miner.get_public_key().compress()
+    }
+
+    pub fn hash(&self, label: &str) -> &Hash {
+        self.blocks.get(label).unwrap_or_else(|| panic!("Unknown block {}", label))
+    }
+
+    // Build the block and add it in chain
+    // The label is registered only if the block got accepted
+    pub async fn add_block(&mut self, spec: BlockSpec) -> Result<Hash, BlockchainError> {
+        assert!(!self.blocks.contains_key(&spec.label), "Block {} already exists", spec.label);
+
+        let tips: IndexSet<Hash> = spec.tips.iter().map(|tip| self.hash(tip).clone()).collect();
+        let (height, timestamp) = {
+            let storage = self.blockchain.get_storage().read().await;
+            let height = blockdag::calculate_height_at_tips(&*storage, tips.iter()).await?;
+            let timestamp = match spec.timestamp {
+                Some(timestamp) => self.genesis_timestamp + timestamp,
+                None => {
+                    let mut timestamp = 0;
+                    for tip in tips.iter() {
+                        timestamp = timestamp.max(storage.get_timestamp_for_block_hash(tip).await?);
+                    }
+                    timestamp + 1
+                }
+            };
+            (height, timestamp)
+        };
+
+        // Unique extra nonce so two blocks with the same content have different hashes
+        let extra_nonce: [u8; EXTRA_NONCE_SIZE] = hash(spec.label.as_bytes()).to_bytes();
+        let miner = spec.miner.unwrap_or_else(|| self.miner());
+        let txs_hashes = spec.txs.iter().map(|tx| tx.hash()).collect();
+        let version = get_version_at_height(self.blockchain.get_network(), height);
+        let mut header = BlockHeader::new(version, height, timestamp, tips, extra_nonce, miner, txs_hashes);
+
+        // Search an extra nonce giving a hash higher than the blocks to be ordered after it
+        let after: Vec<&Hash> = spec.before.iter().map(|label| self.hash(label)).collect();
+        while after.iter().any(|hash| header.hash() <= **hash) {
+            let extra_nonce = hash(&header.extra_nonce).to_bytes();
+            header.set_extra_nonce(extra_nonce);
+        }
+
+        let block = Block::new(Immutable::Owned(header), spec.txs.into_iter().map(Immutable::Owned).collect());
+        let block_hash = block.hash();
+        self.blockchain.add_new_block(block, false, false).await?;
+        self.blocks.insert(spec.label, block_hash.clone());
+
+        Ok(block_hash)
+    }
+
+    // Build a signed transfer of XELIS from the sender
+    // The balance is the plaintext balance of the sender at the reference block
+    pub async fn transfer(&self, sender: &KeyPair, balance: u64, reference: &str, destination: &PublicKey, amount: u64) -> Transaction {
+        self.transfer_with_fee(sender, balance, reference, destination, amount, FeeBuilder::Multiplier(1f64)).await
+    }
+
+    // Same as `transfer` with a custom fee
+    // If the sender has TXs in mempool, the transfer is chained after them
+    // and the balance must be the plaintext balance expected after them
+    pub async fn transfer_with_fee(&self, sender: &KeyPair, balance: u64, reference: &str, destination: &PublicKey, amount: u64, fee: FeeBuilder) -> Transaction {
+        self.build_transfer(sender, balance, reference, destination, amount, fee, true).await
+    }
+
+    // Build a transfer ignoring the TXs of the sender in mempool
+    // It uses the same nonce as the first TX of the sender in mempool, so it can replace it
+    pub async fn replace_transfer(&self, sender: &KeyPair, balance: u64, reference: &str, destination: &PublicKey, amount: u64, fee: FeeBuilder) -> Transaction {
+        self.build_transfer(sender, balance, reference, destination, amount, fee, false).await
+    }
+
+    async fn build_transfer(&self, sender: &KeyPair, balance: u64, reference: &str, destination: &PublicKey, amount: u64, fee: FeeBuilder, chained: bool) -> Transaction {
+        let source = sender.get_public_key().compress();
+        let reference = self.hash(reference);
+
+        let mut state = {
+            let storage = self.blockchain.get_storage().read().await;
+            let topoheight = storage.get_topo_height_for_hash(reference).await.unwrap();
+            let (_, version) = storage.get_balance_at_maximum_topoheight(&source, &XELIS_ASSET, topoheight).await.unwrap()
+                .expect("Sender has no balance at reference");
+            let nonce = storage.get_nonce_at_maximum_topoheight(&source, topoheight).await.unwrap()
+                .map(|(_, version)| version.get_nonce())
+                .unwrap_or(0);
+
+            TestAccountState {
+                balance,
+                ciphertext: version.take_balance(),
+                reference: Reference {
+                    hash: reference.clone(),
+                    topoheight
+                },
+                nonce,
+                destination_registered: storage.is_account_registered_at_topoheight(destination, topoheight).await.unwrap()
+            }
+        };
+
+        if chained {
+            let mempool = self.blockchain.get_mempool().read().await;
+            if let Some(cache) = mempool.get_cache_for(&source) {
+                state.nonce = cache.get_next_nonce();
+                if let Some(ciphertext) = cache.get_balances().get(&XELIS_ASSET) {
+                    state.ciphertext = CiphertextCache::Decompressed(ciphertext.clone());
+                }
+            }
+        }
+
+        let data = TransactionTypeBuilder::Transfers(vec![TransferBuilder {
+            asset: XELIS_ASSET,
+            amount,
+            destination: destination.as_address(false),
+            extra_data: None
+        }]);
+        let builder = TransactionBuilder::new(TxVersion::V0, source, data, fee);
+        builder.build(&mut state, sender).unwrap()
+    }
+
+    // Reward received by the miner of an ordered block, excluding the transactions fees
+    pub async fn miner_reward(&self, label: &str) -> u64 {
+        let storage = self.blockchain.get_storage().read().await;
+        let hash = self.hash(label);
+        let topoheight = storage.get_topo_height_for_hash(hash).await.unwrap();
+        let height = storage.get_height_for_block_hash(hash).await.unwrap();
+        let reward = storage.get_block_reward_at_topo_height(topoheight).unwrap();
+        reward - reward * get_block_dev_fee(height) / 100
+    }
+
+    // Verify the full DAG order from the genesis block
+    // Every other known block must be orphaned
+    pub async fn assert_order(&self, expected: &[&str]) {
+        let storage = self.blockchain.get_storage().read().await;
+        for (topoheight, label) in expected.iter().enumerate() {
+            let hash = storage.get_hash_at_topo_height(topoheight as u64).await.unwrap();
+            assert_eq!(hash, *self.hash(label), "Expected block {} at topoheight {}", label, topoheight);
+            assert!(storage.is_block_topological_ordered(&hash).await, "Block {} is not ordered", label);
+        }
+
+        for (label, hash) in self.blocks.iter().filter(|(label, _)| !expected.contains(&label.as_str())) {
+            assert!(!storage.is_block_topological_ordered(hash).await, "Block {} should be orphaned", label);
+        }
+
+        assert_eq!(self.blockchain.get_topo_height(), expected.len() as u64 - 1);
+    }
+
+    pub async fn assert_orphaned(&self, labels: &[&str]) {
+        let storage = self.blockchain.get_storage().read().await;
+        for label in labels {
+            assert!(!storage.is_block_topological_ordered(self.hash(label)).await, "Block {} should be orphaned", label);
+        }
+    }
+
+    // Verify in which block the transaction got executed, None if it is orphaned
+    pub async fn assert_tx_executed_in(&self, tx: &Hash, label: Option<&str>) {
+        let storage = self.blockchain.get_storage().read().await;
+        match label {
+            Some(label) => assert!(storage.is_tx_executed_in_block(tx, self.hash(label)).unwrap(), "TX {} should be executed in {}", tx, label),
+            None => assert!(!storage.is_tx_executed_in_a_block(tx).unwrap(), "TX {} should be orphaned", tx)
+        }
+    }
+
+    // Verify the latest XELIS balance of the account, None if it has no balance
+    pub async fn assert_balance(&self, keypair: &KeyPair, expected: Option<u64>) {
+        let storage = self.blockchain.get_storage().read().await;
+        let key = keypair.get_public_key().compress();
+        let has_balance = storage.has_balance_for(&key, &XELIS_ASSET).await.unwrap();
+        let Some(expected) = expected else {
+            assert!(!has_balance, "Account should have no balance");
+            return;
+        };
+
+        assert!(has_balance, "Account should have a balance");
+        let (_, version) = storage.get_last_balance(&key, &XELIS_ASSET).await.unwrap();
+        let ciphertext = version.take_balance().take_ciphertext().unwrap();
+        // The ciphertext minus the expected amount must be an encryption of zero
+        assert_eq!(keypair.decrypt_to_point(&(ciphertext - expected)), keypair.decrypt_to_point(&Ciphertext::zero()), "Invalid balance");
+    }
+
+    pub async fn stop(self) {
+        self.blockchain.stop().await;
+    }
+}
+
+// Account state of the sender used to build the transactions
+struct TestAccountState {
+    balance: u64,
+    ciphertext: CiphertextCache,
+    reference: Reference,
+    nonce: u64,
+    destination_registered: bool
+}
+
+impl FeeHelper for TestAccountState {
+    type Error = ();
+
+    fn account_exists(&self, _: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(self.destination_registered)
+    }
+}
+
+impl AccountState for TestAccountState {
+    fn is_mainnet(&self) -> bool {
+        false
+    }
+
+    fn get_account_balance(&self, _: &Hash) -> Result<u64, Self::Error> {
+        Ok(self.balance)
+    }
+
+    fn get_reference(&self) -> Reference {
+        self.reference.clone()
+    }
+
+    fn get_account_ciphertext(&self, _: &Hash) -> Result<CiphertextCache, Self::Error> {
+        Ok(self.ciphertext.clone())
+    }
+
+    fn update_account_balance(&mut self, _: &Hash, balance: u64, ciphertext: Ciphertext) -> Result<(), Self::Error> {
+        self.balance = balance;
+        self.ciphertext = CiphertextCache::Decompressed(ciphertext);
+        Ok(())
+    }
+
+    fn get_nonce(&self) -> Result<u64, Self::Error> {
+        Ok(self.nonce)
+    }
+
+    fn update_nonce(&mut self, new_nonce: u64) -> Result<(), Self::Error> {
+        self.nonce = new_nonce;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_linear_chain() {
+        let mut chain = TestChain::new().await;
+        chain.add_block(BlockSpec::new("a", &[GENESIS])).await.unwrap();
+        chain.add_block(BlockSpec::new("b", &["a"])).await.unwrap();
+        chain.add_block(BlockSpec::new("c", &["b"])).await.unwrap();
+
+        chain.assert_order(&[GENESIS, "a", "b", "c"]).await;
+        assert_eq!(chain.blockchain().get_height(), 3);
+
+        // A block can't be older than its tips
+        let err = chain.add_block(BlockSpec::new("d", &["c"]).timestamp(0)).await.unwrap_err();
+        assert!(matches!(err, BlockchainError::TimestampIsLessThanParent(_)));
+        chain.assert_order(&[GENESIS, "a", "b", "c"]).await;
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_heavier_branch_reorg() {
+        let mut chain = TestChain::new().await;
+        chain.add_block(BlockSpec::new("b1", &[GENESIS])).await.unwrap();
+        chain.add_block(BlockSpec::new("b2", &["b1"])).await.unwrap();
+
+        // Lighter branch, order is not changed
+        chain.add_block(BlockSpec::new("a1", &[GENESIS])).await.unwrap();
+        chain.assert_order(&[GENESIS, "b1", "b2"]).await;
+
+        // Same cumulative difficulty but a higher hash, the DAG is reorganized
+        chain.add_block(BlockSpec::new("a2", &["a1"]).before("b2")).await.unwrap();
+        chain.assert_order(&[GENESIS, "a1", "a2"]).await;
+        chain.assert_orphaned(&["b1", "b2"]).await;
+
+        // Heavier branch, the DAG is reorganized back
+        chain.add_block(BlockSpec::new("b3", &["b2"])).await.unwrap();
+        chain.assert_order(&[GENESIS, "b1", "b2", "b3"]).await;
+        chain.assert_orphaned(&["a1", "a2"]).await;
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_tied_tips_order() {
+        let mut chain = TestChain::new().await;
+        chain.add_block(BlockSpec::new("a1", &[GENESIS])).await.unwrap();
+        chain.add_block(BlockSpec::new("b1", &[GENESIS]).before("a1")).await.unwrap();
+        chain.assert_order(&[GENESIS, "b1"]).await;
+        chain.assert_orphaned(&["a1"]).await;
+
+        // The merge block orders the tied tips by their hash
+        chain.add_block(BlockSpec::new("m", &["a1", "b1"])).await.unwrap();
+        chain.assert_order(&[GENESIS, "b1", "a1", "m"]).await;
+
+        chain.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_reorg_orphans_transaction() {
+        let mut chain = TestChain::new().await;
+        let alice = KeyPair::new();
+        let bob = KeyPair::new();
+
+        chain.add_block(BlockSpec::new("a1", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+        let balance = chain.miner_reward("a1").await;
+        chain.assert_balance(&alice, Some(balance)).await;
+
+        let amount = balance / 2;
+        let tx = chain.transfer(&alice, balance, "a1", &bob.get_public_key().compress(), amount).await;
+        let tx_hash = tx.hash();
+        let fee = tx.get_fee();
+        chain.add_block(BlockSpec::new("a2", &["a1"]).tx(tx)).await.unwrap();
+
+        chain.assert_order(&[GENESIS, "a1", "a2"]).await;
+        chain.assert_tx_executed_in(&tx_hash, Some("a2")).await;
+        chain.assert_balance(&alice, Some(balance - amount - fee)).await;
+        chain.assert_balance(&bob, Some(amount)).await;
+
+        // Branch ordered first without alice reward
+        chain.add_block(BlockSpec::new("b1", &[GENESIS])).await.unwrap();
+        chain.add_block(BlockSpec::new("b2", &["b1"]).before("a2")).await.unwrap();
+
+        chain.assert_order(&[GENESIS, "b1", "b2"]).await;
+        chain.assert_tx_executed_in(&tx_hash, None).await;
+        chain.assert_balance(&alice, None).await;
+        chain.assert_balance(&bob, None).await;
+
+        chain.stop().await;
+    }
+}
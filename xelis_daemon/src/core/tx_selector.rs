@@ -34,27 +34,69 @@ impl PartialEq for TxSelectorEntry<'_> {
 
 impl Eq for TxSelectorEntry<'_> {}
 
+// Compare two fee rates given as (fees, size in bytes)
+fn cmp_fee_rate(a: (u64, usize), b: (u64, usize)) -> Ordering {
+    (a.0 as u128 * b.1 as u128).cmp(&(b.0 as u128 * a.1 as u128))
+}
+
 // this struct is used to store transactions in a queue
 // and to order them by fees
 // Each Transactions is for a specific sender
+// TXs of a sender are ordered by nonces, so a TX can't be selected before the previous ones
+// The queue is scored as a package: the highest fee rate of all its prefixes
+// This allow a TX with a high fee to pay for the previous TXs with low fees
 #[derive(PartialEq, Eq)]
-struct Transactions<'a>(VecDeque<TxSelectorEntry<'a>>);
+struct Transactions<'a> {
+    txs: VecDeque<TxSelectorEntry<'a>>,
+    // Total fees and size of the best package
+    score: (u64, usize),
+    // Count of TXs in the best package
+    package_len: usize
+}
+
+impl<'a> Transactions<'a> {
+    fn new(txs: VecDeque<TxSelectorEntry<'a>>) -> Self {
+        let mut transactions = Self {
+            txs,
+            score: (0, 0),
+            package_len: 0
+        };
+        transactions.update_score();
+        transactions
+    }
+
+    // Search the prefix with the highest cumulative fee rate
+    fn update_score(&mut self) {
+        let (mut fees, mut size) = (0u64, 0usize);
+        self.score = (0, 0);
+        self.package_len = 0;
+        for (i, entry) in self.txs.iter().enumerate() {
+            fees = fees.saturating_add(entry.tx.get_fee());
+            size += entry.size;
+            if self.package_len == 0 || cmp_fee_rate((fees, size), self.score) == Ordering::Greater {
+                self.score = (fees, size);
+                self.package_len = i + 1;
+            }
+        }
+    }
+}
 
 impl PartialOrd for Transactions<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.front().map(|e| e.tx.get_fee()).partial_cmp(&other.0.front().map(|e| e.tx.get_fee()))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Transactions<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.0.front().map(|e| e.tx.get_fee()).cmp(&other.0.front().map(|e| e.tx.get_fee()))
+        cmp_fee_rate(self.score, other.score)
     }
 }
 
 // TX selector is used to select transactions from the mempool
 // It create sub groups of transactions by sender and order them by nonces
-// It joins all sub groups in a queue that is ordered by fees
+// It joins all sub groups in a queue that is ordered by their best package fee rate
+// All the TXs of a package are selected together
 pub struct TxSelector<'a> {
     queue: BinaryHeap<Transactions<'a>>
 }
@@ -67,9 +109,9 @@ impl<'a> TxSelector<'a> {
     {
         let mut queue = BinaryHeap::new();
 
-        // push every group to the queue
-        for group in groups {
-            queue.push(Transactions(VecDeque::from(group)));
+        // push every non empty group to the queue
+        for group in groups.filter(|group| !group.is_empty()) {
+            queue.push(Transactions::new(VecDeque::from(group)));
         }
 
         Self {
@@ -110,18 +152,60 @@ impl<'a> TxSelector<'a> {
         Self::grouped(iter)
     }
 
-    // Get the next transaction with the highest fee
-    pub fn next(&mut self) -> Option<TxSelectorEntry<'a>> {
-        // get the group with the highest fee
+    // Get the next package with the highest fee rate
+    // It must be included entirely or skipped, the next TXs of its sender depend on it
+    pub fn next_package(&mut self) -> Option<Vec<TxSelectorEntry<'a>>> {
         let mut group = self.queue.pop()?;
-        // get the entry with the highest fee from this group
-        let entry = group.0.pop_front()?;
+        let package = group.txs.drain(..group.package_len).collect();
 
-        // if its not empty, push it back to the queue
-        if !group.0.is_empty() {
+        // if its not empty, score the TXs left and push it back to the queue
+        if !group.txs.is_empty() {
+            group.update_score();
             self.queue.push(group);
         }
 
-        Some(entry)
+        Some(package)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{
+        crypto::{Hashable, KeyPair},
+        transaction::builder::FeeBuilder
+    };
+    use crate::core::test_chain::{BlockSpec, TestChain, GENESIS};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_package_selection() {
+        let mut chain = TestChain::new().await;
+        let alice = KeyPair::new();
+        let bob = KeyPair::new();
+        chain.add_block(BlockSpec::new("a", &[GENESIS]).miner(&alice.get_public_key().compress())).await.unwrap();
+        chain.add_block(BlockSpec::new("b", &["a"]).miner(&bob.get_public_key().compress())).await.unwrap();
+
+        let destination = KeyPair::new().get_public_key().compress();
+        let balance = chain.miner_reward("a").await;
+        let low = chain.transfer(&alice, balance, "b", &destination, 1).await;
+        let fee = low.get_fee();
+        chain.blockchain().add_tx_to_mempool(low.clone(), false).await.unwrap();
+
+        // Alice pays for its low fee TX with the next one
+        let high = chain.transfer_with_fee(&alice, balance - 1 - fee, "b", &destination, 1, FeeBuilder::Value(fee * 10)).await;
+        let medium = chain.transfer_with_fee(&bob, chain.miner_reward("b").await, "b", &destination, 1, FeeBuilder::Value(fee * 3)).await;
+
+        let txs: Vec<(Arc<Hash>, Arc<Transaction>)> = [low, high, medium].into_iter()
+            .map(|tx| (Arc::new(tx.hash()), Arc::new(tx)))
+            .collect();
+
+        // Bob TX has a higher fee than the first Alice TX, but not than both Alice TXs together
+        let mut selector = TxSelector::new(txs.iter().map(|(hash, tx)| (tx.size(), hash, tx)));
+        let selected: Vec<Vec<&Arc<Hash>>> = std::iter::from_fn(|| selector.next_package())
+            .map(|package| package.into_iter().map(|entry| entry.hash).collect())
+            .collect();
+        assert_eq!(selected, vec![vec![&txs[0].0, &txs[1].0], vec![&txs[2].0]]);
+
+        chain.stop().await;
+    }
+}
@@ -3,7 +3,7 @@ pub mod p2p;
 pub mod core;
 pub mod config;
 
-use config::{DEV_PUBLIC_KEY, STABLE_LIMIT};
+use config::{DEV_PUBLIC_KEY, EXPORT_CHAIN_BATCH_SIZE, P2P_STATE_SNAPSHOT_MIN_PEERS, PRUNE_SAFETY_LIMIT, STABLE_LIMIT};
 use fern::colors::Color;
 use humantime::format_duration;
 use log::{trace, error, info, warn};
@@ -17,7 +17,11 @@ use xelis_common::{
     config::{VERSION, XELIS_ASSET},
     context::Context,
     crypto::{
-        Address,Hashable
+        Address,
+        Hash,
+        Hashable,
+        KeyPair,
+        PrivateKey
     },
     difficulty::Difficulty,
     network::Network,
@@ -57,7 +61,9 @@ use crate::{
         },
         storage::{
             Storage,
-            SledStorage
+            SledStorage,
+            RocksStorage,
+            MemoryStorage
         }
     },
     config::{
@@ -67,15 +73,28 @@ use crate::{
 };
 use core::{
     blockdag,
+    chain_file::{
+        ChainFileHeader,
+        ChainFileReader,
+        ChainFileWriter
+    },
     hard_fork::{
         get_pow_algorithm_for_version,
         get_version_at_height
     },
-    storage::StorageMode
+    replay,
+    state_snapshot::{
+        apply_state_snapshot,
+        write_state_snapshot,
+        StateSnapshotHeader,
+        StateSnapshotReader
+    },
+    storage::{StorageBackend, StorageMode}
 };
 use std::{
+    collections::HashSet,
     fs::File,
-    io::Write,
+    io::{BufReader, BufWriter, Write},
     net::{IpAddr, SocketAddr},
     sync::Arc,
     time::Duration
@@ -137,7 +156,19 @@ pub struct NodeConfig {
     internal_cache_size: Option<u64>,
     /// Internal DB mode to use
     #[clap(long, value_enum, default_value_t = StorageMode::LowSpace)]
-    internal_db_mode: StorageMode
+    internal_db_mode: StorageMode,
+    /// Database backend to use for the chain storage
+    /// 
+    /// Each backend use its own folder, switching backend require to resync the chain.
+    /// Default is sled, or memory if the simulator is enabled.
+    #[clap(long, value_enum)]
+    storage_backend: Option<StorageBackend>,
+    /// Replay the blocks from topoheight FROM to TO and exit
+    /// 
+    /// This is an offline mode, all the network modules are disabled.
+    /// Blocks are re-executed in memory from the genesis block and the stored state is compared with the replayed one.
+    #[clap(long, num_args = 2, value_names = ["FROM", "TO"])]
+    replay_blocks: Option<Vec<u64>>
 }
 
 const BLOCK_TIME: Difficulty = Difficulty::from_u64(BLOCK_TIME_MILLIS / MILLIS_PER_SECOND);
@@ -155,26 +186,65 @@ async fn main() -> Result<()> {
         warn!("Switching automatically to network {} because of simulator enabled", config.network);
     }
 
-    let blockchain_config = config.nested;
+    let mut blockchain_config = config.nested;
     if let Some(path) = blockchain_config.dir_path.as_ref() {
         if !(path.ends_with("/") || path.ends_with("\\")) {
             return Err(anyhow::anyhow!("Path must end with / or \\"));
         }
     }
 
-    let storage = {
-        let use_cache = if blockchain_config.cache_size > 0 {
-            Some(blockchain_config.cache_size)
-        } else {
-            None
-        };
+    let replay_range = config.replay_blocks.map(|range| (range[0], range[1]));
+    if replay_range.is_some() {
+        info!("Offline mode enabled, all the network modules are disabled");
+        blockchain_config.disable_p2p_server = true;
+        blockchain_config.disable_rpc_server = true;
+        blockchain_config.disable_getwork_server = true;
+        blockchain_config.simulator = None;
+    }
+
+    let use_cache = if blockchain_config.cache_size > 0 {
+        Some(blockchain_config.cache_size)
+    } else {
+        None
+    };
 
-        let dir_path = blockchain_config.dir_path.clone().unwrap_or_default();
-        SledStorage::new(dir_path, use_cache, config.network, config.internal_cache_size, config.internal_db_mode)?
+    let storage_backend = match config.storage_backend {
+        Some(backend) => backend,
+        None if blockchain_config.simulator.is_some() => {
+            info!("Using in-memory storage because of simulator enabled");
+            StorageBackend::Memory
+        },
+        None => StorageBackend::Sled
     };
 
-    let blockchain = Blockchain::new(blockchain_config, config.network, storage).await?;
-    if let Err(e) = run_prompt(prompt, blockchain.clone(), config.network).await {
+    let dir_path = blockchain_config.dir_path.clone().unwrap_or_default();
+    match storage_backend {
+        StorageBackend::Sled => {
+            let storage = SledStorage::new(dir_path, use_cache, config.network, config.internal_cache_size, config.internal_db_mode)?;
+            start_chain(prompt, blockchain_config, config.network, storage, replay_range).await
+        },
+        StorageBackend::RocksDB => {
+            let storage = RocksStorage::new(dir_path, use_cache, config.network, config.internal_cache_size, config.internal_db_mode)?;
+            start_chain(prompt, blockchain_config, config.network, storage, replay_range).await
+        },
+        StorageBackend::Memory => {
+            let storage = MemoryStorage::new(config.network)?;
+            start_chain(prompt, blockchain_config, config.network, storage, replay_range).await
+        }
+    }
+}
+
+// Start the blockchain with the selected storage and run the prompt until exit
+// If a replay range is set, only replay the blocks in it and exit
+async fn start_chain<S: Storage>(prompt: ShareablePrompt, config: Config, network: Network, storage: S, replay_range: Option<(u64, u64)>) -> Result<()> {
+    let blockchain = Blockchain::new(config, network, storage).await?;
+    if let Some((from, to)) = replay_range {
+        let res = replay_blocks_offline(&blockchain, from, to).await;
+        blockchain.stop().await;
+        return res
+    }
+
+    if let Err(e) = run_prompt(prompt, blockchain.clone(), network).await {
         error!("Error while running prompt: {}", e);
     }
 
@@ -182,6 +252,16 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+async fn replay_blocks_offline<S: Storage>(blockchain: &Blockchain<S>, from: u64, to: u64) -> Result<()> {
+    info!("Replaying blocks from topoheight {} to {}, this can take a while...", from, to);
+    if let Some(divergence) = replay::replay_blocks(blockchain.get_storage(), *blockchain.get_network(), from, to).await? {
+        return Err(anyhow::anyhow!("Divergence found: {}", divergence))
+    }
+
+    info!("No divergence found from topoheight {} to {}", from, to);
+    Ok(())
+}
+
 async fn run_prompt<S: Storage>(prompt: ShareablePrompt, blockchain: Arc<Blockchain<S>>, network: Network) -> Result<(), PromptError> {
     let mut context = Context::default();
     context.store(blockchain.clone());
@@ -218,6 +298,12 @@ async fn run_prompt<S: Storage>(prompt: ShareablePrompt, blockchain: Arc<Blockch
     command_manager.add_command(Command::new("list_unexecuted_transactions", "List all unexecuted transactions", CommandHandler::Async(async_handler!(list_unexecuted_transactions::<S>))))?;
     command_manager.add_command(Command::new("swap_blocks_executions_positions", "Swap the position of two blocks executions", CommandHandler::Async(async_handler!(swap_blocks_executions_positions::<S>))))?;
     command_manager.add_command(Command::new("print_balance", "Print the encrypted balance at a specific topoheight", CommandHandler::Async(async_handler!(print_balance::<S>))))?;
+    command_manager.add_command(Command::with_optional_arguments("check_db", "Check the storage consistency and optionally repair it", vec![Arg::new("repair", ArgType::Bool)], CommandHandler::Async(async_handler!(check_db::<S>))))?;
+    command_manager.add_command(Command::with_arguments("export_chain", "Export blocks in topological order to a file", vec![Arg::new("path", ArgType::String)], vec![Arg::new("start", ArgType::Number), Arg::new("end", ArgType::Number)], CommandHandler::Async(async_handler!(export_chain::<S>))))?;
+    command_manager.add_command(Command::with_arguments("import_chain", "Import blocks from an exported chain file", vec![Arg::new("path", ArgType::String)], vec![Arg::new("trusted", ArgType::Bool)], CommandHandler::Async(async_handler!(import_chain::<S>))))?;
+    command_manager.add_command(Command::with_arguments("create_snapshot", "Write a signed snapshot of the state at a stable topoheight", vec![Arg::new("path", ArgType::String)], vec![Arg::new("topoheight", ArgType::Number)], CommandHandler::Async(async_handler!(create_snapshot::<S>))))?;
+    command_manager.add_command(Command::with_required_arguments("load_snapshot", "Load a state snapshot verified against our peers", vec![Arg::new("path", ArgType::String)], CommandHandler::Async(async_handler!(load_snapshot::<S>))))?;
+    command_manager.add_command(Command::with_required_arguments("replay_blocks", "Re-execute blocks in a stable topoheight range and compare the stored state", vec![Arg::new("from", ArgType::Number), Arg::new("to", ArgType::Number)], CommandHandler::Async(async_handler!(replay_blocks::<S>))))?;
 
     // Don't keep the lock for ever
     let (p2p, getwork) = {
@@ -419,6 +505,70 @@ async fn verify_chain<S: Storage>(manager: &CommandManager, mut args: ArgumentMa
     Ok(())
 }
 
+// Walk the whole storage to find dangling or orphaned entries
+async fn check_db<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let repair = if args.has_argument("repair") {
+        args.get_value("repair")?.to_bool()?
+    } else {
+        false
+    };
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+
+    if repair {
+        let prompt = manager.get_prompt();
+        manager.warn("Repair mode will modify the database, make sure to have a backup before continuing");
+        if !prompt.ask_confirmation().await.context("Error while asking confirmation")? {
+            return Ok(())
+        }
+    }
+
+    manager.message("Checking database, this can take a while...");
+    let mut storage = blockchain.get_storage().write().await;
+    let issues = storage.check_integrity(repair).await.context("Error while checking database")?;
+    if issues.is_empty() {
+        manager.message("No issue found");
+        return Ok(())
+    }
+
+    for issue in issues.iter() {
+        manager.warn(format!("- {}", issue));
+    }
+
+    if repair {
+        manager.message(format!("{} issues found and repaired", issues.len()));
+    } else {
+        manager.message(format!("{} issues found, run check_db with repair set to true to fix them", issues.len()));
+    }
+
+    Ok(())
+}
+
+// Re-execute the blocks in memory and report the first divergence with the stored state
+// The chain keeps running, so only the stable topoheights can be replayed
+async fn replay_blocks<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let from = args.get_value("from")?.to_number()?;
+    let to = args.get_value("to")?.to_number()?;
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+
+    let stable_topoheight = blockchain.get_stable_topoheight();
+    if to > stable_topoheight {
+        manager.error(format!("Topoheight {} is above the stable topoheight {}", to, stable_topoheight));
+        return Ok(())
+    }
+
+    manager.message(format!("Replaying blocks from topoheight {} to {}, this can take a while...", from, to));
+    match replay::replay_blocks(blockchain.get_storage(), *blockchain.get_network(), from, to).await.context("Error while replaying blocks")? {
+        Some(divergence) => manager.error(format!("Divergence found: {}", divergence)),
+        None => manager.message("No divergence found")
+    }
+
+    Ok(())
+}
+
 // This is a debug command to see all unexecuted transactions in the chain that can happen due to DAG reorgs
 async fn list_unexecuted_transactions<S: Storage>(manager: &CommandManager, _: ArgumentManager) -> Result<(), CommandError> {
     let context = manager.get_context().lock()?;
@@ -474,6 +624,293 @@ async fn print_balance<S: Storage>(manager: &CommandManager, _: ArgumentManager)
     Ok(())
 }
 
+// Export all blocks with their transactions in topological order to a chain file
+async fn export_chain<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let path = args.get_value("path")?.to_string_value()?;
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+
+    let start = if args.has_argument("start") {
+        args.get_value("start")?.to_number()?
+    } else {
+        0
+    };
+
+    // Only the stable blocks are exported, the others may still be reorganized
+    let stable_topoheight = blockchain.get_stable_topoheight();
+    let end = if args.has_argument("end") {
+        args.get_value("end")?.to_number()?
+    } else {
+        stable_topoheight
+    };
+
+    if start > end || end > stable_topoheight {
+        manager.error(format!("Invalid range, expected start <= end <= {} (stable topoheight)", stable_topoheight));
+        return Ok(())
+    }
+
+    {
+        let storage = blockchain.get_storage().read().await;
+        if let Some(pruned_topoheight) = storage.get_pruned_topoheight().await.context("Error while retrieving pruned topoheight")? {
+            if start <= pruned_topoheight {
+                manager.error(format!("Chain is pruned until topoheight {}, start must be above it", pruned_topoheight));
+                return Ok(())
+            }
+        }
+    }
+
+    manager.message(format!("Exporting blocks from topoheight {} to {} in {}...", start, end, path));
+    let file = File::create(&path).context("Error while creating file")?;
+    let header = ChainFileHeader::new(*blockchain.get_network(), start, end);
+    let mut writer = ChainFileWriter::new(BufWriter::new(file), &header).context("Error while writing header")?;
+
+    let mut topoheight = start;
+    // Last block exported, used to detect a reorg between two batches
+    let mut previous: Option<Hash> = None;
+    while topoheight <= end {
+        let batch_end = end.min(topoheight + EXPORT_CHAIN_BATCH_SIZE - 1);
+        // Only keep the storage locked while reading the blocks of this batch
+        let blocks = {
+            let storage = blockchain.get_storage().read().await;
+            if let Some(previous) = previous.as_ref() {
+                let hash = storage.get_hash_at_topo_height(topoheight - 1).await.context("Error while retrieving hash at topoheight")?;
+                if hash != *previous {
+                    manager.error(format!("Block at topoheight {} changed during the export, aborting", topoheight - 1));
+                    return Ok(())
+                }
+            }
+
+            let mut blocks = Vec::with_capacity((batch_end - topoheight + 1) as usize);
+            for topoheight in topoheight..=batch_end {
+                let hash = storage.get_hash_at_topo_height(topoheight).await.context("Error while retrieving hash at topoheight")?;
+                blocks.push(storage.get_block_by_hash(&hash).await.context("Error while retrieving block")?);
+            }
+            blocks
+        };
+
+        for block in blocks {
+            writer.write_block(&block).context("Error while writing block")?;
+            previous = Some(block.hash());
+        }
+
+        manager.message(format!("Exported {} blocks", batch_end - start + 1));
+        topoheight = batch_end + 1;
+    }
+
+    writer.finish().context("Error while finishing chain file")?;
+    manager.message(format!("Chain exported to {} ({} blocks)", path, end - start + 1));
+
+    Ok(())
+}
+
+// Import blocks from a chain file
+// All blocks are fully verified unless trusted mode is enabled
+async fn import_chain<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let path = args.get_value("path")?.to_string_value()?;
+    let trusted = if args.has_argument("trusted") {
+        args.get_value("trusted")?.to_bool()?
+    } else {
+        false
+    };
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+
+    // First pass: verify the whole file before adding any block
+    manager.message(format!("Verifying chain file {}...", path));
+    let file = File::open(&path).context("Error while opening file")?;
+    let reader = ChainFileReader::new(BufReader::new(file)).context("Error while reading header")?;
+    let header = reader.get_header().clone();
+    if header.network != *blockchain.get_network() {
+        manager.error(format!("Chain file is for network {} but node is running on {}", header.network, blockchain.get_network()));
+        return Ok(())
+    }
+    reader.verify().context("Error while verifying chain file")?;
+
+    if trusted {
+        manager.warn("Trusted mode enabled, PoW and transactions proofs will not be verified!");
+    }
+
+    // Second pass: add all the blocks
+    manager.message(format!("Importing blocks from topoheight {} to {}...", header.start_topoheight, header.end_topoheight));
+    let file = File::open(&path).context("Error while opening file")?;
+    let mut reader = ChainFileReader::new(BufReader::new(file)).context("Error while reading header")?;
+    let (mut added, mut skipped) = (0u64, 0u64);
+    while let Some(block) = reader.next_block().context("Error while reading block")? {
+        let hash = block.hash();
+        let already_in_chain = {
+            let storage = blockchain.get_storage().read().await;
+            storage.has_block_with_hash(&hash).await.context("Error while checking block")?
+        };
+
+        if already_in_chain {
+            skipped += 1;
+            continue;
+        }
+
+        let res = if trusted {
+            blockchain.add_trusted_block(block).await
+        } else {
+            blockchain.add_new_block(block, false, false).await
+        };
+
+        if let Err(e) = res {
+            manager.error(format!("Error while adding block {}: {}", hash, e));
+            break;
+        }
+
+        added += 1;
+        if added % 1000 == 0 {
+            manager.message(format!("Imported {} blocks, current topoheight: {}", added, blockchain.get_topo_height()));
+        }
+    }
+
+    manager.message(format!("Import done: {} blocks added, {} already in chain", added, skipped));
+
+    Ok(())
+}
+
+// Write the whole state at a stable topoheight with the top blocks in a signed snapshot file
+async fn create_snapshot<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let path = args.get_value("path")?.to_string_value()?;
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let prompt = manager.get_prompt();
+
+    let topoheight = if args.has_argument("topoheight") {
+        args.get_value("topoheight")?.to_number()?
+    } else {
+        blockchain.get_stable_topoheight()
+    };
+
+    if topoheight < PRUNE_SAFETY_LIMIT || topoheight > blockchain.get_stable_topoheight() {
+        manager.error(format!("Invalid topoheight, expected {} <= topoheight <= {}", PRUNE_SAFETY_LIMIT, blockchain.get_stable_topoheight()));
+        return Ok(())
+    }
+
+    let private_key = prompt.read_input("Signer private key (hex): ", true).await
+        .context("Error while reading private key")?;
+    let private_key = PrivateKey::from_hex(private_key)
+        .context("Invalid private key")?;
+    let keypair = KeyPair::from_private_key(private_key);
+
+    let merkle_hash = {
+        let storage = blockchain.get_storage().read().await;
+        // Blocks must be stored with their txs and the balances history must be available
+        let pruned_topoheight = blockchain.get_advertised_pruned_topoheight(&storage).await.context("Error while retrieving pruned topoheight")?
+            .max(storage.get_balances_pruned_topoheight().await.context("Error while retrieving balances pruned topoheight")?);
+        if let Some(pruned_topoheight) = pruned_topoheight {
+            if topoheight - PRUNE_SAFETY_LIMIT <= pruned_topoheight {
+                manager.error(format!("Chain is pruned until topoheight {}, topoheight must be above {}", pruned_topoheight, pruned_topoheight + PRUNE_SAFETY_LIMIT));
+                return Ok(())
+            }
+        }
+
+        // Peers only answer for sync blocks
+        let hash = storage.get_hash_at_topo_height(topoheight).await.context("Error while retrieving hash at topoheight")?;
+        if !blockchain.is_sync_block(&storage, &hash).await.context("Error while checking sync block")? {
+            manager.error(format!("Block {} at topoheight {} is not a sync block", hash, topoheight));
+            return Ok(())
+        }
+
+        manager.message(format!("Writing state snapshot at topoheight {} in {}...", topoheight, path));
+        let file = File::create(&path).context("Error while creating file")?;
+        let header = StateSnapshotHeader::new(*blockchain.get_network(), topoheight);
+        let (_, merkle_hash) = write_state_snapshot(&*storage, &header, BufWriter::new(file), &keypair).await
+            .context("Error while writing state snapshot")?;
+        merkle_hash
+    };
+
+    {
+        let mut storage = blockchain.get_storage().write().await;
+        storage.set_state_root_at_topoheight(topoheight, &merkle_hash).await
+            .context("Error while saving merkle hash")?;
+    }
+
+    let signer = keypair.get_public_key().compress().as_address(blockchain.get_network().is_mainnet());
+    manager.message(format!("State snapshot written to {} with merkle hash {}, signed by {}", path, merkle_hash, signer));
+
+    Ok(())
+}
+
+// Load a state snapshot on a chain that only contains the genesis block
+// Its merkle hash must be confirmed by all the peers able to answer, with a minimum of distinct peers
+async fn load_snapshot<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
+    let path = args.get_value("path")?.to_string_value()?;
+
+    let context = manager.get_context().lock()?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+
+    if blockchain.get_topo_height() != 0 {
+        manager.error("A state snapshot can only be loaded on a chain containing only the genesis block");
+        return Ok(())
+    }
+
+    // First pass: verify the whole file before writing anything
+    manager.message(format!("Verifying state snapshot {}...", path));
+    let file = File::open(&path).context("Error while opening file")?;
+    let reader = StateSnapshotReader::new(BufReader::new(file)).context("Error while reading header")?;
+    let header = reader.get_header().clone();
+    if header.network != *blockchain.get_network() {
+        manager.error(format!("State snapshot is for network {} but node is running on {}", header.network, blockchain.get_network()));
+        return Ok(())
+    }
+
+    let verified = reader.verify().context("Error while verifying state snapshot")?;
+    let signer = verified.signer.as_address(blockchain.get_network().is_mainnet());
+    manager.message(format!("State snapshot at topoheight {} with {} assets and {} accounts, merkle hash {} signed by {}", header.topoheight, verified.assets, verified.accounts, verified.merkle_hash, signer));
+
+    // Check the merkle hash against the one reported by our peers
+    let hashes = match blockchain.get_p2p().read().await.as_ref() {
+        Some(p2p) => p2p.request_state_roots(header.topoheight).await,
+        None => {
+            manager.error("P2P is not enabled, state snapshot can't be verified");
+            return Ok(())
+        }
+    };
+
+    // Several connections from the same IP only count as one peer
+    let mut ips = HashSet::new();
+    let hashes: Vec<_> = hashes.into_iter()
+        .filter(|(peer, _)| ips.insert(peer.get_ip()))
+        .collect();
+
+    let mut mismatches = 0;
+    for (peer, hash) in hashes.iter() {
+        if *hash != verified.merkle_hash {
+            manager.warn(format!("{} reported merkle hash {}", peer, hash));
+            mismatches += 1;
+        }
+    }
+
+    if mismatches > 0 {
+        manager.error(format!("{} of {} peers reported a different merkle hash, state snapshot rejected", mismatches, hashes.len()));
+        return Ok(())
+    }
+
+    if hashes.len() < P2P_STATE_SNAPSHOT_MIN_PEERS {
+        manager.error(format!("Merkle hash at topoheight {} confirmed by only {} distinct peers, {} required", header.topoheight, hashes.len(), P2P_STATE_SNAPSHOT_MIN_PEERS));
+        return Ok(())
+    }
+    manager.message(format!("Merkle hash confirmed by {} peers", hashes.len()));
+
+    // Second pass: apply the state
+    let file = File::open(&path).context("Error while opening file")?;
+    let reader = StateSnapshotReader::new(BufReader::new(file)).context("Error while reading header")?;
+    {
+        let mut storage = blockchain.get_storage().write().await;
+        apply_state_snapshot(&mut *storage, reader, &verified.merkle_hash).await
+            .context("Error while applying state snapshot")?;
+    }
+
+    blockchain.reload_from_disk().await.context("Error while reloading chain from disk")?;
+    manager.message(format!("State snapshot loaded, topoheight is now {}", blockchain.get_topo_height()));
+
+    Ok(())
+}
+
 async fn kick_peer<S: Storage>(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
     let context = manager.get_context().lock()?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
@@ -0,0 +1,169 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering}
+};
+use xelis_common::{
+    api::daemon::PacketBandwidth,
+    time::TimestampMillis
+};
+use super::packet::{
+    get_packet_name,
+    BOOTSTRAP_CHAIN_REQUEST_ID,
+    CHAIN_REQUEST_ID,
+    OBJECT_REQUEST_ID,
+    PACKETS_COUNT
+};
+
+// Maximum count of expensive requests accepted per minute from a peer
+// A limit set to 0 is disabled
+#[derive(Clone, Copy)]
+pub struct RequestsLimits {
+    pub chain_requests: u32,
+    pub object_requests: u32,
+    pub bootstrap_chain_requests: u32
+}
+
+impl RequestsLimits {
+    // Get the limit of a packet id, if any
+    pub fn get_limit(&self, packet_id: u8) -> Option<u32> {
+        let limit = match packet_id {
+            CHAIN_REQUEST_ID => self.chain_requests,
+            OBJECT_REQUEST_ID => self.object_requests,
+            BOOTSTRAP_CHAIN_REQUEST_ID => self.bootstrap_chain_requests,
+            _ => return None
+        };
+
+        (limit != 0).then_some(limit)
+    }
+}
+
+// Traffic counters of a packet id
+#[derive(Default)]
+struct PacketCounter {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    packets_in: AtomicU64,
+    packets_out: AtomicU64
+}
+
+// Inbound and outbound traffic broken down by packet id
+// Used for each peer and for the whole P2p server
+pub struct Bandwidth {
+    counters: [PacketCounter; PACKETS_COUNT]
+}
+
+impl Bandwidth {
+    pub fn new() -> Self {
+        Self {
+            counters: std::array::from_fn(|_| PacketCounter::default())
+        }
+    }
+
+    // Count a packet received
+    pub fn record_in(&self, id: u8, size: usize) {
+        if let Some(counter) = self.counters.get(id as usize) {
+            counter.bytes_in.fetch_add(size as u64, Ordering::Relaxed);
+            counter.packets_in.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // Count a packet sent
+    pub fn record_out(&self, id: u8, size: usize) {
+        if let Some(counter) = self.counters.get(id as usize) {
+            counter.bytes_out.fetch_add(size as u64, Ordering::Relaxed);
+            counter.packets_out.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // Total bytes received in packets
+    pub fn get_bytes_in(&self) -> u64 {
+        self.counters.iter().map(|counter| counter.bytes_in.load(Ordering::Relaxed)).sum()
+    }
+
+    // Total bytes sent in packets
+    pub fn get_bytes_out(&self) -> u64 {
+        self.counters.iter().map(|counter| counter.bytes_out.load(Ordering::Relaxed)).sum()
+    }
+
+    // Get the traffic of each packet type seen at least once
+    pub fn get_stats(&self) -> HashMap<Cow<'static, str>, PacketBandwidth> {
+        self.counters.iter()
+            .enumerate()
+            .map(|(id, counter)| (id as u8, PacketBandwidth {
+                bytes_in: counter.bytes_in.load(Ordering::Relaxed),
+                bytes_out: counter.bytes_out.load(Ordering::Relaxed),
+                packets_in: counter.packets_in.load(Ordering::Relaxed),
+                packets_out: counter.packets_out.load(Ordering::Relaxed)
+            }))
+            .filter(|(_, stats)| stats.packets_in != 0 || stats.packets_out != 0)
+            .map(|(id, stats)| (Cow::Borrowed(get_packet_name(id)), stats))
+            .collect()
+    }
+}
+
+// Cost of a request in the token bucket
+// Tokens are scaled so the bucket can be refilled each millisecond without rounding
+const TOKENS_PER_REQUEST: u64 = 60_000;
+
+// Token bucket refilled continuously up to its capacity
+// Each request consumes a token, and is refused when the bucket is empty
+pub struct TokenBucket {
+    capacity: u64,
+    // Tokens added per millisecond, this is the count of requests allowed per minute
+    refill_rate: u64,
+    tokens: u64,
+    last_refill: TimestampMillis
+}
+
+impl TokenBucket {
+    // Create a full bucket allowing N requests per minute
+    pub fn new(per_minute: u32, now: TimestampMillis) -> Self {
+        let capacity = per_minute as u64 * TOKENS_PER_REQUEST;
+        Self {
+            capacity,
+            refill_rate: per_minute as u64,
+            tokens: capacity,
+            last_refill: now
+        }
+    }
+
+    // Consume a token if available
+    pub fn try_consume(&mut self, now: TimestampMillis) -> bool {
+        let elapsed = now.saturating_sub(self.last_refill);
+        self.tokens = self.tokens.saturating_add(elapsed.saturating_mul(self.refill_rate)).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+
+        if self.tokens < TOKENS_PER_REQUEST {
+            return false;
+        }
+
+        self.tokens -= TOKENS_PER_REQUEST;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(60, 0);
+        for _ in 0..60 {
+            assert!(bucket.try_consume(0));
+        }
+        assert!(!bucket.try_consume(0));
+
+        // One token per second
+        assert!(!bucket.try_consume(500));
+        assert!(bucket.try_consume(1000));
+        assert!(!bucket.try_consume(1000));
+
+        // Never above its capacity
+        for _ in 0..60 {
+            assert!(bucket.try_consume(1_000_000));
+        }
+        assert!(!bucket.try_consume(1_000_000));
+    }
+}
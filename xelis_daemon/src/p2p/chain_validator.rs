@@ -333,6 +333,24 @@ impl<S: Storage> PrunedTopoheightProvider for ChainValidator<'_, S> {
     async fn set_pruned_topoheight(&mut self, _: u64) -> Result<(), BlockchainError> {
         Err(BlockchainError::UnsupportedOperation)
     }
+
+    async fn get_txs_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
+        let storage = self.blockchain.get_storage().read().await;
+        storage.get_txs_pruned_topoheight().await
+    }
+
+    async fn set_txs_pruned_topoheight(&mut self, _: u64) -> Result<(), BlockchainError> {
+        Err(BlockchainError::UnsupportedOperation)
+    }
+
+    async fn get_balances_pruned_topoheight(&self) -> Result<Option<u64>, BlockchainError> {
+        let storage = self.blockchain.get_storage().read().await;
+        storage.get_balances_pruned_topoheight().await
+    }
+
+    async fn set_balances_pruned_topoheight(&mut self, _: u64) -> Result<(), BlockchainError> {
+        Err(BlockchainError::UnsupportedOperation)
+    }
 }
 
 #[async_trait]
@@ -2,6 +2,8 @@ use crate::config::{PEER_TIMEOUT_DISCONNECT, PEER_TIMEOUT_INIT_CONNECTION, PEER_
 use super::{
     encryption::Encryption,
     error::P2pError,
+    identity::{NodeIdentity, NodeKey},
+    noise::{NoiseHandshake, MESSAGE_1_SIZE, MESSAGE_2_SIZE, MESSAGE_3_SIZE},
     packet::Packet,
     EncryptionKey
 };
@@ -33,7 +35,7 @@ use xelis_common::{
     serializer::{Reader, Serializer},
 };
 use bytes::Bytes;
-use log::{debug, error, trace, warn};
+use log::{debug, trace, warn};
 
 type P2pResult<T> = Result<T, P2pError>;
 
@@ -71,7 +73,9 @@ pub struct Connection {
     // How many key rotation we sent
     rotate_key_out: AtomicUsize,
     // Encryption state used for packets
-    encryption: Encryption
+    encryption: Encryption,
+    // Static key of the peer authenticated during the key exchange
+    peer_key: Option<NodeKey>
 }
 
 // We are rotating every 1GB sent
@@ -94,58 +98,71 @@ impl Connection {
             rotate_key_in: AtomicUsize::new(0),
             rotate_key_out: AtomicUsize::new(0),
             encryption: Encryption::new(),
+            peer_key: None
         }
     }
 
-    // Do a key exchange with the peer
-    // If we are the client, we send our key first in plaintext
-    // We wait for the peer to send its key
-    // If we are the server, we send back our key
-    // NOTE: This doesn't prevent any MITM at this point
-    // Because a MITM could intercept the key and send its own key to the peer
-    // and play the role as a proxy.
-    // Afaik, there is no way to have a decentralized way to prevent MITM without trusting a third party
-    // (That's what TLS/SSL does with the CA, but it's not decentralized and it's not trustless)
-    // A potential idea would be to hardcode seed nodes keys,
-    // and each nodes share the key of other along the socket address
-    pub async fn exchange_keys(&mut self, buffer: &mut [u8]) -> P2pResult<()> {
+    // Do a key exchange with the peer using a Noise XX handshake
+    // If we initiated the connection, we send the first message
+    // Both sides authenticate with their static node key,
+    // and the derived keys can't be read by someone relaying the messages
+    // A MITM can still do its own handshake with each side,
+    // that's why the static key returned should be compared to a pinned one when possible
+    pub async fn exchange_keys(&mut self, identity: &NodeIdentity, buffer: &mut [u8]) -> P2pResult<NodeKey> {
         trace!("Exchanging keys with {}", self.addr);
 
         // Update our state
         self.set_state(State::KeyExchange);
 
-        // Send our key if we initiated the connection
+        let mut handshake = NoiseHandshake::new(identity, self.is_out());
         if self.is_out() {
-            trace!("Sending our key to {}", self.addr);
-            let packet = self.rotate_key_packet().await?;
-            self.send_bytes(&packet).await?;
-            self.encryption.mark_as_ready();
-        }
+            trace!("Sending our ephemeral key to {}", self.addr);
+            self.send_bytes(&handshake.write_message_1()?).await?;
 
-        trace!("Waiting for key from {}", self.addr);
-        // Wait for the peer to receive its key
-        let Packet::KeyExchange(peer_key) = timeout(
-            Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION),
-            self.read_packet(buffer, 256)
-        ).await?? else {
-            error!("Expected KeyExchange packet");
-            return Err(P2pError::InvalidPacket);
-        };
-
-        // Now that we got the peer key, update our encryption state
-        self.rotate_peer_key(peer_key.into_owned()).await?;
-
-        // Send back our key if we are the server
-        if !self.is_out() {
-            trace!("Replying with our key to {}", self.addr);
-            let packet = self.rotate_key_packet().await?;
-            self.send_bytes(&packet).await?;
-            self.encryption.mark_as_ready();
+            trace!("Waiting for keys from {}", self.addr);
+            let message = self.read_handshake_message(buffer, MESSAGE_2_SIZE).await?;
+            handshake.read_message_2(&message)?;
+
+            trace!("Sending our static key to {}", self.addr);
+            self.send_bytes(&handshake.write_message_3()?).await?;
+        } else {
+            trace!("Waiting for ephemeral key from {}", self.addr);
+            let message = self.read_handshake_message(buffer, MESSAGE_1_SIZE).await?;
+            handshake.read_message_1(&message)?;
+
+            trace!("Replying with our keys to {}", self.addr);
+            self.send_bytes(&handshake.write_message_2()?).await?;
+
+            trace!("Waiting for static key from {}", self.addr);
+            let message = self.read_handshake_message(buffer, MESSAGE_3_SIZE).await?;
+            handshake.read_message_3(&message)?;
         }
 
+        // Now that we got the keys, update our encryption state
+        let (our_key, peer_key, remote_key) = handshake.finish()?;
+        self.encryption.rotate_key(our_key, true).await?;
+        self.rotate_key_out.fetch_add(1, Ordering::Relaxed);
+        self.rotate_peer_key(peer_key).await?;
+        self.encryption.mark_as_ready();
+        self.peer_key = Some(remote_key);
+
         trace!("Key exchange with {} successful", self.addr);
 
-        Ok(())
+        Ok(remote_key)
+    }
+
+    // Read a raw handshake message with the expected size
+    async fn read_handshake_message(&self, buffer: &mut [u8], size: usize) -> P2pResult<Vec<u8>> {
+        let bytes = timeout(
+            Duration::from_millis(PEER_TIMEOUT_INIT_CONNECTION),
+            self.read_packet_bytes(buffer, size as u32)
+        ).await??;
+
+        if bytes.len() != size {
+            return Err(P2pError::InvalidPacketSize);
+        }
+
+        Ok(bytes)
     }
 
     // Verify if its a outgoing connection
@@ -409,6 +426,11 @@ impl Connection {
         &self.addr
     }
 
+    // Get the static key of the peer, set once the key exchange is done
+    pub fn get_peer_key(&self) -> Option<&NodeKey> {
+        self.peer_key.as_ref()
+    }
+
     // Get the total bytes sent
     pub fn bytes_out(&self) -> usize {
         self.bytes_out.load(Ordering::Relaxed)
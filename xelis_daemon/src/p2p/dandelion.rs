@@ -0,0 +1,190 @@
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+    sync::Arc
+};
+use log::{debug, trace};
+use lru::LruCache;
+use rand::{seq::IteratorRandom, Rng};
+use tokio::sync::Mutex;
+use xelis_common::{
+    crypto::Hash,
+    transaction::Transaction,
+    time::{
+        get_current_time_in_millis,
+        get_current_time_in_seconds,
+        TimestampMillis,
+        TimestampSeconds
+    }
+};
+use crate::config::{
+    DANDELION_EMBARGO_MAX_DELAY,
+    DANDELION_EMBARGO_TIMEOUT,
+    DANDELION_EPOCH_DURATION,
+    DANDELION_FLUFFED_CACHE_SIZE,
+    DANDELION_FLUFF_PROBABILITY,
+    DANDELION_STEMPOOL_MAX_TXS
+};
+use super::peer::Peer;
+
+// Dandelion++ state of the transactions propagation
+// A new TX is first relayed along a random path of single peers (stem phase)
+// until a node decides to broadcast it to all its peers (fluff phase)
+// Each stem TX has an embargo timer: if it is not seen in fluff phase before it expires,
+// the node fluffs it itself so a TX can't be lost by a malicious or disconnected peer
+// The stem TXs received are kept in a stempool, hidden from the inventory and object requests,
+// and are only added to the mempool once fluffed so the originating node can't be found by polling them
+pub struct Dandelion {
+    // Peer id selected to relay the stem TXs with the time it was selected
+    stem_peer: Mutex<Option<(u64, TimestampSeconds)>>,
+    // TXs in stem phase with their embargo timeout
+    embargoes: Mutex<HashMap<Hash, TimestampMillis>>,
+    // TXs seen in fluff phase, they must not be stemmed again
+    fluffed: Mutex<LruCache<Hash, ()>>,
+    // Verified TXs received in stem phase, not in the mempool yet
+    stempool: Mutex<HashMap<Hash, Arc<Transaction>>>
+}
+
+impl Dandelion {
+    pub fn new() -> Self {
+        Self {
+            stem_peer: Mutex::new(None),
+            embargoes: Mutex::new(HashMap::new()),
+            fluffed: Mutex::new(LruCache::new(NonZeroUsize::new(DANDELION_FLUFFED_CACHE_SIZE).unwrap())),
+            stempool: Mutex::new(HashMap::new())
+        }
+    }
+
+    // Get the peer to relay a stem TX to
+    // The same peer is used during a whole epoch, except if it is the one that sent us the TX
+    // Outgoing peers are preferred as they were selected by us
+    pub async fn get_stem_peer(&self, peers: &HashSet<Arc<Peer>>, exclude: Option<u64>) -> Option<Arc<Peer>> {
+        let mut stem_peer = self.stem_peer.lock().await;
+        let now = get_current_time_in_seconds();
+        if let Some((id, selected_at)) = stem_peer.as_ref() {
+            if selected_at + DANDELION_EPOCH_DURATION > now && exclude != Some(*id) {
+                if let Some(peer) = peers.iter().find(|peer| peer.get_id() == *id) {
+                    return Some(Arc::clone(peer));
+                }
+            }
+        }
+
+        let candidates = peers.iter().filter(|peer| Some(peer.get_id()) != exclude);
+        let peer = candidates.clone()
+            .filter(|peer| peer.is_out())
+            .choose(&mut rand::thread_rng())
+            .or_else(|| candidates.choose(&mut rand::thread_rng()))?;
+
+        // Don't replace the stem peer of the epoch if it was only excluded
+        if !stem_peer.as_ref().is_some_and(|(id, selected_at)| selected_at + DANDELION_EPOCH_DURATION > now && exclude == Some(*id)) {
+            debug!("Selected {} as new Dandelion stem peer", peer);
+            *stem_peer = Some((peer.get_id(), now));
+        }
+
+        Some(Arc::clone(peer))
+    }
+
+    // Should a stem TX received be fluffed instead of relayed
+    pub fn should_fluff(&self) -> bool {
+        rand::thread_rng().gen_range(0..100) < DANDELION_FLUFF_PROBABILITY
+    }
+
+    // Start the embargo timer of a stem TX
+    // A random delay is added so the node fluffing first can't be predicted
+    pub async fn add_embargo(&self, hash: Hash) {
+        let timeout = get_current_time_in_millis() + DANDELION_EMBARGO_TIMEOUT + rand::thread_rng().gen_range(0..DANDELION_EMBARGO_MAX_DELAY);
+        trace!("Embargo for stem TX {} until {}", hash, timeout);
+        self.embargoes.lock().await.entry(hash).or_insert(timeout);
+    }
+
+    // Mark a TX as seen in fluff phase, its embargo is stopped
+    pub async fn mark_fluffed(&self, hash: &Hash) {
+        if self.embargoes.lock().await.remove(hash).is_some() {
+            trace!("Stem TX {} has been fluffed before its embargo timeout", hash);
+        }
+        self.fluffed.lock().await.put(hash.clone(), ());
+    }
+
+    // Check if a TX was already seen in fluff phase
+    pub async fn is_fluffed(&self, hash: &Hash) -> bool {
+        self.fluffed.lock().await.contains(hash)
+    }
+
+    // Check if a TX is in stem phase, it must not be shared with others peers
+    pub async fn is_embargoed(&self, hash: &Hash) -> bool {
+        self.embargoes.lock().await.contains_key(hash)
+    }
+
+    // Get all the TXs in stem phase
+    pub async fn get_embargoed_txs(&self) -> HashSet<Hash> {
+        self.embargoes.lock().await.keys().cloned().collect()
+    }
+
+    // Keep a verified stem TX out of the mempool until it is fluffed
+    pub async fn add_stem_tx(&self, hash: Hash, tx: Arc<Transaction>) {
+        self.stempool.lock().await.insert(hash, tx);
+    }
+
+    pub async fn is_stempool_full(&self) -> bool {
+        self.stempool.lock().await.len() >= DANDELION_STEMPOOL_MAX_TXS
+    }
+
+    pub async fn has_stem_tx(&self, hash: &Hash) -> bool {
+        self.stempool.lock().await.contains_key(hash)
+    }
+
+    pub async fn get_stem_tx(&self, hash: &Hash) -> Option<Arc<Transaction>> {
+        self.stempool.lock().await.get(hash).cloned()
+    }
+
+    // Remove a stem TX from the stempool to add it in the mempool
+    pub async fn take_stem_tx(&self, hash: &Hash) -> Option<Arc<Transaction>> {
+        self.stempool.lock().await.remove(hash)
+    }
+
+    // Remove and returns all the stem TXs with an embargo expired at this time
+    pub async fn take_expired_embargoes(&self, now: TimestampMillis) -> Vec<Hash> {
+        let mut embargoes = self.embargoes.lock().await;
+        let expired: Vec<Hash> = embargoes.iter()
+            .filter(|(_, timeout)| **timeout <= now)
+            .map(|(hash, _)| hash.clone())
+            .collect();
+
+        for hash in expired.iter() {
+            embargoes.remove(hash);
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::crypto::hash;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_embargoes() {
+        let dandelion = Dandelion::new();
+        let stem = hash(b"stem");
+        let fluffed = hash(b"fluffed");
+        dandelion.add_embargo(stem.clone()).await;
+        dandelion.add_embargo(fluffed.clone()).await;
+
+        assert!(dandelion.is_embargoed(&stem).await);
+
+        // Seen in fluff phase before its timeout
+        dandelion.mark_fluffed(&fluffed).await;
+        assert!(dandelion.is_fluffed(&fluffed).await);
+        assert!(!dandelion.is_fluffed(&stem).await);
+        assert!(!dandelion.is_embargoed(&fluffed).await);
+        assert_eq!(dandelion.get_embargoed_txs().await, HashSet::from([stem.clone()]));
+
+        let now = get_current_time_in_millis();
+        assert!(dandelion.take_expired_embargoes(now).await.is_empty());
+
+        let timeout = now + DANDELION_EMBARGO_TIMEOUT + DANDELION_EMBARGO_MAX_DELAY;
+        assert_eq!(dandelion.take_expired_embargoes(timeout).await, vec![stem]);
+        assert!(dandelion.take_expired_embargoes(timeout).await.is_empty());
+    }
+}
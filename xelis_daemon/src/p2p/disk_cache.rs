@@ -1,4 +1,4 @@
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use log::info;
 use sled::{Config, Db, Mode, Tree};
@@ -28,6 +28,11 @@ pub enum DiskError {
 pub struct DiskCache {
     // All known peers
     peerlist: Tree,
+    // Hostnames of the peers only reachable through a proxy
+    // They are stored in the peerlist using a placeholder IP address
+    hostnames: Tree,
+    // Outgoing peers connected at our last stop
+    anchors: Tree,
     // DB to use
     db: Db,
 }
@@ -45,6 +50,8 @@ impl DiskCache {
 
         Ok(Self {
             peerlist: db.open_tree("peerlist")?,
+            hostnames: db.open_tree("hostnames")?,
+            anchors: db.open_tree("anchors")?,
             db,
         })
     }
@@ -87,9 +94,47 @@ impl DiskCache {
         Ok(())
     }
 
+    // Set the hostname of a peer using its placeholder IP address
+    pub fn set_hostname(&self, peer: &IpAddr, hostname: &str) -> Result<(), DiskError> {
+        self.hostnames.insert(peer.to_bytes(), hostname.as_bytes())?;
+        Ok(())
+    }
+
+    // Get the hostname of a peer using its placeholder IP address
+    pub fn get_hostname(&self, peer: &IpAddr) -> Result<Option<String>, DiskError> {
+        match self.hostnames.get(peer.to_bytes())? {
+            Some(v) => {
+                let hostname = String::from_utf8(v.to_vec()).map_err(|_| ReaderError::InvalidValue)?;
+                Ok(Some(hostname))
+            },
+            None => Ok(None)
+        }
+    }
+
+    // Replace the anchors saved
+    pub fn set_anchors(&self, anchors: &[SocketAddr]) -> Result<(), DiskError> {
+        self.anchors.clear()?;
+        for addr in anchors {
+            self.anchors.insert(addr.to_bytes(), Vec::new())?;
+        }
+        Ok(())
+    }
+
+    // Get all the anchors saved at our last stop
+    pub fn get_anchors(&self) -> Result<Vec<SocketAddr>, DiskError> {
+        self.anchors.iter()
+            .map(|r| {
+                let (k, _) = r?;
+                Ok(SocketAddr::from_bytes(&k)?)
+            })
+            .collect()
+    }
+
     // Clear the peerlist
     pub async fn clear_peerlist(&self) -> Result<(), DiskError> {
         self.peerlist.clear()?;
+        self.hostnames.clear()?;
+        self.anchors.clear()?;
         self.db.flush_async().await?;
         Ok(())
     }
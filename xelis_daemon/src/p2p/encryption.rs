@@ -49,6 +49,8 @@ pub enum EncryptionError {
     CipherError,
     #[error("Not supported")]
     NotSupported,
+    #[error("Invalid handshake message")]
+    InvalidHandshakeMessage,
 }
 
 impl Encryption {
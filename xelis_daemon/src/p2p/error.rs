@@ -48,6 +48,8 @@ pub enum P2pError {
     InvalidMaxChainResponseSize,
     #[error("Invalid max peers, it must be greater than 0")]
     InvalidMaxPeers,
+    #[error("Invalid max outgoing peers, it must be at most {} to keep half of the slots for incoming peers", _0)]
+    InvalidMaxOutgoingPeers(usize),
     #[error("Already closed")]
     AlreadyClosed,
     #[error("Incompatible with configured exclusive nodes")]
@@ -56,8 +58,6 @@ pub enum P2pError {
     NotAllowed,
     #[error("Peer list is full")]
     PeerListFull,
-    #[error("Tracker request has expired, we didn't received a valid response in time")]
-    TrackerRequestExpired,
     #[error("Peer not found by id {}", _0)]
     PeerNotFoundById(u64),
     #[error("Invalid pop count, got {} with only {} blocks", _0, _1)]
@@ -182,6 +182,34 @@ pub enum P2pError {
     SemaphoreAcquireError(#[from] AcquireError),
     #[error(transparent)]
     EncryptionError(#[from] EncryptionError),
+    #[error("Invalid node public key, it must be 32 bytes in hex")]
+    InvalidNodeKey,
+    #[error("Peer {} has a different public key than the one pinned", _0)]
+    PinnedKeyMismatch(SocketAddr),
+    #[error("Peer id {} is not derived from its public key", _0)]
+    InvalidPeerId(u64),
+    #[error("TXs of compact block {} are already requested", _0)]
+    BlockTransactionsAlreadyRequested(Hash),
+    #[error("Received unrequested TXs for compact block {}", _0)]
+    UnrequestedBlockTransactions(Hash),
+    #[error("Invalid TXs received for compact block {}", _0)]
+    InvalidBlockTransactions(Hash),
+    #[error("Compact block {} was rebuilt with a different hash", _0)]
+    InvalidCompactBlock(Hash),
+    #[error("Requests limit of {} per minute exceeded for {} packets", _0, _1)]
+    RequestsLimitExceeded(u32, &'static str),
+    #[error("Invalid proxy address: {}, expected socks5://[username:password@]host:port", _0)]
+    InvalidProxyAddress(String),
+    #[error("Proxy error: {}", _0)]
+    ProxyError(&'static str),
+    #[error("Invalid hostname: {}", _0)]
+    InvalidHostname(String),
+    #[error("No hostname known for {}", _0)]
+    UnknownHostname(SocketAddr),
+    #[error("A proxy is required to connect to {}", _0)]
+    ProxyRequired(SocketAddr),
+    #[error("No peer available to download the blocks range")]
+    NoPeerForBlocksRange,
 }
 
 impl From<BlockchainError> for P2pError {
@@ -0,0 +1,151 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path
+};
+use log::info;
+use rand::rngs::OsRng;
+use x25519_dalek::{PublicKey, StaticSecret};
+use super::error::P2pError;
+
+// Static public key of a node, used to authenticate it during the key exchange
+pub type NodeKey = [u8; 32];
+
+// Persistent identity of our node
+// Its static key is used in the handshake to prove who we are to the peer
+// And our peer id is derived from its public key
+pub struct NodeIdentity {
+    secret: StaticSecret,
+    public_key: NodeKey
+}
+
+impl NodeIdentity {
+    // Generate a new random identity
+    pub fn new() -> Self {
+        Self::from_secret(StaticSecret::random_from_rng(OsRng))
+    }
+
+    fn from_secret(secret: StaticSecret) -> Self {
+        let public_key = PublicKey::from(&secret).to_bytes();
+        Self {
+            secret,
+            public_key
+        }
+    }
+
+    // Load the identity from its file, or create it if it doesn't exist yet
+    // The same identity is kept across restarts so peers can pin our key
+    pub fn load_or_create(path: &str) -> Result<Self, P2pError> {
+        let identity = if Path::new(path).exists() {
+            let bytes = fs::read(path)?;
+            let secret: [u8; 32] = bytes.as_slice().try_into()?;
+            Self::from_secret(StaticSecret::from(secret))
+        } else {
+            let identity = Self::new();
+            write_secret(path, &identity.secret.to_bytes())?;
+            identity
+        };
+
+        info!("P2p node public key: {}", hex::encode(identity.get_public_key()));
+        Ok(identity)
+    }
+
+    // Get the static secret used in the key exchange
+    pub fn get_secret(&self) -> &StaticSecret {
+        &self.secret
+    }
+
+    // Get our public key shared during the key exchange
+    pub fn get_public_key(&self) -> &NodeKey {
+        &self.public_key
+    }
+
+    // Get our peer id derived from our public key
+    pub fn get_peer_id(&self) -> u64 {
+        get_peer_id_from_key(&self.public_key)
+    }
+}
+
+// Write the secret key in a temporary file readable only by us
+// and rename it, so a crash can't leave a truncated key behind
+fn write_secret(path: &str, secret: &[u8]) -> Result<(), P2pError> {
+    let tmp_path = format!("{}.tmp", path);
+    // A previous attempt may have left it with other permissions
+    if let Err(e) = fs::remove_file(&tmp_path) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(e.into());
+        }
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp_path)?;
+    file.write_all(secret)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Derive the peer id from a node public key
+// A peer can't choose its id without owning the key behind it
+pub fn get_peer_id_from_key(key: &NodeKey) -> u64 {
+    let hash = blake3::hash(key);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[0..8]);
+    u64::from_be_bytes(bytes)
+}
+
+// Parse a node address that may be prefixed by its public key in hex to pin it
+// Format is <public key>@<address>
+pub fn parse_pinned_address(value: &str) -> Result<(&str, Option<NodeKey>), P2pError> {
+    match value.split_once('@') {
+        Some((key, addr)) => {
+            let bytes = hex::decode(key).map_err(|_| P2pError::InvalidNodeKey)?;
+            let key: NodeKey = bytes.as_slice().try_into().map_err(|_| P2pError::InvalidNodeKey)?;
+            Ok((addr, Some(key)))
+        },
+        None => Ok((value, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_or_create() {
+        // Random file name so parallel runs don't conflict
+        let path = std::env::temp_dir().join(format!("xelis-node-identity-{}.key", hex::encode(NodeIdentity::new().get_public_key())));
+        let path = path.to_str().unwrap();
+
+        let identity = NodeIdentity::load_or_create(path).unwrap();
+        let loaded = NodeIdentity::load_or_create(path).unwrap();
+        assert_eq!(identity.get_public_key(), loaded.get_public_key());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_pinned_address() {
+        let identity = NodeIdentity::new();
+        let value = format!("{}@127.0.0.1:2125", hex::encode(identity.get_public_key()));
+        let (addr, key) = parse_pinned_address(&value).unwrap();
+        assert_eq!(addr, "127.0.0.1:2125");
+        assert_eq!(key.as_ref(), Some(identity.get_public_key()));
+
+        assert_eq!(parse_pinned_address("127.0.0.1:2125").unwrap(), ("127.0.0.1:2125", None));
+        assert!(parse_pinned_address("abcd@127.0.0.1:2125").is_err());
+    }
+}
@@ -4,12 +4,19 @@ pub mod error;
 pub mod packet;
 pub mod peer_list;
 pub mod chain_validator;
+pub mod identity;
+pub mod bandwidth;
+pub mod proxy;
 mod tracker;
 mod encryption;
 mod disk_cache;
+mod noise;
+mod dandelion;
+mod parallel_sync;
 
 pub use encryption::EncryptionKey;
 
+use futures_util::{stream, StreamExt};
 use indexmap::IndexSet;
 use lru::LruCache;
 use xelis_common::{
@@ -26,6 +33,7 @@ use xelis_common::{
     immutable::Immutable,
     serializer::Serializer,
     tokio::{ThreadPool, spawn_task},
+    transaction::Transaction,
     time::{
         get_current_time_in_millis,
         get_current_time_in_seconds,
@@ -34,13 +42,16 @@ use xelis_common::{
 };
 use crate::{
     config::{
+        get_dns_seeds,
         get_genesis_block_hash,
         get_seed_nodes,
         CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS, CHAIN_SYNC_DELAY, CHAIN_SYNC_REQUEST_EXPONENTIAL_INDEX_START,
-        CHAIN_SYNC_REQUEST_MAX_BLOCKS, CHAIN_SYNC_RESPONSE_MAX_BLOCKS, CHAIN_SYNC_RESPONSE_MIN_BLOCKS,
-        CHAIN_SYNC_TOP_BLOCKS, MILLIS_PER_SECOND, NETWORK_ID, P2P_AUTO_CONNECT_PRIORITY_NODES_DELAY,
-        P2P_EXTEND_PEERLIST_DELAY, P2P_PING_DELAY, P2P_PING_PEER_LIST_DELAY, P2P_PING_PEER_LIST_LIMIT,
-        PEER_FAIL_LIMIT, PEER_MAX_PACKET_SIZE, PEER_TIMEOUT_INIT_CONNECTION, PEER_TIMEOUT_INIT_OUTGOING_CONNECTION,
+        CHAIN_SYNC_PARALLEL_MAX_RANGES, CHAIN_SYNC_PARALLEL_MAX_REQUESTS, CHAIN_SYNC_PARALLEL_RANGE_BLOCKS, CHAIN_SYNC_REQUEST_MAX_BLOCKS, CHAIN_SYNC_RESPONSE_MAX_BLOCKS, CHAIN_SYNC_RESPONSE_MIN_BLOCKS,
+        CHAIN_SYNC_TOP_BLOCKS, DANDELION_EMBARGO_CHECK_INTERVAL, MILLIS_PER_SECOND, NETWORK_ID, P2P_ANCHOR_PEERS, P2P_AUTO_CONNECT_PRIORITY_NODES_DELAY, P2P_DEFAULT_MAX_OUTGOING_PEERS,
+        P2P_DNS_SEEDS_RESOLVE_DELAY, P2P_DNS_SEED_TIMEOUT, P2P_EXTEND_PEERLIST_DELAY, P2P_PING_DELAY, P2P_PING_PEER_LIST_DELAY, P2P_SAVE_ANCHORS_DELAY, P2P_PING_PEER_LIST_LIMIT,
+        PEER_FAIL_LIMIT, PEER_MAX_PACKET_SIZE, PEER_SCORE_DUPLICATE, PEER_SCORE_EVICTION_MARGIN, PEER_SCORE_EVICTION_THRESHOLD, PEER_SCORE_INVALID_OBJECT,
+        PEER_SCORE_SLOW_CHAIN_RESPONSE, PEER_SCORE_TIMELY_CHAIN_RESPONSE, PEER_SCORE_TIMELY_CHAIN_RESPONSE_DELAY, PEER_SCORE_USEFUL_BLOCK,
+        PEER_TIMEOUT_INIT_CONNECTION, PEER_TIMEOUT_INIT_OUTGOING_CONNECTION,
         PRUNE_SAFETY_LIMIT, STABLE_LIMIT, P2P_PING_TIMEOUT, P2P_HEARTBEAT_INTERVAL
     },
     core::{
@@ -60,6 +71,11 @@ use crate::{
                 MAX_ITEMS_PER_PAGE
             },
             chain::CommonPoint,
+            compact_block::{
+                BlockTransactionsRequest,
+                BlockTransactionsResponse,
+                CompactBlock
+            },
             inventory::{
                 NotifyInventoryRequest,
                 NotifyInventoryResponse,
@@ -71,23 +87,29 @@ use crate::{
     rpc::rpc::get_peer_entry
 };
 use self::{
+    bandwidth::{Bandwidth, RequestsLimits},
     connection::{Connection, State},
+    dandelion::Dandelion,
     error::P2pError,
+    identity::{get_peer_id_from_key, NodeIdentity, NodeKey},
     packet::{
         chain::{BlockId, ChainRequest, ChainResponse},
-        handshake::Handshake,
+        handshake::{Capabilities, Handshake},
         object::{ObjectRequest, ObjectResponse, OwnedObjectResponse},
         ping::Ping,
+        get_packet_name,
         Packet,
         PacketWrapper
     },
+    parallel_sync::BlocksRange,
     peer::{Peer, TaskState, Rx},
     peer_list::{PeerList, SharedPeerList},
+    proxy::{get_hostname_address, is_hostname_address, parse_hostname, Socks5Proxy},
     tracker::{ObjectTracker, SharedObjectTracker}
 };
 use tokio::{
     io::AsyncWriteExt,
-    net::{TcpListener, TcpStream},
+    net::{lookup_host, TcpListener, TcpStream},
     select,
     sync::{
         broadcast,
@@ -106,7 +128,7 @@ use tokio::{
 use log::{info, warn, error, debug, trace};
 use std::{
     borrow::Cow,
-    collections::{hash_map::Entry, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     io,
     net::{IpAddr, SocketAddr},
     num::NonZeroUsize,
@@ -117,18 +139,80 @@ use std::{
     time::Duration
 };
 use bytes::Bytes;
-use rand::{seq::IteratorRandom, Rng};
+use rand::seq::{IteratorRandom, SliceRandom};
+use socket2::{Domain, Protocol, Socket, Type};
+
+// Block propagated by a peer, waiting to be processed
+enum PropagatedBlock {
+    // Full header, missing TXs are requested one by one
+    Header(BlockHeader),
+    // Compact block, rebuilt using the TXs from our mempool
+    Compact(CompactBlock)
+}
+
+impl PropagatedBlock {
+    fn get_height(&self) -> u64 {
+        match self {
+            Self::Header(header) => header.get_height(),
+            Self::Compact(compact) => compact.get_height()
+        }
+    }
+}
+
+// Options used to start the P2pServer
+pub struct P2pConfig {
+    // maximum tasks used to handle the incoming connections
+    pub concurrency: usize,
+    // directory used to store the peerlist and the node identity
+    pub dir_path: Option<String>,
+    // node tag sent on handshake
+    pub tag: Option<String>,
+    // max peers accepted by the server
+    pub max_peers: usize,
+    // max outgoing peers, at least half of the slots are kept for incoming peers
+    // if not set, the default value is capped to the allowed slots
+    pub max_outgoing_peers: Option<usize>,
+    // address to listen on
+    pub bind_address: String,
+    // should we connect to the peers from our peerlist
+    pub use_peerlist: bool,
+    // only connect to these nodes if not empty
+    pub exclusive_nodes: Vec<SocketAddr>,
+    // node keys expected for the configured nodes
+    pub pinned_keys: HashMap<SocketAddr, NodeKey>,
+    pub allow_fast_sync_mode: bool,
+    pub allow_boost_sync_mode: bool,
+    // max blocks sent in a chain response
+    pub max_chain_response_size: Option<usize>,
+    // can our peers share our IP
+    pub sharable: bool,
+    pub disable_outgoing_connections: bool,
+    pub enable_dandelion: bool,
+    pub requests_limits: RequestsLimits,
+    // SOCKS5 proxy used for all outgoing connections
+    pub proxy: Option<String>,
+    // hostnames of the configured nodes, resolved by the proxy
+    pub hostnames: HashMap<SocketAddr, String>,
+    // hostname shared to others peers to be reachable through a proxy
+    pub advertised_hostname: Option<String>,
+    // DNS seeds replacing the default ones if not empty
+    pub dns_seeds: Vec<String>
+}
 
 // P2pServer is a fully async TCP server
 // Each connection will block on a data to send or to receive
 // useful for low end hardware
 pub struct P2pServer<S: Storage> {
-    // unique peer id
+    // unique peer id, derived from our identity
     peer_id: u64,
+    // persistent identity used to authenticate the connections
+    identity: NodeIdentity,
     // node tag sent on handshake
     tag: Option<String>,
     // max peers accepted by this server
     max_peers: usize,
+    // max outgoing peers, the others slots are reserved to incoming peers
+    max_outgoing_peers: usize,
     // ip:port address to receive connections
     bind_address: SocketAddr,
     // all peers accepted
@@ -144,7 +228,7 @@ pub struct P2pServer<S: Storage> {
     // Synced cache to prevent concurrent tasks adding the block
     blocks_propagation_queue: Mutex<LruCache<Hash, ()>>,
     // Sender for the blocks processing task to have a ordered queue
-    blocks_processor: Sender<(Arc<Peer>, BlockHeader, Hash)>,
+    blocks_processor: Sender<(Arc<Peer>, PropagatedBlock, Hash)>,
     // allow fast syncing (only balances / assets / Smart Contracts changes)
     // without syncing the history
     allow_fast_sync_mode: bool,
@@ -159,6 +243,9 @@ pub struct P2pServer<S: Storage> {
     // Configured exclusive nodes
     // If not empty, no other peer than those listed can connect to this node
     exclusive_nodes: IndexSet<SocketAddr>,
+    // Public keys expected for configured nodes
+    // A node with another key is rejected after the key exchange
+    pinned_keys: HashMap<SocketAddr, NodeKey>,
     // Are we allowing others nodes to share us as a potential peer ?
     // Also if we allows to be listed in get_peers RPC API
     sharable: bool,
@@ -167,12 +254,49 @@ pub struct P2pServer<S: Storage> {
     outgoing_connections_disabled: AtomicBool,
     // Are we syncing the chain with another peer
     is_syncing: AtomicBool,
+    // Dandelion++ state if enabled
+    // New TXs are relayed to a single peer before being broadcasted
+    dandelion: Option<Dandelion>,
+    // traffic with all peers by packet id
+    bandwidth: Bandwidth,
+    // limits of expensive requests accepted from a peer
+    requests_limits: RequestsLimits,
+    // SOCKS5 proxy used for all outgoing connections
+    // Required to connect to the peers known only by their hostname
+    proxy: Option<Socks5Proxy>,
+    // Hostname shared to others peers to be reachable through a proxy
+    advertised_hostname: Option<(String, u16)>,
+    // DNS seeds resolved to find new peers at startup and when our peerlist is running low
+    dns_seeds: Vec<String>,
     // Exit channel to notify all tasks to stop
     exit_sender: broadcast::Sender<()>
 }
 
 impl<S: Storage> P2pServer<S> {
-    pub fn new(concurrency: usize, dir_path: Option<String>, tag: Option<String>, max_peers: usize, bind_address: String, blockchain: Arc<Blockchain<S>>, use_peerlist: bool, exclusive_nodes: Vec<SocketAddr>, allow_fast_sync_mode: bool, allow_boost_sync_mode: bool, max_chain_response_size: Option<usize>, sharable: bool, disable_outgoing_connections: bool) -> Result<Arc<Self>, P2pError> {
+    pub fn new(config: P2pConfig, blockchain: Arc<Blockchain<S>>) -> Result<Arc<Self>, P2pError> {
+        let P2pConfig {
+            concurrency,
+            dir_path,
+            tag,
+            max_peers,
+            max_outgoing_peers,
+            bind_address,
+            use_peerlist,
+            exclusive_nodes,
+            pinned_keys,
+            allow_fast_sync_mode,
+            allow_boost_sync_mode,
+            max_chain_response_size,
+            sharable,
+            disable_outgoing_connections,
+            enable_dandelion,
+            requests_limits,
+            proxy,
+            hostnames,
+            advertised_hostname,
+            dns_seeds
+        } = config;
+
         if tag.as_ref().is_some_and(|tag| tag.len() == 0 || tag.len() > 16) {
             return Err(P2pError::InvalidTag);
         }
@@ -185,9 +309,40 @@ impl<S: Storage> P2pServer<S> {
             return Err(P2pError::InvalidMaxPeers);
         }
 
-        // set channel to communicate with listener thread
-        let mut rng = rand::thread_rng();
-        let peer_id: u64 = rng.gen(); // generate a random peer id for network
+        // At least half of the slots are kept for incoming peers
+        let max_allowed_outgoing_peers = max_peers - max_peers / 2;
+        let max_outgoing_peers = match max_outgoing_peers {
+            Some(max_outgoing_peers) if max_outgoing_peers > max_allowed_outgoing_peers => {
+                return Err(P2pError::InvalidMaxOutgoingPeers(max_allowed_outgoing_peers));
+            },
+            Some(max_outgoing_peers) => max_outgoing_peers,
+            None => P2P_DEFAULT_MAX_OUTGOING_PEERS.min(max_allowed_outgoing_peers)
+        };
+
+        // Never fallback on direct connections if the proxy is invalid
+        let proxy = proxy.as_deref().map(Socks5Proxy::parse).transpose()?;
+        if let Some(proxy) = proxy.as_ref() {
+            info!("All outgoing P2p connections will use the proxy {}", proxy.get_address());
+        }
+
+        let advertised_hostname = advertised_hostname.as_deref().map(parse_hostname).transpose()?;
+
+        // configured DNS seeds replace the default ones
+        let dns_seeds = if dns_seeds.is_empty() {
+            get_dns_seeds(blockchain.get_network()).iter().map(|seed| seed.to_string()).collect()
+        } else {
+            dns_seeds
+        };
+
+        for seed in dns_seeds.iter() {
+            parse_hostname(seed)?;
+        }
+
+        // load our identity, our peer id is derived from it
+        let dir_path = dir_path.unwrap_or_default();
+        let network = blockchain.get_network().to_string().to_lowercase();
+        let identity = NodeIdentity::load_or_create(&format!("{}node-identity-{}.key", dir_path, network))?;
+        let peer_id = identity.get_peer_id();
         let addr: SocketAddr = bind_address.parse()?; // parse the bind address
         // create mspc channel for connections to peers
         let (connections_sender, connections_receiver) = mpsc::channel(max_peers);
@@ -198,12 +353,18 @@ impl<S: Storage> P2pServer<S> {
         let object_tracker = ObjectTracker::new(blockchain.clone(), exit_receiver);
 
         let (sender, event_receiver) = channel::<Arc<Peer>>(max_peers); 
-        let peer_list = PeerList::new(max_peers, format!("{}peerlist-{}", dir_path.unwrap_or_default(), blockchain.get_network().to_string().to_lowercase()), Some(sender))?;
+        let peer_list = PeerList::new(max_peers, format!("{}peerlist-{}", dir_path, network), Some(sender))?;
+        // register the hostnames of the configured nodes
+        for (addr, hostname) in hostnames {
+            peer_list.get_cache().set_hostname(&addr.ip(), &hostname)?;
+        }
 
         let server = Self {
             peer_id,
+            identity,
             tag,
             max_peers,
+            max_outgoing_peers,
             bind_address: addr,
             peer_list,
             blockchain,
@@ -216,8 +377,15 @@ impl<S: Storage> P2pServer<S> {
             allow_boost_sync_mode,
             max_chain_response_size: max_chain_response_size.unwrap_or(CHAIN_SYNC_DEFAULT_RESPONSE_BLOCKS),
             exclusive_nodes: IndexSet::from_iter(exclusive_nodes.into_iter()),
+            pinned_keys,
             sharable,
             is_syncing: AtomicBool::new(false),
+            dandelion: if enable_dandelion { Some(Dandelion::new()) } else { None },
+            bandwidth: Bandwidth::new(),
+            requests_limits,
+            proxy,
+            advertised_hostname,
+            dns_seeds,
             outgoing_connections_disabled: AtomicBool::new(disable_outgoing_connections),
             exit_sender,
         };
@@ -240,6 +408,11 @@ impl<S: Storage> P2pServer<S> {
         info!("Stopping P2p Server...");
         self.is_running.store(false, Ordering::Release);
 
+        // Keep our best outgoing peers to reconnect to them at the next start
+        if let Err(e) = self.peer_list.save_anchors(P2P_ANCHOR_PEERS).await {
+            error!("Error while saving anchors: {}", e);
+        }
+
         info!("Waiting for all peers to be closed...");
         self.peer_list.close_all().await;
 
@@ -308,8 +481,8 @@ impl<S: Storage> P2pServer<S> {
 
     // connect to seed nodes, start p2p server
     // and wait on all new connections
-    async fn start(self: &Arc<Self>, receiver: Receiver<(SocketAddr, bool)>, blocks_processor_receiver: Receiver<(Arc<Peer>, BlockHeader, Hash)>, event_receiver: Receiver<Arc<Peer>>, use_peerlist: bool, concurrency: usize) -> Result<(), P2pError> {
-        let listener = TcpListener::bind(self.get_bind_address()).await?;
+    async fn start(self: &Arc<Self>, receiver: Receiver<(SocketAddr, bool)>, blocks_processor_receiver: Receiver<(Arc<Peer>, PropagatedBlock, Hash)>, event_receiver: Receiver<Arc<Peer>>, use_peerlist: bool, concurrency: usize) -> Result<(), P2pError> {
+        let listener = bind_listener(self.get_bind_address())?;
         info!("P2p Server will listen on: {}", self.get_bind_address());
 
         let mut exclusive_nodes = self.exclusive_nodes.clone();
@@ -342,6 +515,11 @@ impl<S: Storage> P2pServer<S> {
         // start the event loop task to handle peer disconnect events
         spawn_task("p2p-events", Arc::clone(&self).event_loop(event_receiver));
 
+        // start the task to fluff the stem TXs with an expired embargo
+        if self.dandelion.is_some() {
+            spawn_task("p2p-dandelion", Arc::clone(&self).dandelion_loop());
+        }
+
 
         // start another task for peerlist loop
         if use_peerlist {
@@ -352,6 +530,17 @@ impl<S: Storage> P2pServer<S> {
         spawn_task("p2p-outgoing-connections", Arc::clone(&self).handle_outgoing_connections(priority_connections, receiver, tx.clone()));
         spawn_task("p2p-incoming-connections", Arc::clone(&self).handle_incoming_connections(listener, tx, concurrency));
 
+        // reconnect first to the anchors saved at our last stop
+        if use_peerlist {
+            match self.peer_list.get_cache().get_anchors() {
+                Ok(anchors) => for addr in anchors {
+                    debug!("Connecting to anchor {}", addr);
+                    self.try_to_connect_to_peer(addr, false).await;
+                },
+                Err(e) => error!("Error while loading anchors: {}", e)
+            }
+        }
+
         let mut exit_receiver = self.exit_sender.subscribe();
         loop {
             select! {
@@ -436,11 +625,17 @@ impl<S: Storage> P2pServer<S> {
             if !priority {
                 trace!("checking if connection can be accepted");
                 // check that this incoming peer isn't blacklisted
-                if !self.accept_new_connections().await {
+                if !self.accept_new_outgoing_connections().await {
                     debug!("{} is not allowed, we don't accept any new connection", addr);
                     continue;
                 }
 
+                // don't let a single network group take all our outgoing slots
+                if !self.peer_list.has_outgoing_slot_for_group(&addr.ip()).await {
+                    debug!("{} is not allowed, too many outgoing peers in its network group", addr);
+                    continue;
+                }
+
                 match self.peer_list.is_allowed(&addr.ip()).await {
                     Ok(allowed) => {
                         if !allowed {
@@ -502,11 +697,13 @@ impl<S: Storage> P2pServer<S> {
     // If we can, we will create a new peer and send it to the listener
     async fn handle_incoming_connection(self: &Arc<Self>, res: io::Result<(TcpStream, SocketAddr)>, thread_pool: &ThreadPool, tx: &Sender<(Peer, Rx)>) -> Result<(), P2pError> {
         let (mut stream, addr) = res?;
+        // IPv4 clients of a dual-stack listener are seen as IPv4-mapped IPv6 addresses
+        let addr = get_canonical_address(addr);
 
         // Verify if we can accept new connections
         let reject = !self.is_compatible_with_exclusive_nodes(&addr)
             // check that this incoming peer isn't blacklisted
-            || !self.accept_new_connections().await
+            || !self.accept_new_incoming_connections().await
             || !self.peer_list.is_allowed(&addr.ip()).await?
             || self.is_connected_to_addr(&addr).await;
 
@@ -588,6 +785,11 @@ impl<S: Storage> P2pServer<S> {
             return Err(P2pError::InvalidLocalPort);
         }
 
+        // peer id must be derived from the key authenticated in the key exchange
+        if connection.get_peer_key().map(get_peer_id_from_key) != Some(handshake.get_peer_id()) {
+            return Err(P2pError::InvalidPeerId(handshake.get_peer_id()));
+        }
+
         if self.has_peer_id_used(&handshake.get_peer_id()).await {
             return Err(P2pError::PeerIdAlreadyUsed(handshake.get_peer_id()));
         }
@@ -619,13 +821,30 @@ impl<S: Storage> P2pServer<S> {
         let storage = self.blockchain.get_storage().read().await;
         let (block, top_hash) = storage.get_top_block_header().await?;
         let topoheight = self.blockchain.get_topo_height();
-        let pruned_topoheight = storage.get_pruned_topoheight().await?;
+        let pruned_topoheight = self.blockchain.get_advertised_pruned_topoheight(&storage).await?;
         let cumulative_difficulty = storage.get_cumulative_difficulty_for_block_hash(&top_hash).await.unwrap_or_else(|_| CumulativeDifficulty::zero());
         let genesis_block = get_genesis_block_hash(self.blockchain.get_network());
-        let handshake = Handshake::new(Cow::Owned(VERSION.to_owned()), *self.blockchain.get_network(), Cow::Borrowed(self.get_tag()), Cow::Borrowed(&NETWORK_ID), self.get_peer_id(), self.bind_address.port(), get_current_time_in_seconds(), topoheight, block.get_height(), pruned_topoheight, Cow::Borrowed(&top_hash), Cow::Borrowed(genesis_block), Cow::Borrowed(&cumulative_difficulty), self.sharable);
+        let handshake = Handshake::new(Cow::Owned(VERSION.to_owned()), *self.blockchain.get_network(), Cow::Borrowed(self.get_tag()), Cow::Borrowed(&NETWORK_ID), self.get_peer_id(), self.bind_address.port(), get_current_time_in_seconds(), topoheight, block.get_height(), pruned_topoheight, Cow::Borrowed(&top_hash), Cow::Borrowed(genesis_block), Cow::Borrowed(&cumulative_difficulty), self.sharable, self.get_capabilities());
         Ok(Packet::Handshake(Cow::Owned(handshake)).to_bytes())
     }
 
+    // Check the key of a peer against the keys pinned for its address
+    // An incoming connection comes from a random port, so it is matched by IP
+    // and any key pinned for this IP is accepted
+    fn is_pinned_key_valid(&self, connection: &Connection, key: &NodeKey) -> bool {
+        let addr = connection.get_address();
+        if connection.is_out() {
+            return self.pinned_keys.get(addr).map_or(true, |pinned| pinned == key)
+        }
+
+        let mut pinned_keys = self.pinned_keys.iter()
+            .filter(|(pinned_addr, _)| pinned_addr.ip() == addr.ip())
+            .map(|(_, pinned)| pinned)
+            .peekable();
+
+        pinned_keys.peek().is_none() || pinned_keys.any(|pinned| pinned == key)
+    }
+
     // Create a valid peer using the connection, if an error happen, it will close the stream and return the error
     async fn create_verified_peer(&self, buf: &mut [u8], mut connection: Connection, priority: bool) -> Result<(Peer, Rx), P2pError> {
         let handshake = match self.verify_connection(buf, &mut connection).await {
@@ -648,7 +867,12 @@ impl<S: Storage> P2pServer<S> {
         trace!("New connection: {}", connection);
 
         // Exchange encryption keys
-        connection.exchange_keys(buf).await?;
+        let peer_key = connection.exchange_keys(&self.identity, buf).await?;
+
+        // Verify the peer key if it was pinned
+        if !self.is_pinned_key_valid(connection, &peer_key) {
+            return Err(P2pError::PinnedKeyMismatch(*connection.get_address()));
+        }
 
         // Start handshake now
         connection.set_state(State::Handshake);
@@ -746,7 +970,26 @@ impl<S: Storage> P2pServer<S> {
             }
         }
 
-        let stream = timeout(Duration::from_millis(PEER_TIMEOUT_INIT_OUTGOING_CONNECTION), TcpStream::connect(&addr)).await??;
+        let stream = match self.proxy.as_ref() {
+            Some(proxy) => {
+                // the hostname is resolved by the proxy
+                let host = if is_hostname_address(&addr) {
+                    self.peer_list.get_cache().get_hostname(&addr.ip())?
+                        .ok_or(P2pError::UnknownHostname(addr))?
+                } else {
+                    addr.ip().to_string()
+                };
+
+                timeout(Duration::from_millis(PEER_TIMEOUT_INIT_OUTGOING_CONNECTION), proxy.connect(&host, addr.port())).await??
+            },
+            None => {
+                if is_hostname_address(&addr) {
+                    return Err(P2pError::ProxyRequired(addr));
+                }
+
+                timeout(Duration::from_millis(PEER_TIMEOUT_INIT_OUTGOING_CONNECTION), TcpStream::connect(&addr)).await??
+            }
+        };
         let connection = Connection::new(stream, addr, true);
         Ok(connection)
     }
@@ -763,7 +1006,7 @@ impl<S: Storage> P2pServer<S> {
     // if a peer is given, we will check and update the peers list
     async fn build_generic_ping_packet_with_storage(&self, storage: &S) -> Ping<'_> {
         let (cumulative_difficulty, block_top_hash, pruned_topoheight) = {
-            let pruned_topoheight = match storage.get_pruned_topoheight().await {
+            let pruned_topoheight = match self.blockchain.get_advertised_pruned_topoheight(storage).await {
                 Ok(pruned_topoheight) => pruned_topoheight,
                 Err(e) => {
                     error!("Couldn't get the pruned topoheight from storage for generic ping packet: {}", e);
@@ -879,9 +1122,11 @@ impl<S: Storage> P2pServer<S> {
             return Ok(None)
         }
 
-        let selected = rand::thread_rng().gen_range(0..count);
+        // peers with a better score are more likely to be selected
+        let peers: Vec<Arc<Peer>> = peers.into_iter().collect();
+        let selected = peers.choose_weighted(&mut rand::thread_rng(), |p| p.get_score_weight()).ok();
         // clone the Arc to prevent the lock until the end of the sync request
-        Ok(peers.swap_remove_index(selected))
+        Ok(selected.cloned())
     }
 
     // Check if user has allowed fast sync mode
@@ -988,6 +1233,40 @@ impl<S: Storage> P2pServer<S> {
         }
     }
 
+    // Fluff the stem TXs not seen in fluff phase before their embargo timeout
+    async fn dandelion_loop(self: Arc<Self>) {
+        debug!("Starting dandelion loop...");
+        let Some(dandelion) = self.dandelion.as_ref() else {
+            return;
+        };
+
+        let mut interval = interval(Duration::from_millis(DANDELION_EMBARGO_CHECK_INTERVAL));
+        let mut exit_receiver = self.exit_sender.subscribe();
+        loop {
+            select! {
+                biased;
+                _ = exit_receiver.recv() => {
+                    debug!("Received exit message, exiting dandelion loop");
+                    break;
+                },
+                _ = interval.tick() => {
+                    for hash in dandelion.take_expired_embargoes(get_current_time_in_millis()).await {
+                        // It may have been included in a block or evicted meanwhile
+                        if !dandelion.has_stem_tx(&hash).await && !self.blockchain.get_mempool().read().await.contains_tx(&hash) {
+                            trace!("Stem TX {} is not in mempool anymore, skipping fluff", hash);
+                            continue;
+                        }
+
+                        debug!("Embargo expired for stem TX {}, fluffing it", hash);
+                        self.fluff_tx_hash(hash).await;
+                    }
+                }
+            }
+        }
+
+        debug!("Dandelion loop ended");
+    }
+
     // broadcast generic ping packet every 10s
     // if we have to send our peerlist to all peers, we calculate the ping for each peer
     // instead of being done in each write task of peer, we do it one time so we don't have
@@ -1031,6 +1310,9 @@ impl<S: Storage> P2pServer<S> {
                     // all the peers we already shared with this peer
                     let mut shared_peers = peer.get_peers().lock().await;
 
+                    // peers known only by their hostname, shared separately
+                    let mut hostname_peers = Vec::new();
+
                     // iterate through our peerlist to determinate which peers we have to send
                     for p in all_peers.iter() {
                         // don't send him itself
@@ -1042,6 +1324,11 @@ impl<S: Storage> P2pServer<S> {
                         // if we haven't send him this peer addr and that he don't have him already, insert it
                         let addr = p.get_outgoing_address();
 
+                        if is_hostname_address(addr) {
+                            hostname_peers.push(*addr);
+                            continue;
+                        }
+
                         // Don't share local network addresses if it's external peer
                         if (is_local_address(addr) && !is_local_peer) || !is_valid_address(addr) {
                             debug!("{} is a local address but peer is external, skipping", addr);
@@ -1070,6 +1357,39 @@ impl<S: Storage> P2pServer<S> {
 
                     // update the ping packet with the new peers
                     debug!("Set peers: {:?}, going to {}", new_peers, peer.get_outgoing_address());
+
+                    // our own hostname is shared like a connected peer
+                    let mut hostnames = Vec::with_capacity(hostname_peers.len() + 1);
+                    if let Some((host, port)) = self.advertised_hostname.as_ref() {
+                        hostnames.push((get_hostname_address(host, *port), format!("{}:{}", host, port)));
+                    }
+
+                    for addr in hostname_peers {
+                        match self.peer_list.get_cache().get_hostname(&addr.ip()) {
+                            Ok(Some(host)) => hostnames.push((addr, format!("{}:{}", host, addr.port()))),
+                            Ok(None) => debug!("No hostname found for {}, skipping", addr),
+                            Err(e) => error!("Error while retrieving hostname of {}: {}", addr, e)
+                        }
+                    }
+
+                    let new_hostnames = ping.get_mut_hostnames();
+                    new_hostnames.clear();
+                    for (addr, hostname) in hostnames {
+                        let send = match shared_peers.entry(addr) {
+                            Entry::Occupied(mut e) => e.get_mut().update(Direction::Out),
+                            Entry::Vacant(e) => {
+                                e.insert(Direction::Out);
+                                true
+                            }
+                        };
+
+                        if send {
+                            new_hostnames.insert(hostname);
+                            if new_hostnames.len() >= P2P_PING_PEER_LIST_LIMIT {
+                                break;
+                            }
+                        }
+                    }
                     // send the ping packet to the peer
                     if let Err(e) = peer.send_packet(Packet::Ping(Cow::Borrowed(&ping))).await {
                         debug!("Error sending specific ping packet to {}: {}", peer, e);
@@ -1106,6 +1426,9 @@ impl<S: Storage> P2pServer<S> {
     // try to extend our peerlist each time its possible by searching in known peerlist from disk
     async fn peerlist_loop(self: Arc<Self>) {
         debug!("Starting peerlist task...");
+        self.resolve_dns_seeds().await;
+        let mut last_dns_seeds_resolve = get_current_time_in_seconds();
+        let mut last_anchors_save = last_dns_seeds_resolve;
         loop {
             sleep(Duration::from_secs(P2P_EXTEND_PEERLIST_DELAY)).await;
             if !self.is_running() {
@@ -1113,7 +1436,16 @@ impl<S: Storage> P2pServer<S> {
                 break;
             }
 
-            if self.accept_new_connections().await {
+            // Save our anchors regularly, they would be lost if the node is not stopped properly
+            let now = get_current_time_in_seconds();
+            if now > last_anchors_save + P2P_SAVE_ANCHORS_DELAY {
+                if let Err(e) = self.peer_list.save_anchors(P2P_ANCHOR_PEERS).await {
+                    error!("Error while saving anchors: {}", e);
+                }
+                last_anchors_save = now;
+            }
+
+            if self.accept_new_outgoing_connections().await {
                 let peer = {
                     trace!("Locking peer list write mode (peerlist loop)");
                     match self.peer_list.find_peer_to_connect().await {
@@ -1131,11 +1463,82 @@ impl<S: Storage> P2pServer<S> {
                     self.try_to_connect_to_peer(addr, false).await;
                 } else {
                     trace!("No peer found to connect to");
+                    // our peerlist is running low, search new peers using the DNS seeds
+                    let now = get_current_time_in_seconds();
+                    if now > last_dns_seeds_resolve + P2P_DNS_SEEDS_RESOLVE_DELAY {
+                        self.resolve_dns_seeds().await;
+                        last_dns_seeds_resolve = now;
+                    }
+                }
+            } else if let Some(peer) = self.peer_list.get_worst_peer(true).await {
+                // our outgoing slots are full, make room for a better peer from our peerlist
+                // only the outgoing peers are evicted as the new connection will take their slot
+                let score = peer.get_score();
+                let evict = if score <= PEER_SCORE_EVICTION_THRESHOLD {
+                    true
+                } else {
+                    match self.peer_list.get_peer_to_connect_score().await {
+                        Ok(candidate) => candidate.is_some_and(|candidate| candidate >= score.saturating_add(PEER_SCORE_EVICTION_MARGIN)),
+                        Err(e) => {
+                            error!("Error while searching a peer to replace {}: {}", peer, e);
+                            false
+                        }
+                    }
+                };
+
+                if evict {
+                    info!("Evicting {} because of its score {}", peer.get_outgoing_address(), score);
+                    if let Err(e) = peer.signal_exit().await {
+                        error!("Error while evicting {}: {}", peer, e);
+                    }
                 }
             }
         }
     }
 
+    // Resolve the A and AAAA records of each DNS seed and store them in our peerlist
+    async fn resolve_dns_seeds(&self) {
+        if self.dns_seeds.is_empty() {
+            return;
+        }
+
+        // DNS requests can't go through the SOCKS5 proxy and would leak our IP
+        if self.proxy.is_some() {
+            debug!("Proxy enabled, skipping DNS seeds resolution");
+            return;
+        }
+
+        for seed in self.dns_seeds.iter() {
+            debug!("Resolving DNS seed {}", seed);
+            let addrs = match timeout(Duration::from_millis(P2P_DNS_SEED_TIMEOUT), lookup_host(seed.as_str())).await {
+                Ok(Ok(addrs)) => addrs,
+                Ok(Err(e)) => {
+                    warn!("Error while resolving DNS seed {}: {}", seed, e);
+                    continue;
+                },
+                Err(_) => {
+                    warn!("Timed out while resolving DNS seed {}", seed);
+                    continue;
+                }
+            };
+
+            let mut count = 0;
+            for addr in addrs.map(get_canonical_address) {
+                if is_local_address(&addr) || !is_valid_address(&addr) {
+                    debug!("Invalid address {} from DNS seed {}, skipping", addr, seed);
+                    continue;
+                }
+
+                match self.peer_list.store_peer_address(addr).await {
+                    Ok(true) => count += 1,
+                    Ok(false) => trace!("{} from DNS seed {} already stored in peer list", addr, seed),
+                    Err(e) => error!("Error while storing {} from DNS seed {}: {}", addr, seed, e)
+                }
+            }
+            info!("{} new peers found using DNS seed {}", count, seed);
+        }
+    }
+
     // This function is used to broadcast PeerDisconnected event to listeners
     // We use a channel to avoid having to pass the Blockchain<S> to the Peerlist & Peers
     async fn event_loop(self: Arc<Self>, mut receiver: Receiver<Arc<Peer>>) {
@@ -1170,7 +1573,7 @@ impl<S: Storage> P2pServer<S> {
     }
 
     // Task for all blocks propagation
-    async fn blocks_processing_task(self: Arc<Self>, mut receiver: Receiver<(Arc<Peer>, BlockHeader, Hash)>) {
+    async fn blocks_processing_task(self: Arc<Self>, mut receiver: Receiver<(Arc<Peer>, PropagatedBlock, Hash)>) {
         debug!("Starting blocks processing task");
         let mut server_exit = self.exit_sender.subscribe();
 
@@ -1182,54 +1585,34 @@ impl<S: Storage> P2pServer<S> {
                     break 'main;
                 }
                 msg = receiver.recv() => {
-                    let Some((peer, header, block_hash)) = msg else {
+                    let Some((peer, block, block_hash)) = msg else {
                         debug!("No more blocks to process, stopping blocks processing task");
                         break 'main;
                     };
 
-                    let mut response_blockers: Vec<ResponseBlocker> = Vec::new();
-                    for hash in header.get_txs_hashes() {
-                        let contains = { // we don't lock one time because we may wait on p2p response
-                            // Check in ObjectTracker
-                            if let Some(response_blocker) = self.object_tracker.get_response_blocker_for_requested_object(hash).await {
-                                trace!("{} is already requested, waiting on response blocker for block {}", hash, block_hash);
-                                response_blockers.push(response_blocker);
-                                true
-                            } else {
-                                self.blockchain.has_tx(hash).await.unwrap_or(false)
-                            }
-                        };
-
-                        if !contains { // retrieve one by one to prevent acquiring the lock for nothing
-                            debug!("Requesting TX {} to {} for block {}", hash, peer, block_hash);
-                            if let Err(e) = self.object_tracker.request_object_from_peer(Arc::clone(&peer), ObjectRequest::Transaction(hash.clone()), false).await {
-                                    error!("Error while requesting TX {} to {} for block {}: {}", hash, peer, block_hash, e);
-                                    peer.increment_fail_count();
-                                    continue 'main;
-                            }
-
-                            if let Some(response_blocker) = self.object_tracker.get_response_blocker_for_requested_object(hash).await {
-                                response_blockers.push(response_blocker);
+                    let res = match block {
+                        PropagatedBlock::Header(header) => self.build_block_from_propagated_header(&peer, header, &block_hash).await,
+                        PropagatedBlock::Compact(compact) => match self.build_block_from_compact(&peer, compact).await {
+                            Ok(block) => Ok(block),
+                            Err(e) => {
+                                // A short id may have matched a wrong TX from our mempool, fallback on the full header
+                                debug!("Error while rebuilding compact block {} from {}: {}, requesting its header", block_hash, peer, e);
+                                match peer.request_blocking_object(ObjectRequest::BlockHeader(block_hash.clone())).await {
+                                    Ok(OwnedObjectResponse::BlockHeader(header, _)) => self.build_block_from_propagated_header(&peer, header, &block_hash).await,
+                                    Ok(_) => Err(P2pError::InvalidObjectResponseType),
+                                    Err(e) => Err(e)
+                                }
                             }
                         }
-                    }
-
-                    // Wait on all already requested txs
-                    for mut blocker in response_blockers {
-                        if let Err(e) = blocker.recv().await {
-                            // It's mostly a closed channel error, so we can ignore it
-                            warn!("Error while waiting on response blocker: {}", e);
-                            peer.increment_fail_count();
-                            continue 'main;
-                        }
-                    }
+                    };
 
                     // add immediately the block to chain as we are synced with
-                    let block = match self.blockchain.build_block_from_header(Immutable::Owned(header)).await {
+                    let block = match res {
                         Ok(block) => block,
                         Err(e) => {
                             error!("Error while building block {} from peer {}: {}", block_hash, peer, e);
                             peer.increment_fail_count();
+                            peer.update_score(PEER_SCORE_INVALID_OBJECT);
                             continue 'main;
                         }
                     };
@@ -1238,6 +1621,9 @@ impl<S: Storage> P2pServer<S> {
                     if let Err(e) = self.blockchain.add_new_block(block, true, false).await {
                         error!("Error while adding new block from {}: {}", peer, e);
                         peer.increment_fail_count();
+                        peer.update_score(PEER_SCORE_INVALID_OBJECT);
+                    } else {
+                        peer.update_score(PEER_SCORE_USEFUL_BLOCK);
                     }
                 }
             }
@@ -1246,6 +1632,146 @@ impl<S: Storage> P2pServer<S> {
         debug!("Blocks processing task ended");
     }
 
+    // Build a block from a propagated header
+    // Missing TXs are requested one by one to the peer
+    async fn build_block_from_propagated_header(&self, peer: &Arc<Peer>, header: BlockHeader, block_hash: &Hash) -> Result<Block, P2pError> {
+        let mut response_blockers: Vec<ResponseBlocker> = Vec::new();
+        for hash in header.get_txs_hashes() {
+            let contains = { // we don't lock one time because we may wait on p2p response
+                // Check in ObjectTracker
+                if let Some(response_blocker) = self.object_tracker.get_response_blocker_for_requested_object(hash).await {
+                    trace!("{} is already requested, waiting on response blocker for block {}", hash, block_hash);
+                    response_blockers.push(response_blocker);
+                    true
+                } else {
+                    self.blockchain.has_tx(hash).await.unwrap_or(false)
+                }
+            };
+
+            if !contains { // retrieve one by one to prevent acquiring the lock for nothing
+                debug!("Requesting TX {} to {} for block {}", hash, peer, block_hash);
+                if let Err(e) = self.object_tracker.request_object_from_peer(Arc::clone(peer), ObjectRequest::Transaction(hash.clone()), false).await {
+                    error!("Error while requesting TX {} to {} for block {}: {}", hash, peer, block_hash, e);
+                    return Err(e);
+                }
+
+                if let Some(response_blocker) = self.object_tracker.get_response_blocker_for_requested_object(hash).await {
+                    response_blockers.push(response_blocker);
+                }
+            }
+        }
+
+        // Wait on all already requested txs
+        for mut blocker in response_blockers {
+            if let Err(e) = blocker.recv().await {
+                // It's mostly a closed channel error, so we can ignore it
+                warn!("Error while waiting on response blocker: {}", e);
+                return Err(P2pError::BoostSyncModeBlockerError);
+            }
+        }
+
+        let block = self.blockchain.build_block_from_header(Immutable::Owned(header)).await?;
+        Ok(block)
+    }
+
+    // Rebuild a compact block using the TXs from our mempool
+    // Missing TXs are requested in a single batch to the peer
+    async fn build_block_from_compact(&self, peer: &Arc<Peer>, compact: CompactBlock) -> Result<Block, P2pError> {
+        let short_ids = compact.get_short_ids();
+        let mut transactions: Vec<Option<(Hash, Arc<Transaction>)>> = vec![None; short_ids.len()];
+        {
+            let mempool = self.blockchain.get_mempool().read().await;
+            // TXs matching the same short id can't be used
+            let mut collisions = HashSet::new();
+            for (hash, sorted_tx) in mempool.get_txs() {
+                if let Some(index) = short_ids.get_index_of(&compact.get_short_id(hash)) {
+                    if transactions[index].is_some() {
+                        collisions.insert(index);
+                    } else {
+                        transactions[index] = Some((Hash::clone(hash), Arc::clone(sorted_tx.get_tx())));
+                    }
+                }
+            }
+
+            for index in collisions {
+                transactions[index] = None;
+            }
+        }
+
+        // TXs already included in another block are not in our mempool anymore
+        // Search them in the blocks stored at the heights still reachable by this block before requesting them
+        if transactions.iter().any(Option::is_none) {
+            let storage = self.blockchain.get_storage().read().await;
+            // TXs matching the same short id can't be used
+            let mut collisions = HashSet::new();
+            let height = compact.get_height();
+            for height in height.saturating_sub(STABLE_LIMIT)..=height {
+                for block_hash in storage.get_blocks_at_height(height).await? {
+                    let header = storage.get_block_header_by_hash(&block_hash).await?;
+                    for hash in header.get_txs_hashes() {
+                        let Some(index) = short_ids.get_index_of(&compact.get_short_id(hash)) else {
+                            continue;
+                        };
+
+                        // Same TX included in several blocks or already found in our mempool
+                        if let Some((found, _)) = &transactions[index] {
+                            if found != hash {
+                                collisions.insert(index);
+                            }
+                            continue;
+                        }
+
+                        if storage.has_transaction(hash).await? {
+                            transactions[index] = Some((hash.clone(), storage.get_transaction(hash).await?));
+                        }
+                    }
+                }
+            }
+
+            for index in collisions {
+                transactions[index] = None;
+            }
+        }
+
+        let missing: IndexSet<u16> = transactions.iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_none())
+            .map(|(index, _)| index as u16)
+            .collect();
+
+        if !missing.is_empty() {
+            debug!("Requesting {}/{} TXs to {} for compact block {}", missing.len(), short_ids.len(), peer, compact.get_hash());
+            let request = BlockTransactionsRequest::new(compact.get_hash().clone(), missing.clone());
+            let txs = peer.request_block_transactions(request).await?.take_txs();
+            if txs.len() != missing.len() {
+                return Err(P2pError::InvalidBlockTransactions(compact.get_hash().clone()));
+            }
+
+            for (index, tx) in missing.into_iter().zip(txs) {
+                let hash = tx.hash();
+                if short_ids.get_index(index as usize) != Some(&compact.get_short_id(&hash)) {
+                    return Err(P2pError::InvalidBlockTransactions(compact.get_hash().clone()));
+                }
+                transactions[index as usize] = Some((hash, Arc::new(tx)));
+            }
+        } else {
+            debug!("Compact block {} rebuilt from our mempool", compact.get_hash());
+        }
+
+        let (txs_hashes, txs): (IndexSet<Hash>, Vec<Immutable<Transaction>>) = transactions.into_iter()
+            .flatten()
+            .map(|(hash, tx)| (hash, Immutable::Arc(tx)))
+            .unzip();
+
+        let block_hash = compact.get_hash().clone();
+        let header = compact.into_header(txs_hashes);
+        if header.hash() != block_hash {
+            return Err(P2pError::InvalidCompactBlock(block_hash));
+        }
+
+        Ok(Block::new(Immutable::Owned(header), txs))
+    }
+
     // this function handle the logic to send all packets to the peer
     async fn handle_connection_write_side(&self, peer: &Arc<Peer>, rx: &mut Rx, mut task_rx: oneshot::Receiver<()>) -> Result<(), P2pError> {
         let mut server_exit = self.exit_sender.subscribe();
@@ -1281,6 +1807,8 @@ impl<S: Storage> P2pServer<S> {
                     // there is a overhead of 4 for each packet (packet size u32 4 bytes, packet id u8 is counted in the packet size)
                     trace!("Sending packet with ID {}, size sent: {}, real size: {}", bytes[4], u32::from_be_bytes(bytes[0..4].try_into()?), bytes.len());
                     peer.get_connection().send_bytes(&bytes).await?;
+                    peer.get_bandwidth().record_out(bytes[0], bytes.len());
+                    self.bandwidth.record_out(bytes[0], bytes.len());
                     trace!("data sucessfully sent!");
                 }
             }
@@ -1425,6 +1953,88 @@ impl<S: Storage> P2pServer<S> {
         common_peers
     }
 
+    // Handle a block propagated by a peer, in full or compact form
+    // If we don't have it yet, it is sent to the blocks processor task
+    async fn handle_block_propagation(&self, peer: &Arc<Peer>, block: PropagatedBlock, block_hash: Hash) -> Result<(), P2pError> {
+        // verify that this block wasn't already sent by him
+        {
+            let mut blocks_propagation = peer.get_blocks_propagation().lock().await;
+            if let Some(direction) = blocks_propagation.get_mut(&block_hash) {
+                if !direction.update(Direction::In) {
+                    debug!("{} send us a block ({}) already tracked by him ({:?})", peer, block_hash, direction);
+                    peer.update_score(PEER_SCORE_DUPLICATE);
+                    // return Err(P2pError::AlreadyTrackedBlock(block_hash, *direction))
+                }
+            } else {
+                debug!("Saving {} in blocks propagation cache for {}", block_hash, peer);
+                blocks_propagation.put(block_hash.clone(),  Direction::In);
+            }
+        }
+
+        // Avoid sending the same block to a common peer that may have already got it
+        // because we track peerlist of each peers, we can try to determinate it
+        for common_peer in self.get_common_peers_for(&peer).await {
+            debug!("{} is a common peer with {}, adding block {} to its propagation cache", common_peer, peer, block_hash);
+            let mut blocks_propagation = common_peer.get_blocks_propagation().lock().await;
+            // Out allow to get "In" again, because it's a prediction, don't block it completely
+            if !blocks_propagation.contains(&block_hash) {
+                blocks_propagation.put(block_hash.clone(), Direction::Out);
+            }
+        }
+
+        // check that we don't have this block in our chain
+        {
+            let storage = self.blockchain.get_storage().read().await;
+            if storage.has_block_with_hash(&block_hash).await? {
+                debug!("{}: block at height {} with hash {} is already in our chain. Skipping", peer, block.get_height(), block_hash);
+                return Ok(())
+            }
+        }
+
+        // Check that we are not already waiting on it
+        {
+            let mut blocks_propagation_queue = self.blocks_propagation_queue.lock().await;
+            if blocks_propagation_queue.contains(&block_hash) {
+                debug!("Block {} propagated is already in processing from another peer", block_hash);
+                return Ok(())
+            }
+            blocks_propagation_queue.put(block_hash.clone(), ());
+        }
+
+        let block_height = block.get_height();
+        debug!("Received block at height {} from {}", block_height, peer);
+        let peer = Arc::clone(peer);
+        // This will block the task if the bounded channel is full
+        if let Err(e) = self.blocks_processor.send((peer, block, block_hash)).await {
+            error!("Error while sending block propagated to blocks processor task: {}", e);
+        }
+
+        Ok(())
+    }
+
+    // Search the TXs requested for a block we propagated in compact form
+    // Returns an empty list if we don't have the block
+    async fn get_block_transactions(&self, request: &BlockTransactionsRequest) -> Result<Vec<Transaction>, P2pError> {
+        let header = {
+            let storage = self.blockchain.get_storage().read().await;
+            if !storage.has_block_with_hash(request.get_hash()).await? {
+                debug!("Block {} not found for TXs request", request.get_hash());
+                return Ok(Vec::new())
+            }
+            storage.get_block_header_by_hash(request.get_hash()).await?
+        };
+
+        let mut txs = Vec::with_capacity(request.get_indexes().len());
+        for index in request.get_indexes() {
+            let hash = header.get_txs_hashes().get_index(*index as usize)
+                .ok_or_else(|| P2pError::InvalidBlockTransactions(request.get_hash().clone()))?;
+            let tx = self.blockchain.get_tx(hash).await?;
+            txs.push(tx.as_ref().clone());
+        }
+
+        Ok(txs)
+    }
+
     // Main function used by every nodes connections
     // This is handling each packet available in our p2p protocol
     // Each packet is a enum variant
@@ -1447,6 +2057,9 @@ impl<S: Storage> P2pServer<S> {
 
                 ping.into_owned().update_peer(peer, &self.blockchain).await?;
 
+                // The TX is public now, it must not be stemmed anymore
+                self.mark_tx_fluffed(&hash).await;
+
                 // peer should not send us twice the same transaction
                 debug!("Received tx hash {} from {}", hash, peer.get_outgoing_address());
                 {
@@ -1455,6 +2068,7 @@ impl<S: Storage> P2pServer<S> {
                     if let Some(direction) = txs_cache.get_mut(&hash) {
                         if !direction.update(Direction::In) {
                             debug!("{} send us a transaction ({}) already tracked by him ({:?})", peer, hash, direction);
+                            peer.update_score(PEER_SCORE_DUPLICATE);
                             // return Err(P2pError::AlreadyTrackedTx(hash))
                         }
                     } else {
@@ -1480,65 +2094,67 @@ impl<S: Storage> P2pServer<S> {
                     txs_cache.put(hash.clone(), Direction::Out);
                 }
             },
+            Packet::StemTransaction(tx) => {
+                trace!("{}: Stem Transaction packet", peer);
+                let tx = tx.into_owned();
+                let hash = tx.hash();
+                debug!("Received stem tx {} from {}", hash, peer.get_outgoing_address());
+
+                // He already has it, don't send it back to him
+                peer.get_txs_cache().lock().await.put(hash.clone(), Direction::In);
+
+                if self.blockchain.has_tx(&hash).await? {
+                    debug!("Stem TX {} is already known, ignoring", hash);
+                } else if let Some(dandelion) = self.dandelion.as_ref() {
+                    if dandelion.has_stem_tx(&hash).await {
+                        debug!("Stem TX {} is already in stempool, ignoring", hash);
+                    } else if dandelion.should_fluff() || dandelion.is_stempool_full().await {
+                        // Don't broadcast it, it is fluffed below
+                        self.blockchain.add_tx_to_mempool_with_hash(tx, hash.clone(), false).await?;
+                        self.fluff_tx_hash(hash).await;
+                    } else {
+                        // Verify it before relaying it, but keep it hidden from the others peers until it is fluffed
+                        self.blockchain.verify_tx_for_mempool(&tx, &hash).await?;
+                        dandelion.add_stem_tx(hash.clone(), Arc::new(tx)).await;
+                        if !self.stem_tx(&hash, Some(peer.get_id())).await {
+                            self.fluff_tx_hash(hash).await;
+                        }
+                    }
+                } else {
+                    // Dandelion is disabled, the TX is broadcasted directly
+                    self.blockchain.add_tx_to_mempool_with_hash(tx, hash, true).await?;
+                }
+            },
             Packet::BlockPropagation(packet_wrapper) => {
                 trace!("Received a block propagation packet from {}", peer);
                 let (header, ping) = packet_wrapper.consume();
                 ping.into_owned().update_peer(peer, &self.blockchain).await?;
 
-                // check that the block height is valid
                 let header = header.into_owned();
                 let block_hash = header.hash();
+                self.handle_block_propagation(peer, PropagatedBlock::Header(header), block_hash).await?;
+            },
+            Packet::CompactBlockPropagation(packet_wrapper) => {
+                trace!("Received a compact block propagation packet from {}", peer);
+                let (compact, ping) = packet_wrapper.consume();
+                ping.into_owned().update_peer(peer, &self.blockchain).await?;
 
-                // verify that this block wasn't already sent by him
-                {
-                    let mut blocks_propagation = peer.get_blocks_propagation().lock().await;
-                    if let Some(direction) = blocks_propagation.get_mut(&block_hash) {
-                        if !direction.update(Direction::In) {
-                            debug!("{} send us a block ({}) already tracked by him ({:?})", peer, block_hash, direction);
-                            // return Err(P2pError::AlreadyTrackedBlock(block_hash, *direction))
-                        }
-                    } else {
-                        debug!("Saving {} in blocks propagation cache for {}", block_hash, peer);
-                        blocks_propagation.put(block_hash.clone(),  Direction::In);
-                    }
-                }
-
-                // Avoid sending the same block to a common peer that may have already got it
-                // because we track peerlist of each peers, we can try to determinate it
-                for common_peer in self.get_common_peers_for(&peer).await {
-                    debug!("{} is a common peer with {}, adding block {} to its propagation cache", common_peer, peer, block_hash);
-                    let mut blocks_propagation = common_peer.get_blocks_propagation().lock().await;
-                    // Out allow to get "In" again, because it's a prediction, don't block it completely
-                    if !blocks_propagation.contains(&block_hash) {
-                        blocks_propagation.put(block_hash.clone(), Direction::Out);
-                    }
-                }
-
-                // check that we don't have this block in our chain
-                {
-                    let storage = self.blockchain.get_storage().read().await;
-                    if storage.has_block_with_hash(&block_hash).await? {
-                        debug!("{}: {} with hash {} is already in our chain. Skipping", peer, header, block_hash);
-                        return Ok(())
-                    }
-                }
-
-                // Check that we are not already waiting on it
-                {
-                    let mut blocks_propagation_queue = self.blocks_propagation_queue.lock().await;
-                    if blocks_propagation_queue.contains(&block_hash) {
-                        debug!("Block {} propagated is already in processing from another peer", block_hash);
-                        return Ok(())
-                    }
-                    blocks_propagation_queue.put(block_hash.clone(), ());
-                }
+                let compact = compact.into_owned();
+                let block_hash = compact.get_hash().clone();
+                self.handle_block_propagation(peer, PropagatedBlock::Compact(compact), block_hash).await?;
+            },
+            Packet::BlockTransactionsRequest(request) => {
+                trace!("Received a block transactions request from {}", peer);
+                let txs = self.get_block_transactions(&request).await?;
+                peer.send_packet(Packet::BlockTransactionsResponse(BlockTransactionsResponse::new(request.get_hash().clone(), txs))).await?;
+            },
+            Packet::BlockTransactionsResponse(response) => {
+                trace!("Received a block transactions response from {}", peer);
+                let sender = peer.take_block_transactions_channel(response.get_hash()).await
+                    .ok_or_else(|| P2pError::UnrequestedBlockTransactions(response.get_hash().clone()))?;
 
-                let block_height = header.get_height();
-                debug!("Received block at height {} from {}", block_height, peer);
-                let peer = Arc::clone(peer);
-                // This will block the task if the bounded channel is full
-                if let Err(e) = self.blocks_processor.send((peer, header, block_hash)).await {
-                    error!("Error while sending block propagated to blocks processor task: {}", e);
+                if sender.send(response).is_err() {
+                    error!("Error while sending block transactions response to channel of {}", peer);
                 }
             },
             Packet::ChainRequest(packet_wrapper) => {
@@ -1588,7 +2204,7 @@ impl<S: Storage> P2pServer<S> {
             Packet::Ping(ping) => {
                 trace!("Received a ping packet from {}", peer);
                 let current_time = get_current_time_in_seconds();
-                let empty_peer_list = ping.get_peers().is_empty();
+                let empty_peer_list = ping.get_peers().is_empty() && ping.get_hostnames().is_empty();
 
                 // update the last ping only if he respect the protocol rules
                 peer.set_last_ping(current_time);
@@ -1613,12 +2229,36 @@ impl<S: Storage> P2pServer<S> {
                             return Err(P2pError::InvalidPeerlist)
                         }
 
+                        // placeholder addresses are only shared as hostnames
+                        if is_hostname_address(addr) {
+                            error!("{} is a hostname address from {}", addr, peer);
+                            return Err(P2pError::InvalidPeerlist)
+                        }
+
                         if !self.is_connected_to_addr(addr).await && !self.peer_list.has_peer_stored(&addr.ip()).await? {
                             if !self.peer_list.store_peer_address(*addr).await? {
                                 debug!("{} already stored in peer list", addr);
                             }
                         }
                     }
+
+                    // hostnames can only be reached through our proxy
+                    if self.proxy.is_some() {
+                        for hostname in ping.get_hostnames() {
+                            let (host, port) = parse_hostname(hostname)?;
+                            if self.advertised_hostname.as_ref().is_some_and(|(h, p)| *h == host && *p == port) {
+                                continue;
+                            }
+
+                            let addr = get_hostname_address(&host, port);
+                            if !self.is_connected_to_addr(&addr).await && !self.peer_list.has_peer_stored(&addr.ip()).await? {
+                                self.peer_list.get_cache().set_hostname(&addr.ip(), &host)?;
+                                if !self.peer_list.store_peer_address(addr).await? {
+                                    debug!("{} already stored in peer list", hostname);
+                                }
+                            }
+                        }
+                    }
                 }
 
                 ping.into_owned().update_peer(peer, &self.blockchain).await?;
@@ -1665,14 +2305,25 @@ impl<S: Storage> P2pServer<S> {
                     },
                     ObjectRequest::Transaction(hash) => {
                         debug!("{} asked tx {}", peer, hash);
-                        match self.blockchain.get_tx(hash).await {
-                            Ok(tx) => {
-                                debug!("tx {} found, sending it", hash);
-                                peer.send_packet(Packet::ObjectResponse(ObjectResponse::Transaction(Cow::Borrowed(&tx)))).await?;
-                            },
-                            Err(e) => {
-                                debug!("{} asked tx '{}' but not present in our chain: {}", peer, hash, e);
-                                peer.send_packet(Packet::ObjectResponse(ObjectResponse::NotFound(request))).await?;
+                        // A TX in stem phase must not be shared before it is fluffed
+                        let embargoed = match self.dandelion.as_ref() {
+                            Some(dandelion) => dandelion.is_embargoed(hash).await,
+                            None => false
+                        };
+
+                        if embargoed {
+                            debug!("{} asked tx '{}' which is in stem phase", peer, hash);
+                            peer.send_packet(Packet::ObjectResponse(ObjectResponse::NotFound(request))).await?;
+                        } else {
+                            match self.blockchain.get_tx(hash).await {
+                                Ok(tx) => {
+                                    debug!("tx {} found, sending it", hash);
+                                    peer.send_packet(Packet::ObjectResponse(ObjectResponse::Transaction(Cow::Borrowed(&tx)))).await?;
+                                },
+                                Err(e) => {
+                                    debug!("{} asked tx '{}' but not present in our chain: {}", peer, hash, e);
+                                    peer.send_packet(Packet::ObjectResponse(ObjectResponse::NotFound(request))).await?;
+                                }
                             }
                         }
                     }
@@ -1711,11 +2362,18 @@ impl<S: Storage> P2pServer<S> {
                 let page_id = request.page().unwrap_or(0);
                 let skip = page_id as usize * NOTIFY_MAX_LEN;
 
+                // Our TXs in stem phase must not be shared before they are fluffed
+                let embargoed = match self.dandelion.as_ref() {
+                    Some(dandelion) => dandelion.get_embargoed_txs().await,
+                    None => HashSet::new()
+                };
+
                 let packet = {
                     let mempool = self.blockchain.get_mempool().read().await;
                     let nonces_cache = mempool.get_caches();
                     let txs = nonces_cache.values()
                         .flat_map(|v| v.get_txs())
+                        .filter(|tx| !embargoed.contains(tx.as_ref()))
                         .skip(skip).take(NOTIFY_MAX_LEN)
                         .map(|tx| Cow::Borrowed(tx.as_ref()))
                         .collect::<IndexSet<_>>();
@@ -1830,8 +2488,22 @@ impl<S: Storage> P2pServer<S> {
     async fn listen_connection(self: &Arc<Self>, buf: &mut [u8], peer: &Arc<Peer>) -> Result<(), P2pError> {
         // Read & parse the packet
         // 16 additional bytes are for AEAD
-        let packet = peer.get_connection().read_packet(buf, PEER_MAX_PACKET_SIZE).await?;
+        let bytes = peer.get_connection().read_packet_bytes(buf, PEER_MAX_PACKET_SIZE).await?;
+        let packet = peer.get_connection().read_packet_from_bytes(&bytes).await?;
         let packet_id = packet.get_id();
+        peer.get_bandwidth().record_in(packet_id, bytes.len());
+        self.bandwidth.record_in(packet_id, bytes.len());
+
+        // Expensive requests are limited, priority nodes are trusted
+        if let Some(limit) = self.requests_limits.get_limit(packet_id).filter(|_| !peer.is_priority()) {
+            if !peer.consume_request(packet_id, limit).await {
+                warn!("{} exceeded the limit of {} {} packets per minute, graylisting it", peer, limit, get_packet_name(packet_id));
+                self.peer_list.set_graylist_for_peer(&peer.get_ip()).await?;
+                peer.close_and_temp_ban().await?;
+                return Err(P2pError::RequestsLimitExceeded(limit, get_packet_name(packet_id)));
+            }
+        }
+
         // Handle the packet
         if let Err(e) = self.handle_incoming_packet(&peer, packet).await {
             match e {
@@ -1846,6 +2518,7 @@ impl<S: Storage> P2pServer<S> {
                 e => {
                     error!("Error occured while handling incoming packet #{} from {}: {}", packet_id, peer, e);
                     peer.increment_fail_count();
+                    peer.update_score(PEER_SCORE_INVALID_OBJECT);
                 }
             }
         }
@@ -2154,104 +2827,32 @@ impl<S: Storage> P2pServer<S> {
                     }
                 }
             }
+        } else if self.allow_boost_sync() {
+            // no rewind are needed, validate all the headers first and download the blocks from all our peers
+            let total_requested = self.header_first_sync(peer, blocks, common_topoheight).await?;
+            info!("we've synced {} on {} blocks and {} top blocks from {} and others peers", total_requested, blocks_len, top_len, peer);
         } else {
             // no rewind are needed, process normally
             // it will first add blocks to sync, and then all alt-tips blocks if any (top blocks)
             let mut total_requested: usize = 0;
-            let mut final_blocker = None;
-            // If boost sync is allowed, we can request all blocks in parallel,
-            // Create a new group in Object Tracker to be notified of a failure
-            let (group_id, mut notifier) = if self.allow_boost_sync() {
-                let (group_id, notifier) = self.object_tracker.get_group_manager().next_group_id().await;
-                (Some(group_id), Some(notifier))
-            } else {
-                (None, None)
-            };
-
-            // Peekable is here to help to know if we are at the last element
-            // so we create only one channel for the last blocker
-            let mut blocks_iter = blocks.into_iter().peekable();
-            while let Some(hash) = blocks_iter.next() {
+            for hash in blocks {
                 if !self.blockchain.has_block(&hash).await? {
                     trace!("Block {} is not found, asking it to {} (index = {})", hash, peer.get_outgoing_address(), total_requested);
-                    // if it's allowed by the user, request all blocks in parallel
-                    if self.allow_boost_sync() {
-                        if let Some(notifier) = &mut notifier {
-                            // Check if we don't have any message pending in the channel
-                            if let Ok(err) = notifier.try_recv() {
-                                debug!("An error has occured in batch while requesting chain in boost mode");
-                                return Err(P2pError::BoostSyncModeFailed(Box::new(err)).into());
-                            }
-                        }
-
-                        let is_last = blocks_iter.peek().is_none();
-                        if let Some(blocker) = self.object_tracker.request_object_from_peer_with(Arc::clone(peer), ObjectRequest::Block(hash.clone()), group_id, is_last, is_last).await? {
-                            final_blocker = Some(blocker);
-                        }
+                    // request them one by one and wait for the response
+                    let response = peer.request_blocking_object(ObjectRequest::Block(hash)).await?;
+                    if let OwnedObjectResponse::Block(block, hash) = response {
+                        trace!("Received block {} at height {} from {}", hash, block.get_height(), peer);
+                        self.blockchain.add_new_block(block, false, false).await?;
                     } else {
-                        // Otherwise, request them one by one and wait for the response
-                        let response = peer.request_blocking_object(ObjectRequest::Block(hash)).await?;
-                        if let OwnedObjectResponse::Block(block, hash) = response {
-                            trace!("Received block {} at height {} from {}", hash, block.get_height(), peer);
-                            self.blockchain.add_new_block(block, false, false).await?;
-                        } else {
-                            error!("{} sent us an invalid block response", peer);
-                            return Err(P2pError::ExpectedBlock.into())
-                        }
+                        error!("{} sent us an invalid block response", peer);
+                        return Err(P2pError::ExpectedBlock.into())
                     }
                     total_requested += 1;
                 } else {
-                    trace!("Block {} is already in chain, verify if its in DAG", hash);
-
-                    let block = {
-                        let mut storage = self.blockchain.get_storage().write().await;
-                        if !storage.is_block_topological_ordered(&hash).await {
-                            match storage.delete_block_with_hash(&hash).await {
-                                Ok(block) => Some(block),
-                                Err(e) => {
-                                    // This shouldn't happen, but in case
-                                    error!("Error while deleting block {} from storage to re-execute it for chain sync: {}", hash, e);
-                                    continue;
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    };
-
-                    if let Some(block) = block {
-                        warn!("Block {} is already in chain but not in DAG, re-executing it", hash);
-                        self.blockchain.add_new_block(block, false, false).await?;
-                    } else {
-                        trace!("Block {} is already in DAG, skipping it", hash);
-                    }
+                    self.execute_block_if_not_in_dag(&hash).await?;
                 }
             }
 
-            if let (Some(mut notifier), Some(mut blocker)) = (notifier, final_blocker) {
-                debug!("Waiting for final blocker to finish...");
-                select! {
-                    res = &mut notifier => {
-                        let err = res.map_err(|e| P2pError::BoostSyncModeBlockerResponseError(e))?;
-                        debug!("An error has occured while requesting chain in boost mode: {}", err);
-                        return Err(err.into());
-                    },
-                    res = blocker.recv() => match res {
-                        Ok(()) => {
-                            debug!("Final blocker finished");
-                            if let Some(group_id) = group_id {
-                                self.object_tracker.get_group_manager().unregister_group(group_id).await;
-                            } else {
-                                warn!("Group ID is None while it should not be");
-                            }
-                        },
-                        Err(e) => {
-                            error!("Error while waiting for final blocker: {}", e);
-                            return Err(P2pError::BoostSyncModeBlockerError.into());
-                        }
-                    }
-                }
-            }
             info!("we've synced {} on {} blocks and {} top blocks from {}", total_requested, blocks_len, top_len, peer);
         }
 
@@ -2271,6 +2872,204 @@ impl<S: Storage> P2pServer<S> {
         Ok(())
     }
 
+    // Re-execute a block that we already have but which is not ordered in the DAG
+    async fn execute_block_if_not_in_dag(&self, hash: &Hash) -> Result<(), BlockchainError> {
+        trace!("Block {} is already in chain, verify if its in DAG", hash);
+
+        let block = {
+            let mut storage = self.blockchain.get_storage().write().await;
+            if !storage.is_block_topological_ordered(hash).await {
+                match storage.delete_block_with_hash(hash).await {
+                    Ok(block) => Some(block),
+                    Err(e) => {
+                        // This shouldn't happen, but in case
+                        error!("Error while deleting block {} from storage to re-execute it for chain sync: {}", hash, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            }
+        };
+
+        if let Some(block) = block {
+            warn!("Block {} is already in chain but not in DAG, re-executing it", hash);
+            self.blockchain.add_new_block(block, false, false).await?;
+        } else {
+            trace!("Block {} is already in DAG, skipping it", hash);
+        }
+
+        Ok(())
+    }
+
+    // Header-first sync used in boost sync mode
+    // All the blocks headers are requested at the same time to the peer and validated in order using the chain validator
+    // Then, the blocks are split in ranges and their TXs are downloaded in parallel from all the peers having them
+    // Ranges are downloaded in a dedicated task and added in order to the chain as soon as they are received
+    // Returns the count of blocks synced
+    async fn header_first_sync(&self, peer: &Arc<Peer>, blocks: IndexSet<Hash>, common_topoheight: u64) -> Result<usize, BlockchainError> {
+        let mut chain_validator = ChainValidator::new(&self.blockchain, common_topoheight + 1);
+        let mut responses = stream::iter(blocks)
+            .map(|hash| async move {
+                // blocks already in chain are re-executed in order below if needed
+                if self.blockchain.has_block(&hash).await? {
+                    return Ok::<_, BlockchainError>((hash, None))
+                }
+
+                trace!("Request block header for header-first sync: {}", hash);
+                let response = peer.request_blocking_object(ObjectRequest::BlockHeader(hash)).await?;
+                if let OwnedObjectResponse::BlockHeader(header, hash) = response {
+                    trace!("Received {} with hash {}", header, hash);
+                    Ok((hash, Some(header)))
+                } else {
+                    error!("{} sent us an invalid object response", peer);
+                    Err(BlockchainError::from(P2pError::ExpectedBlock))
+                }
+            })
+            .buffered(CHAIN_SYNC_PARALLEL_MAX_REQUESTS);
+
+        while let Some(res) = responses.next().await {
+            match res? {
+                (hash, Some(header)) => chain_validator.insert_block(hash, header).await?,
+                (hash, None) => self.execute_block_if_not_in_dag(&hash).await?
+            }
+        }
+
+        let headers: Vec<(Hash, Arc<BlockHeader>)> = chain_validator.get_blocks().collect();
+        let total = headers.len();
+        if total == 0 {
+            return Ok(0)
+        }
+
+        // The peer that sent us the headers is the first one to request
+        let mut peers = vec![Arc::clone(peer)];
+        peers.extend(self.peer_list.get_cloned_peers().await.into_iter().filter(|p| p.get_id() != peer.get_id()));
+
+        let ranges = BlocksRange::split(headers, CHAIN_SYNC_PARALLEL_RANGE_BLOCKS);
+        debug!("Downloading {} blocks in {} ranges from {} peers", total, ranges.len(), peers.len());
+
+        // Keep downloading the next ranges while the blocks are executed
+        let (sender, mut receiver) = channel(CHAIN_SYNC_PARALLEL_MAX_RANGES);
+        let downloads = spawn_task("p2p-boost-sync", Self::download_blocks_ranges(Arc::clone(&self.blockchain), Arc::clone(peer), peers, ranges, sender));
+        let res = self.add_downloaded_ranges(peer, &mut receiver).await;
+        // Stop the downloads if the sync got interrupted
+        downloads.abort();
+
+        res.map(|_| total)
+    }
+
+    // Add in chain the blocks of each range received, in order
+    async fn add_downloaded_ranges(&self, peer: &Arc<Peer>, receiver: &mut Receiver<Result<(BlocksRange, HashMap<Hash, Arc<Transaction>>), P2pError>>) -> Result<(), BlockchainError> {
+        while let Some(res) = receiver.recv().await {
+            let (range, txs) = res?;
+            for (hash, header) in range.take_headers() {
+                // it may have been propagated to us in the meantime
+                if self.blockchain.has_block(&hash).await? {
+                    trace!("Block {} is already in chain, skipping it", hash);
+                    continue;
+                }
+
+                let mut transactions = Vec::with_capacity(header.get_txs_count());
+                for tx_hash in header.get_txs_hashes() {
+                    // TXs not downloaded by this range were already known or requested by a previous range
+                    if let Some(tx) = txs.get(tx_hash) {
+                        transactions.push(Immutable::Arc(Arc::clone(tx)));
+                    } else if let Some(tx) = self.blockchain.get_tx(tx_hash).await.ok() {
+                        trace!("Found the transaction {} on disk", tx_hash);
+                        transactions.push(Immutable::Arc(tx));
+                    } else {
+                        // It may have been removed from the mempool in the meantime
+                        let response = peer.request_blocking_object(ObjectRequest::Transaction(tx_hash.clone())).await?;
+                        if let OwnedObjectResponse::Transaction(tx, _) = response {
+                            transactions.push(Immutable::Owned(tx));
+                        } else {
+                            error!("{} sent us an invalid block response", peer);
+                            return Err(P2pError::ExpectedTransaction.into())
+                        }
+                    }
+                }
+
+                trace!("Adding block {} from header-first sync", hash);
+                let block = Block::new(Immutable::Arc(header), transactions);
+                self.blockchain.add_new_block(block, false, false).await?; // don't broadcast block because it's syncing
+            }
+        }
+
+        Ok(())
+    }
+
+    // Download the TXs of the ranges at the same time and send them in order to the channel
+    // It stops after the first range that can't be downloaded or when the receiver is dropped
+    async fn download_blocks_ranges(blockchain: Arc<Blockchain<S>>, sync_peer: Arc<Peer>, peers: Vec<Arc<Peer>>, ranges: Vec<BlocksRange>, sender: Sender<Result<(BlocksRange, HashMap<Hash, Arc<Transaction>>), P2pError>>) {
+        let (blockchain, sync_peer, peers) = (&blockchain, &sync_peer, &peers);
+        let mut downloads = stream::iter(ranges.into_iter().enumerate())
+            .map(|(index, range)| async move {
+                let txs = Self::request_blocks_range(blockchain, sync_peer, peers, &range, index).await?;
+                Ok::<_, P2pError>((range, txs))
+            })
+            .buffered(peers.len().min(CHAIN_SYNC_PARALLEL_MAX_RANGES));
+
+        while let Some(res) = downloads.next().await {
+            let failed = res.is_err();
+            if sender.send(res).await.is_err() || failed {
+                break;
+            }
+        }
+    }
+
+    // Request the TXs of a blocks range, the N-th range is requested first to the N-th peer
+    // If a peer fails or times out, the range is reassigned to the next peer having its blocks
+    async fn request_blocks_range(blockchain: &Blockchain<S>, sync_peer: &Arc<Peer>, peers: &[Arc<Peer>], range: &BlocksRange, index: usize) -> Result<HashMap<Hash, Arc<Transaction>>, P2pError> {
+        let candidates = peers.iter()
+            .cycle()
+            .skip(index % peers.len())
+            .take(peers.len())
+            .filter(|p| !p.get_connection().is_closed())
+            // The sync peer sent us the headers so it has all the blocks
+            .filter(|p| p.get_id() == sync_peer.get_id() || p.get_height() >= range.get_max_height());
+
+        let mut last_error = None;
+        for peer in candidates {
+            match Self::request_range_txs_from(blockchain, peer, range).await {
+                Ok(txs) => return Ok(txs),
+                Err(e) => {
+                    debug!("Error while requesting blocks range {} to {}, reassigning it: {}", index, peer, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(P2pError::BoostSyncModeFailed(Box::new(last_error.unwrap_or(P2pError::NoPeerForBlocksRange))))
+    }
+
+    // Request at the same time to a peer all the TXs of a blocks range that we don't have yet
+    async fn request_range_txs_from(blockchain: &Blockchain<S>, peer: &Arc<Peer>, range: &BlocksRange) -> Result<HashMap<Hash, Arc<Transaction>>, P2pError> {
+        let mut requests = stream::iter(range.get_txs())
+            .map(|hash| async move {
+                if blockchain.has_tx(hash).await? {
+                    return Ok::<_, P2pError>(None)
+                }
+
+                let response = peer.request_blocking_object(ObjectRequest::Transaction(hash.clone())).await?;
+                if let OwnedObjectResponse::Transaction(tx, hash) = response {
+                    Ok(Some((hash, Arc::new(tx))))
+                } else {
+                    error!("{} sent us an invalid object response", peer);
+                    Err(P2pError::ExpectedTransaction)
+                }
+            })
+            .buffer_unordered(CHAIN_SYNC_PARALLEL_MAX_REQUESTS);
+
+        let mut txs = HashMap::new();
+        while let Some(res) = requests.next().await {
+            if let Some((hash, tx)) = res? {
+                txs.insert(hash, tx);
+            }
+        }
+
+        Ok(txs)
+    }
+
     // determine if we are connected to a priority node and that this node is equal / greater to our chain
     async fn is_connected_to_a_synced_priority_node(&self) -> bool {
         let topoheight = self.blockchain.get_topo_height();
@@ -2292,6 +3091,15 @@ impl<S: Storage> P2pServer<S> {
         &self.tag
     }
 
+    // Get the optional features we support, advertised in our handshake
+    pub fn get_capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::default();
+        capabilities.insert(Capabilities::MERKLE_HASH);
+        capabilities.insert(Capabilities::COMPACT_BLOCKS);
+        capabilities.insert(Capabilities::STEM_TRANSACTIONS);
+        capabilities
+    }
+
     // Get the maximum peers count allowed to be connected
     pub fn get_max_peers(&self) -> usize {
         self.max_peers
@@ -2302,11 +3110,27 @@ impl<S: Storage> P2pServer<S> {
         self.peer_id
     }
 
+    // Get the maximum outgoing peers count, priority nodes excluded
+    pub fn get_max_outgoing_peers(&self) -> usize {
+        self.max_outgoing_peers
+    }
+
     // Check if we are accepting new connections by verifying if we have free slots available
     pub async fn accept_new_connections(&self) -> bool {
         self.get_peer_count().await < self.get_max_peers()
     }
 
+    // Check if we have a free outgoing slot available
+    pub async fn accept_new_outgoing_connections(&self) -> bool {
+        self.accept_new_connections().await && self.peer_list.count_peers(true).await < self.max_outgoing_peers
+    }
+
+    // Check if we have a free incoming slot available
+    // Incoming peers can't take the slots reserved to our outgoing peers
+    pub async fn accept_new_incoming_connections(&self) -> bool {
+        self.accept_new_connections().await && self.peer_list.count_peers(false).await < self.max_peers - self.max_outgoing_peers
+    }
+
     // Returns the count of peers connected
     pub async fn get_peer_count(&self) -> usize {
         self.peer_list.size().await
@@ -2347,6 +3171,11 @@ impl<S: Storage> P2pServer<S> {
         &self.bind_address
     }
 
+    // Get the traffic with all peers since the start
+    pub fn get_bandwidth(&self) -> &Bandwidth {
+        &self.bandwidth
+    }
+
     // Get our peerlist
     pub fn get_peer_list(&self) -> &SharedPeerList {
         &self.peer_list
@@ -2355,7 +3184,81 @@ impl<S: Storage> P2pServer<S> {
     // Broadcast a new transaction hash using propagation packet
     // This is used so we don't overload the network during spam or high transactions count
     // We simply share its hash to nodes and others nodes can check if they have it already or not
+    // If Dandelion is enabled, a TX not seen in fluff phase is relayed to our stem peer instead
     pub async fn broadcast_tx_hash(&self, tx: Hash) {
+        if let Some(dandelion) = self.dandelion.as_ref() {
+            if !dandelion.is_fluffed(&tx).await && self.stem_tx(&tx, None).await {
+                return;
+            }
+        }
+
+        self.fluff_tx_hash(tx).await;
+    }
+
+    // Relay a TX in stem phase to our stem peer and start its embargo timer
+    // Returns false if it couldn't be relayed and must be fluffed
+    async fn stem_tx(&self, hash: &Hash, from: Option<u64>) -> bool {
+        let Some(dandelion) = self.dandelion.as_ref() else {
+            return false;
+        };
+
+        let tx = match dandelion.get_stem_tx(hash).await {
+            Some(tx) => tx,
+            None => match self.blockchain.get_tx(hash).await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    debug!("Error while retrieving stem TX {}: {}", hash, e);
+                    return false;
+                }
+            }
+        };
+
+        // Only the peers handling stem TXs can be selected, others would broadcast it directly
+        let peers: HashSet<_> = self.peer_list.get_cloned_peers().await.into_iter()
+            .filter(|peer| peer.has_capability(Capabilities::STEM_TRANSACTIONS))
+            .collect();
+        let Some(peer) = dandelion.get_stem_peer(&peers, from).await else {
+            debug!("No stem peer available for TX {}", hash);
+            return false;
+        };
+
+        trace!("Relaying stem TX {} to {}", hash, peer);
+        if let Err(e) = peer.send_packet(Packet::StemTransaction(Cow::Borrowed(tx.as_ref()))).await {
+            debug!("Error while relaying stem TX {} to {}: {}", hash, peer, e);
+            return false;
+        }
+
+        peer.get_txs_cache().lock().await.put(hash.clone(), Direction::In);
+        dandelion.add_embargo(hash.clone()).await;
+        true
+    }
+
+    // Mark a TX as public, it must not be stemmed anymore
+    // If it was in our stempool, it is added to the mempool
+    // Returns false if the stem TX couldn't be added to the mempool
+    async fn mark_tx_fluffed(&self, hash: &Hash) -> bool {
+        let Some(dandelion) = self.dandelion.as_ref() else {
+            return true;
+        };
+
+        dandelion.mark_fluffed(hash).await;
+        if let Some(tx) = dandelion.take_stem_tx(hash).await {
+            let storage = self.blockchain.get_storage().read().await;
+            if let Err(e) = self.blockchain.add_tx_to_mempool_with_storage_and_hash(&*storage, tx, hash.clone(), false).await {
+                debug!("Error while adding stem TX {} to mempool: {}", hash, e);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Broadcast the TX hash to all our peers (fluff phase)
+    async fn fluff_tx_hash(&self, tx: Hash) {
+        if !self.mark_tx_fluffed(&tx).await {
+            return;
+        }
+
         debug!("Broadcasting tx hash {}", tx);
         let ping = self.build_generic_ping_packet().await;
         debug!("Ping packet has been generated for tx broadcast");
@@ -2397,8 +3300,10 @@ impl<S: Storage> P2pServer<S> {
         // we build the ping packet ourself this time (we have enough data for it)
         // because this function can be call from Blockchain, which would lead to a deadlock
         let ping = Ping::new(Cow::Borrowed(hash), our_topoheight, our_height, pruned_topoheight, cumulative_difficulty, IndexSet::new());
-        let block_packet = Packet::BlockPropagation(PacketWrapper::new(Cow::Borrowed(block), Cow::Borrowed(&ping)));
-        let packet_block_bytes = Bytes::from(block_packet.to_bytes());
+        let packet_block_bytes = Bytes::from(Packet::BlockPropagation(PacketWrapper::new(Cow::Borrowed(block), Cow::Borrowed(&ping))).to_bytes());
+        // Send it in compact form when possible to the peers supporting it so they can rebuild it using their mempool
+        let packet_compact_block_bytes = CompactBlock::new(block, hash.clone())
+            .map(|compact| Bytes::from(Packet::CompactBlockPropagation(PacketWrapper::new(Cow::Owned(compact), Cow::Borrowed(&ping))).to_bytes()));
         let packet_ping_bytes = Bytes::from(Packet::Ping(Cow::Owned(ping)).to_bytes());
 
         trace!("Locking peer list for broadcasting block {}", hash);
@@ -2423,7 +3328,11 @@ impl<S: Storage> P2pServer<S> {
                     blocks_propagation.put(hash.clone(), if lock { Direction::Both } else { Direction::Out });
 
                     debug!("Broadcast {} to {} (lock: {})", hash, peer, lock);
-                    if let Err(e) = peer.send_bytes(packet_block_bytes.clone()).await {
+                    let bytes = match packet_compact_block_bytes.as_ref() {
+                        Some(bytes) if peer.has_capability(Capabilities::COMPACT_BLOCKS) => bytes.clone(),
+                        _ => packet_block_bytes.clone()
+                    };
+                    if let Err(e) = peer.send_bytes(bytes).await {
                         debug!("Error on broadcast block {} to {}: {}", hash, peer, e);
                     }
                     trace!("{} has been broadcasted to {}", hash, peer);
@@ -2555,11 +3464,62 @@ impl<S: Storage> P2pServer<S> {
                 }
                 StepResponse::BlocksMetadata(blocks)
             },
+            StepRequest::MerkleHash(topoheight) => {
+                // Only the state roots stored when their topoheight became stable are served
+                // Computing another one would require to scan the whole state
+                if !storage.has_state_root_at_topoheight(topoheight).await? {
+                    debug!("{} requested a merkle hash not stored at topoheight {}", peer, topoheight);
+                    return Err(BlockchainError::StateNotAvailable(topoheight))
+                }
+
+                StepResponse::MerkleHash(storage.get_state_root_at_topoheight(topoheight).await?)
+            },
         };
         peer.send_packet(Packet::BootstrapChainResponse(BootstrapChainResponse::new(response))).await?;
         Ok(())
     }
 
+    // Request the state root at a stable topoheight from all our peers
+    // Only the peers supporting this step and having this topoheight not pruned are requested
+    // They are all requested at the same time, each request is bounded by the bootstrap step timeout
+    pub async fn request_state_roots(&self, topoheight: u64) -> Vec<(Arc<Peer>, Hash)> {
+        let peers: Vec<_> = self.peer_list.get_cloned_peers().await.into_iter()
+            .filter(|peer| {
+                if !peer.has_capability(Capabilities::MERKLE_HASH) {
+                    trace!("Skipping {} for state root, merkle hash step is not supported", peer);
+                    return false
+                }
+
+                if peer.get_topoheight() < topoheight || peer.get_pruned_topoheight().is_some_and(|pruned| pruned >= topoheight) {
+                    trace!("Skipping {} for state root at topoheight {}", peer, topoheight);
+                    return false
+                }
+
+                true
+            })
+            .collect();
+
+        let count = peers.len().max(1);
+        stream::iter(peers)
+            .map(|peer| async move {
+                match peer.request_boostrap_chain(StepRequest::MerkleHash(topoheight)).await {
+                    Ok(StepResponse::MerkleHash(hash)) => Some((peer, hash)),
+                    Ok(response) => {
+                        debug!("Received an invalid step response {:?} from {}", response.kind(), peer);
+                        None
+                    },
+                    Err(e) => {
+                        debug!("Error while requesting state root at topoheight {} from {}: {}", topoheight, peer, e);
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(count)
+            .filter_map(|res| async move { res })
+            .collect()
+            .await
+    }
+
     // Build a block id list to share our DAG order and chain state
     // Block id list must be in descending order and unique hash / topoheight
     // This is used to search the common point between two peers
@@ -2912,8 +3872,16 @@ impl<S: Storage> P2pServer<S> {
             PacketWrapper::new(Cow::Owned(request), Cow::Owned(ping))
         };
 
+        let start = get_current_time_in_millis();
         let response = peer.request_sync_chain(packet).await?;
 
+        // Slow peers are less likely to be selected for the next chain sync
+        if get_current_time_in_millis() - start <= PEER_SCORE_TIMELY_CHAIN_RESPONSE_DELAY {
+            peer.update_score(PEER_SCORE_TIMELY_CHAIN_RESPONSE);
+        } else {
+            peer.update_score(PEER_SCORE_SLOW_CHAIN_RESPONSE);
+        }
+
         // Check that the peer followed our requirements
         if response.blocks_size() > requested_max_size {
             return Err(P2pError::InvalidChainResponseSize(response.blocks_size(), requested_max_size).into())
@@ -2931,6 +3899,36 @@ impl<S: Storage> P2pServer<S> {
     }
 }
 
+// Bind the P2p listener on the address
+// An unspecified IPv6 address accepts both IPv4 and IPv6 connections,
+// whatever the default of the platform is
+fn bind_listener(addr: &SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(*addr), Type::STREAM, Some(Protocol::TCP))?;
+    if let IpAddr::V6(ip) = addr.ip() {
+        socket.set_only_v6(!ip.is_unspecified())?;
+    }
+
+    // Same behavior as the default listener
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&(*addr).into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+// Convert an IPv4-mapped IPv6 address to its IPv4 address
+pub fn get_canonical_address(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ipv4) => SocketAddr::new(IpAddr::V4(ipv4), addr.port()),
+            None => addr
+        },
+        IpAddr::V4(_) => addr
+    }
+}
+
 // Check if a socket address is a local address
 pub fn is_local_address(socket_addr: &SocketAddr) -> bool {
     match socket_addr.ip() {
@@ -2966,6 +3964,29 @@ mod tests {
     use std::str::FromStr;
     use super::*;
 
+    #[test]
+    fn test_get_canonical_address() {
+        let addr = SocketAddr::from_str("[::ffff:1.1.1.1]:2125").unwrap();
+        assert_eq!(get_canonical_address(addr), SocketAddr::from_str("1.1.1.1:2125").unwrap());
+
+        let addr = SocketAddr::from_str("[2001:db8::1]:2125").unwrap();
+        assert_eq!(get_canonical_address(addr), addr);
+    }
+
+    #[tokio::test]
+    async fn test_bind_dual_stack() {
+        let listener = match bind_listener(&SocketAddr::from_str("[::]:0").unwrap()) {
+            Ok(listener) => listener,
+            // IPv6 is not available on this host
+            Err(_) => return
+        };
+
+        let port = listener.local_addr().unwrap().port();
+        let client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let (_, addr) = listener.accept().await.unwrap();
+        assert_eq!(get_canonical_address(addr), client.local_addr().unwrap());
+    }
+
     #[test]
     fn test_is_local_address() {
         assert!(is_local_address(&SocketAddr::from_str("172.20.0.1:2125").unwrap()));
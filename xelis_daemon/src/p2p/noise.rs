@@ -0,0 +1,327 @@
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    ChaCha20Poly1305,
+    KeyInit
+};
+use rand::rngs::OsRng;
+use x25519_dalek::{PublicKey, StaticSecret};
+use super::{
+    encryption::{EncryptionError, EncryptionKey},
+    identity::{NodeIdentity, NodeKey}
+};
+
+// Key exchange based on the Noise XX pattern
+// using X25519 for Diffie-Hellman, ChaCha20-Poly1305 and BLAKE3
+//
+// -> e
+// <- e, ee, s, es
+// -> s, se
+//
+// Both sides send their static node key encrypted and prove they own it
+// The derived keys can't be known by someone relaying the messages,
+// and if the static key received is pinned, a MITM can't replace it
+// Name is exactly 32 bytes so it is used directly as the initial hash
+const PROTOCOL_NAME: &[u8; 32] = b"Noise_XX_25519_ChaChaPoly_BLAKE3";
+
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+
+// Expected size of each handshake message
+// Payloads are empty, only their authentication tag is sent
+pub const MESSAGE_1_SIZE: usize = KEY_SIZE;
+pub const MESSAGE_2_SIZE: usize = KEY_SIZE + KEY_SIZE + TAG_SIZE + TAG_SIZE;
+pub const MESSAGE_3_SIZE: usize = KEY_SIZE + TAG_SIZE + TAG_SIZE;
+
+// HKDF from the Noise specs using BLAKE3 keyed hash as HMAC
+fn hkdf(chaining_key: &[u8; 32], input: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let temp_key = blake3::keyed_hash(chaining_key, input);
+    let output1 = blake3::keyed_hash(temp_key.as_bytes(), &[1]);
+    let mut buffer = [0u8; 33];
+    buffer[..32].copy_from_slice(output1.as_bytes());
+    buffer[32] = 2;
+    let output2 = blake3::keyed_hash(temp_key.as_bytes(), &buffer);
+    (*output1.as_bytes(), *output2.as_bytes())
+}
+
+// Diffie-Hellman between our secret and the peer public key
+// Low order points are rejected as they would give a known shared secret
+fn dh(secret: &StaticSecret, public: &PublicKey) -> Result<[u8; 32], EncryptionError> {
+    let shared = secret.diffie_hellman(public);
+    if !shared.was_contributory() {
+        return Err(EncryptionError::InvalidKey);
+    }
+    Ok(shared.to_bytes())
+}
+
+fn read_key(bytes: &[u8]) -> Result<PublicKey, EncryptionError> {
+    let key: [u8; KEY_SIZE] = bytes.try_into().map_err(|_| EncryptionError::InvalidHandshakeMessage)?;
+    Ok(PublicKey::from(key))
+}
+
+// State shared by both sides during the handshake
+// Every message is hashed in the transcript and each DH result is mixed in the chaining key
+struct SymmetricState {
+    chaining_key: [u8; 32],
+    hash: [u8; 32],
+    cipher: Option<ChaCha20Poly1305>,
+    nonce: u64
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        Self {
+            chaining_key: *PROTOCOL_NAME,
+            hash: *PROTOCOL_NAME,
+            cipher: None,
+            nonce: 0
+        }
+    }
+
+    // 4 bytes set to 0 followed by the counter in little endian
+    fn nonce_bytes(&self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        nonce
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.hash);
+        hasher.update(data);
+        self.hash = *hasher.finalize().as_bytes();
+    }
+
+    fn mix_key(&mut self, input: &[u8]) {
+        let (chaining_key, key) = hkdf(&self.chaining_key, input);
+        self.chaining_key = chaining_key;
+        self.cipher = Some(ChaCha20Poly1305::new(&key.into()));
+        self.nonce = 0;
+    }
+
+    // Encrypt using the transcript hash as associated data
+    // Before any DH, data is sent in plaintext
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let ciphertext = match self.cipher.as_ref() {
+            Some(cipher) => {
+                let payload = Payload { msg: plaintext, aad: &self.hash };
+                let ciphertext = cipher.encrypt(&self.nonce_bytes().into(), payload)
+                    .map_err(|_| EncryptionError::CipherError)?;
+                self.nonce += 1;
+                ciphertext
+            },
+            None => plaintext.to_vec()
+        };
+
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let plaintext = match self.cipher.as_ref() {
+            Some(cipher) => {
+                let payload = Payload { msg: ciphertext, aad: &self.hash };
+                let plaintext = cipher.decrypt(&self.nonce_bytes().into(), payload)
+                    .map_err(|_| EncryptionError::InvalidHandshakeMessage)?;
+                self.nonce += 1;
+                plaintext
+            },
+            None => ciphertext.to_vec()
+        };
+
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    // Derive the two keys used once the handshake is done
+    // First one is for the initiator messages, second one for the responder messages
+    fn split(&self) -> (EncryptionKey, EncryptionKey) {
+        hkdf(&self.chaining_key, &[])
+    }
+}
+
+pub struct NoiseHandshake<'a> {
+    state: SymmetricState,
+    identity: &'a NodeIdentity,
+    // True if we initiated the connection
+    initiator: bool,
+    // Ephemeral key generated for this handshake only
+    ephemeral: StaticSecret,
+    remote_ephemeral: Option<PublicKey>,
+    remote_static: Option<PublicKey>
+}
+
+impl<'a> NoiseHandshake<'a> {
+    pub fn new(identity: &'a NodeIdentity, initiator: bool) -> Self {
+        Self {
+            state: SymmetricState::new(),
+            identity,
+            initiator,
+            ephemeral: StaticSecret::random_from_rng(OsRng),
+            remote_ephemeral: None,
+            remote_static: None
+        }
+    }
+
+    fn get_remote_ephemeral(&self) -> Result<&PublicKey, EncryptionError> {
+        self.remote_ephemeral.as_ref().ok_or(EncryptionError::InvalidHandshakeMessage)
+    }
+
+    fn get_remote_static(&self) -> Result<&PublicKey, EncryptionError> {
+        self.remote_static.as_ref().ok_or(EncryptionError::InvalidHandshakeMessage)
+    }
+
+    // Write our ephemeral key to the responder
+    // -> e
+    pub fn write_message_1(&mut self) -> Result<Vec<u8>, EncryptionError> {
+        let ephemeral = PublicKey::from(&self.ephemeral);
+        self.state.mix_hash(ephemeral.as_bytes());
+
+        let mut message = ephemeral.as_bytes().to_vec();
+        message.extend(self.state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_message_1(&mut self, message: &[u8]) -> Result<(), EncryptionError> {
+        if message.len() != MESSAGE_1_SIZE {
+            return Err(EncryptionError::InvalidHandshakeMessage);
+        }
+
+        let remote_ephemeral = read_key(&message[..KEY_SIZE])?;
+        self.state.mix_hash(remote_ephemeral.as_bytes());
+        self.remote_ephemeral = Some(remote_ephemeral);
+
+        self.state.decrypt_and_hash(&message[KEY_SIZE..])?;
+        Ok(())
+    }
+
+    // Write our ephemeral key and our encrypted static key to the initiator
+    // <- e, ee, s, es
+    pub fn write_message_2(&mut self) -> Result<Vec<u8>, EncryptionError> {
+        let ephemeral = PublicKey::from(&self.ephemeral);
+        self.state.mix_hash(ephemeral.as_bytes());
+        let mut message = ephemeral.as_bytes().to_vec();
+
+        let ee = dh(&self.ephemeral, self.get_remote_ephemeral()?)?;
+        self.state.mix_key(&ee);
+
+        message.extend(self.state.encrypt_and_hash(self.identity.get_public_key())?);
+
+        let es = dh(self.identity.get_secret(), self.get_remote_ephemeral()?)?;
+        self.state.mix_key(&es);
+
+        message.extend(self.state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_message_2(&mut self, message: &[u8]) -> Result<(), EncryptionError> {
+        if message.len() != MESSAGE_2_SIZE {
+            return Err(EncryptionError::InvalidHandshakeMessage);
+        }
+
+        let remote_ephemeral = read_key(&message[..KEY_SIZE])?;
+        self.state.mix_hash(remote_ephemeral.as_bytes());
+        let ee = dh(&self.ephemeral, &remote_ephemeral)?;
+        self.state.mix_key(&ee);
+        self.remote_ephemeral = Some(remote_ephemeral);
+
+        let offset = KEY_SIZE + KEY_SIZE + TAG_SIZE;
+        let remote_static = read_key(&self.state.decrypt_and_hash(&message[KEY_SIZE..offset])?)?;
+        let es = dh(&self.ephemeral, &remote_static)?;
+        self.state.mix_key(&es);
+        self.remote_static = Some(remote_static);
+
+        self.state.decrypt_and_hash(&message[offset..])?;
+        Ok(())
+    }
+
+    // Write our encrypted static key to the responder
+    // -> s, se
+    pub fn write_message_3(&mut self) -> Result<Vec<u8>, EncryptionError> {
+        let mut message = self.state.encrypt_and_hash(self.identity.get_public_key())?;
+
+        let se = dh(self.identity.get_secret(), self.get_remote_ephemeral()?)?;
+        self.state.mix_key(&se);
+
+        message.extend(self.state.encrypt_and_hash(&[])?);
+        Ok(message)
+    }
+
+    pub fn read_message_3(&mut self, message: &[u8]) -> Result<(), EncryptionError> {
+        if message.len() != MESSAGE_3_SIZE {
+            return Err(EncryptionError::InvalidHandshakeMessage);
+        }
+
+        let offset = KEY_SIZE + TAG_SIZE;
+        let remote_static = read_key(&self.state.decrypt_and_hash(&message[..offset])?)?;
+        let se = dh(&self.ephemeral, &remote_static)?;
+        self.state.mix_key(&se);
+        self.remote_static = Some(remote_static);
+
+        self.state.decrypt_and_hash(&message[offset..])?;
+        Ok(())
+    }
+
+    // Finish the handshake once all the messages are exchanged
+    // Returns our key to encrypt, the peer key to decrypt and the peer static key
+    pub fn finish(self) -> Result<(EncryptionKey, EncryptionKey, NodeKey), EncryptionError> {
+        let remote_static = self.get_remote_static()?.to_bytes();
+        let (initiator_key, responder_key) = self.state.split();
+        if self.initiator {
+            Ok((initiator_key, responder_key, remote_static))
+        } else {
+            Ok((responder_key, initiator_key, remote_static))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake() {
+        let alice = NodeIdentity::new();
+        let bob = NodeIdentity::new();
+
+        let mut initiator = NoiseHandshake::new(&alice, true);
+        let mut responder = NoiseHandshake::new(&bob, false);
+
+        let message = initiator.write_message_1().unwrap();
+        assert_eq!(message.len(), MESSAGE_1_SIZE);
+        responder.read_message_1(&message).unwrap();
+
+        let message = responder.write_message_2().unwrap();
+        assert_eq!(message.len(), MESSAGE_2_SIZE);
+        initiator.read_message_2(&message).unwrap();
+
+        let message = initiator.write_message_3().unwrap();
+        assert_eq!(message.len(), MESSAGE_3_SIZE);
+        responder.read_message_3(&message).unwrap();
+
+        let (alice_key, alice_peer_key, alice_remote) = initiator.finish().unwrap();
+        let (bob_key, bob_peer_key, bob_remote) = responder.finish().unwrap();
+
+        assert_eq!(alice_key, bob_peer_key);
+        assert_eq!(bob_key, alice_peer_key);
+        assert_ne!(alice_key, bob_key);
+        assert_eq!(&alice_remote, bob.get_public_key());
+        assert_eq!(&bob_remote, alice.get_public_key());
+    }
+
+    #[test]
+    fn test_handshake_tampered() {
+        let alice = NodeIdentity::new();
+        let bob = NodeIdentity::new();
+
+        let mut initiator = NoiseHandshake::new(&alice, true);
+        let mut responder = NoiseHandshake::new(&bob, false);
+
+        let message = initiator.write_message_1().unwrap();
+        responder.read_message_1(&message).unwrap();
+
+        // A relay changing the encrypted static key is detected
+        let mut message = responder.write_message_2().unwrap();
+        message[KEY_SIZE] ^= 1;
+        assert!(initiator.read_message_2(&message).is_err());
+    }
+}
@@ -111,7 +111,9 @@ pub enum StepKind {
     Keys,
     Nonces,
     Balances,
-    BlocksMetadata
+    BlocksMetadata,
+    // Not part of the fast sync, used to verify a state snapshot
+    MerkleHash
 }
 
 impl StepKind {
@@ -122,7 +124,7 @@ impl StepKind {
             Self::Keys => Self::Balances,
             Self::Balances => Self::Nonces,
             Self::Nonces => Self::BlocksMetadata,
-            Self::BlocksMetadata => return None
+            Self::BlocksMetadata | Self::MerkleHash => return None
         })
     }
 }
@@ -141,7 +143,9 @@ pub enum StepRequest<'a> {
     // Max topoheight, Accounts
     Nonces(u64, Cow<'a, IndexSet<PublicKey>>),
     // Request blocks metadata starting topoheight
-    BlocksMetadata(u64)
+    BlocksMetadata(u64),
+    // Request the balances merkle hash at a stable topoheight
+    MerkleHash(u64)
 }
 
 impl<'a> StepRequest<'a> {
@@ -152,7 +156,8 @@ impl<'a> StepRequest<'a> {
             Self::Keys(_, _, _) => StepKind::Keys,
             Self::Balances(_, _, _, _) => StepKind::Balances,
             Self::Nonces(_, _) => StepKind::Nonces,
-            Self::BlocksMetadata(_) => StepKind::BlocksMetadata
+            Self::BlocksMetadata(_) => StepKind::BlocksMetadata,
+            Self::MerkleHash(_) => StepKind::MerkleHash
         }
     }
 
@@ -163,7 +168,8 @@ impl<'a> StepRequest<'a> {
             Self::Keys(_, topo, _) => topo,
             Self::Balances(_, _, _, topo) => topo,
             Self::Nonces(topo, _) => topo,
-            Self::BlocksMetadata(topo) => topo
+            Self::BlocksMetadata(topo) => topo,
+            Self::MerkleHash(topo) => topo
         })
     }
 }
@@ -241,6 +247,9 @@ impl Serializer for StepRequest<'_> {
             5 => {
                 Self::BlocksMetadata(reader.read_u64()?)
             },
+            6 => {
+                Self::MerkleHash(reader.read_u64()?)
+            },
             id => {
                 debug!("Received invalid value for StepResponse: {}", id);
                 return Err(ReaderError::InvalidValue)
@@ -285,6 +294,10 @@ impl Serializer for StepRequest<'_> {
                 writer.write_u8(5);
                 writer.write_u64(topoheight);
             },
+            Self::MerkleHash(topoheight) => {
+                writer.write_u8(6);
+                writer.write_u64(topoheight);
+            },
         };
     }
 
@@ -295,7 +308,8 @@ impl Serializer for StepRequest<'_> {
             Self::Keys(min, max, page) => min.size() + max.size() + page.size(),
             Self::Balances(key, asset, min, max) => key.size() + asset.size() + min.size() + max.size(),
             Self::Nonces(topoheight, nonces) => topoheight.size() + nonces.size(),
-            Self::BlocksMetadata(topoheight) => topoheight.size()
+            Self::BlocksMetadata(topoheight) => topoheight.size(),
+            Self::MerkleHash(topoheight) => topoheight.size()
         };
         // 1 for the id
         size + 1
@@ -316,6 +330,8 @@ pub enum StepResponse {
     Nonces(Vec<u64>),
     // top blocks metadata
     BlocksMetadata(IndexSet<BlockMetadata>),
+    // Balances merkle hash at the requested topoheight
+    MerkleHash(Hash)
 }
 
 impl StepResponse {
@@ -326,7 +342,8 @@ impl StepResponse {
             Self::Keys(_, _) => StepKind::Keys,
             Self::Balances(_) => StepKind::Balances,
             Self::Nonces(_) => StepKind::Nonces,
-            Self::BlocksMetadata(_) => StepKind::BlocksMetadata
+            Self::BlocksMetadata(_) => StepKind::BlocksMetadata,
+            Self::MerkleHash(_) => StepKind::MerkleHash
         }
     }
 }
@@ -373,6 +390,9 @@ impl Serializer for StepResponse {
             5 => {
                 Self::BlocksMetadata(IndexSet::read(reader)?)
             },
+            6 => {
+                Self::MerkleHash(reader.read_hash()?)
+            },
             id => {
                 debug!("Received invalid value for StepResponse: {}", id);
                 return Err(ReaderError::InvalidValue)
@@ -410,6 +430,10 @@ impl Serializer for StepResponse {
             Self::BlocksMetadata(blocks) => {
                 writer.write_u8(5);
                 blocks.write(writer);
+            },
+            Self::MerkleHash(hash) => {
+                writer.write_u8(6);
+                writer.write_hash(hash);
             }
         };
     }
@@ -433,6 +457,9 @@ impl Serializer for StepResponse {
             },
             Self::BlocksMetadata(blocks) => {
                 blocks.size()
+            },
+            Self::MerkleHash(hash) => {
+                hash.size()
             }
         };
         // 1 for the id
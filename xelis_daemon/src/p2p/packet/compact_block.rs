@@ -0,0 +1,287 @@
+use indexmap::IndexSet;
+use log::debug;
+use xelis_common::{
+    block::BlockHeader,
+    crypto::{Hash, HASH_SIZE},
+    serializer::{
+        Reader,
+        ReaderError,
+        Serializer,
+        Writer
+    },
+    transaction::Transaction
+};
+
+// Short id of a TX in a compact block
+pub type ShortTxId = u64;
+
+// Compute the key used to derive the short ids of a block
+// Salted with a random value so nobody can create TXs colliding in advance
+fn get_short_ids_key(hash: &Hash, salt: u64) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(hash.as_bytes());
+    hasher.update(&salt.to_be_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+// A compact block is a block header with its TXs hashes replaced by short ids
+// The receiver rebuilds the block using the TXs from its mempool
+// and only requests the missing ones in a single batch
+#[derive(Clone, Debug)]
+pub struct CompactBlock {
+    // Block header without its TXs hashes
+    header: BlockHeader,
+    // Hash of the full block, verified once rebuilt
+    hash: Hash,
+    // Random value used to derive the short ids
+    salt: u64,
+    // Short ids of the TXs in the same order as in the block
+    short_ids: IndexSet<ShortTxId>,
+    // Key derived from the hash and the salt, not serialized
+    key: [u8; 32]
+}
+
+impl CompactBlock {
+    // Build a compact block from a full block header
+    // Returns None if the block has no TXs or if two short ids are colliding
+    pub fn new(header: &BlockHeader, hash: Hash) -> Option<Self> {
+        if header.get_txs_count() == 0 {
+            return None;
+        }
+
+        let salt: u64 = rand::random();
+        let key = get_short_ids_key(&hash, salt);
+        let mut short_ids = IndexSet::with_capacity(header.get_txs_count());
+        for tx in header.get_txs_hashes() {
+            if !short_ids.insert(Self::compute_short_id(&key, tx)) {
+                debug!("Short id collision for TX {} in block {}", tx, hash);
+                return None;
+            }
+        }
+
+        let mut header = header.clone();
+        header.txs_hashes = IndexSet::new();
+
+        Some(Self {
+            header,
+            hash,
+            salt,
+            short_ids,
+            key
+        })
+    }
+
+    fn compute_short_id(key: &[u8; 32], tx: &Hash) -> ShortTxId {
+        let hash = blake3::keyed_hash(key, tx.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash.as_bytes()[0..8]);
+        ShortTxId::from_be_bytes(bytes)
+    }
+
+    // Get the short id of a TX for this block
+    pub fn get_short_id(&self, tx: &Hash) -> ShortTxId {
+        Self::compute_short_id(&self.key, tx)
+    }
+
+    // Get the short ids of the block TXs
+    pub fn get_short_ids(&self) -> &IndexSet<ShortTxId> {
+        &self.short_ids
+    }
+
+    // Get the hash of the full block
+    pub fn get_hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn get_height(&self) -> u64 {
+        self.header.get_height()
+    }
+
+    // Rebuild the full block header using the TXs hashes found
+    pub fn into_header(self, txs_hashes: IndexSet<Hash>) -> BlockHeader {
+        let mut header = self.header;
+        header.txs_hashes = txs_hashes;
+        header
+    }
+}
+
+impl Serializer for CompactBlock {
+    fn write(&self, writer: &mut Writer) {
+        self.header.write(writer);
+        writer.write_hash(&self.hash);
+        writer.write_u64(&self.salt);
+        writer.write_u16(self.short_ids.len() as u16);
+        for short_id in &self.short_ids {
+            writer.write_u64(short_id);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let header = BlockHeader::read(reader)?;
+        // TXs hashes are replaced by the short ids
+        if header.get_txs_count() != 0 {
+            return Err(ReaderError::InvalidValue);
+        }
+
+        let hash = reader.read_hash()?;
+        let salt = reader.read_u64()?;
+        let count = reader.read_u16()?;
+        if count == 0 {
+            return Err(ReaderError::InvalidValue);
+        }
+
+        // Don't trust the count for the allocation, it grows as the items are read
+        let mut short_ids = IndexSet::new();
+        for _ in 0..count {
+            if !short_ids.insert(reader.read_u64()?) {
+                debug!("Error, duplicate short id found in compact block");
+                return Err(ReaderError::InvalidValue);
+            }
+        }
+
+        let key = get_short_ids_key(&hash, salt);
+        Ok(Self {
+            header,
+            hash,
+            salt,
+            short_ids,
+            key
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.header.size() + HASH_SIZE + self.salt.size() + 2 + self.short_ids.len() * 8
+    }
+}
+
+// Request the TXs missing to rebuild a compact block
+#[derive(Clone, Debug)]
+pub struct BlockTransactionsRequest {
+    // Hash of the compact block
+    hash: Hash,
+    // Indexes of the TXs in the block
+    indexes: IndexSet<u16>
+}
+
+impl BlockTransactionsRequest {
+    pub fn new(hash: Hash, indexes: IndexSet<u16>) -> Self {
+        Self {
+            hash,
+            indexes
+        }
+    }
+
+    pub fn get_hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn get_indexes(&self) -> &IndexSet<u16> {
+        &self.indexes
+    }
+}
+
+impl Serializer for BlockTransactionsRequest {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.hash);
+        writer.write_u16(self.indexes.len() as u16);
+        for index in &self.indexes {
+            writer.write_u16(*index);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let hash = reader.read_hash()?;
+        let count = reader.read_u16()?;
+        // Don't trust the count for the allocation, it grows as the items are read
+        let mut indexes = IndexSet::new();
+        for _ in 0..count {
+            if !indexes.insert(reader.read_u16()?) {
+                return Err(ReaderError::InvalidValue);
+            }
+        }
+
+        Ok(Self::new(hash, indexes))
+    }
+
+    fn size(&self) -> usize {
+        HASH_SIZE + 2 + self.indexes.len() * 2
+    }
+}
+
+// TXs requested for a compact block, in the same order as the requested indexes
+// Empty if the block is not found
+#[derive(Debug)]
+pub struct BlockTransactionsResponse {
+    hash: Hash,
+    txs: Vec<Transaction>
+}
+
+impl BlockTransactionsResponse {
+    pub fn new(hash: Hash, txs: Vec<Transaction>) -> Self {
+        Self {
+            hash,
+            txs
+        }
+    }
+
+    pub fn get_hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn take_txs(self) -> Vec<Transaction> {
+        self.txs
+    }
+}
+
+impl Serializer for BlockTransactionsResponse {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_hash(&self.hash);
+        writer.write_u16(self.txs.len() as u16);
+        for tx in &self.txs {
+            tx.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        let hash = reader.read_hash()?;
+        let count = reader.read_u16()?;
+        // Don't trust the count for the allocation, it grows as the items are read
+        let mut txs = Vec::new();
+        for _ in 0..count {
+            txs.push(Transaction::read(reader)?);
+        }
+
+        Ok(Self::new(hash, txs))
+    }
+
+    fn size(&self) -> usize {
+        HASH_SIZE + 2 + self.txs.iter().map(|tx| tx.size()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{
+        block::BlockVersion,
+        crypto::{hash, Hashable, KeyPair}
+    };
+    use super::*;
+
+    #[test]
+    fn test_compact_block() {
+        let miner = KeyPair::new().get_public_key().compress();
+        let txs_hashes: IndexSet<Hash> = (0..10u8).map(|i| hash(&[i])).collect();
+        let header = BlockHeader::new(BlockVersion::V0, 1, 0, IndexSet::new(), [0u8; 32], miner, txs_hashes.clone());
+        let block_hash = header.hash();
+
+        let compact = CompactBlock::new(&header, block_hash.clone()).unwrap();
+        let compact = CompactBlock::from_bytes(&compact.to_bytes()).unwrap();
+        assert_eq!(compact.get_short_ids().len(), txs_hashes.len());
+        for (tx, short_id) in txs_hashes.iter().zip(compact.get_short_ids()) {
+            assert_eq!(compact.get_short_id(tx), *short_id);
+        }
+
+        let rebuilt = compact.into_header(txs_hashes);
+        assert_eq!(rebuilt.hash(), block_hash);
+    }
+}
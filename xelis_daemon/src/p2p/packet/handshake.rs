@@ -17,6 +17,45 @@ use std::{
     fmt::{Display, Error, Formatter}
 };
 
+// Optional features supported by a node, advertised in its handshake
+// A feature is only used with the peers that advertised it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities(u64);
+
+impl Capabilities {
+    // Peer answers the merkle hash bootstrap step
+    pub const MERKLE_HASH: Self = Self(1 << 0);
+    // Peer rebuilds blocks propagated in compact form
+    pub const COMPACT_BLOCKS: Self = Self(1 << 1);
+    // Peer handles TXs relayed in Dandelion stem phase
+    pub const STEM_TRANSACTIONS: Self = Self(1 << 2);
+
+    // Check if all the features given are supported
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // Add the features given
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl Serializer for Capabilities {
+    // Unknown features are kept, they may come from a newer version
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u64(&self.0);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
+        Ok(Self(reader.read_u64()?))
+    }
+
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+}
+
 // this Handshake is the first data sent when connecting to the server
 // If handshake is valid, server reply with his own handshake
 // We just have to repeat this request to all peers until we reach max connection
@@ -52,13 +91,15 @@ pub struct Handshake<'a> {
     cumulative_difficulty: Cow<'a, CumulativeDifficulty>,
     // By default it's true, and peer allow to be shared to others and/or through API
     // If false, we must not share it
-    can_be_shared: bool
+    can_be_shared: bool,
+    // optional features supported by the node
+    capabilities: Capabilities
 } // Server reply with his own list of peers, but we remove all already known by requester for the response.
 
 impl<'a> Handshake<'a> {
     pub const MAX_LEN: usize = 16;
 
-    pub fn new(version: Cow<'a, String>, network: Network, node_tag: Cow<'a, Option<String>>, network_id: Cow<'a, [u8; 16]>, peer_id: u64, local_port: u16, utc_time: TimestampSeconds, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, top_hash: Cow<'a, Hash>, genesis_hash: Cow<'a, Hash>, cumulative_difficulty: Cow<'a, CumulativeDifficulty>, can_be_shared: bool) -> Self {
+    pub fn new(version: Cow<'a, String>, network: Network, node_tag: Cow<'a, Option<String>>, network_id: Cow<'a, [u8; 16]>, peer_id: u64, local_port: u16, utc_time: TimestampSeconds, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, top_hash: Cow<'a, Hash>, genesis_hash: Cow<'a, Hash>, cumulative_difficulty: Cow<'a, CumulativeDifficulty>, can_be_shared: bool, capabilities: Capabilities) -> Self {
         debug_assert!(version.len() > 0 && version.len() <= Handshake::MAX_LEN);
         // version cannot be greater than 16 chars
         if let Some(node_tag) = node_tag.as_ref() {
@@ -80,14 +121,15 @@ impl<'a> Handshake<'a> {
             top_hash,
             genesis_hash,
             cumulative_difficulty,
-            can_be_shared
+            can_be_shared,
+            capabilities
         }
     }
 
     // Create a new peer using its connection and this handshake packet
     pub fn create_peer(self, connection: Connection, priority: bool, peer_list: SharedPeerList) -> (Peer, Rx) {
         let peers = HashSet::new();
-        Peer::new(connection, self.get_peer_id(), self.node_tag.into_owned(), self.local_port, self.version.into_owned(), self.top_hash.into_owned(), self.topoheight, self.height, self.pruned_topoheight, priority, self.cumulative_difficulty.into_owned(), peer_list, peers, self.can_be_shared)
+        Peer::new(connection, self.get_peer_id(), self.node_tag.into_owned(), self.local_port, self.version.into_owned(), self.top_hash.into_owned(), self.topoheight, self.height, self.pruned_topoheight, priority, self.cumulative_difficulty.into_owned(), peer_list, peers, self.can_be_shared, self.capabilities)
     }
 
     pub fn get_local_port(&self) -> u16 {
@@ -140,7 +182,7 @@ impl<'a> Handshake<'a> {
 }
 
 impl Serializer for Handshake<'_> {
-    // 1 + MAX(16) + 1 + MAX(16) + 16 + 8 + 8 + 8 + 32 + 1 + 24 * 16 + 1
+    // 1 + MAX(16) + 1 + MAX(16) + 16 + 8 + 8 + 8 + 32 + 1 + 24 * 16 + 1 + 8
     fn write(&self, writer: &mut Writer) {
         // daemon version
         writer.write_string(&self.version);
@@ -162,6 +204,7 @@ impl Serializer for Handshake<'_> {
         writer.write_hash(&self.genesis_hash); // Genesis Hash
         self.cumulative_difficulty.write(writer); // Cumulative Difficulty
         writer.write_bool(self.can_be_shared); // Can be shared
+        self.capabilities.write(writer); // Capabilities
     }
 
     fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
@@ -203,8 +246,9 @@ impl Serializer for Handshake<'_> {
         let genesis_hash = reader.read_hash()?;
         let cumulative_difficulty = CumulativeDifficulty::read(reader)?;
         let can_be_shared = reader.read_bool()?;
+        let capabilities = Capabilities::read(reader)?;
 
-        Ok(Handshake::new(Cow::Owned(version), network, Cow::Owned(node_tag), Cow::Owned(network_id), peer_id, local_port, utc_time, topoheight, height, pruned_topoheight, Cow::Owned(top_hash), Cow::Owned(genesis_hash), Cow::Owned(cumulative_difficulty), can_be_shared))
+        Ok(Handshake::new(Cow::Owned(version), network, Cow::Owned(node_tag), Cow::Owned(network_id), peer_id, local_port, utc_time, topoheight, height, pruned_topoheight, Cow::Owned(top_hash), Cow::Owned(genesis_hash), Cow::Owned(cumulative_difficulty), can_be_shared, capabilities))
     }
 
     fn size(&self) -> usize {
@@ -235,7 +279,9 @@ impl Serializer for Handshake<'_> {
         // Cumulative Difficulty
         self.cumulative_difficulty.size() +
         // Can be shared
-        self.can_be_shared.size()
+        self.can_be_shared.size() +
+        // Capabilities
+        self.capabilities.size()
     }
 }
 
@@ -5,8 +5,10 @@ pub mod object;
 pub mod inventory;
 pub mod bootstrap_chain;
 pub mod peer_disconnected;
+pub mod compact_block;
 
 use self::bootstrap_chain::{BootstrapChainRequest, BootstrapChainResponse};
+use self::compact_block::{BlockTransactionsRequest, BlockTransactionsResponse, CompactBlock};
 use self::inventory::{NotifyInventoryResponse, NotifyInventoryRequest};
 use self::object::{ObjectRequest, ObjectResponse};
 use self::chain::{ChainRequest, ChainResponse};
@@ -18,7 +20,8 @@ use log::{debug, trace};
 use xelis_common::{
     serializer::{Serializer, Reader, ReaderError, Writer},
     block::BlockHeader,
-    crypto::Hash
+    crypto::Hash,
+    transaction::Transaction
 };
 
 use super::EncryptionKey;
@@ -28,16 +31,47 @@ const KEY_EXCHANGE_ID: u8 = 0;
 const HANDSHAKE_ID: u8 = 1;
 const TX_PROPAGATION_ID: u8 = 2;
 const BLOCK_PROPAGATION_ID: u8 = 3;
-const CHAIN_REQUEST_ID: u8 = 4;
+pub const CHAIN_REQUEST_ID: u8 = 4;
 const CHAIN_RESPONSE_ID: u8 = 5;
 const PING_ID: u8 = 6;
-const OBJECT_REQUEST_ID: u8 = 7;
+pub const OBJECT_REQUEST_ID: u8 = 7;
 const OBJECT_RESPONSE_ID: u8 = 8;
 const NOTIFY_INV_REQUEST_ID: u8 = 9; 
 const NOTIFY_INV_RESPONSE_ID: u8 = 10;
-const BOOTSTRAP_CHAIN_REQUEST_ID: u8 = 11;
+pub const BOOTSTRAP_CHAIN_REQUEST_ID: u8 = 11;
 const BOOTSTRAP_CHAIN_RESPONSE_ID: u8 = 12;
 const PEER_DISCONNECTED_ID: u8 = 13;
+const COMPACT_BLOCK_PROPAGATION_ID: u8 = 14;
+const BLOCK_TXS_REQUEST_ID: u8 = 15;
+const BLOCK_TXS_RESPONSE_ID: u8 = 16;
+const STEM_TX_ID: u8 = 17;
+// Count of registered packet ids
+pub const PACKETS_COUNT: usize = STEM_TX_ID as usize + 1;
+
+// Get the name of a packet id, used in the bandwidth stats
+pub fn get_packet_name(id: u8) -> &'static str {
+    match id {
+        KEY_EXCHANGE_ID => "key_exchange",
+        HANDSHAKE_ID => "handshake",
+        TX_PROPAGATION_ID => "transaction_propagation",
+        BLOCK_PROPAGATION_ID => "block_propagation",
+        CHAIN_REQUEST_ID => "chain_request",
+        CHAIN_RESPONSE_ID => "chain_response",
+        PING_ID => "ping",
+        OBJECT_REQUEST_ID => "object_request",
+        OBJECT_RESPONSE_ID => "object_response",
+        NOTIFY_INV_REQUEST_ID => "notify_inventory_request",
+        NOTIFY_INV_RESPONSE_ID => "notify_inventory_response",
+        BOOTSTRAP_CHAIN_REQUEST_ID => "bootstrap_chain_request",
+        BOOTSTRAP_CHAIN_RESPONSE_ID => "bootstrap_chain_response",
+        PEER_DISCONNECTED_ID => "peer_disconnected",
+        COMPACT_BLOCK_PROPAGATION_ID => "compact_block_propagation",
+        BLOCK_TXS_REQUEST_ID => "block_transactions_request",
+        BLOCK_TXS_RESPONSE_ID => "block_transactions_response",
+        STEM_TX_ID => "stem_transaction",
+        _ => "unknown"
+    }
+}
 
 // PacketWrapper allows us to link any Packet to a Ping
 #[derive(Debug)]
@@ -97,6 +131,13 @@ pub enum Packet<'a> {
     BootstrapChainRequest(BootstrapChainRequest<'a>),
     BootstrapChainResponse(BootstrapChainResponse),
     PeerDisconnected(PacketPeerDisconnected),
+    // block propagated with short TXs ids, rebuilt from the mempool
+    CompactBlockPropagation(PacketWrapper<'a, CompactBlock>),
+    // TXs missing to rebuild a compact block
+    BlockTransactionsRequest(BlockTransactionsRequest),
+    BlockTransactionsResponse(BlockTransactionsResponse),
+    // TX relayed to a single peer during its Dandelion stem phase
+    StemTransaction(Cow<'a, Transaction>),
     // Encryption
     KeyExchange(Cow<'a, EncryptionKey>),
 }
@@ -117,6 +158,10 @@ impl Packet<'_> {
             Packet::BootstrapChainRequest(_) => BOOTSTRAP_CHAIN_REQUEST_ID,
             Packet::BootstrapChainResponse(_) => BOOTSTRAP_CHAIN_RESPONSE_ID,
             Packet::PeerDisconnected(_) => PEER_DISCONNECTED_ID,
+            Packet::CompactBlockPropagation(_) => COMPACT_BLOCK_PROPAGATION_ID,
+            Packet::BlockTransactionsRequest(_) => BLOCK_TXS_REQUEST_ID,
+            Packet::BlockTransactionsResponse(_) => BLOCK_TXS_RESPONSE_ID,
+            Packet::StemTransaction(_) => STEM_TX_ID,
             Packet::KeyExchange(_) => KEY_EXCHANGE_ID,
         }
     }
@@ -141,6 +186,10 @@ impl<'a> Serializer for Packet<'a> {
             BOOTSTRAP_CHAIN_REQUEST_ID => Packet::BootstrapChainRequest(BootstrapChainRequest::read(reader)?),
             BOOTSTRAP_CHAIN_RESPONSE_ID => Packet::BootstrapChainResponse(BootstrapChainResponse::read(reader)?),
             PEER_DISCONNECTED_ID => Packet::PeerDisconnected(PacketPeerDisconnected::read(reader)?),
+            COMPACT_BLOCK_PROPAGATION_ID => Packet::CompactBlockPropagation(PacketWrapper::read(reader)?),
+            BLOCK_TXS_REQUEST_ID => Packet::BlockTransactionsRequest(BlockTransactionsRequest::read(reader)?),
+            BLOCK_TXS_RESPONSE_ID => Packet::BlockTransactionsResponse(BlockTransactionsResponse::read(reader)?),
+            STEM_TX_ID => Packet::StemTransaction(Cow::Owned(Transaction::read(reader)?)),
             id => {
                 debug!("invalid packet id received: {}", id);
                 return Err(ReaderError::InvalidValue)
@@ -170,6 +219,10 @@ impl<'a> Serializer for Packet<'a> {
             Packet::BootstrapChainRequest(request) => (BOOTSTRAP_CHAIN_REQUEST_ID, request),
             Packet::BootstrapChainResponse(response) => (BOOTSTRAP_CHAIN_RESPONSE_ID, response),
             Packet::PeerDisconnected(disconnected) => (PEER_DISCONNECTED_ID, disconnected),
+            Packet::CompactBlockPropagation(block) => (COMPACT_BLOCK_PROPAGATION_ID, block),
+            Packet::BlockTransactionsRequest(request) => (BLOCK_TXS_REQUEST_ID, request),
+            Packet::BlockTransactionsResponse(response) => (BLOCK_TXS_RESPONSE_ID, response),
+            Packet::StemTransaction(tx) => (STEM_TX_ID, tx.as_ref()),
         };
 
         let packet = serializer.to_bytes();
@@ -19,6 +19,7 @@ use crate::{
     p2p::{
         error::P2pError,
         peer::Peer,
+        proxy::{get_hostname_address, parse_hostname},
         is_local_address,
     },
     rpc::rpc::get_peer_entry
@@ -38,7 +39,10 @@ pub struct Ping<'a> {
     height: u64,
     pruned_topoheight: Option<u64>,
     cumulative_difficulty: CumulativeDifficulty,
-    peer_list: IndexSet<SocketAddr>
+    peer_list: IndexSet<SocketAddr>,
+    // Peers reachable only by their hostname through a proxy, like onion addresses
+    // Sent only when not empty, so it stays compatible with the previous format
+    hostnames: IndexSet<String>
 }
 
 impl<'a> Ping<'a> {
@@ -49,7 +53,8 @@ impl<'a> Ping<'a> {
             height,
             pruned_topoheight,
             cumulative_difficulty,
-            peer_list
+            peer_list,
+            hostnames: IndexSet::new()
         }
     }
 
@@ -89,6 +94,24 @@ impl<'a> Ping<'a> {
             trace!("End locking for PeerStateUpdated event");
         }
 
+        if !self.hostnames.is_empty() {
+            debug!("Received hostnames ({:?}) for {}", self.hostnames, peer.get_outgoing_address());
+            let mut shared_peers = peer.get_peers().lock().await;
+            for hostname in &self.hostnames {
+                // Hostnames are tracked using their placeholder address
+                let (host, port) = parse_hostname(hostname)?;
+                let addr = get_hostname_address(&host, port);
+                if let Some(direction) = shared_peers.get_mut(&addr) {
+                    if !direction.update(Direction::In) {
+                        let d = *direction;
+                        return Err(P2pError::DuplicatedPeer(addr, *peer.get_outgoing_address(), d))
+                    }
+                } else {
+                    shared_peers.insert(addr, Direction::In);
+                }
+            }
+        }
+
         if !self.peer_list.is_empty() {
             debug!("Received a peer list ({:?}) for {}", self.peer_list, peer.get_outgoing_address());
             let mut shared_peers = peer.get_peers().lock().await;
@@ -149,6 +172,14 @@ impl<'a> Ping<'a> {
     pub fn get_mut_peers(&mut self) -> &mut IndexSet<SocketAddr> {
         &mut self.peer_list
     }
+
+    pub fn get_hostnames(&self) -> &IndexSet<String> {
+        &self.hostnames
+    }
+
+    pub fn get_mut_hostnames(&mut self) -> &mut IndexSet<String> {
+        &mut self.hostnames
+    }
 }
 
 impl Serializer for Ping<'_> {
@@ -162,6 +193,13 @@ impl Serializer for Ping<'_> {
         for peer in &self.peer_list {
             peer.write(writer);
         }
+
+        if !self.hostnames.is_empty() {
+            writer.write_u8(self.hostnames.len() as u8);
+            for hostname in &self.hostnames {
+                writer.write_string(hostname);
+            }
+        }
     }
 
     fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
@@ -191,7 +229,25 @@ impl Serializer for Ping<'_> {
             }
         }
 
-        Ok(Self { top_hash, topoheight, height, pruned_topoheight, cumulative_difficulty, peer_list })
+        // Ping is always the last part of a packet
+        let mut hostnames = IndexSet::new();
+        if reader.size() > 0 {
+            let hostnames_len = reader.read_u8()? as usize;
+            if hostnames_len == 0 || hostnames_len > P2P_PING_PEER_LIST_LIMIT {
+                debug!("Invalid hostnames count in ping packet: {}", hostnames_len);
+                return Err(ReaderError::InvalidValue)
+            }
+
+            for _ in 0..hostnames_len {
+                let hostname = reader.read_string()?;
+                if parse_hostname(&hostname).is_err() || !hostnames.insert(hostname) {
+                    debug!("Invalid or duplicated hostname in ping packet");
+                    return Err(ReaderError::InvalidValue)
+                }
+            }
+        }
+
+        Ok(Self { top_hash, topoheight, height, pruned_topoheight, cumulative_difficulty, peer_list, hostnames })
     }
 
     fn size(&self) -> usize {
@@ -202,7 +258,8 @@ impl Serializer for Ping<'_> {
         self.cumulative_difficulty.size() +
         // u8 for the length of the peer list
         1 +
-        self.peer_list.iter().map(|p| p.size()).sum::<usize>()
+        self.peer_list.iter().map(|p| p.size()).sum::<usize>() +
+        if self.hostnames.is_empty() { 0 } else { 1 + self.hostnames.iter().map(|h| 1 + h.len()).sum::<usize>() }
     }
 }
 
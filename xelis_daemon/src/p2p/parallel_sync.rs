@@ -0,0 +1,91 @@
+use std::{collections::HashSet, sync::Arc};
+use indexmap::IndexSet;
+use xelis_common::{
+    block::BlockHeader,
+    crypto::Hash
+};
+
+// Range of consecutive blocks downloaded from a single peer during a header-first sync
+// Its headers are already validated, only the TXs of the blocks are requested
+pub struct BlocksRange {
+    // Blocks headers in topological order
+    headers: Vec<(Hash, Arc<BlockHeader>)>,
+    // TXs to request for this range
+    // A TX included in blocks of several ranges is only requested by the first one
+    txs: IndexSet<Hash>,
+    // Highest block height of the range, a peer below it can't have all the blocks
+    max_height: u64
+}
+
+impl BlocksRange {
+    // Split the validated headers in ranges of N blocks, keeping their order
+    pub fn split(headers: Vec<(Hash, Arc<BlockHeader>)>, size: usize) -> Vec<Self> {
+        let mut seen_txs = HashSet::new();
+        let mut ranges = Vec::new();
+        let mut iter = headers.into_iter().peekable();
+        while iter.peek().is_some() {
+            let headers: Vec<(Hash, Arc<BlockHeader>)> = iter.by_ref().take(size.max(1)).collect();
+            let txs = headers.iter()
+                .flat_map(|(_, header)| header.get_txs_hashes())
+                .filter(|tx| seen_txs.insert((*tx).clone()))
+                .cloned()
+                .collect();
+            let max_height = headers.iter()
+                .map(|(_, header)| header.get_height())
+                .max()
+                .unwrap_or(0);
+
+            ranges.push(Self {
+                headers,
+                txs,
+                max_height
+            });
+        }
+
+        ranges
+    }
+
+    pub fn get_txs(&self) -> &IndexSet<Hash> {
+        &self.txs
+    }
+
+    pub fn get_max_height(&self) -> u64 {
+        self.max_height
+    }
+
+    pub fn take_headers(self) -> Vec<(Hash, Arc<BlockHeader>)> {
+        self.headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xelis_common::{
+        block::BlockVersion,
+        crypto::{hash, Hashable, KeyPair}
+    };
+    use super::*;
+
+    #[test]
+    fn test_split_ranges() {
+        let miner = KeyPair::new().get_public_key().compress();
+        let headers: Vec<(Hash, Arc<BlockHeader>)> = (0..5u64).map(|height| {
+            // Each block shares one TX with the previous one
+            let txs_hashes = IndexSet::from([hash(&height.to_be_bytes()), hash(&(height + 1).to_be_bytes())]);
+            let header = BlockHeader::new(BlockVersion::V0, height, 0, IndexSet::new(), [0u8; 32], miner.clone(), txs_hashes);
+            (header.hash(), Arc::new(header))
+        }).collect();
+
+        let ranges = BlocksRange::split(headers, 2);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges.iter().map(|range| range.get_max_height()).collect::<Vec<_>>(), vec![1, 3, 4]);
+        // First range requests its 3 TXs, the next ones only their new TXs
+        assert_eq!(ranges.iter().map(|range| range.get_txs().len()).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        let heights: Vec<u64> = ranges.into_iter()
+            .flat_map(|range| range.take_headers())
+            .map(|(_, header)| header.get_height())
+            .collect();
+        assert_eq!(heights, vec![0, 1, 2, 3, 4]);
+    }
+}
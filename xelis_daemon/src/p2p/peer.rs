@@ -3,7 +3,8 @@ use crate::{
         PEER_FAIL_TIME_RESET, PEER_BLOCK_CACHE_SIZE, PEER_TX_CACHE_SIZE,
         PEER_TEMP_BAN_TIME, PEER_TIMEOUT_BOOTSTRAP_STEP,
         PEER_TIMEOUT_REQUEST_OBJECT, CHAIN_SYNC_TIMEOUT_SECS,
-        PEER_PACKET_CHANNEL_SIZE
+        PEER_PACKET_CHANNEL_SIZE, PEER_SCORE_MAX, PEER_SCORE_MIN,
+        PEER_SCORE_TIMEOUT
     },
     p2p::packet::PacketWrapper
 };
@@ -14,6 +15,7 @@ use xelis_common::{
     serializer::Serializer,
     time::{
         TimestampSeconds,
+        get_current_time_in_millis,
         get_current_time_in_seconds
     }
 };
@@ -28,12 +30,18 @@ use super::{
             ChainRequest,
             ChainResponse
         },
+        compact_block::{
+            BlockTransactionsRequest,
+            BlockTransactionsResponse
+        },
         object::{
             ObjectRequest,
             OwnedObjectResponse
         },
+        handshake::Capabilities,
         Packet
     },
+    bandwidth::{Bandwidth, TokenBucket},
     peer_list::SharedPeerList,
     connection::Connection,
     error::P2pError
@@ -45,7 +53,7 @@ use std::{
     fmt::{Display, Error, Formatter},
     hash::{Hash as StdHash, Hasher},
     net::{IpAddr, SocketAddr},
-    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering},
     time::Duration
 };
 use tokio::{
@@ -111,6 +119,8 @@ pub struct Peer {
     last_fail_count: AtomicU64,
     // fail count: if greater than 20, we should close this connection
     fail_count: AtomicU8,
+    // score based on the usefulness of the peer, restored from the stored peerlist
+    score: AtomicI32,
     // shared pointer to the peer list in case of disconnection
     peer_list: SharedPeerList,
     // map of requested objects from this peer
@@ -142,10 +152,18 @@ pub struct Peer {
     bootstrap_chain: Mutex<Option<Sender<StepResponse>>>,
     // used to wait on chain response when syncing chain
     sync_chain: Mutex<Option<Sender<ChainResponse>>>,
+    // used to wait on the missing TXs of compact blocks
+    block_transactions: Mutex<HashMap<Hash, Sender<BlockTransactionsResponse>>>,
+    // traffic with this peer by packet id
+    bandwidth: Bandwidth,
+    // token buckets limiting the expensive requests by packet id
+    requests_limits: Mutex<HashMap<u8, TokenBucket>>,
     // IP address with local port
     outgoing_address: SocketAddr,
     // Determine if this peer allows to be shared to others and/or through API
     sharable: bool,
+    // optional features supported by the peer
+    capabilities: Capabilities,
     // Channel to send bytes to the writer task
     tx: Tx,
     // Channel to notify the tasks to exit
@@ -156,7 +174,7 @@ pub struct Peer {
 }
 
 impl Peer {
-    pub fn new(connection: Connection, id: u64, node_tag: Option<String>, local_port: u16, version: String, top_hash: Hash, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, priority: bool, cumulative_difficulty: CumulativeDifficulty, peer_list: SharedPeerList, peers_received: HashSet<SocketAddr>, sharable: bool) -> (Self, Rx) {
+    pub fn new(connection: Connection, id: u64, node_tag: Option<String>, local_port: u16, version: String, top_hash: Hash, topoheight: u64, height: u64, pruned_topoheight: Option<u64>, priority: bool, cumulative_difficulty: CumulativeDifficulty, peer_list: SharedPeerList, peers_received: HashSet<SocketAddr>, sharable: bool, capabilities: Capabilities) -> (Self, Rx) {
         let mut outgoing_address = *connection.get_address();
         outgoing_address.set_port(local_port);
 
@@ -180,6 +198,7 @@ impl Peer {
             priority,
             last_fail_count: AtomicU64::new(0),
             fail_count: AtomicU8::new(0),
+            score: AtomicI32::new(0),
             last_chain_sync: AtomicU64::new(0),
             peer_list,
             objects_requested: Mutex::new(HashMap::new()),
@@ -196,8 +215,12 @@ impl Peer {
             is_pruned: AtomicBool::new(pruned_topoheight.is_some()),
             bootstrap_chain: Mutex::new(None),
             sync_chain: Mutex::new(None),
+            block_transactions: Mutex::new(HashMap::new()),
+            bandwidth: Bandwidth::new(),
+            requests_limits: Mutex::new(HashMap::new()),
             outgoing_address,
             sharable,
+            capabilities,
             exit_channel,
             tx,
             read_task: Mutex::new(TaskState::Inactive),
@@ -225,6 +248,21 @@ impl Peer {
         &self.blocks_propagation
     }
 
+    // Get the traffic with this peer
+    pub fn get_bandwidth(&self) -> &Bandwidth {
+        &self.bandwidth
+    }
+
+    // Consume a request from the token bucket of this packet id
+    // Returns false if the peer sent more than the limit per minute
+    pub async fn consume_request(&self, packet_id: u8, per_minute: u32) -> bool {
+        let now = get_current_time_in_millis();
+        let mut requests_limits = self.requests_limits.lock().await;
+        requests_limits.entry(packet_id)
+            .or_insert_with(|| TokenBucket::new(per_minute, now))
+            .try_consume(now)
+    }
+
     // Get its connection object to manage p2p communication
     pub fn get_connection(&self) -> &Connection {
         &self.connection
@@ -250,6 +288,11 @@ impl Peer {
         &self.version
     }
 
+    // Check if the peer supports the features given
+    pub fn has_capability(&self, capabilities: Capabilities) -> bool {
+        self.capabilities.contains(capabilities)
+    }
+
     // Get the topoheight of the peer
     pub fn get_topoheight(&self) -> u64 {
         self.topoheight.load(Ordering::Acquire)
@@ -377,6 +420,30 @@ impl Peer {
         self.set_last_fail_count(current_time);
     }
 
+    // Get the score of the peer
+    pub fn get_score(&self) -> i32 {
+        self.score.load(Ordering::Acquire)
+    }
+
+    // Set the score of the peer, used to restore it from the stored peerlist
+    pub fn set_score(&self, score: i32) {
+        self.score.store(score.clamp(PEER_SCORE_MIN, PEER_SCORE_MAX), Ordering::Release);
+    }
+
+    // Reward or penalize the peer, the score stays in its bounds
+    pub fn update_score(&self, delta: i32) {
+        trace!("Updating score of {} by {}", self.get_outgoing_address(), delta);
+        let _ = self.score.fetch_update(Ordering::AcqRel, Ordering::Acquire, |score| {
+            Some(score.saturating_add(delta).clamp(PEER_SCORE_MIN, PEER_SCORE_MAX))
+        });
+    }
+
+    // Get the weight of the peer for a random selection
+    // A peer at the minimum score still has a small chance to be selected
+    pub fn get_score_weight(&self) -> u64 {
+        (self.get_score() - PEER_SCORE_MIN) as u64 + 1
+    }
+
     // Get the last time we got a chain sync request
     // This is used to prevent spamming the chain sync packet
     pub fn get_last_chain_sync(&self) -> TimestampSeconds {
@@ -422,6 +489,7 @@ impl Peer {
             Ok(res) => res?,
             Err(e) => {
                 trace!("Requested data has timed out");
+                self.update_score(PEER_SCORE_TIMEOUT);
                 let mut objects = self.objects_requested.lock().await;
                 objects.remove(&request); // remove it from request list
                 return Err(P2pError::AsyncTimeOut(e));
@@ -460,6 +528,7 @@ impl Peer {
             Ok(res) => res?,
             Err(e) => {
                 debug!("Requested bootstrap chain step {:?} has timed out", step_kind);
+                self.update_score(PEER_SCORE_TIMEOUT);
                 return Err(P2pError::AsyncTimeOut(e));
             }
         };
@@ -490,6 +559,7 @@ impl Peer {
             Ok(res) => res?,
             Err(e) => {
                 debug!("Requested sync chain step timed out");
+                self.update_score(PEER_SCORE_TIMEOUT);
                 return Err(P2pError::AsyncTimeOut(e));
             }
         };
@@ -497,6 +567,42 @@ impl Peer {
         Ok(response)
     }
 
+    // Request the missing TXs of a compact block and wait on them until we receive them or until timeout
+    pub async fn request_block_transactions(&self, request: BlockTransactionsRequest) -> Result<BlockTransactionsResponse, P2pError> {
+        debug!("Requesting {} TXs for compact block {}", request.get_indexes().len(), request.get_hash());
+        let hash = request.get_hash().clone();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        {
+            let mut block_transactions = self.block_transactions.lock().await;
+            if block_transactions.contains_key(&hash) {
+                return Err(P2pError::BlockTransactionsAlreadyRequested(hash));
+            }
+            block_transactions.insert(hash.clone(), sender);
+        }
+
+        if let Err(e) = self.send_packet(Packet::BlockTransactionsRequest(request)).await {
+            self.block_transactions.lock().await.remove(&hash);
+            return Err(e);
+        }
+
+        let response = match timeout(Duration::from_millis(PEER_TIMEOUT_REQUEST_OBJECT), receiver).await {
+            Ok(res) => res?,
+            Err(e) => {
+                debug!("Requested TXs for compact block {} have timed out", hash);
+                self.update_score(PEER_SCORE_TIMEOUT);
+                self.block_transactions.lock().await.remove(&hash);
+                return Err(P2pError::AsyncTimeOut(e));
+            }
+        };
+
+        Ok(response)
+    }
+
+    // Get the channel waiting on the TXs of a compact block
+    pub async fn take_block_transactions_channel(&self, hash: &Hash) -> Option<Sender<BlockTransactionsResponse>> {
+        self.block_transactions.lock().await.remove(hash)
+    }
+
     // Get the bootstrap chain channel
     // Like the sync chain channel, but for bootstrap (fast sync) syncing
     pub fn get_bootstrap_chain_channel(&self) -> &Mutex<Option<Sender<StepResponse>>> {
@@ -671,7 +777,7 @@ impl Display for Peer {
         let read_task = self.read_task.try_lock().map(|v| *v).unwrap_or(TaskState::Unknown);
         let write_task = self.write_task.try_lock().map(|v| *v).unwrap_or(TaskState::Unknown);
 
-        write!(f, "Peer[connection: {}, id: {}, topoheight: {}, top hash: {}, height: {}, pruned: {}, priority: {}, tag: {}, version: {}, fail count: {}, out: {}, peers: {}, tasks: {:?}/{:?}]",
+        write!(f, "Peer[connection: {}, id: {}, topoheight: {}, top hash: {}, height: {}, pruned: {}, priority: {}, tag: {}, version: {}, fail count: {}, score: {}, out: {}, peers: {}, tasks: {:?}/{:?}]",
             self.get_connection(),
             self.get_id(),
             self.get_topoheight(),
@@ -682,6 +788,7 @@ impl Display for Peer {
             self.get_node_tag().as_ref().unwrap_or(&"None".to_owned()),
             self.get_version(),
             self.get_fail_count(),
+            self.get_score(),
             self.is_out(),
             peers,
             read_task,
@@ -2,6 +2,7 @@ use crate::{
     config::{
         PEER_FAIL_TO_CONNECT_LIMIT,
         PEER_TEMP_BAN_TIME_ON_CONNECT,
+        P2P_MAX_OUTGOING_PEERS_PER_GROUP,
         P2P_PEERLIST_RETRY_AFTER
     },
     p2p::packet::peer_disconnected::PacketPeerDisconnected
@@ -10,9 +11,12 @@ use super::{
     disk_cache::{DiskCache, DiskError},
     error::P2pError,
     packet::Packet,
-    peer::Peer
+    peer::Peer,
+    proxy::is_hostname_address,
+    is_local_address
 };
 use std::{
+    cmp::Reverse,
     collections::{HashMap, HashSet},
     fmt::{self, Display, Formatter},
     net::{IpAddr, SocketAddr},
@@ -47,6 +51,36 @@ pub struct PeerList {
     cache: DiskCache
 }
 
+// Network group of an IP address, its IPv4 /16 or IPv6 /32 prefix
+// Addresses in the same group are likely controlled by the same operator
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum NetworkGroup {
+    V4([u8; 2]),
+    V6([u8; 4])
+}
+
+impl NetworkGroup {
+    // Local addresses have no group, they are not limited
+    // Same for the peers known by their hostname as their address is derived from its hash
+    pub fn from_ip(ip: &IpAddr) -> Option<Self> {
+        let addr = SocketAddr::new(*ip, 0);
+        if is_local_address(&addr) || is_hostname_address(&addr) {
+            return None;
+        }
+
+        Some(match ip {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                Self::V4([octets[0], octets[1]])
+            },
+            IpAddr::V6(ip) => {
+                let octets = ip.octets();
+                Self::V6([octets[0], octets[1], octets[2], octets[3]])
+            }
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 enum PeerListEntryState {
     Whitelist,
@@ -54,7 +88,7 @@ enum PeerListEntryState {
     Blacklist,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct PeerListEntry {
     first_seen: Option<TimestampSeconds>,
     last_seen: Option<TimestampSeconds>,
@@ -67,7 +101,11 @@ pub struct PeerListEntry {
     local_port: Option<u16>,
     // Until when the peer is banned
     temp_ban_until: Option<u64>,
-    state: PeerListEntryState
+    state: PeerListEntryState,
+    // Score of the peer at its last disconnection
+    // Peers with a better score are preferred when connecting to a new peer
+    #[serde(default)]
+    score: i32
 }
 
 impl PeerList {
@@ -149,6 +187,12 @@ impl PeerList {
     // Add a new peer to the list
     // This will returns an error if peerlist is full
     pub async fn add_peer(&self, peer: &Arc<Peer>, max_peers: usize) -> Result<(), P2pError> {
+        // restore the score of its previous connections
+        let ip = peer.get_outgoing_address().ip();
+        if self.cache.has_peerlist_entry(&ip)? {
+            peer.set_score(self.cache.get_peerlist_entry(&ip)?.get_score());
+        }
+
         {
             let mut peers = self.peers.write().await;
             if peers.len() >= max_peers {
@@ -183,6 +227,7 @@ impl PeerList {
 
             entry.set_last_seen(get_current_time_in_seconds());
             entry.set_local_port(peer.get_local_port());
+            entry.set_score(peer.get_score());
 
             self.cache.set_peerlist_entry(&ip, entry)?;
         } else {
@@ -190,6 +235,7 @@ impl PeerList {
             let mut entry = PeerListEntry::new(Some(peer.get_local_port()), PeerListEntryState::Graylist);
             entry.set_first_seen(peer.get_connection().connected_on());
             entry.set_last_seen(get_current_time_in_seconds());
+            entry.set_score(peer.get_score());
 
             self.cache.set_peerlist_entry(&ip, entry)?;
         }
@@ -226,6 +272,63 @@ impl PeerList {
         peers.len()
     }
 
+    // Count the connected peers by direction, priority peers are excluded
+    pub async fn count_peers(&self, out: bool) -> usize {
+        let peers = self.peers.read().await;
+        peers.values()
+            .filter(|peer| peer.is_out() == out && !peer.is_priority())
+            .count()
+    }
+
+    // Count the outgoing peers of each network group, priority peers are excluded
+    fn internal_count_outgoing_groups(peers: &HashMap<u64, Arc<Peer>>) -> HashMap<NetworkGroup, usize> {
+        let mut groups = HashMap::new();
+        for peer in peers.values().filter(|peer| peer.is_out() && !peer.is_priority()) {
+            if let Some(group) = NetworkGroup::from_ip(&peer.get_ip()) {
+                *groups.entry(group).or_insert(0) += 1;
+            }
+        }
+        groups
+    }
+
+    // Check if we can have another outgoing peer in the network group of this IP
+    pub async fn has_outgoing_slot_for_group(&self, ip: &IpAddr) -> bool {
+        let Some(group) = NetworkGroup::from_ip(ip) else {
+            return true;
+        };
+
+        let peers = self.peers.read().await;
+        Self::internal_count_outgoing_groups(&peers).get(&group).copied().unwrap_or(0) < P2P_MAX_OUTGOING_PEERS_PER_GROUP
+    }
+
+    // Save our best outgoing peers to reconnect to them first at the next start
+    // It prevents an attacker to replace all our peers while we are offline
+    pub async fn save_anchors(&self, count: usize) -> Result<(), P2pError> {
+        let mut peers: Vec<Arc<Peer>> = {
+            let peers = self.peers.read().await;
+            peers.values()
+                .filter(|peer| peer.is_out() && !peer.is_priority())
+                .cloned()
+                .collect()
+        };
+
+        peers.sort_by_key(|peer| Reverse(peer.get_score()));
+        let anchors: Vec<SocketAddr> = peers.iter()
+            .take(count)
+            .map(|peer| *peer.get_outgoing_address())
+            .collect();
+
+        // Keep the previous anchors if we are not connected to any outgoing peer
+        if anchors.is_empty() {
+            debug!("No outgoing peer to save as anchor");
+            return Ok(())
+        }
+
+        debug!("Saving anchors: {:?}", anchors);
+        self.cache.set_anchors(&anchors)?;
+        Ok(())
+    }
+
     pub async fn close_all(&self) {
         trace!("closing all peers");
         let peers = {
@@ -435,20 +538,53 @@ impl PeerList {
         self.set_state_to_address(ip, PeerListEntryState::Whitelist).await
     }
 
+    // Get the connected peer with the lowest score in the outgoing or incoming slots, priority peers are excluded
+    pub async fn get_worst_peer(&self, out: bool) -> Option<Arc<Peer>> {
+        let peers = self.peers.read().await;
+        peers.values()
+            .filter(|peer| !peer.is_priority() && peer.is_out() == out)
+            .min_by_key(|peer| peer.get_score())
+            .cloned()
+    }
+
     // Find a peer to connect to from the stored peerlist
     // This will return None if no peer is found
-    // We will search for a whitelisted peer first, then a graylisted peer
     // If a peer is found, we update its last connection try time
     pub async fn find_peer_to_connect(&self) -> Result<Option<SocketAddr>, P2pError> {
+        let current_time = get_current_time_in_seconds();
+        Ok(match self.search_peer_to_connect(current_time).await? {
+            Some((ip, addr, score)) => {
+                debug!("Found peer to connect: {} (score: {}), updating last connection try", addr, score);
+                let mut entry = self.cache.get_peerlist_entry(&ip)?;
+                entry.set_last_connection_try(current_time);
+                self.cache.set_peerlist_entry(&ip, entry)?;
+                Some(addr)
+            },
+            None => None
+        })
+    }
+
+    // Get the score of the peer we would connect to from the stored peerlist
+    // Used to know if it's worth to evict a connected peer for it
+    pub async fn get_peer_to_connect_score(&self) -> Result<Option<i32>, P2pError> {
+        Ok(self.search_peer_to_connect(get_current_time_in_seconds()).await?.map(|(_, _, score)| score))
+    }
+
+    // Search the best peer we can connect to from the stored peerlist
+    // We will search for a whitelisted peer first, then a graylisted peer
+    // In each state, the peer with the best score is selected
+    async fn search_peer_to_connect(&self, current_time: TimestampSeconds) -> Result<Option<(IpAddr, SocketAddr, i32)>, P2pError> {
         let peers = self.peers.read().await;
         let peerlist_entries = self.cache.get_peerlist_entries();
 
-        let current_time = get_current_time_in_seconds();
+        // Don't connect to too many peers of the same network group
+        let groups = Self::internal_count_outgoing_groups(&peers);
 
-        // Search the first peer that we can connect to
-        let mut potential_gray_peer = None;
+        // Search the best peers that we can connect to
+        let mut potential_white_peer: Option<(IpAddr, SocketAddr, i32)> = None;
+        let mut potential_gray_peer: Option<(IpAddr, SocketAddr, i32)> = None;
         for res in peerlist_entries {
-            let (ip, mut entry) = res?;
+            let (ip, entry) = res?;
 
             // If the peer is blacklisted or temp banned, skip it
             if *entry.get_state() == PeerListEntryState::Blacklist || entry.get_temp_ban_until().map(|temp_ban_until| temp_ban_until > current_time).unwrap_or(false) {
@@ -458,31 +594,28 @@ impl PeerList {
 
             if let Some(local_port) = entry.get_local_port() {
                 let addr = SocketAddr::new(ip, local_port);
+                if NetworkGroup::from_ip(&ip).and_then(|group| groups.get(&group)).is_some_and(|count| *count >= P2P_MAX_OUTGOING_PEERS_PER_GROUP) {
+                    trace!("Skipping {} because its network group is full", addr);
+                    continue;
+                }
+
                 if entry.get_last_connection_try().unwrap_or(0) + (entry.get_fail_count() as u64 * P2P_PEERLIST_RETRY_AFTER) <= current_time && Self::internal_get_peer_by_addr(&peers, &addr).is_none() {
-                    // Store it if we don't have any whitelisted peer to connect to
-                    if potential_gray_peer.is_none() && *entry.get_state() == PeerListEntryState::Graylist {
-                        potential_gray_peer = Some((ip, addr));
-                    } else if *entry.get_state() == PeerListEntryState::Whitelist {
-                        debug!("Found peer to connect: {}, updating last connection try", addr);
-                        entry.set_last_connection_try(current_time);
-                        self.cache.set_peerlist_entry(&ip, entry)?;
-                        return Ok(Some(addr));
+                    let potential_peer = if *entry.get_state() == PeerListEntryState::Whitelist {
+                        &mut potential_white_peer
+                    } else {
+                        &mut potential_gray_peer
+                    };
+
+                    // Keep the first peer found with the best score
+                    if potential_peer.as_ref().map_or(true, |(_, _, score)| entry.get_score() > *score) {
+                        *potential_peer = Some((ip, addr, entry.get_score()));
                     }
                 }
             }
         }
 
         // If we didn't find a whitelisted peer, try to connect to a graylisted peer
-        Ok(match potential_gray_peer {
-            Some((ip, addr)) => {
-                debug!("Found gray peer to connect: {}, updating last connection try", addr);
-                let mut entry = self.cache.get_peerlist_entry(&ip)?;
-                entry.set_last_connection_try(current_time);
-                self.cache.set_peerlist_entry(&ip, entry)?;
-                Some(addr)
-            },
-            None => None
-        })
+        Ok(potential_white_peer.or(potential_gray_peer))
     }
 
 
@@ -543,7 +676,8 @@ impl PeerListEntry {
             fail_count: 0,
             local_port,
             temp_ban_until: None,
-            state
+            state,
+            score: 0
         }
     }
 
@@ -598,6 +732,14 @@ impl PeerListEntry {
     fn get_local_port(&self) -> Option<u16> {
         self.local_port
     }
+
+    fn get_score(&self) -> i32 {
+        self.score
+    }
+
+    fn set_score(&mut self, score: i32) {
+        self.score = score;
+    }
 }
 
 impl Display for PeerListEntry {
@@ -605,8 +747,9 @@ impl Display for PeerListEntry {
         let current_time = get_current_time_in_seconds();
         write!(
             f,
-            "PeerListEntry[state: {:?}, first seen: {}, last seen: {}, last try: {}]",
+            "PeerListEntry[state: {:?}, score: {}, first seen: {}, last seen: {}, last try: {}]",
             self.state,
+            self.score,
             self.first_seen.map(|v| format!("{} ago", format_duration(Duration::from_secs(current_time - v)))).unwrap_or_else(|| "never".to_string()),
             self.last_seen.map(|v| format!("{} ago", format_duration(Duration::from_secs(current_time - v)))).unwrap_or_else(|| "never".to_string()),
             self.last_connection_try.map(|v| format!("{} ago", format_duration(Duration::from_secs(current_time - v)))).unwrap_or_else(|| "never".to_string())
@@ -642,6 +785,7 @@ impl Serializer for PeerListEntry {
         writer.write_optional_non_zero_u16(self.local_port);
         self.temp_ban_until.write(writer);
         self.state.write(writer);
+        writer.write_u32(&(self.score as u32));
     }
 
     fn read(reader: &mut Reader) -> Result<Self, ReaderError> {
@@ -652,6 +796,12 @@ impl Serializer for PeerListEntry {
         let local_port = reader.read_optional_non_zero_u16()?;
         let temp_ban_until = Option::read(reader)?;
         let state = PeerListEntryState::read(reader)?;
+        // Entries stored by a previous version have no score
+        let score = if reader.size() > 0 {
+            reader.read_u32()? as i32
+        } else {
+            0
+        };
 
         Ok(Self {
             first_seen,
@@ -660,7 +810,36 @@ impl Serializer for PeerListEntry {
             fail_count,
             local_port,
             temp_ban_until,
-            state
+            state,
+            score
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_group() {
+        let group = |ip: &str| NetworkGroup::from_ip(&ip.parse().unwrap());
+        assert_eq!(group("1.2.3.4"), group("1.2.200.100"));
+        assert_ne!(group("1.2.3.4"), group("1.3.3.4"));
+        assert_eq!(group("2001:db8:1::1"), group("2001:db8:ffff::1"));
+        assert_ne!(group("2001:db8::1"), group("2001:db9::1"));
+        assert_eq!(group("127.0.0.1"), None);
+        assert_eq!(group("192.168.1.1"), None);
+    }
+
+    #[test]
+    fn test_peerlist_entry_score() {
+        let mut entry = PeerListEntry::new(Some(2125), PeerListEntryState::Graylist);
+        entry.set_score(-42);
+        let bytes = entry.to_bytes();
+        assert_eq!(PeerListEntry::from_bytes(&bytes).unwrap().get_score(), -42);
+
+        // Entry stored by a previous version, without the score
+        let entry = PeerListEntry::from_bytes(&bytes[..bytes.len() - 4]).unwrap();
+        assert_eq!(entry, PeerListEntry::new(Some(2125), PeerListEntryState::Graylist));
+    }
+}
@@ -1,10 +1,7 @@
 use std::{
     borrow::Cow,
     time::{Duration, Instant},
-    sync::{
-        Arc,
-        atomic::{AtomicU64, Ordering}
-    },
+    sync::Arc,
     collections::HashMap
 };
 use bytes::Bytes;
@@ -12,7 +9,6 @@ use tokio::{
     sync::{
         mpsc::{Sender, Receiver, self},
         RwLock,
-        oneshot,
         Mutex,
         broadcast
     },
@@ -30,7 +26,12 @@ use crate::{
         blockchain::Blockchain,
         storage::Storage
     },
-    config::PEER_TIMEOUT_REQUEST_OBJECT
+    config::{
+        PEER_SCORE_INVALID_OBJECT,
+        PEER_SCORE_TIMEOUT,
+        PEER_SCORE_USEFUL_TX,
+        PEER_TIMEOUT_REQUEST_OBJECT
+    }
 };
 use super::{
     packet::{
@@ -86,21 +87,18 @@ struct Request {
     response: Option<OwnedObjectResponse>,
     // Timestamp when it got requested
     requested_at: Option<Instant>,
-    // If it linked to a group
-    group_id: Option<u64>,
     // If it has to be broadcast on handling or not
     broadcast: bool
 }
 
 impl Request {
-    pub fn new(request: ObjectRequest, peer: Arc<Peer>, group_id: Option<u64>, broadcast: bool) -> Self {
+    pub fn new(request: ObjectRequest, peer: Arc<Peer>, broadcast: bool) -> Self {
         Self {
             request,
             peer,
             sender: None,
             response: None,
             requested_at: None,
-            group_id,
             broadcast
         }
     }
@@ -121,10 +119,6 @@ impl Request {
         self.response.take()
     }
 
-    pub fn get_group_id(&self) -> Option<u64> {
-        self.group_id
-    }
-
     pub fn set_requested(&mut self) {
         self.requested_at = Some(Instant::now());
     }
@@ -166,47 +160,6 @@ impl Drop for Request {
     }
 }
 
-pub struct GroupManager {
-    // This is used to have unique id for each group of requests
-    group_id: AtomicU64,
-    groups: Mutex<HashMap<u64, oneshot::Sender<P2pError>>>
-}
-
-impl GroupManager {
-    pub fn new() -> Self {
-        Self {
-            group_id: AtomicU64::new(0),
-            groups: Mutex::new(HashMap::new())
-        }
-    }
-
-    // Generate a new group id
-    pub async fn next_group_id(&self) -> (u64, oneshot::Receiver<P2pError>) {
-        let mut groups = self.groups.lock().await;
-        let id = self.group_id.fetch_add(1, Ordering::SeqCst);
-
-        let (sender, receiver) = oneshot::channel();
-        groups.insert(id, sender);
-        (id, receiver)
-    }
-
-    // Unregister an existing group id by removing it
-    pub async fn unregister_group(&self, group_id: u64) {
-        let mut groups = self.groups.lock().await;
-        groups.remove(&group_id);
-    }
-
-    // Notify the requester about the failure
-    pub async fn notify_group(&self, group_id: u64, err: P2pError) {
-        let mut groups = self.groups.lock().await;
-        if let Some(sender) = groups.remove(&group_id) {
-            if sender.send(err).is_err() {
-                warn!("Error while sending group error");
-            }
-        }
-    }
-}
-
 struct ExpirableCache {
     cache: Mutex<HashMap<Hash, Instant>>
 }
@@ -246,9 +199,6 @@ pub struct ObjectTracker {
     handler_sender: Sender<OwnedObjectResponse>,
     // queue of requests with preserved order
     queue: RwLock<Queue<Hash, Request>>,
-    // Group Manager for batched requests
-    // If one fail, all the group is removed
-    group: GroupManager,
     // Requests that should be ignored
     // They got canceled but already requested
     cache: ExpirableCache
@@ -272,7 +222,6 @@ impl ObjectTracker {
             request_sender,
             handler_sender,
             queue: RwLock::new(Queue::new()),
-            group: GroupManager::new(),
             cache: ExpirableCache::new()
         });
         
@@ -324,16 +273,12 @@ impl ObjectTracker {
         self.cache.remove(hash).await
     }
 
-    // Returns the group manager used
-    pub fn get_group_manager(&self) -> &GroupManager {
-        &self.group
-    }
-
     // Handle the object response and returns the error if any
     async fn handle_object_response_internal<S: Storage>(&self, blockchain: &Arc<Blockchain<S>>, response: OwnedObjectResponse, broadcast: bool, peer: &Arc<Peer>) -> Result<(), P2pError> {
         match response {
             OwnedObjectResponse::Transaction(tx, hash) => {
                 blockchain.add_tx_to_mempool_with_hash(tx, hash, broadcast).await?;
+                peer.update_score(PEER_SCORE_USEFUL_TX);
             },
             OwnedObjectResponse::Block(block, _) => {
                 // We don't broadcast it to others peers but we broadcast it to our miners in case
@@ -385,10 +330,9 @@ impl ObjectTracker {
                     Some(response) => {
                         let (_, request) = queue.pop().unwrap();
                         if let Err(e) = self.handle_object_response_internal(&blockchain, response, request.broadcast(), request.get_peer()).await {
-                            if request.get_group_id().is_none() {
-                                warn!("Error while handling object response for {} in ObjectTracker from {}: {}", request.get_hash(), request.get_peer(), e);
-                            }
-                            self.clean_queue(&mut queue, request.get_peer().get_id(), request.get_group_id().map(|v| (v, e))).await;
+                            warn!("Error while handling object response for {} in ObjectTracker from {}: {}", request.get_hash(), request.get_peer(), e);
+                            request.get_peer().update_score(PEER_SCORE_INVALID_OBJECT);
+                            self.clean_queue(&mut queue, request.get_peer().get_id()).await;
                         }
                     },
                     None => {
@@ -397,7 +341,8 @@ impl ObjectTracker {
                             if requested_at.elapsed() > TIME_OUT {
                                 warn!("Request timed out for object {}", request.get_hash());
                                 let (_, request) = queue.pop().unwrap();
-                                self.clean_queue(&mut queue, request.get_peer().get_id(), request.get_group_id().map(|v| (v, P2pError::TrackerRequestExpired))).await;
+                                request.get_peer().update_score(PEER_SCORE_TIMEOUT);
+                                self.clean_queue(&mut queue, request.get_peer().get_id()).await;
                             } else {
                                 break;
                             }
@@ -471,17 +416,17 @@ impl ObjectTracker {
 
     // Request the object from the peer or return false if it is already requested
     pub async fn request_object_from_peer(&self, peer: Arc<Peer>, request: ObjectRequest, broadcast: bool) -> Result<bool, P2pError> {
-        self.request_object_from_peer_with(peer, request, None, false, broadcast).await?;
+        self.request_object_from_peer_with(peer, request, false, broadcast).await?;
         Ok(true)
     }
 
     // Request the object from the peer and returns the response blocker
-    pub async fn request_object_from_peer_with(&self, peer: Arc<Peer>, request: ObjectRequest, group_id: Option<u64>, blocker: bool, broadcast: bool) -> Result<Option<ResponseBlocker>, P2pError> {
+    pub async fn request_object_from_peer_with(&self, peer: Arc<Peer>, request: ObjectRequest, blocker: bool, broadcast: bool) -> Result<Option<ResponseBlocker>, P2pError> {
         trace!("Requesting object {} from {}", request.get_hash(), peer);
         let (listener, hash) = {
             let mut queue = self.queue.write().await;
             let hash = request.get_hash().clone();
-            let mut req = Request::new(request, peer, group_id, broadcast);
+            let mut req = Request::new(request, peer, broadcast);
 
             let listener = if blocker {
                 Some(req.get_response_blocker())
@@ -501,15 +446,9 @@ impl ObjectTracker {
         Ok(listener)
     }
 
-    // Clean the queue from all requests from the given peer or from the group if it is specified
-    async fn clean_queue(&self, queue: &mut Queue<Hash, Request>, peer_id: u64, group: Option<(u64, P2pError)>) {
+    // Clean the queue from all requests from the given peer
+    async fn clean_queue(&self, queue: &mut Queue<Hash, Request>, peer_id: u64) {
         let iter = queue.extract_if(|(_, request)| {
-            if let (Some((failed_group, _)), Some(request_group)) = (group.as_ref(), request.get_group_id()) {
-                if *failed_group == request_group {
-                    return true;
-                }
-            }
-
             let peer = request.get_peer();
             if peer.get_id() == peer_id || peer.get_connection().is_closed() {
                 return true;
@@ -534,12 +473,6 @@ impl ObjectTracker {
             debug!("Adding requested object with hash {} in expirable cache", hash);
             self.cache.insert(hash).await;
         }
-
-        // Delete all from the same group if one of them failed
-        if let Some((group, err)) = group {
-            debug!("Group {} failed", group);
-            self.group.notify_group(group, err).await;
-        }
     }
 
     // Request the object from the peer
@@ -555,10 +488,10 @@ impl ObjectTracker {
             let peer = request.get_peer();
             if peer.get_connection().is_closed() {
                 warn!("Peer {} is disconnected but still has a pending request object {}", peer, request_hash);
-                Some((peer.get_id(), request.get_group_id().map(|v| (v, P2pError::Disconnected))))
+                Some(peer.get_id())
             } else if let Err(e) = peer.send_bytes(packet).await {
                 warn!("Error while requesting object {} using Object Tracker: {}", request_hash, e);
-                Some((peer.get_id(), request.get_group_id().map(|v| (v, e))))
+                Some(peer.get_id())
             } else {
                 None
             }
@@ -567,9 +500,9 @@ impl ObjectTracker {
             None
         };
 
-        if let Some((peer_id, group)) = fail {
+        if let Some(peer_id) = fail {
             warn!("cleaning queue because of failure");
-            self.clean_queue(&mut queue, peer_id, group).await;
+            self.clean_queue(&mut queue, peer_id).await;
         }
     }
 }
\ No newline at end of file
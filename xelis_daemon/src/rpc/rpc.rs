@@ -13,6 +13,7 @@ use crate::{
         },
         hard_fork::get_pow_algorithm_for_version,
         error::BlockchainError,
+        fee_estimator::MAX_CONFIRMATION_TARGET,
         mempool::Mempool,
         storage::Storage
     },
@@ -40,7 +41,12 @@ use xelis_common::{
         XELIS_ASSET
     },
     context::Context,
-    crypto::{Address, AddressType, Hash},
+    crypto::{
+        state_tree::{balance_path, nonce_path},
+        Address,
+        AddressType,
+        Hash
+    },
     difficulty::{
         CumulativeDifficulty,
         Difficulty
@@ -174,7 +180,10 @@ pub async fn get_block_response_for_hash<S: Storage>(blockchain: &Blockchain<S>,
     }
 
     let value: Value = if include_txs {
-        let block = storage.get_block_by_hash(&hash).await.context("Error while retrieving full block")?;
+        let block = match storage.get_block_by_hash(&hash).await {
+            Err(e @ BlockchainError::BlockTxsPruned(_)) => return Err(InternalRpcError::InvalidParamsAny(e.into())),
+            res => res.context("Error while retrieving full block")?
+        };
         let total_size_in_bytes = block.size();
         get_block_response(blockchain, storage, hash, &block, total_size_in_bytes).await?
     } else {
@@ -183,8 +192,11 @@ pub async fn get_block_response_for_hash<S: Storage>(blockchain: &Blockchain<S>,
 
         // calculate total size in bytes
         let mut total_size_in_bytes = header.size();
+        // Pruned transactions are not counted
         for tx_hash in header.get_txs_hashes() {
-            total_size_in_bytes += storage.get_transaction_size(tx_hash).await.context(format!("Error while retrieving transaction {tx_hash} size"))?;
+            if storage.has_transaction(tx_hash).await.context(format!("Error while checking if transaction {tx_hash} exists"))? {
+                total_size_in_bytes += storage.get_transaction_size(tx_hash).await.context(format!("Error while retrieving transaction {tx_hash} size"))?;
+            }
         }
 
         let mainnet = blockchain.get_network().is_mainnet();
@@ -264,6 +276,8 @@ pub async fn get_peer_entry(peer: &Peer) -> PeerEntry {
         connected_on: peer.get_connection().connected_on(),
         bytes_recv: peer.get_connection().bytes_in(),
         bytes_sent: peer.get_connection().bytes_out(),
+        bandwidth: peer.get_bandwidth().get_stats(),
+        score: peer.get_score()
     }
 }
 
@@ -292,6 +306,8 @@ pub fn register_methods<S: Storage>(handler: &mut RPCHandler<Arc<Blockchain<S>>>
     handler.register_method("get_nonce", async_handler!(get_nonce::<S>));
     handler.register_method("has_nonce", async_handler!(has_nonce::<S>));
     handler.register_method("get_nonce_at_topoheight", async_handler!(get_nonce_at_topoheight::<S>));
+    handler.register_method("get_state_root", async_handler!(get_state_root::<S>));
+    handler.register_method("get_account_proof", async_handler!(get_account_proof::<S>));
     handler.register_method("get_asset", async_handler!(get_asset::<S>));
     handler.register_method("get_assets", async_handler!(get_assets::<S>));
     handler.register_method("count_assets", async_handler!(count_assets::<S>));
@@ -304,6 +320,7 @@ pub fn register_methods<S: Storage>(handler: &mut RPCHandler<Arc<Blockchain<S>>>
     handler.register_method("get_peers", async_handler!(get_peers::<S>));
     handler.register_method("get_mempool", async_handler!(get_mempool::<S>));
     handler.register_method("get_estimated_fee_rates", async_handler!(get_estimated_fee_rates::<S>));
+    handler.register_method("get_estimated_fee_rate_for_target", async_handler!(get_estimated_fee_rate_for_target::<S>));
     handler.register_method("get_tips", async_handler!(get_tips::<S>));
     handler.register_method("get_dag_order", async_handler!(get_dag_order::<S>));
     handler.register_method("get_blocks_range_by_topoheight", async_handler!(get_blocks_range_by_topoheight::<S>));
@@ -556,13 +573,14 @@ async fn get_info<S: Storage>(context: &Context, body: Value) -> Result<Value, I
     let height = blockchain.get_height();
     let topoheight = blockchain.get_topo_height();
     let stableheight = blockchain.get_stable_height();
-    let (top_block_hash, circulating_supply, pruned_topoheight, average_block_time) = {
+    let (top_block_hash, circulating_supply, pruned_topoheight, average_block_time, retention) = {
         let storage = blockchain.get_storage().read().await;
         let top_block_hash = storage.get_hash_at_topo_height(topoheight).await.context("Error while retrieving hash at topo height")?;
         let supply = storage.get_supply_at_topo_height(topoheight).await.context("Error while retrieving supply at topo height")?;
         let pruned_topoheight = storage.get_pruned_topoheight().await.context("Error while retrieving pruned topoheight")?;
         let average_block_time = blockchain.get_average_block_time::<S>(&storage).await.context("Error while retrieving average block time")?;
-        (top_block_hash, supply, pruned_topoheight, average_block_time)
+        let retention = blockchain.get_retention_policy(&storage).await.context("Error while retrieving retention policy")?;
+        (top_block_hash, supply, pruned_topoheight, average_block_time, retention)
     };
     let difficulty = blockchain.get_difficulty().await;
     let block_time_target = BLOCK_TIME_MILLIS;
@@ -588,7 +606,8 @@ async fn get_info<S: Storage>(context: &Context, body: Value) -> Result<Value, I
         miner_reward,
         mempool_size,
         version,
-        network
+        network,
+        retention: Some(retention)
     }))
 }
 
@@ -609,6 +628,59 @@ async fn get_balance_at_topoheight<S: Storage>(context: &Context, body: Value) -
     Ok(json!(balance))
 }
 
+async fn get_state_root<S: Storage>(context: &Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetStateRootParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let root = blockchain.get_state_root_at_topoheight(params.topoheight).await.context("Error while retrieving state root")?;
+    Ok(json!(GetStateRootResult {
+        topoheight: params.topoheight,
+        root
+    }))
+}
+
+async fn get_account_proof<S: Storage>(context: &Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetAccountProofParams = parse_params(body)?;
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    if params.address.is_mainnet() != blockchain.get_network().is_mainnet() {
+        return Err(InternalRpcError::InvalidParamsAny(BlockchainError::InvalidNetwork.into()))
+    }
+
+    let key = params.address.get_public_key();
+
+    // The storage lock is only held to read the account at the state tree topoheight
+    let (topoheight, mut tree, nonce, version) = {
+        let storage = blockchain.get_storage().read().await;
+        let (topoheight, tree) = blockchain.get_state_tree().await.context("Error while retrieving state tree")?;
+        // Only the state tree at the last stable topoheight is kept in memory
+        if let Some(requested) = params.topoheight.filter(|requested| *requested != topoheight) {
+            return Err(InternalRpcError::InvalidParamsAny(BlockchainError::StateNotAvailable(requested).into()))
+        }
+
+        let nonce = storage.get_nonce_at_maximum_topoheight(key, topoheight).await.context("Error while retrieving nonce")?
+            .map(|(_, version)| version.get_nonce())
+            .unwrap_or(0);
+
+        let (_, version) = storage.get_balance_at_maximum_topoheight(key, &params.asset, topoheight).await.context("Error while retrieving balance")?
+            .context("No balance found for this asset at this topoheight")?;
+        (topoheight, tree, nonce, version)
+    };
+
+    let root = tree.root().context("Error while computing state root")?;
+    let nonce_proof = tree.prove(&nonce_path(key)).context("Error while building nonce proof")?
+        .context("Account is not registered at this topoheight")?;
+    let balance_proof = tree.prove(&balance_path(key, &params.asset)).context("Error while building balance proof")?
+        .context("Balance is not in the state tree")?;
+
+    Ok(json!(GetAccountProofResult {
+        topoheight,
+        root,
+        nonce,
+        nonce_proof,
+        balance: version.take_balance(),
+        balance_proof
+    }))
+}
+
 async fn has_nonce<S: Storage>(context: &Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: HasNonceParams = parse_params(body)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
@@ -775,6 +847,7 @@ async fn p2p_status<S: Storage>(context: &Context, body: Value) -> Result<Value,
             let max_peers = p2p.get_max_peers();
             let our_topoheight = blockchain.get_topo_height();
             let peer_count = p2p.get_peer_count().await;
+            let bandwidth = p2p.get_bandwidth();
 
             Ok(json!(P2pStatusResult {
                 peer_count,
@@ -783,7 +856,10 @@ async fn p2p_status<S: Storage>(context: &Context, body: Value) -> Result<Value,
                 our_topoheight,
                 best_topoheight,
                 median_topoheight,
-                max_peers
+                max_peers,
+                bytes_in: bandwidth.get_bytes_in(),
+                bytes_out: bandwidth.get_bytes_out(),
+                bandwidth: bandwidth.get_stats()
             }))
         },
         None => Err(InternalRpcError::InvalidParamsAny(ApiError::NoP2p.into()))
@@ -843,6 +919,28 @@ async fn get_estimated_fee_rates<S: Storage>(context: &Context, body: Value) ->
     Ok(json!(estimated))
 }
 
+async fn get_estimated_fee_rate_for_target<S: Storage>(context: &Context, body: Value) -> Result<Value, InternalRpcError> {
+    let params: GetEstimatedFeeRateForTargetParams = parse_params(body)?;
+    if params.target == 0 || params.target > MAX_CONFIRMATION_TARGET {
+        return Err(InternalRpcError::InvalidParams("Target must be between 1 and the maximum confirmation target"))
+    }
+
+    let blockchain: &Arc<Blockchain<S>> = context.get()?;
+    let mempool = blockchain.get_mempool().read().await;
+    let historical = mempool.estimate_fee_rate_for_target(params.target);
+    // Fallback on the current mempool when not enough TXs were included
+    let fee_rate = match historical {
+        Some(fee_rate) => fee_rate,
+        None => mempool.estimate_fee_rates()?.medium
+    };
+
+    Ok(json!(TargetFeeRateEstimated {
+        fee_rate,
+        target: params.target,
+        historical: historical.is_some()
+    }))
+}
+
 async fn get_blocks_at_height<S: Storage>(context: &Context, body: Value) -> Result<Value, InternalRpcError> {
     let params: GetBlocksAtHeightParams = parse_params(body)?;
     let blockchain: &Arc<Blockchain<S>> = context.get()?;
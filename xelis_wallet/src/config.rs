@@ -11,6 +11,9 @@ pub const KEY_SIZE: usize = 32;
 pub const DEFAULT_DAEMON_ADDRESS: &str = "http://127.0.0.1:8080";
 // Auto reconnect interval in seconds for Network Handler
 pub const AUTO_RECONNECT_INTERVAL: u64 = 5;
+// Fee rate margin in percent added to the minimum increase required to replace a transaction
+// The bumped transaction may be slightly bigger than the replaced one
+pub const FEE_BUMP_MARGIN_PERCENT: u64 = 1;
 
 lazy_static! {
     pub static ref PASSWORD_ALGORITHM: Argon2<'static> = {
@@ -36,9 +36,12 @@ use xelis_common::{
         GetMempoolCacheResult,
         IsAccountRegisteredParams,
         TransactionOrphanedEvent,
+        TransactionExpiredEvent,
         GetTransactionExecutorParams,
         GetTransactionExecutorResult,
-        GetStableBalanceResult
+        GetStableBalanceResult,
+        GetEstimatedFeeRateForTargetParams,
+        TargetFeeRateEstimated
     },
     account::VersionedBalance,
     crypto::{
@@ -142,6 +145,12 @@ impl DaemonAPI {
         Ok(receiver)
     }
 
+    pub async fn on_transaction_expired_event(&self) -> Result<EventReceiver<TransactionExpiredEvent>> {
+        trace!("on_transaction_expired_event");
+        let receiver = self.client.subscribe_event(NotifyEvent::TransactionExpired, self.capacity).await?;
+        Ok(receiver)
+    }
+
     pub async fn on_stable_height_changed_event(&self) -> Result<EventReceiver<StableHeightChangedEvent>> {
         trace!("on_stable_height_changed_event");
         let receiver = self.client.subscribe_event(NotifyEvent::StableHeightChanged, self.capacity).await?;
@@ -289,6 +298,14 @@ impl DaemonAPI {
         Ok(is_executed)
     }
 
+    pub async fn get_estimated_fee_rate_for_target(&self, target: u64) -> Result<TargetFeeRateEstimated> {
+        trace!("get_estimated_fee_rate_for_target");
+        let estimated = self.client.call_with("get_estimated_fee_rate_for_target", &GetEstimatedFeeRateForTargetParams {
+            target
+        }).await?;
+        Ok(estimated)
+    }
+
     pub async fn get_mempool_cache(&self, address: &Address) -> Result<GetMempoolCacheResult> {
         trace!("get_mempool_cache");
         let cache = self.client.call_with("get_mempool_cache", &GetMempoolCacheParams {
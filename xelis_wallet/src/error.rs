@@ -87,7 +87,13 @@ pub enum WalletError {
     #[error(transparent)]
     AEADCipherFormatError(#[from] CipherFormatError),
     #[error("No network handler available")]
-    NoNetworkHandler
+    NoNetworkHandler,
+    #[error("Transaction {} is not in mempool", _0)]
+    TxNotInMempool(Hash),
+    #[error("Transaction {} with nonce {} can't be replaced, only the oldest transaction in mempool (nonce {}) can be", _0, _1, _2)]
+    TxNotReplaceable(Hash, u64, u64),
+    #[error("Fee rate of the bumped transaction is below the {} per KB required", _0)]
+    FeeBumpTooLow(u64)
 }
 
 impl WalletError {
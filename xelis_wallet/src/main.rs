@@ -404,7 +404,7 @@ async fn setup_wallet_command_manager(wallet: Arc<Wallet>, command_manager: &Com
 
     // Add wallet commands
     command_manager.add_command(Command::new("change_password", "Set a new password to open your wallet", CommandHandler::Async(async_handler!(change_password))))?;
-    command_manager.add_command(Command::with_optional_arguments("transfer", "Send asset to a specified address", vec![Arg::new("asset", ArgType::Hash)], CommandHandler::Async(async_handler!(transfer))))?;
+    command_manager.add_command(Command::with_optional_arguments("transfer", "Send asset to a specified address", vec![Arg::new("asset", ArgType::Hash), Arg::new("fee_target", ArgType::Number)], CommandHandler::Async(async_handler!(transfer))))?;
     command_manager.add_command(Command::with_optional_arguments("transfer_all", "Send all your asset balance to a specified address", vec![Arg::new("asset", ArgType::Hash)], CommandHandler::Async(async_handler!(transfer_all))))?;
     command_manager.add_command(Command::new("burn", "Burn amount of asset", CommandHandler::Async(async_handler!(burn))))?;
     command_manager.add_command(Command::new("display_address", "Show your wallet address", CommandHandler::Async(async_handler!(display_address))))?;
@@ -422,6 +422,7 @@ async fn setup_wallet_command_manager(wallet: Arc<Wallet>, command_manager: &Com
         command_manager.add_command(Command::with_optional_arguments("online_mode", "Set your wallet in online mode", vec![Arg::new("daemon_address", ArgType::String)], CommandHandler::Async(async_handler!(online_mode))))?;
         command_manager.add_command(Command::new("offline_mode", "Set your wallet in offline mode", CommandHandler::Async(async_handler!(offline_mode))))?;
         command_manager.add_command(Command::with_optional_arguments("rescan", "Rescan balance and transactions", vec![Arg::new("topoheight", ArgType::Number)], CommandHandler::Async(async_handler!(rescan))))?;
+        command_manager.add_command(Command::with_required_arguments("bump_fee", "Resend a transaction stuck in mempool with a higher fee", vec![Arg::new("tx_hash", ArgType::Hash)], CommandHandler::Async(async_handler!(bump_fee))))?;
     }
 
     #[cfg(feature = "api_server")]
@@ -668,7 +669,8 @@ async fn change_password(manager: &CommandManager, _: ArgumentManager) -> Result
 }
 
 // Create a new transfer to a specified address
-async fn transfer(manager: &CommandManager, _: ArgumentManager) -> Result<(), CommandError> {
+// If a fee target is set, the fee rate is estimated by the daemon for the TX to be included within this count of blocks
+async fn transfer(manager: &CommandManager, mut args: ArgumentManager) -> Result<(), CommandError> {
     let prompt = manager.get_prompt();
     let context = manager.get_context().lock()?;
     let wallet: &Arc<Wallet> = context.get()?;
@@ -680,9 +682,12 @@ async fn transfer(manager: &CommandManager, _: ArgumentManager) -> Result<(), Co
     ).await.context("Error while reading address")?;
     let address = Address::from_string(&str_address).context("Invalid address")?;
 
-    let asset = prompt.read_hash(
-        prompt.colorize_str(Color::Green, "Asset (default XELIS): ")
-    ).await.ok();
+    let mut asset = args.get_value("asset").and_then(|v| v.to_hash()).ok();
+    if asset.is_none() {
+        asset = prompt.read_hash(
+            prompt.colorize_str(Color::Green, "Asset (default XELIS): ")
+        ).await.ok();
+    }
 
     let asset = asset.unwrap_or(XELIS_ASSET);
 
@@ -714,7 +719,22 @@ async fn transfer(manager: &CommandManager, _: ArgumentManager) -> Result<(), Co
         asset,
         extra_data: None
     };
-    let tx = match wallet.create_transaction(TransactionTypeBuilder::Transfers(vec![transfer]), FeeBuilder::default()).await {
+    let fee = if args.has_argument("fee_target") {
+        let target = args.get_value("fee_target")?.to_number()?;
+        #[cfg(feature = "network_handler")]
+        {
+            wallet.get_fee_builder_for_target(target).await.context("Error while estimating fee rate")?
+        }
+        #[cfg(not(feature = "network_handler"))]
+        {
+            manager.warn(format!("Fee target {} ignored, network handler is not enabled", target));
+            FeeBuilder::default()
+        }
+    } else {
+        FeeBuilder::default()
+    };
+
+    let tx = match wallet.create_transaction(TransactionTypeBuilder::Transfers(vec![transfer]), fee).await {
         Ok(tx) => tx,
         Err(e) => {
             manager.error(&format!("Error while creating transaction: {}", e));
@@ -928,6 +948,34 @@ async fn clear_tx_cache(manager: &CommandManager, _: ArgumentManager) -> Result<
     Ok(())
 }
 
+// Replace a transaction stuck in mempool by the same one with a higher fee
+#[cfg(feature = "network_handler")]
+async fn bump_fee(manager: &CommandManager, mut arguments: ArgumentManager) -> Result<(), CommandError> {
+    let hash = arguments.get_value("tx_hash")?.to_hash()?;
+    let prompt = manager.get_prompt();
+    let context = manager.get_context().lock()?;
+    let wallet: &Arc<Wallet> = context.get()?;
+
+    manager.message(format!("Transaction {} will be replaced, all the transactions sent after it will be dropped", hash));
+    if !prompt.ask_confirmation().await.context("Error while confirming action")? {
+        manager.message("Transaction has been aborted");
+        return Ok(())
+    }
+
+    manager.message("Building transaction...");
+    let tx = match wallet.create_fee_bump_transaction(&hash).await {
+        Ok(tx) => tx,
+        Err(e) => {
+            manager.error(format!("Error while bumping fee: {}", e));
+            return Ok(())
+        }
+    };
+
+    manager.message(format!("New fee: {}", format_xelis(tx.get_fee())));
+    broadcast_tx(wallet, manager, tx).await;
+    Ok(())
+}
+
 // Set your wallet in online mode
 #[cfg(feature = "network_handler")]
 async fn online_mode(manager: &CommandManager, mut arguments: ArgumentManager) -> Result<(), CommandError> {
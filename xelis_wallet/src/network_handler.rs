@@ -3,6 +3,7 @@ use std::{
         HashMap,
         HashSet
     },
+    future,
     sync::Arc,
     time::Duration
 };
@@ -855,6 +856,16 @@ impl NetworkHandler {
         // This is rare event but may happen if someone try to do something shady
         let mut on_transaction_orphaned = self.api.on_transaction_orphaned_event().await?;
 
+        // Our pending TXs may expire in mempool, the unconfirmed balances built on them are outdated
+        // An older daemon may not support this event, it's not required to stay in sync
+        let mut on_transaction_expired = match self.api.on_transaction_expired_event().await {
+            Ok(receiver) => Some(receiver),
+            Err(e) => {
+                warn!("Couldn't subscribe to transaction expired event: {}", e);
+                None
+            }
+        };
+
         // Network events to detect if we are online or offline
         let mut on_connection = self.api.on_connection().await;
         let mut on_connection_lost = self.api.on_connection_lost().await;
@@ -914,6 +925,21 @@ impl NetworkHandler {
                         storage.clear_tx_cache();
                     }
                 },
+                res = async {
+                    match on_transaction_expired.as_mut() {
+                        Some(receiver) => receiver.next().await,
+                        None => future::pending().await
+                    }
+                } => {
+                    let event = res?;
+                    debug!("on transaction expired event {}", event.data.hash);
+                    let tx = event.data;
+                    if tx.source == address {
+                        warn!("Transaction {} expired in mempool, it must be built again", tx.hash);
+                        let mut storage = self.wallet.get_storage().write().await;
+                        storage.delete_unconfirmed_balances().await;
+                    }
+                },
                 // Detect network events
                 res = on_connection.recv() => {
                     trace!("on_connection");
@@ -74,10 +74,16 @@ use {
             NetworkHandler,
             SharedNetworkHandler
         },
+        config::FEE_BUMP_MARGIN_PERCENT,
         daemon_api::DaemonAPI,
         storage::Balance,
     },
-    xelis_common::config::XELIS_ASSET,
+    xelis_common::{
+        config::{BYTES_PER_KB, REPLACE_BY_FEE_MIN_INCREASE_PERCENT, XELIS_ASSET},
+        crypto::Hash,
+        serializer::Serializer,
+        transaction::{builder::TransferBuilder, TransactionType}
+    },
 };
 use rand::{rngs::OsRng, RngCore};
 use log::{
@@ -761,11 +767,124 @@ impl Wallet {
         Err(WalletError::NotOnlineMode)
     }
 
+    // Build the fee to pay for a TX to be included within the target blocks
+    // The fee rate is estimated by the daemon, the minimum fees are paid if they are higher
+    #[cfg(feature = "network_handler")]
+    pub async fn get_fee_builder_for_target(&self, target: u64) -> Result<FeeBuilder, WalletError> {
+        trace!("get fee builder for target {}", target);
+        let network_handler = self.network_handler.lock().await;
+        let api = network_handler.as_ref().ok_or(WalletError::NotOnlineMode)?.get_api();
+        let estimated = api.get_estimated_fee_rate_for_target(target).await?;
+        Ok(FeeBuilder::Rate(estimated.fee_rate))
+    }
+
+    // Rebuild a transaction stuck in the daemon mempool with a higher fee
+    // The new transaction has the same nonce and payload, so the daemon replaces the previous one
+    // Only the oldest transaction in mempool can be rebuilt because it is based on the confirmed balances,
+    // the transactions sent after it are dropped by the daemon as they are not valid anymore
+    // This will apply the changes to the storage
+    #[cfg(feature = "network_handler")]
+    pub async fn create_fee_bump_transaction(&self, hash: &Hash) -> Result<Transaction, WalletError> {
+        trace!("create fee bump transaction for {}", hash);
+        let (tx, removed_fees) = {
+            let network_handler = self.network_handler.lock().await;
+            let api = network_handler.as_ref().ok_or(WalletError::NotOnlineMode)?.get_api();
+            let cache = api.get_mempool_cache(&self.get_address()).await?;
+            if !cache.get_txs().contains(hash) {
+                return Err(WalletError::TxNotInMempool(hash.clone()))
+            }
+
+            let tx = api.get_transaction(hash).await?;
+            if tx.get_nonce() != cache.get_min() {
+                return Err(WalletError::TxNotReplaceable(hash.clone(), tx.get_nonce(), cache.get_min()))
+            }
+
+            // The daemon requires the new transaction to pay for all the ones it drops
+            let mut removed_fees = tx.get_fee();
+            for tx_hash in cache.get_txs().iter().filter(|tx_hash| *tx_hash != hash) {
+                removed_fees = removed_fees.saturating_add(api.get_transaction(tx_hash).await?.get_fee());
+            }
+
+            (tx, removed_fees)
+        };
+
+        // Decrypt our outgoing transfers to build the same payload
+        let transaction_type = match tx.get_data() {
+            TransactionType::Burn(payload) => TransactionTypeBuilder::Burn(payload.clone()),
+            TransactionType::Transfers(transfers) => {
+                let mut builders = Vec::with_capacity(transfers.len());
+                for transfer in transfers {
+                    let commitment = transfer.get_commitment().decompress().map_err(|_| WalletError::CiphertextDecode)?;
+                    let handle = transfer.get_sender_handle().decompress().map_err(|_| WalletError::CiphertextDecode)?;
+                    let extra_data = match transfer.get_extra_data() {
+                        Some(cipher) => Some(self.decrypt_extra_data(cipher.clone(), &handle, Role::Sender)?),
+                        None => None
+                    };
+                    let amount = self.decrypt_ciphertext(Ciphertext::new(commitment, handle)).await?;
+
+                    builders.push(TransferBuilder {
+                        asset: transfer.get_asset().clone(),
+                        amount,
+                        destination: transfer.get_destination().as_address(self.network.is_mainnet()),
+                        extra_data
+                    });
+                }
+                TransactionTypeBuilder::Transfers(builders)
+            }
+        };
+
+        // Fee rate per KB required by the daemon to replace it, computed the same way
+        let replaced_size = tx.size() as u64;
+        let replaced_fee_rate = tx.get_fee().saturating_mul(BYTES_PER_KB as u64) / replaced_size.max(1);
+        let required_fee_rate = replaced_fee_rate.saturating_mul(100 + REPLACE_BY_FEE_MIN_INCREASE_PERCENT) / 100;
+        let fee_rate = required_fee_rate.saturating_mul(100 + FEE_BUMP_MARGIN_PERCENT) / 100;
+        // Total fee required to pay for all the dropped transactions
+        let required_fee = removed_fees.saturating_mul(100 + REPLACE_BY_FEE_MIN_INCREASE_PERCENT) / 100;
+
+        let mut storage = self.storage.write().await;
+        let reference = Reference {
+            topoheight: storage.get_synced_topoheight()?,
+            hash: storage.get_top_block_hash()?
+        };
+
+        // The fee doesn't change the size of the transaction
+        // so it is built again only once if it's too big for the fee paid
+        let mut size = replaced_size;
+        let (mut state, transaction) = loop {
+            // Round up so the fee per KB is always enough
+            let fee = ((fee_rate.saturating_mul(size) + BYTES_PER_KB as u64 - 1) / BYTES_PER_KB as u64).max(required_fee);
+            debug!("Bumping fee of transaction {} from {} to {} for {} bytes", hash, tx.get_fee(), fee, size);
+
+            let mut state = TransactionBuilderState::new(self.network.is_mainnet(), reference.clone(), tx.get_nonce());
+            for asset in transaction_type.used_assets() {
+                let balance = storage.get_balance_for(asset).await?;
+                state.add_balance(asset.clone(), balance);
+            }
+
+            let transaction = self.create_transaction_with(&mut state, transaction_type.clone(), FeeBuilder::Value(fee))?;
+            let final_size = transaction.size() as u64;
+            if fee.saturating_mul(BYTES_PER_KB as u64) / final_size.max(1) >= required_fee_rate {
+                break (state, transaction)
+            }
+
+            if final_size <= size {
+                return Err(WalletError::FeeBumpTooLow(required_fee_rate))
+            }
+            size = final_size;
+        };
+
+        // Unconfirmed balances were built on the replaced transaction
+        storage.delete_unconfirmed_balances().await;
+        state.apply_changes(&mut storage).await?;
+
+        Ok(transaction)
+    }
+
     // Search if possible all registered keys for the transaction type
     #[cfg(feature = "network_handler")]
     pub async fn add_registered_keys_for_fees_estimation(&self, state: &mut EstimateFeesState, fee: &FeeBuilder, transaction_type: &TransactionTypeBuilder) -> Result<(), WalletError> {
         trace!("add registered keys for fees estimation");
-        if let FeeBuilder::Multiplier(_) = fee {
+        if let FeeBuilder::Multiplier(_) | FeeBuilder::Rate(_) = fee {
             // To pay exact fees needed, we must verify that we don't have to pay more than needed
             let used_keys = transaction_type.used_keys();
             if !used_keys.is_empty() {